@@ -56,6 +56,66 @@ def source_patches(source: Any) -> list[str]:
     return []
 
 
+def entry_url(entry: Any) -> str:
+    if isinstance(entry, dict) and not entry.get("url") and entry.get("git_url"):
+        rev = str(entry.get("git_rev") or entry.get("git_commit") or "").strip()
+        git_url = str(entry.get("git_url")).strip().rstrip("/")
+        return f"git+{git_url}#{rev}" if rev and git_url else ""
+    return first_url(entry)
+
+
+def extra_sources(source: Any) -> list[dict[str, Any]]:
+    if not isinstance(source, list):
+        return []
+    entries = []
+    for item in source[1:]:
+        url = entry_url(item)
+        if not url:
+            continue
+        folder = item.get("folder") if isinstance(item, dict) else None
+        patches = item.get("patches") if isinstance(item, dict) else None
+        entries.append(
+            {
+                "url": url,
+                "folder": str(folder).strip() if folder is not None else "",
+                "patches": normalize_list(patches),
+                "sha256": source_digest(item, "sha256"),
+                "md5": source_digest(item, "md5"),
+                "git_depth": git_depth(item),
+            }
+        )
+    return entries
+
+
+def source_digest(source: Any, key: str) -> str:
+    source = primary_source(source)
+    if isinstance(source, dict):
+        value = source.get(key)
+        return str(value).strip() if value is not None else ""
+    return ""
+
+
+def git_depth(source: Any) -> int | None:
+    source = primary_source(source)
+    if not isinstance(source, dict):
+        return None
+    try:
+        depth = int(source.get("git_depth"))
+    except (TypeError, ValueError):
+        return None
+    return depth if depth > 0 else None
+
+
+def test_requires(meta: Any) -> list[str]:
+    requires = normalize_list(meta.get_value("test/requires", default=[]))
+    outputs = meta.meta.get("outputs") or []
+    for output in outputs if isinstance(outputs, list) else []:
+        test = output.get("test") if isinstance(output, dict) else None
+        if isinstance(test, dict):
+            requires.extend(normalize_list(test.get("requires")))
+    return list(dict.fromkeys(requires))
+
+
 def build_script(value: Any) -> str | None:
     if value is None:
         return None
@@ -74,18 +134,48 @@ def default_payload(recipe_dir: pathlib.Path, skip: bool) -> dict[str, Any]:
         "build_number": "0",
         "source_url": "",
         "source_folder": "",
+        "source_sha256": "",
+        "source_md5": "",
+        "source_git_depth": None,
         "homepage": "",
+        "dev_url": "",
+        "doc_url": "",
         "license": "NOASSERTION",
         "summary": f"Generated package for {recipe_dir.name}",
         "source_patches": [],
+        "extra_sources": [],
         "build_script": None,
         "noarch_python": False,
+        "noarch_generic": False,
+        "script_env": [],
+        "entry_points": [],
+        "test_commands": [],
+        "test_dep_specs_raw": [],
         "build_dep_specs_raw": [],
         "host_dep_specs_raw": [],
         "run_dep_specs_raw": [],
+        "optional_dep_specs_raw": [],
+        "recipe_maintainers": [],
+        "identifiers": [],
+        "additional_platforms": [],
+        "skip_lints": [],
+        "deprecated": "",
     }
 
 
+def deprecation_note(extra: dict[str, Any]) -> str:
+    notes = []
+    marker = extra.get("deprecated")
+    if marker is True:
+        notes.append("deprecated")
+    elif isinstance(marker, str) and marker.strip():
+        notes.append(f"deprecated: {marker.strip()}")
+    successor = extra.get("superseded-by") or extra.get("superseded_by")
+    if successor is not None and str(successor).strip():
+        notes.append(f"superseded by {str(successor).strip()}")
+    return "; ".join(notes)
+
+
 def emit(payload: dict[str, Any]) -> int:
     json.dump(payload, sys.stdout, sort_keys=True)
     sys.stdout.write("\n")
@@ -129,10 +219,16 @@ def main() -> int:
     source = meta.get_value("source", default={})
     payload["source_url"] = first_url(source)
     payload["source_folder"] = source_folder(source)
+    payload["source_sha256"] = source_digest(source, "sha256")
+    payload["source_md5"] = source_digest(source, "md5")
+    payload["source_git_depth"] = git_depth(source)
     payload["source_patches"] = source_patches(source)
+    payload["extra_sources"] = extra_sources(source)
 
     about = meta.get_value("about", default={}) or {}
     payload["homepage"] = str(about.get("home") or "").strip()
+    payload["dev_url"] = str(about.get("dev_url") or "").strip()
+    payload["doc_url"] = str(about.get("doc_url") or "").strip()
     payload["license"] = str(about.get("license") or "NOASSERTION").strip() or "NOASSERTION"
     payload["summary"] = (
         str(about.get("summary") or "").strip()
@@ -143,6 +239,11 @@ def main() -> int:
 
     noarch = meta.get_value("build/noarch", default=False)
     payload["noarch_python"] = str(noarch).strip().lower() == "python"
+    payload["noarch_generic"] = str(noarch).strip().lower() == "generic"
+    payload["script_env"] = normalize_list(meta.get_value("build/script_env", default=[]))
+    payload["entry_points"] = normalize_list(meta.get_value("build/entry_points", default=[]))
+    payload["test_commands"] = normalize_list(meta.get_value("test/commands", default=[]))
+    payload["test_dep_specs_raw"] = test_requires(meta)
 
     payload["build_dep_specs_raw"] = normalize_list(
         meta.get_value("requirements/build", default=[])
@@ -153,6 +254,17 @@ def main() -> int:
     payload["run_dep_specs_raw"] = normalize_list(
         meta.get_value("requirements/run", default=[])
     )
+    payload["optional_dep_specs_raw"] = normalize_list(
+        meta.get_value("requirements/run_constrained", default=[])
+    )
+
+    extra = meta.get_value("extra", default={}) or {}
+    if isinstance(extra, dict):
+        payload["recipe_maintainers"] = normalize_list(extra.get("recipe-maintainers"))
+        payload["identifiers"] = normalize_list(extra.get("identifiers"))
+        payload["additional_platforms"] = normalize_list(extra.get("additional-platforms"))
+        payload["skip_lints"] = normalize_list(extra.get("skip-lints"))
+        payload["deprecated"] = deprecation_note(extra)
 
     return emit(payload)
 
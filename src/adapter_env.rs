@@ -0,0 +1,288 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
+use crate::config::AdapterEnvConfig;
+use crate::priority_specs::log_external_progress;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Directory below the topdir holding the managed adapter venv.
+pub const ADAPTER_ENV_DIR: &str = "adapter-env";
+
+/// Prints `conda-build` and `conda` versions; fails when either is missing.
+const HEALTH_CHECK: &str = "import conda, conda_build, conda_build.api; \
+print(conda_build.__version__, conda.__version__)";
+
+/// `<topdir>/adapter-env/state.json`: what the venv was built from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AdapterEnvState {
+    /// `[adapter-env] requirements` the venv was created from.
+    pub requirements: Vec<String>,
+    /// `pip freeze` of the venv; reinstalled as is when the venv is recreated
+    /// with unchanged requirements.
+    pub frozen: Vec<String>,
+    pub conda_build_version: String,
+    pub conda_version: String,
+    pub created_at_utc: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdapterEnv {
+    pub python: PathBuf,
+    pub state: AdapterEnvState,
+    /// The venv was (re)created by this call.
+    pub created: bool,
+}
+
+fn env_root(topdir: &Path) -> PathBuf {
+    topdir.join(ADAPTER_ENV_DIR)
+}
+
+fn venv_python(venv: &Path) -> PathBuf {
+    venv.join("bin").join("python")
+}
+
+fn read_state(root: &Path) -> Option<AdapterEnvState> {
+    let raw = fs::read_to_string(root.join("state.json")).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Return the managed venv, creating it on first use and recreating it when
+/// the configured requirements changed or the health check fails.
+pub fn ensure_adapter_env(topdir: &Path, config: &AdapterEnvConfig) -> Result<AdapterEnv> {
+    let root = env_root(topdir);
+    let venv = root.join("venv");
+    let state = read_state(&root);
+    if let Some(state) = state.as_ref()
+        && state.requirements == config.requirements
+    {
+        match health_check(&venv_python(&venv)) {
+            Ok(_) => {
+                return Ok(AdapterEnv {
+                    python: venv_python(&venv),
+                    state: state.clone(),
+                    created: false,
+                });
+            }
+            Err(err) => log_external_progress(format!(
+                "phase=adapter-env status=unhealthy venv={} error={}",
+                venv.display(),
+                err
+            )),
+        }
+    }
+    // Unchanged requirements reinstall the frozen set, so a damaged venv comes
+    // back with the same versions.
+    let frozen = state
+        .filter(|state| state.requirements == config.requirements && !state.frozen.is_empty())
+        .map(|state| state.frozen);
+    create_adapter_env(topdir, config, frozen)
+}
+
+/// Rebuild the venv from the configured requirements, re-resolving every
+/// unpinned dependency (`adapter update`).
+pub fn update_adapter_env(topdir: &Path, config: &AdapterEnvConfig) -> Result<AdapterEnv> {
+    create_adapter_env(topdir, config, None)
+}
+
+/// Build a fresh venv next to the current one and swap it in once it passes
+/// the health check; a failed build leaves the current venv untouched.
+fn create_adapter_env(
+    topdir: &Path,
+    config: &AdapterEnvConfig,
+    frozen: Option<Vec<String>>,
+) -> Result<AdapterEnv> {
+    let root = env_root(topdir);
+    fs::create_dir_all(&root).with_context(|| format!("creating {}", root.display()))?;
+    let staging = root.join(format!("venv.tmp-{}", std::process::id()));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("removing stale {}", staging.display()))?;
+    }
+    let built = build_venv(&root, &staging, config, frozen.as_deref());
+    let (conda_build_version, conda_version, frozen) = match built {
+        Ok(built) => built,
+        Err(err) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(err);
+        }
+    };
+
+    let venv = root.join("venv");
+    let retired = root.join(format!("venv.old-{}", std::process::id()));
+    if venv.exists() {
+        fs::rename(&venv, &retired).with_context(|| format!("retiring {}", venv.display()))?;
+    }
+    fs::rename(&staging, &venv).with_context(|| format!("activating {}", venv.display()))?;
+    let _ = fs::remove_dir_all(&retired);
+
+    let state = AdapterEnvState {
+        requirements: config.requirements.clone(),
+        frozen,
+        conda_build_version,
+        conda_version,
+        created_at_utc: chrono::Utc::now().to_rfc3339(),
+    };
+    let payload = serde_json::to_string_pretty(&state).context("serializing adapter env state")?;
+    write_atomic(root.join("state.json"), payload)
+        .with_context(|| format!("writing {}/state.json", root.display()))?;
+    log_external_progress(format!(
+        "phase=adapter-env status=created venv={} conda_build={} conda={}",
+        venv.display(),
+        state.conda_build_version,
+        state.conda_version
+    ));
+    Ok(AdapterEnv {
+        python: venv_python(&venv),
+        state,
+        created: true,
+    })
+}
+
+/// Create the venv at `staging` and install the requirements. Only console
+/// script shebangs keep the staging path after the rename, and the adapter
+/// always runs `bin/python` directly.
+fn build_venv(
+    root: &Path,
+    staging: &Path,
+    config: &AdapterEnvConfig,
+    frozen: Option<&[String]>,
+) -> Result<(String, String, Vec<String>)> {
+    log_external_progress(format!(
+        "phase=adapter-env status=creating python={} venv={} requirements={}",
+        config.python,
+        staging.display(),
+        if frozen.is_some() {
+            "frozen"
+        } else {
+            "configured"
+        }
+    ));
+    run_checked(
+        Command::new(&config.python)
+            .arg("-m")
+            .arg("venv")
+            .arg(staging),
+        "creating adapter venv",
+    )?;
+    let python = venv_python(staging);
+    let requirements_file = root.join(format!("requirements.tmp-{}.txt", std::process::id()));
+    let lines = frozen.unwrap_or(&config.requirements).join("\n");
+    write_atomic(&requirements_file, format!("{lines}\n"))
+        .with_context(|| format!("writing {}", requirements_file.display()))?;
+    let installed = run_checked(
+        Command::new(&python)
+            .args([
+                "-m",
+                "pip",
+                "install",
+                "--disable-pip-version-check",
+                "--no-input",
+            ])
+            .arg("-r")
+            .arg(&requirements_file),
+        "installing adapter requirements",
+    );
+    let _ = fs::remove_file(&requirements_file);
+    installed?;
+    let (conda_build_version, conda_version) = health_check(&python)?;
+    let freeze = run_checked(
+        Command::new(&python).args(["-m", "pip", "freeze", "--disable-pip-version-check"]),
+        "freezing adapter requirements",
+    )?;
+    let frozen = freeze
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok((conda_build_version, conda_version, frozen))
+}
+
+/// Import conda-build in the venv; returns the conda-build and conda versions.
+pub fn health_check(python: &Path) -> Result<(String, String)> {
+    if !python.exists() {
+        bail!("{} does not exist", python.display());
+    }
+    let stdout = run_checked(
+        Command::new(python).arg("-c").arg(HEALTH_CHECK),
+        "adapter env health check",
+    )?;
+    let mut versions = stdout.split_whitespace();
+    match (versions.next(), versions.next()) {
+        (Some(conda_build), Some(conda)) => Ok((conda_build.to_string(), conda.to_string())),
+        _ => bail!("adapter env health check printed no versions: {stdout:?}"),
+    }
+}
+
+fn run_checked(command: &mut Command, what: &str) -> Result<String> {
+    let output = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .audited_output()
+        .with_context(|| format!("{what}: starting {:?}", command.get_program()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("")
+            .trim();
+        bail!("{what} failed ({}): {tail}", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Stand-in interpreter: `-m venv <dir>` creates `<dir>/bin/python` as a
+    /// copy of itself, `-m pip freeze` prints a pin, `-c` prints versions.
+    const FAKE_PYTHON: &str = "#!/bin/sh\n\
+case \"$1 $2\" in\n\
+  '-m venv') mkdir -p \"$3/bin\" && cp \"$0\" \"$3/bin/python\" ;;\n\
+  '-m pip') if [ \"$3\" = freeze ]; then echo 'conda-build==24.9.0'; fi ;;\n\
+  -c*) echo '24.9.0 24.9.2' ;;\n\
+esac\n";
+
+    #[test]
+    fn adapter_env_is_created_once_and_recreated_when_requirements_change() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let fake = tmp.path().join("python3");
+        fs::write(&fake, FAKE_PYTHON).expect("write fake python");
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755)).expect("chmod");
+        let topdir = tmp.path().join("topdir");
+        let mut config = AdapterEnvConfig {
+            managed: true,
+            python: fake.display().to_string(),
+            ..AdapterEnvConfig::default()
+        };
+
+        let created = ensure_adapter_env(&topdir, &config).expect("create env");
+        assert!(created.created);
+        assert_eq!(created.python, topdir.join("adapter-env/venv/bin/python"));
+        assert_eq!(created.state.conda_build_version, "24.9.0");
+        assert_eq!(
+            created.state.frozen,
+            vec!["conda-build==24.9.0".to_string()]
+        );
+
+        let reused = ensure_adapter_env(&topdir, &config).expect("reuse env");
+        assert!(!reused.created);
+
+        config.requirements = vec!["conda-build==24.11.0".to_string()];
+        let recreated = ensure_adapter_env(&topdir, &config).expect("recreate env");
+        assert!(recreated.created);
+        assert_eq!(recreated.state.requirements, config.requirements);
+        assert!(
+            update_adapter_env(&topdir, &config)
+                .expect("update")
+                .created
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temp files of concurrent writers within this process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Drop-in replacement for [`fs::write`] that never leaves a truncated file
+/// behind: `contents` go to a hidden temp file next to `path`, are fsynced and
+/// renamed over `path`, and the directory is fsynced so the rename survives a
+/// crash. Readers see either the old or the new file. An existing file keeps
+/// its permissions, so staged scripts stay executable.
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} has no file name", path.display()),
+        )
+    })?;
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = dir.join(format!(
+        ".{}.tmp-{}-{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        if let Ok(existing) = fs::metadata(path) {
+            file.set_permissions(existing.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written?;
+    // Not every filesystem supports fsync on a directory handle.
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn write_atomic_replaces_contents_and_keeps_permissions() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let script = tmp.path().join("build.sh");
+        write_atomic(&script, "#!/bin/bash\necho old\n").expect("first write");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        write_atomic(&script, "#!/bin/bash\necho new\n").expect("replace");
+        assert_eq!(
+            fs::read_to_string(&script).expect("read"),
+            "#!/bin/bash\necho new\n"
+        );
+        assert_eq!(
+            fs::metadata(&script).expect("stat").permissions().mode() & 0o777,
+            0o755
+        );
+        let leftovers = fs::read_dir(tmp.path())
+            .expect("read dir")
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != "build.sh")
+            .count();
+        assert_eq!(leftovers, 0);
+
+        assert!(write_atomic(tmp.path().join("missing/report.json"), "{}").is_err());
+        assert_eq!(
+            fs::read_dir(tmp.path()).expect("read dir").count(),
+            1,
+            "failed writes leave no temp file"
+        );
+    }
+}
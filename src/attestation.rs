@@ -0,0 +1,634 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
+use crate::catalog::file_sha256;
+use crate::config::AttestationConfig;
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory below the target root holding the statements, one
+/// `<package>/<version>/` dir per built package.
+pub const ATTESTATIONS_DIR: &str = "attestations";
+
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+const PREDICATE_TYPE_BASE: &str = "https://github.com/sagrudd/bioconda2rpm/attestation";
+
+/// Pipeline stages in chain order; each statement names the one before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Render,
+    Build,
+    Validate,
+    Publish,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Render => "render",
+            Stage::Build => "build",
+            Stage::Validate => "validate",
+            Stage::Publish => "publish",
+        }
+    }
+
+    pub fn previous(self) -> Option<Stage> {
+        match self {
+            Stage::Render => None,
+            Stage::Build => Some(Stage::Render),
+            Stage::Validate => Some(Stage::Build),
+            Stage::Publish => Some(Stage::Validate),
+        }
+    }
+
+    pub fn predicate_type(self) -> String {
+        format!("{PREDICATE_TYPE_BASE}/{}/v1", self.as_str())
+    }
+}
+
+/// A file by name and digest, as in in-toto subjects and materials.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResourceDescriptor {
+    pub name: String,
+    pub digest: BTreeMap<String, String>,
+}
+
+impl ResourceDescriptor {
+    /// Descriptor of `path` named relative to `root` when below it.
+    pub fn of_file(path: &Path, root: &Path) -> Result<Self> {
+        Ok(Self {
+            name: path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string(),
+            digest: BTreeMap::from([("sha256".to_string(), file_sha256(path)?)]),
+        })
+    }
+
+    pub fn sha256(&self) -> Option<&str> {
+        self.digest.get("sha256").map(String::as_str)
+    }
+}
+
+/// The statement a stage references as its predecessor.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PreviousStatement {
+    pub stage: String,
+    /// Relative to the attestations dir; absolute in statements written
+    /// before that.
+    pub path: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StagePredicate {
+    pub stage: String,
+    pub package: String,
+    pub version: String,
+    pub target_id: String,
+    pub finished_at_utc: String,
+    /// Inputs of the stage; from `build` on, outputs of the previous stage.
+    pub materials: Vec<ResourceDescriptor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<PreviousStatement>,
+    /// Stage-specific facts, e.g. the `validate` result or `publish` repository.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+}
+
+/// An in-toto v1 statement; signed by a detached `<statement>.sig`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject: Vec<ResourceDescriptor>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    pub predicate: StagePredicate,
+}
+
+/// What a stage attests to, before signing.
+#[derive(Debug, Clone)]
+pub struct StageRecord<'a> {
+    pub stage: Stage,
+    pub package: &'a str,
+    pub version: &'a str,
+    pub target_id: &'a str,
+    pub subjects: Vec<ResourceDescriptor>,
+    pub materials: Vec<ResourceDescriptor>,
+    pub details: BTreeMap<String, String>,
+}
+
+/// Writes and signs the statements of one target tree with the
+/// `[attestation] signing-key`.
+#[derive(Debug, Clone)]
+pub struct Attestor {
+    signing_key: PathBuf,
+    dir: PathBuf,
+}
+
+impl Attestor {
+    /// `None` when the config has no `signing-key`.
+    pub fn from_config(config: &AttestationConfig, target_root: &Path) -> Result<Option<Self>> {
+        let Some(signing_key) = config.signing_key.as_ref() else {
+            return Ok(None);
+        };
+        if !signing_key.is_file() {
+            bail!(
+                "attestation signing key {} does not exist",
+                signing_key.display()
+            );
+        }
+        Ok(Some(Self::new(signing_key.clone(), target_root)))
+    }
+
+    pub fn new(signing_key: PathBuf, target_root: &Path) -> Self {
+        Self {
+            signing_key,
+            dir: target_root.join(ATTESTATIONS_DIR),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Statement path of `stage` for a package. Every name carries a
+    /// timestamp: a version can be rebuilt and promoted more than once, and
+    /// each published RPM keeps the statements of the build it came from.
+    pub fn statement_path(&self, stage: Stage, package: &str, version: &str) -> PathBuf {
+        let dir = self.dir.join(package).join(version);
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S%.6fZ");
+        let mut path = dir.join(format!("{}-{stamp}.intoto.json", stage.as_str()));
+        let mut suffix = 1;
+        while path.exists() {
+            suffix += 1;
+            path = dir.join(format!("{}-{stamp}-{suffix}.intoto.json", stage.as_str()));
+        }
+        path
+    }
+
+    /// Write and sign the statement of `record`, linking it to `previous`,
+    /// the statement of the stage before. Returns the statement path.
+    pub fn attest(&self, record: StageRecord<'_>, previous: Option<&Path>) -> Result<PathBuf> {
+        let previous = match (record.stage.previous(), previous) {
+            (Some(stage), Some(path)) => Some(PreviousStatement {
+                stage: stage.as_str().to_string(),
+                path: path
+                    .strip_prefix(&self.dir)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string(),
+                sha256: file_sha256(path)?,
+            }),
+            (Some(stage), None) => bail!(
+                "{} statement of {} needs the {} statement",
+                record.stage.as_str(),
+                record.package,
+                stage.as_str()
+            ),
+            (None, _) => None,
+        };
+        let statement = Statement {
+            statement_type: STATEMENT_TYPE.to_string(),
+            subject: record.subjects,
+            predicate_type: record.stage.predicate_type(),
+            predicate: StagePredicate {
+                stage: record.stage.as_str().to_string(),
+                package: record.package.to_string(),
+                version: record.version.to_string(),
+                target_id: record.target_id.to_string(),
+                finished_at_utc: Utc::now().to_rfc3339(),
+                materials: record.materials,
+                previous,
+                details: record.details,
+            },
+        };
+        let path = self.statement_path(record.stage, record.package, record.version);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+        let payload = serde_json::to_string_pretty(&statement).context("serializing statement")?;
+        write_atomic(&path, payload).with_context(|| format!("writing {}", path.display()))?;
+        self.sign(&path)?;
+        Ok(path)
+    }
+
+    /// Newest `stage` statement of `package` whose subjects include every
+    /// digest in `digests`.
+    pub fn find_covering_statement(
+        &self,
+        stage: Stage,
+        package: &str,
+        digests: &BTreeSet<String>,
+    ) -> Result<Option<(PathBuf, Statement)>> {
+        let package_dir = self.dir.join(package);
+        if !package_dir.is_dir() {
+            return Ok(None);
+        }
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&package_dir)
+            .with_context(|| format!("reading {}", package_dir.display()))?
+        {
+            let version_dir = entry?.path();
+            if version_dir.is_dir() {
+                collect_statements(&version_dir, stage, false, &mut paths)?;
+            }
+        }
+        let mut best: Option<(PathBuf, Statement)> = None;
+        for path in paths {
+            let statement = read_statement(&path)?;
+            let produced: BTreeSet<&str> = statement
+                .subject
+                .iter()
+                .filter_map(ResourceDescriptor::sha256)
+                .collect();
+            if !digests
+                .iter()
+                .all(|digest| produced.contains(digest.as_str()))
+            {
+                continue;
+            }
+            if best.as_ref().is_none_or(|(_, current)| {
+                current.predicate.finished_at_utc < statement.predicate.finished_at_utc
+            }) {
+                best = Some((path, statement));
+            }
+        }
+        Ok(best)
+    }
+
+    fn sign(&self, statement: &Path) -> Result<()> {
+        let signature = signature_path(statement);
+        let output = Command::new("openssl")
+            .args(["dgst", "-sha256", "-sign"])
+            .arg(&self.signing_key)
+            .arg("-out")
+            .arg(&signature)
+            .arg(statement)
+            .audited_output()
+            .context("running openssl to sign an attestation")?;
+        if !output.status.success() {
+            bail!(
+                "signing {} failed: {}",
+                statement.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn signature_path(statement: &Path) -> PathBuf {
+    let mut name = statement.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+pub fn read_statement(path: &Path) -> Result<Statement> {
+    let raw = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("parsing statement {}", path.display()))
+}
+
+fn verify_signature(statement: &Path, public_key: &Path) -> Result<()> {
+    let signature = signature_path(statement);
+    if !signature.is_file() {
+        bail!(
+            "{} has no signature {}",
+            statement.display(),
+            signature.display()
+        );
+    }
+    let output = Command::new("openssl")
+        .args(["dgst", "-sha256", "-verify"])
+        .arg(public_key)
+        .arg("-signature")
+        .arg(&signature)
+        .arg(statement)
+        .audited_output()
+        .context("running openssl to verify an attestation")?;
+    if !output.status.success() {
+        bail!(
+            "signature of {} does not verify against {}",
+            statement.display(),
+            public_key.display()
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifiedLink {
+    pub stage: Stage,
+    pub statement: PathBuf,
+    pub package: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainVerification {
+    pub rpm_sha256: String,
+    /// Publish first, render last.
+    pub links: Vec<VerifiedLink>,
+}
+
+/// Check the chain of a published RPM: the newest publish statement listing
+/// its digest, then each previous statement in turn. Every statement must be
+/// signed by `public_key`, match the digest its successor recorded, and
+/// produce every material its successor consumed.
+pub fn verify_chain(
+    rpm: &Path,
+    attestations_dir: &Path,
+    public_key: &Path,
+) -> Result<ChainVerification> {
+    let rpm_sha256 = file_sha256(rpm)?;
+    let mut publish = Vec::new();
+    collect_statements(attestations_dir, Stage::Publish, true, &mut publish)?;
+    publish.sort();
+    let mut current = None;
+    for path in publish.into_iter().rev() {
+        let statement = read_statement(&path)?;
+        if statement
+            .subject
+            .iter()
+            .any(|subject| subject.sha256() == Some(rpm_sha256.as_str()))
+        {
+            current = Some((path, statement));
+            break;
+        }
+    }
+    let Some((mut path, mut statement)) = current else {
+        bail!(
+            "no publish statement under {} lists {} (sha256 {rpm_sha256})",
+            attestations_dir.display(),
+            rpm.display()
+        );
+    };
+
+    let mut links = Vec::new();
+    let mut stage = Stage::Publish;
+    loop {
+        verify_signature(&path, public_key)?;
+        if statement.statement_type != STATEMENT_TYPE
+            || statement.predicate_type != stage.predicate_type()
+            || statement.predicate.stage != stage.as_str()
+        {
+            bail!("{} is not a {} statement", path.display(), stage.as_str());
+        }
+        if stage == Stage::Validate
+            && let Some(result) = statement.predicate.details.get("result")
+            && result.starts_with("failed")
+        {
+            bail!("{} records a failed validation: {result}", path.display());
+        }
+        links.push(VerifiedLink {
+            stage,
+            statement: path.clone(),
+            package: statement.predicate.package.clone(),
+            version: statement.predicate.version.clone(),
+        });
+        let Some(previous_stage) = stage.previous() else {
+            break;
+        };
+        let Some(previous) = statement.predicate.previous.clone() else {
+            bail!(
+                "{} does not reference a {} statement",
+                path.display(),
+                previous_stage.as_str()
+            );
+        };
+        let previous_path = attestations_dir.join(&previous.path);
+        if previous.stage != previous_stage.as_str() {
+            bail!(
+                "{} references a {} statement, expected {}",
+                path.display(),
+                previous.stage,
+                previous_stage.as_str()
+            );
+        }
+        let digest = file_sha256(&previous_path)
+            .with_context(|| format!("{} references a missing statement", path.display()))?;
+        if digest != previous.sha256 {
+            bail!(
+                "{} changed after {} referenced it",
+                previous_path.display(),
+                path.display()
+            );
+        }
+        let previous_statement = read_statement(&previous_path)?;
+        let produced: BTreeSet<&str> = previous_statement
+            .subject
+            .iter()
+            .filter_map(ResourceDescriptor::sha256)
+            .collect();
+        if let Some(material) = statement
+            .predicate
+            .materials
+            .iter()
+            .find(|material| !material.sha256().is_some_and(|sha| produced.contains(sha)))
+        {
+            bail!(
+                "{} consumed {} which {} did not produce",
+                path.display(),
+                material.name,
+                previous_path.display()
+            );
+        }
+        path = previous_path;
+        statement = previous_statement;
+        stage = previous_stage;
+    }
+    Ok(ChainVerification { rpm_sha256, links })
+}
+
+/// Statements of `stage` in `dir`, and below it with `recursive`. Matches
+/// `<stage>-<utc>.intoto.json` and the `<stage>.intoto.json` of older builds.
+fn collect_statements(
+    dir: &Path,
+    stage: Stage,
+    recursive: bool,
+    out: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let fixed = format!("{}.intoto.json", stage.as_str());
+    let prefix = format!("{}-", stage.as_str());
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = entry
+            .with_context(|| format!("reading entry in {}", dir.display()))?
+            .path();
+        if path.is_dir() {
+            if recursive {
+                collect_statements(&path, stage, recursive, out)?;
+            }
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| {
+                name == fixed || (name.starts_with(&prefix) && name.ends_with(".intoto.json"))
+            })
+        {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record<'a>(
+        stage: Stage,
+        subjects: Vec<ResourceDescriptor>,
+        materials: Vec<ResourceDescriptor>,
+    ) -> StageRecord<'a> {
+        StageRecord {
+            stage,
+            package: "samtools",
+            version: "1.21",
+            target_id: "almalinux-9.7-x86_64",
+            subjects,
+            materials,
+            details: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn chain_verifies_from_published_rpm_back_to_render() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = tmp.path();
+        let key = root.join("key.pem");
+        let public_key = root.join("pub.pem");
+        assert!(
+            Command::new("openssl")
+                .args([
+                    "ecparam",
+                    "-name",
+                    "prime256v1",
+                    "-genkey",
+                    "-noout",
+                    "-out"
+                ])
+                .arg(&key)
+                .status()
+                .expect("openssl")
+                .success()
+        );
+        assert!(
+            Command::new("openssl")
+                .args(["ec", "-pubout", "-in"])
+                .arg(&key)
+                .arg("-out")
+                .arg(&public_key)
+                .status()
+                .expect("openssl")
+                .success()
+        );
+
+        let file = |name: &str, body: &str| {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+            fs::write(&path, body).expect("write");
+            ResourceDescriptor::of_file(&path, root).expect("digest")
+        };
+        let recipe = file("recipes/samtools/meta.yaml", "package: {name: samtools}");
+        let rebuilt_recipe = recipe.clone();
+        let spec = file(
+            "SPECS/phoreus-samtools-1.21.spec",
+            "Name: phoreus-samtools-1.21",
+        );
+        let rpm = file("RPMS/x86_64/phoreus-samtools-1.21-1.21-1.x86_64.rpm", "rpm");
+        let published = file(
+            "stable/x86_64/phoreus-samtools-1.21-1.21-1.x86_64.rpm",
+            "rpm",
+        );
+
+        let attestor = Attestor::new(key.clone(), root);
+        let render = attestor
+            .attest(
+                record(Stage::Render, vec![spec.clone()], vec![recipe]),
+                None,
+            )
+            .expect("render");
+        let build = attestor
+            .attest(
+                record(Stage::Build, vec![rpm.clone()], vec![spec]),
+                Some(&render),
+            )
+            .expect("build");
+        let mut validate = record(Stage::Validate, vec![rpm.clone()], vec![rpm.clone()]);
+        validate
+            .details
+            .insert("result".to_string(), "passed".to_string());
+        let validate = attestor.attest(validate, Some(&build)).expect("validate");
+        attestor
+            .attest(
+                record(Stage::Publish, vec![published], vec![rpm]),
+                Some(&validate),
+            )
+            .expect("publish");
+
+        let chain = verify_chain(
+            &root.join("stable/x86_64/phoreus-samtools-1.21-1.21-1.x86_64.rpm"),
+            attestor.dir(),
+            &public_key,
+        )
+        .expect("chain verifies");
+        assert_eq!(
+            chain
+                .links
+                .iter()
+                .map(|link| link.stage.as_str())
+                .collect::<Vec<_>>(),
+            vec!["publish", "validate", "build", "render"]
+        );
+
+        // A rebuild of the same version writes new statements and leaves the
+        // chain of the RPM already published intact.
+        let respec = file(
+            "SPECS/phoreus-samtools-1.21.spec",
+            "Name: phoreus-samtools-1.21\nRelease: 2",
+        );
+        let rerender = attestor
+            .attest(
+                record(Stage::Render, vec![respec.clone()], vec![rebuilt_recipe]),
+                None,
+            )
+            .expect("render again");
+        assert_ne!(rerender, render);
+        attestor
+            .attest(
+                record(Stage::Build, vec![respec.clone()], vec![respec]),
+                Some(&rerender),
+            )
+            .expect("build again");
+
+        // Statements name their predecessor relative to the attestations dir,
+        // so the chain survives moving it.
+        let moved = root.join("moved-attestations");
+        fs::rename(attestor.dir(), &moved).expect("move attestations");
+        let published_rpm = root.join("stable/x86_64/phoreus-samtools-1.21-1.21-1.x86_64.rpm");
+        let chain = verify_chain(&published_rpm, &moved, &public_key).expect("chain verifies");
+        assert_eq!(
+            chain.links[2].statement,
+            moved.join(build.strip_prefix(attestor.dir()).expect("relative"))
+        );
+
+        // Rewriting an earlier statement breaks the digest link to it.
+        let build = chain.links[2].statement.clone();
+        let tampered = fs::read_to_string(&build)
+            .expect("read build")
+            .replace("1.21", "1.22");
+        fs::write(&build, tampered).expect("tamper");
+        let err = verify_chain(&published_rpm, &moved, &public_key).expect_err("tampered chain");
+        assert!(format!("{err:#}").contains("changed after"));
+    }
+}
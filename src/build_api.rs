@@ -0,0 +1,265 @@
+use crate::build_lock;
+use crate::http_server::{Handler, HttpRequest, HttpResponse, serve_connections};
+use crate::priority_specs::log_external_progress;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/// Environment variable holding the bearer token `--api-listen` requires.
+pub const API_TOKEN_ENV: &str = "BIOCONDA2RPM_API_TOKEN";
+
+/// `POST /builds` body.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubmitBuildsBody {
+    packages: Vec<String>,
+}
+
+/// `POST /builds` answer.
+#[derive(Debug, Serialize)]
+struct SubmitBuildsResponse {
+    target_id: String,
+    owner_pid: u32,
+    queued_packages: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiError {
+    error: String,
+}
+
+struct BuildApi {
+    topdir: PathBuf,
+    target_id: String,
+    token: String,
+    accepting: Arc<AtomicBool>,
+}
+
+/// Keeps the API accepting submissions; dropping it answers later ones with
+/// `503`, so nothing is queued after the build stopped draining requests.
+pub struct BuildApiHandle {
+    pub addr: SocketAddr,
+    accepting: Arc<AtomicBool>,
+}
+
+impl Drop for BuildApiHandle {
+    fn drop(&mut self) {
+        self.accepting.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Bearer token from [`API_TOKEN_ENV`]; the API is never served without one.
+pub fn api_token_from_env() -> Result<String> {
+    let token = std::env::var(API_TOKEN_ENV).unwrap_or_default();
+    if token.trim().is_empty() {
+        bail!("--api-listen requires a bearer token in {API_TOKEN_ENV}");
+    }
+    Ok(token.trim().to_string())
+}
+
+/// Serve the build request API of the session owning `topdir` on `listen`.
+/// Accepted packages go through the same request file as forwarded `build`
+/// invocations, so the owner deduplicates and reruns them the same way.
+pub fn start(
+    listen: SocketAddr,
+    topdir: PathBuf,
+    target_id: String,
+    token: String,
+) -> Result<BuildApiHandle> {
+    let listener = TcpListener::bind(listen).with_context(|| format!("binding {listen}"))?;
+    let addr = listener.local_addr().unwrap_or(listen);
+    let accepting = Arc::new(AtomicBool::new(true));
+    let api = Arc::new(BuildApi {
+        topdir,
+        target_id,
+        token,
+        accepting: Arc::clone(&accepting),
+    });
+    let handler: Handler = Arc::new(move |request: &HttpRequest| api.handle(request));
+    thread::Builder::new()
+        .name("bioconda2rpm-build-api".to_string())
+        .spawn(move || serve_connections(listener, "build-api", handler))
+        .context("starting build API thread")?;
+    Ok(BuildApiHandle { addr, accepting })
+}
+
+impl BuildApi {
+    fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        if request.path != "/builds" {
+            return error("404 Not Found", &format!("no route {}", request.path));
+        }
+        if !request
+            .bearer_token()
+            .is_some_and(|token| tokens_match(token, &self.token))
+        {
+            return error("401 Unauthorized", "missing or invalid bearer token");
+        }
+        match request.method.as_str() {
+            "GET" | "HEAD" => match build_lock::lookup_build_runtime(&self.topdir) {
+                Ok(snapshot) => HttpResponse::json("200 OK", &snapshot),
+                Err(err) => error("500 Internal Server Error", &format!("{err:#}")),
+            },
+            "POST" => self.submit(request),
+            _ => error("405 Method Not Allowed", "use GET or POST"),
+        }
+    }
+
+    fn submit(&self, request: &HttpRequest) -> HttpResponse {
+        if !self.accepting.load(Ordering::SeqCst) {
+            return error(
+                "503 Service Unavailable",
+                "the build session no longer accepts requests",
+            );
+        }
+        let body: SubmitBuildsBody = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(err) => return error("400 Bad Request", &format!("invalid body: {err}")),
+        };
+        let packages = body
+            .packages
+            .iter()
+            .map(|package| package.trim().to_string())
+            .filter(|package| !package.is_empty())
+            .collect::<Vec<_>>();
+        if packages.is_empty() {
+            return error("400 Bad Request", "packages must name at least one package");
+        }
+        if let Some(invalid) = packages.iter().find(|package| !is_package_name(package)) {
+            return error(
+                "400 Bad Request",
+                &format!("'{invalid}' is not a package name of [A-Za-z0-9._+-]"),
+            );
+        }
+        let submitted_host = match request.peer {
+            Some(peer) => format!("api:{}", peer.ip()),
+            None => "api".to_string(),
+        };
+        if let Err(err) = build_lock::submit_build_request(
+            &self.topdir,
+            &self.target_id,
+            &packages,
+            &submitted_host,
+        ) {
+            return error("500 Internal Server Error", &format!("{err:#}"));
+        }
+        log_external_progress(format!(
+            "phase=build-api status=accepted target_id={} submit_host={} packages={}",
+            self.target_id,
+            submitted_host,
+            packages.join(",")
+        ));
+        HttpResponse::json(
+            "202 Accepted",
+            &SubmitBuildsResponse {
+                target_id: self.target_id.clone(),
+                owner_pid: std::process::id(),
+                queued_packages: packages,
+            },
+        )
+    }
+}
+
+fn error(status: &'static str, message: &str) -> HttpResponse {
+    HttpResponse::json(
+        status,
+        &ApiError {
+            error: message.to_string(),
+        },
+    )
+}
+
+/// Compares every byte, so the time taken does not reveal the matching prefix.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn is_package_name(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn request(addr: SocketAddr, method: &str, token: Option<&str>, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).expect("connect");
+        let auth = token
+            .map(|token| format!("Authorization: Bearer {token}\r\n"))
+            .unwrap_or_default();
+        write!(
+            stream,
+            "{method} /builds HTTP/1.1\r\nHost: test\r\n{auth}Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .expect("send request");
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).expect("read response");
+        raw.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn submitted_packages_join_the_forwarded_request_queue() {
+        let topdir = tempfile::tempdir().expect("tempdir");
+        let handle = start(
+            "127.0.0.1:0".parse().expect("addr"),
+            topdir.path().to_path_buf(),
+            "target-a".to_string(),
+            "s3cret".to_string(),
+        )
+        .expect("start api");
+        let addr = handle.addr;
+
+        let body = r#"{"packages": ["samtools", " bwa "]}"#;
+        assert_eq!(
+            request(addr, "POST", None, body),
+            "HTTP/1.1 401 Unauthorized"
+        );
+        assert_eq!(
+            request(addr, "POST", Some("s3cre7"), body),
+            "HTTP/1.1 401 Unauthorized"
+        );
+        assert_eq!(
+            request(
+                addr,
+                "POST",
+                Some("s3cret"),
+                r#"{"packages": ["bad name"]}"#
+            ),
+            "HTTP/1.1 400 Bad Request"
+        );
+        assert_eq!(
+            request(addr, "POST", Some("s3cret"), body),
+            "HTTP/1.1 202 Accepted"
+        );
+        assert_eq!(request(addr, "GET", Some("s3cret"), ""), "HTTP/1.1 200 OK");
+
+        let drained = build_lock::drain_forwarded_build_requests(topdir.path(), "target-a")
+            .expect("drain requests");
+        assert_eq!(
+            drained
+                .iter()
+                .map(|queued| queued.package.as_str())
+                .collect::<Vec<_>>(),
+            vec!["samtools", "bwa"]
+        );
+        assert_eq!(drained[0].submitted_host, "api:127.0.0.1");
+
+        drop(handle);
+        assert_eq!(
+            request(addr, "POST", Some("s3cret"), body),
+            "HTTP/1.1 503 Service Unavailable"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
 use anyhow::{Context, Result, bail};
 use fs2::FileExt;
 use serde::{Deserialize, Serialize};
@@ -5,16 +7,41 @@ use std::fs;
 use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 const LOCK_FILE_NAME: &str = ".bioconda2rpm-artifacts.lock";
 const STATE_FILE_NAME: &str = ".bioconda2rpm-active-builds.json";
 const REQUESTS_FILE_NAME: &str = ".bioconda2rpm-build-requests.jsonl";
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// How long `--takeover-stale-owner` waits for a signalled owner to release the lock.
+const TAKEOVER_WAIT: Duration = Duration::from_secs(60);
+
+/// Bumped by the build loop on every queue, package and container output
+/// event. Owners whose loop reports progress only refresh their heartbeat
+/// while it advances, so a deadlocked queue or a silent container reads as hung.
+static PROGRESS_MARKER: AtomicU64 = AtomicU64::new(0);
+
+/// Record that the owning session made progress since the last heartbeat.
+pub fn note_progress() {
+    PROGRESS_MARKER.fetch_add(1, Ordering::Relaxed);
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuildSessionKind {
     Build,
     GeneratePrioritySpecs,
     Regression,
+    Workspace,
+    Bisect,
+    Dev,
+    ExportBundle,
+    BootstrapPhoreus,
+    BackfillReports,
+    Gc,
+    Promote,
 }
 
 impl BuildSessionKind {
@@ -23,8 +50,29 @@ impl BuildSessionKind {
             BuildSessionKind::Build => "build",
             BuildSessionKind::GeneratePrioritySpecs => "generate-priority-specs",
             BuildSessionKind::Regression => "regression",
+            BuildSessionKind::Workspace => "workspace",
+            BuildSessionKind::Bisect => "bisect",
+            BuildSessionKind::Dev => "dev",
+            BuildSessionKind::ExportBundle => "export-bundle",
+            BuildSessionKind::BootstrapPhoreus => "bootstrap-phoreus",
+            BuildSessionKind::BackfillReports => "backfill-reports",
+            BuildSessionKind::Gc => "gc",
+            BuildSessionKind::Promote => "promote",
         }
     }
+
+    /// Sessions whose loop calls [`note_progress`]; the rest run a single
+    /// step and keep a plain timer heartbeat.
+    fn reports_progress(self) -> bool {
+        matches!(
+            self,
+            BuildSessionKind::Build
+                | BuildSessionKind::GeneratePrioritySpecs
+                | BuildSessionKind::Regression
+                | BuildSessionKind::Bisect
+                | BuildSessionKind::Dev
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +100,7 @@ pub struct LookupActiveBuildEntry {
     pub force_rebuild: bool,
     pub host: String,
     pub started_at_utc: String,
+    pub heartbeat_at_utc: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -74,6 +123,36 @@ pub struct BuildLookupSnapshot {
     pub updated_at_utc: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceLockStatus {
+    pub topdir: String,
+    pub lock_path: String,
+    pub lock_held: bool,
+    pub owner: Option<LookupActiveBuildEntry>,
+    pub owner_alive: Option<bool>,
+    pub heartbeat_age_seconds: Option<i64>,
+    pub stale: bool,
+    pub stale_reason: Option<String>,
+    pub queued_requests: usize,
+    pub checked_at_utc: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceLockBreak {
+    pub topdir: String,
+    pub previous_owner: Option<LookupActiveBuildEntry>,
+    pub stale_reason: Option<String>,
+    pub forced: bool,
+    pub removed_files: Vec<String>,
+}
+
+/// How forwarded clients treat an owner whose heartbeat stopped advancing.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatPolicy {
+    pub stale_after: Duration,
+    pub takeover: bool,
+}
+
 pub enum BuildAcquireOutcome {
     Owner(BuildSessionGuard),
     Forwarded(ForwardedBuildRequest),
@@ -91,6 +170,8 @@ struct ActiveBuildEntry {
     #[serde(default = "default_host_name")]
     host: String,
     started_at_utc: String,
+    #[serde(default)]
+    heartbeat_at_utc: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -114,6 +195,21 @@ pub struct BuildSessionGuard {
     requests_file: PathBuf,
     pid: u32,
     session_kind: BuildSessionKind,
+    recovered_stale: Option<String>,
+    heartbeat: Option<(mpsc::Sender<()>, thread::JoinHandle<()>)>,
+}
+
+enum WorkspaceLockAttempt {
+    Acquired {
+        lock_file: fs::File,
+        recovered_stale: Option<String>,
+    },
+    Busy,
+    /// The recorded owner is gone but another process, such as a child it
+    /// left running, still holds the lock.
+    Orphaned {
+        reason: String,
+    },
 }
 
 fn default_session_kind() -> String {
@@ -139,15 +235,7 @@ pub fn lookup_build_runtime(topdir: &Path) -> Result<BuildLookupSnapshot> {
     let active_entries = active_state
         .entries
         .into_iter()
-        .map(|entry| LookupActiveBuildEntry {
-            pid: entry.pid,
-            target_id: entry.target_id,
-            packages: entry.packages,
-            session_kind: entry.session_kind,
-            force_rebuild: entry.force_rebuild,
-            host: entry.host,
-            started_at_utc: entry.started_at_utc,
-        })
+        .map(LookupActiveBuildEntry::from)
         .collect::<Vec<_>>();
     let queued_requests = load_queued_requests(&requests_file)?;
     let (running_containers, container_probe_error) = probe_running_containers();
@@ -163,6 +251,98 @@ pub fn lookup_build_runtime(topdir: &Path) -> Result<BuildLookupSnapshot> {
     })
 }
 
+impl From<ActiveBuildEntry> for LookupActiveBuildEntry {
+    fn from(entry: ActiveBuildEntry) -> Self {
+        Self {
+            pid: entry.pid,
+            target_id: entry.target_id,
+            packages: entry.packages,
+            session_kind: entry.session_kind,
+            force_rebuild: entry.force_rebuild,
+            host: entry.host,
+            started_at_utc: entry.started_at_utc,
+            heartbeat_at_utc: entry.heartbeat_at_utc,
+        }
+    }
+}
+
+pub fn inspect_workspace_lock(
+    topdir: &Path,
+    heartbeat_stale_after: Duration,
+) -> Result<WorkspaceLockStatus> {
+    let lock_path = topdir.join(LOCK_FILE_NAME);
+    let state_file = topdir.join(STATE_FILE_NAME);
+    let requests_file = topdir.join(REQUESTS_FILE_NAME);
+    let lock_held = detect_lock_held(&lock_path)?;
+    let owner = load_state(&state_file)
+        .unwrap_or_default()
+        .entries
+        .into_iter()
+        .next();
+    let owner_alive = owner
+        .as_ref()
+        .filter(|entry| entry.host == current_host_name())
+        .map(|entry| process_is_alive(entry.pid));
+    let heartbeat_age_seconds = owner.as_ref().and_then(heartbeat_age_seconds);
+    let stale_reason = owner.as_ref().and_then(|entry| {
+        stale_owner_reason(entry, lock_held)
+            .or_else(|| heartbeat_stale_reason(entry, heartbeat_stale_after))
+    });
+    let queued_requests = load_queued_requests(&requests_file)?.len();
+
+    Ok(WorkspaceLockStatus {
+        topdir: topdir.to_string_lossy().to_string(),
+        lock_path: lock_path.to_string_lossy().to_string(),
+        lock_held,
+        owner: owner.map(LookupActiveBuildEntry::from),
+        owner_alive,
+        heartbeat_age_seconds,
+        stale: stale_reason.is_some(),
+        stale_reason,
+        queued_requests,
+        checked_at_utc: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+pub fn break_workspace_lock(
+    topdir: &Path,
+    heartbeat_stale_after: Duration,
+    force: bool,
+) -> Result<WorkspaceLockBreak> {
+    let status = inspect_workspace_lock(topdir, heartbeat_stale_after)?;
+    if status.lock_held && !status.stale && !force {
+        let owner = status
+            .owner
+            .as_ref()
+            .map(|entry| format!("pid={} host={}", entry.pid, entry.host))
+            .unwrap_or_else(|| "unknown".to_string());
+        bail!(
+            "workspace lock {} is held by a live owner ({}); re-run with --force to break it anyway",
+            status.lock_path,
+            owner
+        );
+    }
+
+    let mut removed_files = Vec::new();
+    for path in [topdir.join(LOCK_FILE_NAME), topdir.join(STATE_FILE_NAME)] {
+        match fs::remove_file(&path) {
+            Ok(()) => removed_files.push(path.to_string_lossy().to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("removing {}", path.display()));
+            }
+        }
+    }
+
+    Ok(WorkspaceLockBreak {
+        topdir: status.topdir,
+        previous_owner: status.owner,
+        stale_reason: status.stale_reason,
+        forced: force,
+        removed_files,
+    })
+}
+
 impl BuildSessionGuard {
     pub fn acquire(
         topdir: &Path,
@@ -177,15 +357,16 @@ impl BuildSessionGuard {
         let lock_path = topdir.join(LOCK_FILE_NAME);
         let state_file = topdir.join(STATE_FILE_NAME);
         let requests_file = topdir.join(REQUESTS_FILE_NAME);
-        let lock_file = fs::OpenOptions::new()
-            .create(true)
-            .read(true)
-            .write(true)
-            .open(&lock_path)
-            .with_context(|| format!("opening lock file {}", lock_path.to_string_lossy()))?;
-
-        if let Err(err) = lock_file.try_lock_exclusive() {
-            if err.kind() == ErrorKind::WouldBlock {
+
+        let (lock_file, recovered_stale) = match try_lock_workspace(&lock_path, &state_file)? {
+            WorkspaceLockAttempt::Acquired {
+                lock_file,
+                recovered_stale,
+            } => (lock_file, recovered_stale),
+            WorkspaceLockAttempt::Orphaned { reason } => {
+                bail!(orphaned_lock_message(&lock_path, &reason))
+            }
+            WorkspaceLockAttempt::Busy => {
                 let active = load_state(&state_file).unwrap_or_default();
                 let owner = active
                     .entries
@@ -207,10 +388,7 @@ impl BuildSessionGuard {
                     state_file.to_string_lossy()
                 );
             }
-            return Err(err).with_context(|| {
-                format!("acquiring workspace lock {}", lock_path.to_string_lossy())
-            });
-        }
+        };
         Self::initialize_locked_session(
             lock_file,
             lock_path.as_path(),
@@ -220,30 +398,58 @@ impl BuildSessionGuard {
             packages,
             session_kind,
             force_rebuild,
+            recovered_stale,
         )
     }
 
+    /// Reason the previous owner's lock was treated as stale and recovered, if any.
+    pub fn recovered_stale_owner(&self) -> Option<&str> {
+        self.recovered_stale.as_deref()
+    }
+
     pub fn acquire_or_forward_build(
         topdir: &Path,
         target_id: &str,
         packages: &[String],
         force_rebuild: bool,
+        heartbeat_policy: &HeartbeatPolicy,
     ) -> Result<BuildAcquireOutcome> {
         fs::create_dir_all(topdir)
             .with_context(|| format!("creating topdir {}", topdir.to_string_lossy()))?;
         let lock_path = topdir.join(LOCK_FILE_NAME);
         let state_file = topdir.join(STATE_FILE_NAME);
-        let lock_file = fs::OpenOptions::new()
-            .create(true)
-            .read(true)
-            .write(true)
-            .open(&lock_path)
-            .with_context(|| format!("opening lock file {}", lock_path.to_string_lossy()))?;
 
-        match lock_file.try_lock_exclusive() {
-            Ok(()) => {
+        let mut attempt = try_lock_workspace(&lock_path, &state_file)?;
+        if let WorkspaceLockAttempt::Orphaned { reason } = &attempt {
+            bail!(orphaned_lock_message(&lock_path, reason));
+        }
+        if matches!(attempt, WorkspaceLockAttempt::Busy) {
+            let active = load_state(&state_file).unwrap_or_default();
+            if let Some((owner, reason)) = active.entries.first().and_then(|owner| {
+                heartbeat_stale_reason(owner, heartbeat_policy.stale_after)
+                    .map(|reason| (owner, reason))
+            }) {
+                if !heartbeat_policy.takeover {
+                    bail!(
+                        "workspace build session owner looks hung: {} (state file: {}); re-run with --takeover-stale-owner or use `lock break`",
+                        reason,
+                        state_file.to_string_lossy()
+                    );
+                }
+                let lock_file = take_over_hung_owner(&lock_path, owner, TAKEOVER_WAIT)?;
+                attempt = WorkspaceLockAttempt::Acquired {
+                    lock_file,
+                    recovered_stale: Some(format!("{reason}; stopped with SIGTERM")),
+                };
+            }
+        }
+
+        match attempt {
+            WorkspaceLockAttempt::Acquired {
+                lock_file,
+                recovered_stale,
+            } => {
                 let requests_file = topdir.join(REQUESTS_FILE_NAME);
-                let state_file = topdir.join(STATE_FILE_NAME);
                 let guard = Self::initialize_locked_session(
                     lock_file,
                     lock_path.as_path(),
@@ -253,10 +459,12 @@ impl BuildSessionGuard {
                     packages,
                     BuildSessionKind::Build,
                     force_rebuild,
+                    recovered_stale,
                 )?;
                 Ok(BuildAcquireOutcome::Owner(guard))
             }
-            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+            WorkspaceLockAttempt::Orphaned { .. } => unreachable!("orphaned locks fail above"),
+            WorkspaceLockAttempt::Busy => {
                 let active = load_state(&state_file).unwrap_or_default();
                 let Some(owner) = active.entries.first() else {
                     bail!(
@@ -290,7 +498,7 @@ impl BuildSessionGuard {
                 if queued_packages.is_empty() {
                     bail!("no package names to submit to active build queue");
                 }
-                append_build_request(topdir, target_id, &queued_packages)?;
+                submit_build_request(topdir, target_id, &queued_packages, &current_host_name())?;
                 Ok(BuildAcquireOutcome::Forwarded(ForwardedBuildRequest {
                     owner_pid: owner.pid,
                     owner_target_id: owner.target_id.clone(),
@@ -298,9 +506,6 @@ impl BuildSessionGuard {
                     queued_packages,
                 }))
             }
-            Err(err) => Err(err).with_context(|| {
-                format!("acquiring workspace lock {}", lock_path.to_string_lossy())
-            }),
         }
     }
 
@@ -314,6 +519,7 @@ impl BuildSessionGuard {
         packages: &[String],
         session_kind: BuildSessionKind,
         force_rebuild: bool,
+        recovered_stale: Option<String>,
     ) -> Result<Self> {
         let pid = std::process::id();
         let entry = ActiveBuildEntry {
@@ -324,6 +530,7 @@ impl BuildSessionGuard {
             force_rebuild,
             host: current_host_name(),
             started_at_utc: chrono::Utc::now().to_rfc3339(),
+            heartbeat_at_utc: Some(chrono::Utc::now().to_rfc3339()),
         };
         let state = ActiveBuildState {
             entries: vec![entry],
@@ -339,18 +546,77 @@ impl BuildSessionGuard {
             .flush()
             .with_context(|| format!("flushing lock file {}", lock_path.to_string_lossy()))?;
 
+        let heartbeat = spawn_heartbeat(state_file.clone(), pid, session_kind.reports_progress());
         Ok(Self {
             lock_file,
             state_file,
             requests_file,
             pid,
             session_kind,
+            recovered_stale,
+            heartbeat,
+        })
+    }
+}
+
+fn spawn_heartbeat(
+    state_file: PathBuf,
+    pid: u32,
+    gated_on_progress: bool,
+) -> Option<(mpsc::Sender<()>, thread::JoinHandle<()>)> {
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let handle = thread::Builder::new()
+        .name("bioconda2rpm-heartbeat".to_string())
+        .spawn(move || {
+            let mut seen = PROGRESS_MARKER.load(Ordering::Relaxed);
+            while let Err(mpsc::RecvTimeoutError::Timeout) =
+                stop_rx.recv_timeout(HEARTBEAT_INTERVAL)
+            {
+                if gated_on_progress {
+                    let current = PROGRESS_MARKER.load(Ordering::Relaxed);
+                    let _ = refresh_heartbeat_on_progress(&state_file, pid, &mut seen, current);
+                } else {
+                    let _ = touch_heartbeat(&state_file, pid);
+                }
+            }
         })
+        .ok()?;
+    Some((stop_tx, handle))
+}
+
+/// Refresh the heartbeat when the progress marker moved past `seen`;
+/// returns whether it did.
+fn refresh_heartbeat_on_progress(
+    state_file: &Path,
+    pid: u32,
+    seen: &mut u64,
+    current: u64,
+) -> Result<bool> {
+    if current == *seen {
+        return Ok(false);
     }
+    *seen = current;
+    touch_heartbeat(state_file, pid)?;
+    Ok(true)
+}
+
+fn touch_heartbeat(state_file: &Path, pid: u32) -> Result<()> {
+    let _state_lock = lock_state(state_file)?;
+    let mut state = load_state(state_file)?;
+    let Some(entry) = state.entries.iter_mut().find(|entry| entry.pid == pid) else {
+        return Ok(());
+    };
+    entry.heartbeat_at_utc = Some(chrono::Utc::now().to_rfc3339());
+    write_state_locked(state_file, &state)
 }
 
 impl Drop for BuildSessionGuard {
     fn drop(&mut self) {
+        if let Some((stop_tx, handle)) = self.heartbeat.take() {
+            drop(stop_tx);
+            let _ = handle.join();
+        }
+        let state_lock = lock_state(&self.state_file).ok();
         let mut state = load_state(&self.state_file).unwrap_or_default();
         state.entries.retain(|entry| entry.pid != self.pid);
         if state.entries.is_empty() {
@@ -359,8 +625,9 @@ impl Drop for BuildSessionGuard {
                 let _ = fs::remove_file(&self.requests_file);
             }
         } else {
-            let _ = write_state(&self.state_file, &state);
+            let _ = write_state_locked(&self.state_file, &state);
         }
+        drop(state_lock);
         let _ = self.lock_file.unlock();
     }
 }
@@ -455,6 +722,162 @@ fn load_state(path: &Path) -> Result<ActiveBuildState> {
         .with_context(|| format!("parsing active build state {}", path.to_string_lossy()))
 }
 
+fn open_lock_file(lock_path: &Path) -> Result<fs::File> {
+    fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(false)
+        .open(lock_path)
+        .with_context(|| format!("opening lock file {}", lock_path.to_string_lossy()))
+}
+
+fn try_lock_workspace(lock_path: &Path, state_file: &Path) -> Result<WorkspaceLockAttempt> {
+    let lock_file = open_lock_file(lock_path)?;
+    match lock_file.try_lock_exclusive() {
+        Ok(()) => {
+            // Any state left behind while nobody held the lock belongs to an owner that
+            // exited without running its cleanup.
+            let recovered_stale = load_state(state_file)
+                .unwrap_or_default()
+                .entries
+                .first()
+                .and_then(|owner| stale_owner_reason(owner, false));
+            Ok(WorkspaceLockAttempt::Acquired {
+                lock_file,
+                recovered_stale,
+            })
+        }
+        Err(err) if err.kind() == ErrorKind::WouldBlock => {
+            let active = load_state(state_file).unwrap_or_default();
+            let Some(reason) = active
+                .entries
+                .first()
+                .and_then(|owner| stale_owner_reason(owner, true))
+            else {
+                return Ok(WorkspaceLockAttempt::Busy);
+            };
+            // The recorded owner is gone but the lock is still held, for example by an
+            // orphaned child. The lock file is never unlinked: a fresh inode would let
+            // two starters both win, and leave the holder running unguarded.
+            Ok(WorkspaceLockAttempt::Orphaned { reason })
+        }
+        Err(err) => Err(err)
+            .with_context(|| format!("acquiring workspace lock {}", lock_path.to_string_lossy())),
+    }
+}
+
+/// Stop a hung owner on this host with SIGTERM and lock the workspace once it
+/// has released the lock. The lock is never taken while the owner holds it.
+fn take_over_hung_owner(
+    lock_path: &Path,
+    owner: &ActiveBuildEntry,
+    wait: Duration,
+) -> Result<fs::File> {
+    if owner.host != current_host_name() {
+        bail!(
+            "cannot take over owner pid={} on host {} from {}; stop it there and retry",
+            owner.pid,
+            owner.host,
+            current_host_name()
+        );
+    }
+    if owner.pid == std::process::id() {
+        bail!("cannot take over the workspace from its own owner process");
+    }
+    if process_is_alive(owner.pid) {
+        let status = Command::new("kill")
+            .args(["-TERM", &owner.pid.to_string()])
+            .audited_status()
+            .context("running kill")?;
+        if !status.success() && process_is_alive(owner.pid) {
+            bail!(
+                "cannot take over owner pid={}: kill -TERM failed",
+                owner.pid
+            );
+        }
+    }
+    let lock_file = open_lock_file(lock_path)?;
+    let deadline = Instant::now() + wait;
+    loop {
+        match lock_file.try_lock_exclusive() {
+            Ok(()) => return Ok(lock_file),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("acquiring workspace lock {}", lock_path.display()));
+            }
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "cannot take over owner pid={}: workspace lock {} is still held {}s after SIGTERM; stop the owner (kill -9) and retry",
+                owner.pid,
+                lock_path.display(),
+                wait.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+fn orphaned_lock_message(lock_path: &Path, reason: &str) -> String {
+    format!(
+        "{reason}, but workspace lock {} is still held, likely by a process it left running; stop that process (`fuser {}` lists it) and retry",
+        lock_path.display(),
+        lock_path.display()
+    )
+}
+
+fn stale_owner_reason(owner: &ActiveBuildEntry, lock_held: bool) -> Option<String> {
+    if !lock_held {
+        return Some(format!(
+            "owner pid={} host={} released the workspace lock without clearing its state",
+            owner.pid, owner.host
+        ));
+    }
+    if owner.host == current_host_name() && !process_is_alive(owner.pid) {
+        return Some(format!(
+            "owner pid={} is no longer running on host {}",
+            owner.pid, owner.host
+        ));
+    }
+    None
+}
+
+fn heartbeat_age_seconds(owner: &ActiveBuildEntry) -> Option<i64> {
+    let heartbeat = owner.heartbeat_at_utc.as_deref()?;
+    let at = chrono::DateTime::parse_from_rfc3339(heartbeat).ok()?;
+    Some((chrono::Utc::now() - at.with_timezone(&chrono::Utc)).num_seconds())
+}
+
+/// Owners written before heartbeats existed carry no timestamp and are never judged hung.
+fn heartbeat_stale_reason(owner: &ActiveBuildEntry, stale_after: Duration) -> Option<String> {
+    let age = heartbeat_age_seconds(owner)?;
+    if age < 0 || (age as u64) < stale_after.as_secs() {
+        return None;
+    }
+    Some(format!(
+        "owner pid={} host={} heartbeat is {}s old (stale after {}s)",
+        owner.pid,
+        owner.host,
+        age,
+        stale_after.as_secs()
+    ))
+}
+
+fn process_is_alive(pid: u32) -> bool {
+    let proc_root = Path::new("/proc");
+    if proc_root.is_dir() {
+        return proc_root.join(pid.to_string()).exists();
+    }
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .audited_status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
 fn detect_lock_held(lock_path: &Path) -> Result<bool> {
     let Some(parent) = lock_path.parent() else {
         return Ok(false);
@@ -514,7 +937,7 @@ fn load_queued_requests(path: &Path) -> Result<Vec<LookupQueuedBuildRequest>> {
 fn probe_running_containers() -> (Vec<String>, Option<String>) {
     let output = Command::new("docker")
         .args(["ps", "--format", "{{.Names}}"])
-        .output();
+        .audited_output();
     let Ok(output) = output else {
         return (Vec::new(), Some("docker command unavailable".to_string()));
     };
@@ -536,17 +959,44 @@ fn probe_running_containers() -> (Vec<String>, Option<String>) {
     (containers, None)
 }
 
+/// Lock `<state file>.lock`, which serialises writers and read-modify-write
+/// cycles of the state file; the state file itself is replaced on every
+/// write and cannot carry the lock. Released when the returned file drops.
+fn lock_state(path: &Path) -> Result<fs::File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("opening active build state lock {}", lock_path.display()))?;
+    file.lock_exclusive()
+        .with_context(|| format!("locking active build state lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
 fn write_state(path: &Path, state: &ActiveBuildState) -> Result<()> {
-    let tmp = path.with_extension("tmp");
+    let _state_lock = lock_state(path)?;
+    write_state_locked(path, state)
+}
+
+fn write_state_locked(path: &Path, state: &ActiveBuildState) -> Result<()> {
     let payload = serde_json::to_vec_pretty(state).context("serializing active build state")?;
-    fs::write(&tmp, payload)
-        .with_context(|| format!("writing active build temp state {}", tmp.to_string_lossy()))?;
-    fs::rename(&tmp, path)
-        .with_context(|| format!("committing active build state {}", path.to_string_lossy()))?;
-    Ok(())
+    write_atomic(path, payload)
+        .with_context(|| format!("writing active build state {}", path.to_string_lossy()))
 }
 
-fn append_build_request(topdir: &Path, target_id: &str, packages: &[String]) -> Result<()> {
+/// Queue `packages` for the build session owning `topdir`, as a forwarded
+/// `build` does; the owner picks them up with
+/// [`drain_forwarded_build_requests`].
+pub fn submit_build_request(
+    topdir: &Path,
+    target_id: &str,
+    packages: &[String],
+    submitted_host: &str,
+) -> Result<()> {
     let requests_file = topdir.join(REQUESTS_FILE_NAME);
     let mut file = fs::OpenOptions::new()
         .create(true)
@@ -561,7 +1011,7 @@ fn append_build_request(topdir: &Path, target_id: &str, packages: &[String]) ->
         pid: std::process::id(),
         target_id: target_id.to_string(),
         packages: packages.to_vec(),
-        submitted_host: current_host_name(),
+        submitted_host: submitted_host.to_string(),
         submitted_at_utc: chrono::Utc::now().to_rfc3339(),
     };
     let payload = serde_json::to_string(&request).context("serializing build queue request")?;
@@ -668,6 +1118,231 @@ mod tests {
         let _ = fs::remove_dir_all(&topdir);
     }
 
+    const STALE_AFTER: Duration = Duration::from_secs(600);
+
+    fn seed_owner_state(topdir: &Path, pid: u32) {
+        write_state(
+            &topdir.join(STATE_FILE_NAME),
+            &ActiveBuildState {
+                entries: vec![ActiveBuildEntry {
+                    pid,
+                    target_id: "target-a".to_string(),
+                    packages: vec!["samtools".to_string()],
+                    session_kind: BuildSessionKind::Build.as_str().to_string(),
+                    force_rebuild: false,
+                    host: current_host_name(),
+                    started_at_utc: "2026-03-02T00:00:00Z".to_string(),
+                    heartbeat_at_utc: None,
+                }],
+            },
+        )
+        .expect("write state");
+    }
+
+    /// A separate process holding the workspace lock, like a child an owner
+    /// left running.
+    fn hold_lock_in_child(lock_path: &Path) -> std::process::Child {
+        // `--close` keeps the lock in flock itself, so killing it releases the lock.
+        let child = Command::new("flock")
+            .arg("--close")
+            .arg(lock_path)
+            .args(["sleep", "60"])
+            .spawn()
+            .expect("spawn flock");
+        while !detect_lock_held(lock_path).expect("probe lock") {
+            thread::sleep(Duration::from_millis(20));
+        }
+        child
+    }
+
+    #[test]
+    fn inspect_and_acquire_recover_state_left_by_dead_owner() {
+        let topdir = tempdir("stale-owner");
+        seed_owner_state(&topdir, u32::MAX);
+
+        let status = inspect_workspace_lock(&topdir, STALE_AFTER).expect("inspect lock");
+        assert!(!status.lock_held);
+        assert!(status.stale);
+        assert_eq!(status.owner.as_ref().map(|owner| owner.pid), Some(u32::MAX));
+
+        let outcome = BuildSessionGuard::acquire_or_forward_build(
+            &topdir,
+            "target-a",
+            &["bcftools".to_string()],
+            false,
+            &HeartbeatPolicy {
+                stale_after: STALE_AFTER,
+                takeover: false,
+            },
+        )
+        .expect("acquire after stale owner");
+        let BuildAcquireOutcome::Owner(guard) = outcome else {
+            panic!("stale owner must not receive forwarded requests");
+        };
+        assert!(guard.recovered_stale_owner().is_some());
+        let status = inspect_workspace_lock(&topdir, STALE_AFTER).expect("inspect recovered lock");
+        assert!(status.lock_held);
+        assert!(!status.stale);
+        assert_eq!(status.owner_alive, Some(true));
+        drop(guard);
+
+        // A dead owner whose lock is still held is reported, and the lock file is
+        // left alone.
+        use std::os::unix::fs::MetadataExt;
+        let lock_path = topdir.join(LOCK_FILE_NAME);
+        let mut orphan = hold_lock_in_child(&lock_path);
+        let inode = fs::metadata(&lock_path).expect("lock metadata").ino();
+        seed_owner_state(&topdir, u32::MAX);
+        let policy = HeartbeatPolicy {
+            stale_after: STALE_AFTER,
+            takeover: true,
+        };
+        let Err(err) = BuildSessionGuard::acquire_or_forward_build(
+            &topdir,
+            "target-a",
+            &["bcftools".to_string()],
+            false,
+            &policy,
+        ) else {
+            panic!("an orphan holding the lock must keep it");
+        };
+        assert!(err.to_string().contains("is still held"));
+        assert_eq!(
+            fs::metadata(&lock_path).expect("lock metadata").ino(),
+            inode
+        );
+        orphan.kill().expect("kill orphan");
+        orphan.wait().expect("reap orphan");
+        let outcome = BuildSessionGuard::acquire_or_forward_build(
+            &topdir,
+            "target-a",
+            &["bcftools".to_string()],
+            false,
+            &policy,
+        )
+        .expect("acquire after the orphan exits");
+        assert!(matches!(outcome, BuildAcquireOutcome::Owner(_)));
+        drop(outcome);
+
+        let _ = fs::remove_dir_all(&topdir);
+    }
+
+    #[test]
+    fn hung_owner_is_reported_and_taken_over_on_request() {
+        let topdir = tempdir("hung-owner");
+        let owner = BuildSessionGuard::acquire(
+            &topdir,
+            "target-a",
+            &["samtools".to_string()],
+            BuildSessionKind::Build,
+            false,
+        )
+        .expect("acquire lock");
+        let state_file = topdir.join(STATE_FILE_NAME);
+        let mut state = load_state(&state_file).expect("load state");
+        assert!(state.entries[0].heartbeat_at_utc.is_some());
+        state.entries[0].heartbeat_at_utc = Some("2026-03-02T00:00:00Z".to_string());
+        write_state(&state_file, &state).expect("age heartbeat");
+        let mut seen = 7;
+        assert!(
+            !refresh_heartbeat_on_progress(&state_file, owner.pid, &mut seen, 7)
+                .expect("idle heartbeat tick")
+        );
+
+        let status = inspect_workspace_lock(&topdir, STALE_AFTER).expect("inspect lock");
+        assert!(status.lock_held);
+        assert!(status.stale);
+        assert!(status.heartbeat_age_seconds.unwrap_or_default() > 600);
+
+        assert!(
+            refresh_heartbeat_on_progress(&state_file, owner.pid, &mut seen, 8)
+                .expect("progress heartbeat tick")
+        );
+        assert_eq!(seen, 8);
+        let status = inspect_workspace_lock(&topdir, STALE_AFTER).expect("inspect lock");
+        assert!(!status.stale);
+        write_state(&state_file, &state).expect("age heartbeat again");
+
+        let packages = ["bcftools".to_string()];
+        let Err(err) = BuildSessionGuard::acquire_or_forward_build(
+            &topdir,
+            "target-a",
+            &packages,
+            false,
+            &HeartbeatPolicy {
+                stale_after: STALE_AFTER,
+                takeover: false,
+            },
+        ) else {
+            panic!("hung owner must not receive forwarded requests");
+        };
+        assert!(err.to_string().contains("looks hung"));
+        assert!(!topdir.join(REQUESTS_FILE_NAME).exists());
+
+        let takeover = HeartbeatPolicy {
+            stale_after: STALE_AFTER,
+            takeover: true,
+        };
+        let Err(err) = BuildSessionGuard::acquire_or_forward_build(
+            &topdir, "target-a", &packages, false, &takeover,
+        ) else {
+            panic!("the owner process cannot take itself over");
+        };
+        assert!(err.to_string().contains("cannot take over"));
+        drop(owner);
+
+        // A hung owner in another process is stopped, and its lock is only taken
+        // once it has released it.
+        let lock_path = topdir.join(LOCK_FILE_NAME);
+        let mut hung = hold_lock_in_child(&lock_path);
+        state.entries[0].pid = hung.id();
+        write_state(&state_file, &state).expect("seed hung owner");
+        let outcome = BuildSessionGuard::acquire_or_forward_build(
+            &topdir, "target-a", &packages, false, &takeover,
+        )
+        .expect("take over hung owner");
+        let BuildAcquireOutcome::Owner(guard) = outcome else {
+            panic!("takeover should become the owner");
+        };
+        assert!(
+            guard
+                .recovered_stale_owner()
+                .is_some_and(|reason| reason.contains("heartbeat") && reason.contains("SIGTERM"))
+        );
+        assert!(!hung.wait().expect("reap hung owner").success());
+        drop(guard);
+
+        let _ = fs::remove_dir_all(&topdir);
+    }
+
+    #[test]
+    fn break_workspace_lock_requires_force_for_live_owner() {
+        let topdir = tempdir("break-live");
+        let guard = BuildSessionGuard::acquire(
+            &topdir,
+            "target-a",
+            &["samtools".to_string()],
+            BuildSessionKind::Build,
+            false,
+        )
+        .expect("acquire lock");
+
+        let err =
+            break_workspace_lock(&topdir, STALE_AFTER, false).expect_err("live owner must be kept");
+        assert!(err.to_string().contains("--force"));
+
+        let broken = break_workspace_lock(&topdir, STALE_AFTER, true).expect("forced break");
+        assert!(broken.forced);
+        assert_eq!(
+            broken.previous_owner.map(|owner| owner.pid),
+            Some(std::process::id())
+        );
+        assert!(!topdir.join(STATE_FILE_NAME).exists());
+        drop(guard);
+
+        let _ = fs::remove_dir_all(&topdir);
+    }
+
     #[test]
     fn lookup_build_runtime_reports_active_and_queued_state() {
         let topdir = tempdir("lookup-runtime");
@@ -685,6 +1360,7 @@ mod tests {
                     force_rebuild: false,
                     host: "host-a".to_string(),
                     started_at_utc: "2026-03-02T00:00:00Z".to_string(),
+                    heartbeat_at_utc: None,
                 }],
             },
         )
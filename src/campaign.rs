@@ -0,0 +1,685 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
+use crate::build_lock;
+use crate::cli::{CampaignArgs, Cli, Command, default_topdir};
+use crate::priority_specs::log_external_progress;
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use clap::Parser;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const CAMPAIGN_LOCK_FILE_NAME: &str = ".bioconda2rpm-campaign.lock";
+/// Same default as `lock status --heartbeat-stale-minutes`.
+const WORKSPACE_STALE_AFTER: Duration = Duration::from_secs(600);
+
+/// Final summary line of the command that ran last in this process.
+static STEP_SUMMARY: Mutex<Option<String>> = Mutex::new(None);
+
+/// Remember a command's final `key=value` summary line for the campaign rollup.
+pub fn record_step_summary(line: &str) {
+    if let Ok(mut slot) = STEP_SUMMARY.lock() {
+        *slot = Some(line.to_string());
+    }
+}
+
+fn take_step_summary() -> Option<String> {
+    STEP_SUMMARY.lock().ok().and_then(|mut slot| slot.take())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CampaignSpec {
+    name: String,
+    #[serde(default)]
+    topdir: Option<PathBuf>,
+    steps: Vec<CampaignStepSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CampaignStepSpec {
+    name: String,
+    /// bioconda2rpm subcommand and arguments, e.g. `[regression, --mode, pr]`.
+    #[serde(default)]
+    command: Vec<String>,
+    /// External program and arguments, e.g. a publish or notification hook.
+    #[serde(default)]
+    run: Vec<String>,
+    #[serde(default)]
+    when: StepCondition,
+}
+
+/// When a step runs, relative to the outcome of the steps before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StepCondition {
+    #[default]
+    OnSuccess,
+    OnFailure,
+    Always,
+}
+
+impl StepCondition {
+    fn as_str(self) -> &'static str {
+        match self {
+            StepCondition::OnSuccess => "on-success",
+            StepCondition::OnFailure => "on-failure",
+            StepCondition::Always => "always",
+        }
+    }
+
+    fn applies(self, failed_so_far: bool) -> bool {
+        match self {
+            StepCondition::OnSuccess => !failed_so_far,
+            StepCondition::OnFailure => failed_so_far,
+            StepCondition::Always => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignStepResult {
+    pub name: String,
+    /// `command` (bioconda2rpm subcommand) or `run` (external program).
+    pub kind: String,
+    pub argv: Vec<String>,
+    pub when: StepCondition,
+    /// `succeeded`, `failed`, `skipped` or `lock-busy`.
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub detail: Option<String>,
+    pub started_at_utc: Option<String>,
+    pub duration_secs: f64,
+    /// Fields of the step's final `key=value` summary line.
+    pub summary: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignReport {
+    pub name: String,
+    pub spec_path: String,
+    pub topdir: String,
+    /// `running` until the last step finished, then `succeeded` or `failed`.
+    pub status: String,
+    pub started_at_utc: String,
+    pub finished_at_utc: Option<String>,
+    pub steps_total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub packages_requested: usize,
+    pub kpi_denominator: usize,
+    pub kpi_successes: usize,
+    pub kpi_success_rate: Option<f64>,
+    pub steps: Vec<CampaignStepResult>,
+}
+
+#[derive(Debug)]
+pub struct CampaignSummary {
+    pub name: String,
+    pub status: String,
+    pub steps: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub report_json: PathBuf,
+    pub report_md: PathBuf,
+}
+
+/// A validated step with its bioconda2rpm arguments already parsed.
+struct PlannedStep {
+    spec: CampaignStepSpec,
+    argv: Vec<String>,
+    command: Option<Command>,
+}
+
+/// Run every step of the campaign in `args.spec` in order. bioconda2rpm steps
+/// are executed in this process through `run_step`, so each one takes and
+/// releases the workspace lock itself and never forwards to a sibling step.
+pub fn run_campaign(
+    args: &CampaignArgs,
+    mut run_step: impl FnMut(Command) -> ExitCode,
+) -> Result<CampaignSummary> {
+    let spec = load_campaign_spec(&args.spec)?;
+    let topdir = args
+        .topdir
+        .clone()
+        .or_else(|| spec.topdir.clone())
+        .unwrap_or_else(default_topdir);
+    let plan = plan_campaign(&spec, &topdir)?;
+    let reports_dir = args
+        .reports_dir
+        .clone()
+        .unwrap_or_else(|| topdir.join("campaigns"));
+    let slug = campaign_slug(&spec.name);
+    let report_json = reports_dir.join(format!("campaign_{slug}.json"));
+    let report_md = reports_dir.join(format!("campaign_{slug}.md"));
+
+    if args.dry_run {
+        for (idx, step) in plan.iter().enumerate() {
+            println!(
+                "campaign-plan step={} name={} kind={} when={} argv={}",
+                idx + 1,
+                step.spec.name,
+                if step.command.is_some() {
+                    "command"
+                } else {
+                    "run"
+                },
+                step.spec.when.as_str(),
+                step.argv.join(" ")
+            );
+        }
+        return Ok(CampaignSummary {
+            name: spec.name,
+            status: "planned".to_string(),
+            steps: plan.len(),
+            succeeded: 0,
+            failed: 0,
+            skipped: 0,
+            report_json,
+            report_md,
+        });
+    }
+
+    fs::create_dir_all(&topdir)
+        .with_context(|| format!("creating campaign topdir {}", topdir.display()))?;
+    fs::create_dir_all(&reports_dir)
+        .with_context(|| format!("creating campaign reports dir {}", reports_dir.display()))?;
+    let lock_path = topdir.join(CAMPAIGN_LOCK_FILE_NAME);
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("opening campaign lock {}", lock_path.display()))?;
+    if lock_file.try_lock_exclusive().is_err() {
+        bail!(
+            "another campaign is already running in {} (lock file: {})",
+            topdir.display(),
+            lock_path.display()
+        );
+    }
+
+    let mut report = CampaignReport {
+        name: spec.name.clone(),
+        spec_path: args.spec.display().to_string(),
+        topdir: topdir.display().to_string(),
+        status: "running".to_string(),
+        started_at_utc: Utc::now().to_rfc3339(),
+        finished_at_utc: None,
+        steps_total: plan.len(),
+        succeeded: 0,
+        failed: 0,
+        skipped: 0,
+        packages_requested: 0,
+        kpi_denominator: 0,
+        kpi_successes: 0,
+        kpi_success_rate: None,
+        steps: Vec::new(),
+    };
+    log_external_progress(format!(
+        "phase=campaign status=started name={} steps={} topdir={} pid={}",
+        spec.name,
+        plan.len(),
+        topdir.display(),
+        process::id()
+    ));
+
+    for (idx, step) in plan.into_iter().enumerate() {
+        let failed_so_far = report.failed > 0;
+        let kind = if step.command.is_some() {
+            "command"
+        } else {
+            "run"
+        };
+        let mut result = CampaignStepResult {
+            name: step.spec.name.clone(),
+            kind: kind.to_string(),
+            argv: step.argv.clone(),
+            when: step.spec.when,
+            status: "skipped".to_string(),
+            exit_code: None,
+            detail: None,
+            started_at_utc: None,
+            duration_secs: 0.0,
+            summary: BTreeMap::new(),
+        };
+        if !step.spec.when.applies(failed_so_far) {
+            result.detail = Some(format!(
+                "when={} and campaign has {} failed step(s)",
+                step.spec.when.as_str(),
+                report.failed
+            ));
+        } else {
+            log_external_progress(format!(
+                "phase=campaign-step status=started campaign={} step={}/{} name={} kind={}",
+                spec.name,
+                idx + 1,
+                report.steps_total,
+                step.spec.name,
+                kind
+            ));
+            result.started_at_utc = Some(Utc::now().to_rfc3339());
+            let started = Instant::now();
+            match step.command {
+                Some(command) => run_command_step(command, &mut result, &mut run_step),
+                None => {
+                    // Hooks read the rollup through the environment; keep it current.
+                    rollup_report(&mut report);
+                    write_campaign_reports(&report, &report_json, &report_md)?;
+                    run_external_step(&step.argv, &report, &report_json, &mut result);
+                }
+            }
+            result.duration_secs = started.elapsed().as_secs_f64();
+            log_external_progress(format!(
+                "phase=campaign-step status={} campaign={} name={} exit_code={} duration_secs={:.1}",
+                result.status,
+                spec.name,
+                result.name,
+                result
+                    .exit_code
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
+                result.duration_secs
+            ));
+        }
+        report.steps.push(result);
+        rollup_report(&mut report);
+        write_campaign_reports(&report, &report_json, &report_md)?;
+    }
+
+    report.status = if report.failed > 0 {
+        "failed".to_string()
+    } else {
+        "succeeded".to_string()
+    };
+    report.finished_at_utc = Some(Utc::now().to_rfc3339());
+    write_campaign_reports(&report, &report_json, &report_md)?;
+    log_external_progress(format!(
+        "phase=campaign status={} name={} succeeded={} failed={} skipped={} report_md={}",
+        report.status,
+        report.name,
+        report.succeeded,
+        report.failed,
+        report.skipped,
+        report_md.display()
+    ));
+    let _ = FileExt::unlock(&lock_file);
+
+    Ok(CampaignSummary {
+        name: report.name,
+        status: report.status,
+        steps: report.steps_total,
+        succeeded: report.succeeded,
+        failed: report.failed,
+        skipped: report.skipped,
+        report_json,
+        report_md,
+    })
+}
+
+fn run_command_step(
+    command: Command,
+    result: &mut CampaignStepResult,
+    run_step: &mut impl FnMut(Command) -> ExitCode,
+) {
+    let (step_topdir, _) = crate::audit_target(&command);
+    if let Ok(status) = build_lock::inspect_workspace_lock(&step_topdir, WORKSPACE_STALE_AFTER)
+        && status.lock_held
+        && !status.stale
+    {
+        // A build would forward its packages to the foreign owner and report
+        // success without building anything, so never start while one is active.
+        result.status = "lock-busy".to_string();
+        result.detail = Some(format!(
+            "workspace {} is locked by {}",
+            step_topdir.display(),
+            status
+                .owner
+                .map(|owner| format!("pid={} kind={}", owner.pid, owner.session_kind))
+                .unwrap_or_else(|| "an unknown owner".to_string())
+        ));
+        return;
+    }
+    take_step_summary();
+    let exit = run_step(command);
+    if let Some(line) = take_step_summary() {
+        result.summary = parse_summary_fields(&line);
+    }
+    if exit == ExitCode::SUCCESS {
+        result.status = "succeeded".to_string();
+        result.exit_code = Some(0);
+    } else {
+        result.status = "failed".to_string();
+        result.exit_code = Some(1);
+    }
+}
+
+fn run_external_step(
+    argv: &[String],
+    report: &CampaignReport,
+    report_json: &Path,
+    result: &mut CampaignStepResult,
+) {
+    let status = process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .env("BIOCONDA2RPM_CAMPAIGN", &report.name)
+        .env(
+            "BIOCONDA2RPM_CAMPAIGN_STATUS",
+            if report.failed > 0 {
+                "failed"
+            } else {
+                "succeeded"
+            },
+        )
+        .env("BIOCONDA2RPM_CAMPAIGN_TOPDIR", &report.topdir)
+        .env("BIOCONDA2RPM_CAMPAIGN_REPORT", report_json)
+        .audited_status();
+    match status {
+        Ok(status) => {
+            result.exit_code = status.code();
+            result.status = if status.success() {
+                "succeeded".to_string()
+            } else {
+                "failed".to_string()
+            };
+        }
+        Err(err) => {
+            result.status = "failed".to_string();
+            result.detail = Some(format!("failed to start {}: {err}", argv[0]));
+        }
+    }
+}
+
+fn load_campaign_spec(path: &Path) -> Result<CampaignSpec> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("reading campaign file {}", path.display()))?;
+    serde_yaml::from_str(&text).with_context(|| format!("parsing campaign file {}", path.display()))
+}
+
+/// Validate every step before anything runs, injecting the shared `--topdir`
+/// into bioconda2rpm steps that do not set their own.
+fn plan_campaign(spec: &CampaignSpec, topdir: &Path) -> Result<Vec<PlannedStep>> {
+    if spec.name.trim().is_empty() {
+        bail!("campaign name must not be empty");
+    }
+    if spec.steps.is_empty() {
+        bail!("campaign '{}' has no steps", spec.name);
+    }
+    let mut seen = HashSet::new();
+    let mut plan = Vec::new();
+    for step in &spec.steps {
+        if !seen.insert(step.name.as_str()) {
+            bail!("campaign step name '{}' is used more than once", step.name);
+        }
+        match (step.command.is_empty(), step.run.is_empty()) {
+            (false, true) => {
+                let mut argv = step.command.clone();
+                if !argv
+                    .iter()
+                    .any(|arg| arg == "--topdir" || arg.starts_with("--topdir="))
+                {
+                    argv.push("--topdir".to_string());
+                    argv.push(topdir.display().to_string());
+                }
+                let command = Cli::try_parse_from(
+                    std::iter::once("bioconda2rpm".to_string()).chain(argv.iter().cloned()),
+                )
+                .map_err(|err| {
+                    anyhow::anyhow!("campaign step '{}' has invalid arguments: {err}", step.name)
+                })?
+                .command;
+                if matches!(command, Command::Campaign(_)) {
+                    bail!("campaign step '{}' cannot run a nested campaign", step.name);
+                }
+                plan.push(PlannedStep {
+                    spec: step.clone(),
+                    argv,
+                    command: Some(command),
+                });
+            }
+            (true, false) => plan.push(PlannedStep {
+                spec: step.clone(),
+                argv: step.run.clone(),
+                command: None,
+            }),
+            _ => bail!(
+                "campaign step '{}' must set exactly one of `command` or `run`",
+                step.name
+            ),
+        }
+    }
+    Ok(plan)
+}
+
+fn campaign_slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Split `build requested=3 order=a->b report_md=/x.md` into its `key=value` fields.
+fn parse_summary_fields(line: &str) -> BTreeMap<String, String> {
+    line.split_whitespace()
+        .filter_map(|token| token.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn rollup_report(report: &mut CampaignReport) {
+    let count = |status: &str| report.steps.iter().filter(|s| s.status == status).count();
+    report.succeeded = count("succeeded");
+    report.failed = count("failed") + count("lock-busy");
+    report.skipped = count("skipped");
+    let sum = |key: &str| {
+        report
+            .steps
+            .iter()
+            .filter_map(|step| step.summary.get(key))
+            .filter_map(|value| value.parse::<usize>().ok())
+            .sum::<usize>()
+    };
+    report.packages_requested = sum("requested");
+    report.kpi_denominator = sum("kpi_denominator");
+    report.kpi_successes = sum("kpi_successes");
+    report.kpi_success_rate = (report.kpi_denominator > 0)
+        .then(|| report.kpi_successes as f64 * 100.0 / report.kpi_denominator as f64);
+}
+
+fn write_campaign_reports(report: &CampaignReport, json_path: &Path, md_path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(report).context("serializing campaign report")?;
+    write_atomic(json_path, json)
+        .with_context(|| format!("writing campaign report {}", json_path.display()))?;
+    write_atomic(md_path, render_campaign_markdown(report))
+        .with_context(|| format!("writing campaign report {}", md_path.display()))?;
+    Ok(())
+}
+
+fn render_campaign_markdown(report: &CampaignReport) -> String {
+    let mut md = String::new();
+    md.push_str(&format!("# Campaign {}\n\n", report.name));
+    md.push_str(&format!("- Status: `{}`\n", report.status));
+    md.push_str(&format!("- Campaign file: `{}`\n", report.spec_path));
+    md.push_str(&format!("- Topdir: `{}`\n", report.topdir));
+    md.push_str(&format!("- Started: {}\n", report.started_at_utc));
+    if let Some(finished) = &report.finished_at_utc {
+        md.push_str(&format!("- Finished: {finished}\n"));
+    }
+    md.push_str(&format!(
+        "- Steps: {} (succeeded {}, failed {}, skipped {})\n",
+        report.steps_total, report.succeeded, report.failed, report.skipped
+    ));
+    md.push_str(&format!(
+        "- Packages requested: {}\n",
+        report.packages_requested
+    ));
+    if let Some(rate) = report.kpi_success_rate {
+        md.push_str(&format!(
+            "- KPI: {}/{} ({rate:.2}%)\n",
+            report.kpi_successes, report.kpi_denominator
+        ));
+    }
+    md.push_str("\n## Steps\n\n");
+    md.push_str("| Step | Kind | When | Status | Exit | Duration (s) | Result |\n");
+    md.push_str("|---|---|---|---|---|---|---|\n");
+    for step in &report.steps {
+        let outcome = step
+            .detail
+            .clone()
+            .or_else(|| {
+                step.summary.get("report_md").map(|md| {
+                    format!(
+                        "requested={} kpi={} report `{md}`",
+                        step.summary.get("requested").map_or("-", String::as_str),
+                        step.summary
+                            .get("kpi_success_rate")
+                            .map_or("-", String::as_str)
+                    )
+                })
+            })
+            .unwrap_or_default();
+        md.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {:.1} | {} |\n",
+            step.name,
+            step.kind,
+            step.when.as_str(),
+            step.status,
+            step.exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            step.duration_secs,
+            outcome.replace('|', "\\|")
+        ));
+    }
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn spec(yaml: &str) -> CampaignSpec {
+        serde_yaml::from_str(yaml).expect("parse campaign yaml")
+    }
+
+    #[test]
+    fn plan_injects_topdir_and_rejects_invalid_steps() {
+        let campaign = spec(
+            r#"
+name: weekly
+steps:
+  - name: pr-x86
+    command: [regression, --tools-csv, tools.csv, --mode, pr, --arch, x86-64]
+  - name: status
+    command: [lock, status, --topdir, /other]
+  - name: notify
+    run: [sh, -c, "echo $BIOCONDA2RPM_CAMPAIGN_STATUS"]
+    when: always
+"#,
+        );
+        let plan = plan_campaign(&campaign, Path::new("/srv/b2r")).expect("plan");
+        assert_eq!(plan.len(), 3);
+        assert_eq!(
+            &plan[0].argv[plan[0].argv.len() - 2..],
+            ["--topdir", "/srv/b2r"]
+        );
+        assert!(matches!(plan[0].command, Some(Command::Regression(_))));
+        assert_eq!(plan[1].argv.last().map(String::as_str), Some("/other"));
+        assert!(plan[2].command.is_none());
+        assert_eq!(plan[2].spec.when, StepCondition::Always);
+
+        let both = spec("name: x\nsteps:\n  - name: a\n    command: [lookup]\n    run: [true]\n");
+        assert!(plan_campaign(&both, Path::new("/t")).is_err());
+        let nested = spec("name: x\nsteps:\n  - name: a\n    command: [campaign, other.yaml]\n");
+        let err = plan_campaign(&nested, Path::new("/t"))
+            .err()
+            .expect("nested");
+        assert!(err.to_string().contains("nested campaign"));
+        let invalid = spec("name: x\nsteps:\n  - name: a\n    command: [build, --no-such-flag]\n");
+        assert!(plan_campaign(&invalid, Path::new("/t")).is_err());
+    }
+
+    #[test]
+    fn campaign_runs_steps_by_condition_and_rolls_up_summaries() {
+        let tmp = TempDir::new().expect("create temp dir");
+        let spec_path = tmp.path().join("campaign.yaml");
+        fs::write(
+            &spec_path,
+            r#"
+name: Nightly Rollout
+steps:
+  - name: pr
+    command: [regression, --tools-csv, tools.csv]
+  - name: nightly
+    command: [regression, --tools-csv, tools.csv, --mode, nightly]
+  - name: publish
+    command: [lookup]
+  - name: notify
+    run: [sh, -c, 'test "$BIOCONDA2RPM_CAMPAIGN_STATUS" = failed && test -f "$BIOCONDA2RPM_CAMPAIGN_REPORT"']
+    when: on-failure
+"#,
+        )
+        .expect("write campaign");
+        let args = CampaignArgs {
+            spec: spec_path,
+            topdir: Some(tmp.path().join("top")),
+            reports_dir: None,
+            dry_run: false,
+        };
+        let mut calls = 0;
+        let summary = run_campaign(&args, |command| {
+            calls += 1;
+            let Command::Regression(regression) = command else {
+                panic!("only regression steps should run");
+            };
+            if regression.mode == crate::cli::RegressionMode::Pr {
+                record_step_summary(
+                    "regression mode=Pr requested=10 kpi_denominator=8 kpi_successes=6 kpi_success_rate=75.00% report_md=/r/pr.md",
+                );
+                ExitCode::SUCCESS
+            } else {
+                record_step_summary("regression mode=Nightly requested=4 kpi_denominator=4 kpi_successes=2");
+                ExitCode::FAILURE
+            }
+        })
+        .expect("run campaign");
+        assert_eq!(calls, 2);
+        assert_eq!(summary.status, "failed");
+        assert_eq!(
+            (summary.succeeded, summary.failed, summary.skipped),
+            (2, 1, 1)
+        );
+        assert!(
+            summary
+                .report_json
+                .ends_with("campaigns/campaign_nightly-rollout.json")
+        );
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&summary.report_json).expect("read json"))
+                .expect("parse json");
+        assert_eq!(report["packages_requested"], 14);
+        assert_eq!(report["kpi_successes"], 8);
+        assert_eq!(report["steps"][2]["status"], "skipped");
+        assert_eq!(report["steps"][3]["status"], "succeeded");
+        let md = fs::read_to_string(&summary.report_md).expect("read md");
+        assert!(md.contains("- KPI: 8/12 (66.67%)"));
+        assert!(md.contains("| pr | command | on-success | succeeded | 0 |"));
+        assert!(md.contains("requested=10 kpi=75.00% report `/r/pr.md`"));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
+use crate::config::CatalogConfig;
+use crate::telemetry::{clone_io_error, parse_header_list, post_json};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Extra request headers for the catalog endpoint, in the
+/// `OTEL_EXPORTER_OTLP_HEADERS` format, so tokens stay out of the config file.
+pub const CATALOG_HEADERS_ENV: &str = "BIOCONDA2RPM_CATALOG_HEADERS";
+/// Pending records, kept per target so they survive offline runs.
+pub const CATALOG_OUTBOX_DIR: &str = "catalog-outbox";
+
+/// Metadata of one built package as posted to the catalog.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CatalogRecord {
+    /// Payload RPM name, e.g. `phoreus-samtools-1.21`.
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    pub target_id: String,
+    /// Lmod module, `<tool>/<version>`.
+    pub module: String,
+    pub rpms: Vec<CatalogArtifact>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sbom: Option<String>,
+    pub built_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CatalogArtifact {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// Records left in the outbox after a flush.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushSummary {
+    pub sent: usize,
+    pub pending: usize,
+}
+
+/// Posts [`CatalogRecord`]s to the `[catalog]` endpoint. Build workers only
+/// write records to the outbox; the run flushes it at start and end, and a
+/// record is removed once the endpoint accepted it, so records of unreachable
+/// or offline runs go out with the next flush.
+#[derive(Debug, Clone)]
+pub struct CatalogPublisher {
+    url: String,
+    headers: Vec<(String, String)>,
+    sbom_url: Option<String>,
+    retries: u32,
+    offline: bool,
+    outbox_dir: PathBuf,
+}
+
+impl CatalogPublisher {
+    /// `None` when the config has no catalog `url`.
+    pub fn from_config(config: &CatalogConfig, target_root: &Path) -> Result<Option<Self>> {
+        let Some(url) = config.url.as_deref() else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            url: url.to_string(),
+            headers: parse_header_list(
+                &std::env::var(CATALOG_HEADERS_ENV).unwrap_or_default(),
+                CATALOG_HEADERS_ENV,
+            )?,
+            sbom_url: config.sbom_url.clone(),
+            retries: config.retries,
+            offline: config.offline,
+            outbox_dir: target_root.join(CATALOG_OUTBOX_DIR),
+        }))
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn outbox_dir(&self) -> &Path {
+        &self.outbox_dir
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// SBOM link of a record from the `sbom-url` template.
+    pub fn sbom_link(
+        &self,
+        name: &str,
+        version: &str,
+        arch: &str,
+        target_id: &str,
+    ) -> Option<String> {
+        self.sbom_url.as_ref().map(|template| {
+            template
+                .replace("{name}", name)
+                .replace("{version}", version)
+                .replace("{arch}", arch)
+                .replace("{target_id}", target_id)
+        })
+    }
+
+    /// Writes `record` to the outbox without contacting the endpoint.
+    pub fn enqueue(&self, record: &CatalogRecord) -> Result<PathBuf> {
+        fs::create_dir_all(&self.outbox_dir)
+            .with_context(|| format!("creating {}", self.outbox_dir.display()))?;
+        let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%6f");
+        let path = self
+            .outbox_dir
+            .join(format!("{stamp}-{}-{}.json", record.name, record.arch));
+        let body = serde_json::to_string_pretty(record).context("serializing catalog record")?;
+        write_atomic(&path, body).with_context(|| format!("writing {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Sends queued records oldest first and stops at the first one the
+    /// endpoint does not accept; offline publishers send nothing. Retries
+    /// back off in the calling thread, so only the run itself flushes.
+    pub fn flush(&self) -> Result<FlushSummary> {
+        let pending = outbox_records(&self.outbox_dir)?;
+        let mut summary = FlushSummary {
+            sent: 0,
+            pending: pending.len(),
+        };
+        if self.offline {
+            return Ok(summary);
+        }
+        for path in pending {
+            let body =
+                fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+            self.post_with_retries(&body)
+                .with_context(|| format!("publishing {}", path.display()))?;
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+            summary.sent += 1;
+            summary.pending -= 1;
+        }
+        Ok(summary)
+    }
+
+    fn post_with_retries(&self, body: &str) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match post_json(&self.url, &self.headers, body, "catalog publish") {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= self.retries => return Err(err),
+                Err(_) => {
+                    std::thread::sleep(Duration::from_secs(1 << attempt.min(5)));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Queued record files, oldest first.
+pub fn outbox_records(outbox_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !outbox_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut records = Vec::new();
+    for entry in
+        fs::read_dir(outbox_dir).with_context(|| format!("reading {}", outbox_dir.display()))?
+    {
+        let path = entry
+            .with_context(|| format!("reading entry in {}", outbox_dir.display()))?
+            .path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            records.push(path);
+        }
+    }
+    records.sort();
+    Ok(records)
+}
+
+/// SHA-256 of `bytes`, piped to the host `sha256sum`.
+pub fn bytes_sha256(bytes: &[u8]) -> Result<String> {
+    let (mut child, pending_audit) = Command::new("sha256sum")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .audited_spawn()
+        .context("running sha256sum")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(bytes)
+            .context("writing data to sha256sum")?;
+    }
+    let output = child.wait_with_output();
+    pending_audit.finish(&output.as_ref().map(|o| o.status).map_err(clone_io_error));
+    let output = output.context("waiting for sha256sum")?;
+    if !output.status.success() {
+        bail!(
+            "sha256sum failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+        .context("empty sha256sum output")
+}
+
+/// SHA-256 of `path` from the host `sha256sum`.
+pub fn file_sha256(path: &Path) -> Result<String> {
+    let output = Command::new("sha256sum")
+        .arg(path)
+        .audited_output()
+        .with_context(|| format!("running sha256sum on {}", path.display()))?;
+    if !output.status.success() {
+        bail!(
+            "sha256sum {} failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+        .with_context(|| format!("empty sha256sum output for {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str) -> CatalogRecord {
+        CatalogRecord {
+            name: name.to_string(),
+            version: "1.21".to_string(),
+            arch: "x86_64".to_string(),
+            target_id: "almalinux-9-7-x86_64".to_string(),
+            module: "samtools/1.21".to_string(),
+            rpms: vec![CatalogArtifact {
+                path: "RPMS/x86_64/phoreus-samtools-1.21-1.21-1.el9.x86_64.rpm".to_string(),
+                sha256: "ab".repeat(32),
+            }],
+            sbom: None,
+            built_at: "2026-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn offline_publisher_keeps_records_in_outbox_in_order() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let config = CatalogConfig {
+            url: Some("https://catalog.example.org/api/packages".to_string()),
+            sbom_url: Some(
+                "https://sbom.example.org/{target_id}/{name}-{version}.{arch}".to_string(),
+            ),
+            retries: 0,
+            offline: true,
+        };
+        let publisher = CatalogPublisher::from_config(&config, tmp.path())
+            .expect("publisher")
+            .expect("catalog configured");
+        assert_eq!(
+            publisher
+                .sbom_link("phoreus-samtools-1.21", "1.21", "x86_64", "el9")
+                .as_deref(),
+            Some("https://sbom.example.org/el9/phoreus-samtools-1.21-1.21.x86_64")
+        );
+        for name in ["phoreus-samtools-1.21", "phoreus-bcftools-1.21"] {
+            publisher.enqueue(&record(name)).expect("queue record");
+        }
+        assert_eq!(
+            publisher.flush().expect("offline flush"),
+            FlushSummary {
+                sent: 0,
+                pending: 2,
+            }
+        );
+        let queued = outbox_records(publisher.outbox_dir()).expect("outbox");
+        let names = queued
+            .iter()
+            .map(|path| {
+                serde_json::from_str::<CatalogRecord>(&fs::read_to_string(path).expect("read"))
+                    .expect("record")
+                    .name
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["phoreus-samtools-1.21", "phoreus-bcftools-1.21"]
+        );
+
+        assert!(
+            CatalogPublisher::from_config(&CatalogConfig::default(), tmp.path())
+                .expect("no catalog")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::atomic_file::write_atomic;
+use crate::download;
+use crate::priority_specs::{compare_version_labels, log_external_progress, normalize_name};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// File name of the channel state in a reports dir.
+pub const CHANNEL_STATE_FILE: &str = "channel_state.json";
+
+/// Newest version and build number of a package in the channel.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChannelVersion {
+    pub version: String,
+    pub build_number: u64,
+}
+
+/// `<reports-dir>/channel_state.json`: channel versions of the tracked
+/// packages as of the last `channel-updates` run, keyed by normalized name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChannelState {
+    pub channel_url: String,
+    pub subdirs: Vec<String>,
+    pub updated_at_utc: String,
+    pub packages: BTreeMap<String, ChannelVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Repodata {
+    #[serde(default)]
+    packages: HashMap<String, RepodataRecord>,
+    #[serde(default, rename = "packages.conda")]
+    packages_conda: HashMap<String, RepodataRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepodataRecord {
+    name: String,
+    version: String,
+    #[serde(default)]
+    build_number: u64,
+}
+
+/// Conda subdirs holding packages for `target_arch`.
+pub fn channel_subdirs(target_arch: &str) -> Vec<String> {
+    let platform = match target_arch {
+        "aarch64" => "linux-aarch64",
+        _ => "linux-64",
+    };
+    vec!["noarch".to_string(), platform.to_string()]
+}
+
+/// Newest version per normalized package name over `current_repodata.json`
+/// of each subdir of `channel_url`.
+pub fn fetch_channel_versions(
+    channel_url: &str,
+    subdirs: &[String],
+) -> Result<BTreeMap<String, ChannelVersion>> {
+    let mut versions = BTreeMap::new();
+    for subdir in subdirs {
+        let url = format!(
+            "{}/{subdir}/current_repodata.json",
+            channel_url.trim_end_matches('/')
+        );
+        let bytes = download::manager().fetch_bytes(&url)?;
+        merge_repodata(&mut versions, &bytes).with_context(|| format!("parsing {url}"))?;
+    }
+    Ok(versions)
+}
+
+fn merge_repodata(versions: &mut BTreeMap<String, ChannelVersion>, raw: &[u8]) -> Result<()> {
+    let repodata: Repodata = serde_json::from_slice(raw)?;
+    for record in repodata
+        .packages
+        .into_values()
+        .chain(repodata.packages_conda.into_values())
+    {
+        let candidate = ChannelVersion {
+            version: record.version,
+            build_number: record.build_number,
+        };
+        let key = normalize_name(&record.name);
+        match versions.get(&key) {
+            Some(current) if !is_newer(&candidate, current) => {}
+            _ => {
+                versions.insert(key, candidate);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_newer(candidate: &ChannelVersion, current: &ChannelVersion) -> bool {
+    compare_version_labels(&candidate.version, &current.version)
+        .then(candidate.build_number.cmp(&current.build_number))
+        == Ordering::Greater
+}
+
+/// Tracked packages whose channel version or build number differs from
+/// `previous`, in tracked order. Packages missing from the channel are left
+/// out; without a previous state every tracked package in the channel counts
+/// as changed.
+pub fn changed_packages(
+    previous: Option<&ChannelState>,
+    current: &BTreeMap<String, ChannelVersion>,
+    tracked: &[String],
+) -> Vec<String> {
+    tracked
+        .iter()
+        .filter(|software| {
+            let key = normalize_name(software);
+            let Some(now) = current.get(&key) else {
+                return false;
+            };
+            previous
+                .and_then(|state| state.packages.get(&key))
+                .is_none_or(|before| before != now)
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn load_channel_state(reports_dir: &Path) -> Result<Option<ChannelState>> {
+    let path = reports_dir.join(CHANNEL_STATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("reading channel state {}", path.display()))?;
+    let state = serde_json::from_str(&raw)
+        .with_context(|| format!("parsing channel state {}", path.display()))?;
+    Ok(Some(state))
+}
+
+/// Record the channel versions of the tracked packages. Packages in `failed`
+/// keep their previous version, or stay absent, so the next run retries them.
+pub fn save_channel_state(
+    reports_dir: &Path,
+    channel_url: &str,
+    subdirs: &[String],
+    previous: Option<&ChannelState>,
+    current: &BTreeMap<String, ChannelVersion>,
+    tracked: &[String],
+    failed: &[String],
+) -> Result<()> {
+    let failed: Vec<String> = failed.iter().map(|name| normalize_name(name)).collect();
+    let mut packages = BTreeMap::new();
+    for software in tracked {
+        let key = normalize_name(software);
+        let version = if failed.contains(&key) {
+            previous.and_then(|state| state.packages.get(&key))
+        } else {
+            current.get(&key)
+        };
+        if let Some(version) = version {
+            packages.insert(key, version.clone());
+        }
+    }
+    let state = ChannelState {
+        channel_url: channel_url.to_string(),
+        subdirs: subdirs.to_vec(),
+        updated_at_utc: Utc::now().to_rfc3339(),
+        packages,
+    };
+    let path = reports_dir.join(CHANNEL_STATE_FILE);
+    let payload = serde_json::to_string_pretty(&state).context("serializing channel state")?;
+    write_atomic(&path, payload)
+        .with_context(|| format!("writing channel state {}", path.display()))?;
+    log_external_progress(format!(
+        "phase=channel-updates status=state-saved path={} packages={} retried_next_run={}",
+        path.display(),
+        state.packages.len(),
+        failed.len()
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_changes_select_bumped_tracked_packages_and_retry_failures() {
+        let mut current = BTreeMap::new();
+        merge_repodata(
+            &mut current,
+            br#"{
+                "packages": {
+                    "samtools-1.20-h1_0.tar.bz2": {"name": "samtools", "version": "1.20", "build_number": 0},
+                    "samtools-1.21-h1_0.tar.bz2": {"name": "samtools", "version": "1.21", "build_number": 0},
+                    "bwa-0.7.18-h1_1.tar.bz2": {"name": "bwa", "version": "0.7.18", "build_number": 1}
+                },
+                "packages.conda": {
+                    "samtools-1.21-h1_2.conda": {"name": "samtools", "version": "1.21", "build_number": 2},
+                    "untracked-1.0-0.conda": {"name": "untracked", "version": "1.0"}
+                }
+            }"#,
+        )
+        .expect("parse repodata");
+        assert_eq!(
+            current["samtools"],
+            ChannelVersion {
+                version: "1.21".to_string(),
+                build_number: 2
+            }
+        );
+
+        let tracked = vec![
+            "samtools".to_string(),
+            "BWA".to_string(),
+            "not-in-channel".to_string(),
+        ];
+        assert_eq!(
+            changed_packages(None, &current, &tracked),
+            vec!["samtools".to_string(), "BWA".to_string()]
+        );
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let subdirs = channel_subdirs("x86_64");
+        assert_eq!(subdirs, vec!["noarch".to_string(), "linux-64".to_string()]);
+        let url = "https://conda.example.invalid/bioconda";
+        save_channel_state(
+            tmp.path(),
+            url,
+            &subdirs,
+            None,
+            &current,
+            &tracked,
+            &["BWA".to_string()],
+        )
+        .expect("save state");
+        let state = load_channel_state(tmp.path())
+            .expect("load state")
+            .expect("state exists");
+        assert!(!state.packages.contains_key("bwa"));
+        assert_eq!(
+            changed_packages(Some(&state), &current, &tracked),
+            vec!["BWA".to_string()]
+        );
+
+        current.insert(
+            "samtools".to_string(),
+            ChannelVersion {
+                version: "1.22".to_string(),
+                build_number: 0,
+            },
+        );
+        assert_eq!(
+            changed_packages(Some(&state), &current, &tracked),
+            vec!["samtools".to_string(), "BWA".to_string()]
+        );
+        save_channel_state(
+            tmp.path(),
+            url,
+            &subdirs,
+            Some(&state),
+            &current,
+            &tracked,
+            &[],
+        )
+        .expect("save state");
+        let state = load_channel_state(tmp.path())
+            .expect("load")
+            .expect("state");
+        assert!(changed_packages(Some(&state), &current, &tracked).is_empty());
+        assert_eq!(state.packages["samtools"].version, "1.22");
+    }
+}
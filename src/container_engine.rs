@@ -0,0 +1,471 @@
+use crate::audit::AuditedCommand;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// Open-file limit requested for build containers; rootless engines are
+/// capped at the host hard limit because they cannot raise it.
+const BUILD_NOFILE_LIMIT: u64 = 65536;
+
+/// Label keys set on every container this tool starts, so containers left
+/// behind by a killed run can be found by workspace and target.
+pub const TOPDIR_LABEL: &str = "io.bioconda2rpm.topdir";
+pub const TARGET_ID_LABEL: &str = "io.bioconda2rpm.target-id";
+
+/// Kernel directory listing registered `binfmt_misc` handlers.
+const BINFMT_MISC_DIR: &str = "/proc/sys/fs/binfmt_misc";
+
+static ENGINE_CAPABILITIES: OnceLock<Mutex<HashMap<String, EngineCapabilities>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineFlavor {
+    Docker,
+    Podman,
+    Unknown,
+}
+
+impl EngineFlavor {
+    fn as_str(self) -> &'static str {
+        match self {
+            EngineFlavor::Docker => "docker",
+            EngineFlavor::Podman => "podman",
+            EngineFlavor::Unknown => "unknown",
+        }
+    }
+}
+
+/// What the container engine on this host can do, from `<engine> info`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineCapabilities {
+    pub flavor: EngineFlavor,
+    pub version: String,
+    pub rootless: bool,
+    /// cgroup hierarchy version (1 or 2) when the engine reports it.
+    pub cgroup_version: Option<u32>,
+    /// Docker daemon runs with `userns-remap`; bind-mounted files would be
+    /// owned by the remapped uid unless containers opt out with `--userns=host`.
+    pub userns_remap: bool,
+    /// Rootless engine maps container uids onto the user's subordinate ids.
+    pub id_mapping: bool,
+    /// `nofile` value passed with `--ulimit`; `None` when it is not passed.
+    pub nofile_limit: Option<u64>,
+}
+
+impl EngineCapabilities {
+    fn undetected() -> Self {
+        Self {
+            flavor: EngineFlavor::Unknown,
+            version: String::new(),
+            rootless: false,
+            cgroup_version: None,
+            userns_remap: false,
+            id_mapping: false,
+            nofile_limit: None,
+        }
+    }
+
+    /// Extra `run` arguments adapting build containers to this engine.
+    pub fn run_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.flavor == EngineFlavor::Docker && self.userns_remap && !self.rootless {
+            args.push("--userns=host".to_string());
+        }
+        if let Some(limit) = self.nofile_limit {
+            args.push("--ulimit".to_string());
+            args.push(format!("nofile={limit}:{limit}"));
+        }
+        args
+    }
+
+    /// Conditions known to make builds behave differently from rootful engines.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.flavor == EngineFlavor::Unknown {
+            warnings.push(
+                "engine info unavailable; running containers without capability adjustments"
+                    .to_string(),
+            );
+        }
+        if self.rootless && self.cgroup_version == Some(1) {
+            warnings.push(
+                "rootless engine on cgroup v1: container memory/cpu limits are not enforced"
+                    .to_string(),
+            );
+        }
+        if self.rootless && !self.id_mapping {
+            warnings.push(
+                "rootless engine without subordinate id mapping: packages that chown to other users fail"
+                    .to_string(),
+            );
+        }
+        if self.rootless
+            && let Some(limit) = self.nofile_limit
+            && limit < BUILD_NOFILE_LIMIT
+        {
+            warnings.push(format!(
+                "rootless engine: open-file limit capped at host hard limit {limit}"
+            ));
+        }
+        warnings
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "engine_flavor={} engine_version={} engine_rootless={} engine_cgroup={} engine_userns={} engine_ulimit_nofile={}",
+            self.flavor.as_str(),
+            if self.version.is_empty() {
+                "unknown"
+            } else {
+                &self.version
+            },
+            self.rootless,
+            self.cgroup_version
+                .map(|v| format!("v{v}"))
+                .unwrap_or_else(|| "unknown".to_string()),
+            if self.userns_remap {
+                "remap"
+            } else if self.id_mapping {
+                "rootless-mapping"
+            } else {
+                "host"
+            },
+            self.nofile_limit
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "unset".to_string())
+        )
+    }
+}
+
+/// Capabilities of `engine`, detected once per process and cached.
+pub fn engine_capabilities(engine: &str) -> EngineCapabilities {
+    let cache = ENGINE_CAPABILITIES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(cached) = cache.lock()
+        && let Some(caps) = cached.get(engine)
+    {
+        return caps.clone();
+    }
+    let caps = detect_engine_capabilities(engine);
+    if let Ok(mut cached) = cache.lock() {
+        cached.insert(engine.to_string(), caps.clone());
+    }
+    caps
+}
+
+fn detect_engine_capabilities(engine: &str) -> EngineCapabilities {
+    let output = Command::new(engine)
+        .arg("info")
+        .arg("--format")
+        .arg("{{json .}}")
+        .audited_output();
+    match output {
+        Ok(output) if output.status.success() => parse_engine_info(
+            &String::from_utf8_lossy(&output.stdout),
+            host_nofile_hard_limit(),
+        ),
+        _ => EngineCapabilities::undetected(),
+    }
+}
+
+fn host_nofile_hard_limit() -> Option<u64> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("ulimit -Hn")
+        .audited_output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Parse `docker info` or `podman info` JSON. `host_nofile_hard` is the
+/// host's hard open-file limit (`None` when unlimited or unknown).
+fn parse_engine_info(raw: &str, host_nofile_hard: Option<u64>) -> EngineCapabilities {
+    let Ok(info) = serde_json::from_str::<Value>(raw) else {
+        return EngineCapabilities::undetected();
+    };
+    let mut caps = if let Some(host) = info.get("host").filter(|v| v.is_object()) {
+        EngineCapabilities {
+            flavor: EngineFlavor::Podman,
+            version: info
+                .pointer("/version/Version")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            rootless: host
+                .pointer("/security/rootless")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            cgroup_version: host
+                .get("cgroupVersion")
+                .and_then(Value::as_str)
+                .and_then(parse_cgroup_version),
+            userns_remap: false,
+            id_mapping: host
+                .pointer("/idMappings/uidmap")
+                .and_then(Value::as_array)
+                .is_some_and(|map| map.len() > 1),
+            nofile_limit: None,
+        }
+    } else if let Some(version) = info.get("ServerVersion").and_then(Value::as_str) {
+        let security = info
+            .get("SecurityOptions")
+            .and_then(Value::as_array)
+            .map(|opts| opts.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let has_option = |name: &str| {
+            security
+                .iter()
+                .any(|opt| opt.split(',').any(|field| field == format!("name={name}")))
+        };
+        let rootless = has_option("rootless");
+        EngineCapabilities {
+            flavor: EngineFlavor::Docker,
+            version: version.to_string(),
+            rootless,
+            cgroup_version: info
+                .get("CgroupVersion")
+                .and_then(Value::as_str)
+                .and_then(parse_cgroup_version),
+            userns_remap: has_option("userns"),
+            // Rootless docker always runs under rootlesskit's id mapping.
+            id_mapping: rootless,
+            nofile_limit: None,
+        }
+    } else {
+        return EngineCapabilities::undetected();
+    };
+    caps.nofile_limit = Some(if caps.rootless {
+        host_nofile_hard
+            .map(|hard| hard.min(BUILD_NOFILE_LIMIT))
+            .unwrap_or(BUILD_NOFILE_LIMIT)
+    } else {
+        BUILD_NOFILE_LIMIT
+    });
+    caps
+}
+
+fn parse_cgroup_version(raw: &str) -> Option<u32> {
+    raw.trim().trim_start_matches('v').parse().ok()
+}
+
+/// `run` arguments labelling a container with its workspace and target.
+pub fn ownership_labels(topdir: &Path, target_id: &str) -> Vec<String> {
+    vec![
+        "--label".to_string(),
+        format!("{TOPDIR_LABEL}={}", topdir.display()),
+        "--label".to_string(),
+        format!("{TARGET_ID_LABEL}={target_id}"),
+    ]
+}
+
+/// A container carrying [`ownership_labels`], as listed by `<engine> ps -a`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabeledContainer {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+}
+
+/// Containers, running or exited, started for `topdir` (and `target_id`
+/// when given).
+pub fn list_labeled_containers(
+    engine: &str,
+    topdir: &Path,
+    target_id: Option<&str>,
+) -> Result<Vec<LabeledContainer>> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("ps")
+        .arg("-a")
+        .arg("--filter")
+        .arg(format!("label={TOPDIR_LABEL}={}", topdir.display()));
+    if let Some(target_id) = target_id {
+        cmd.arg("--filter")
+            .arg(format!("label={TARGET_ID_LABEL}={target_id}"));
+    }
+    let output = cmd
+        .arg("--format")
+        .arg("{{.ID}}\t{{.Names}}\t{{.Status}}")
+        .audited_output()
+        .with_context(|| format!("listing containers with {engine} ps"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{engine} ps exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_container_listing(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_container_listing(raw: &str) -> Vec<LabeledContainer> {
+    raw.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let id = fields.next()?.trim();
+            if id.is_empty() {
+                return None;
+            }
+            Some(LabeledContainer {
+                id: id.to_string(),
+                name: fields.next().unwrap_or_default().trim().to_string(),
+                status: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Force-remove the labelled containers of `topdir`/`target_id` left by
+/// earlier runs and return them. Only call this while holding the workspace
+/// lock, otherwise a live build's containers are removed too. With `dry_run`
+/// the containers are only listed.
+pub fn reap_stale_containers(
+    engine: &str,
+    topdir: &Path,
+    target_id: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<LabeledContainer>> {
+    let stale = list_labeled_containers(engine, topdir, target_id)?;
+    if stale.is_empty() || dry_run {
+        return Ok(stale);
+    }
+    let output = Command::new(engine)
+        .arg("rm")
+        .arg("-f")
+        .args(stale.iter().map(|container| &container.id))
+        .audited_output()
+        .with_context(|| format!("removing stale containers with {engine} rm"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{engine} rm -f exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(stale)
+}
+
+/// Enabled qemu `binfmt_misc` handler running `target_arch` binaries on this
+/// host, e.g. `qemu-aarch64` registered by `qemu-user-static` or
+/// `tonistiigi/binfmt`. Without one, foreign-arch containers fail to start.
+pub fn qemu_binfmt_handler(target_arch: &str) -> Option<String> {
+    qemu_binfmt_handler_in(Path::new(BINFMT_MISC_DIR), target_arch)
+}
+
+fn qemu_binfmt_handler_in(binfmt_dir: &Path, target_arch: &str) -> Option<String> {
+    [
+        format!("qemu-{target_arch}"),
+        format!("qemu-{target_arch}-static"),
+    ]
+    .into_iter()
+    .find(|name| {
+        fs::read_to_string(binfmt_dir.join(name))
+            .is_ok_and(|raw| raw.lines().next().map(str::trim) == Some("enabled"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_engine_info_reads_rootless_podman() {
+        let caps = parse_engine_info(
+            r#"{"host":{"cgroupVersion":"v2","security":{"rootless":true},
+                "idMappings":{"uidmap":[{"container_id":0,"host_id":1000,"size":1},
+                {"container_id":1,"host_id":100000,"size":65536}]}},
+                "version":{"Version":"4.9.4"}}"#,
+            Some(4096),
+        );
+        assert_eq!(caps.flavor, EngineFlavor::Podman);
+        assert!(caps.rootless);
+        assert!(caps.id_mapping);
+        assert_eq!(caps.cgroup_version, Some(2));
+        assert_eq!(caps.nofile_limit, Some(4096));
+        assert_eq!(caps.run_args(), vec!["--ulimit", "nofile=4096:4096"]);
+        assert_eq!(caps.warnings().len(), 1);
+        assert!(
+            caps.summary()
+                .contains("engine_flavor=podman engine_version=4.9.4")
+        );
+    }
+
+    #[test]
+    fn parse_engine_info_reads_docker_security_options() {
+        let caps = parse_engine_info(
+            r#"{"ServerVersion":"27.3.1","CgroupVersion":"1",
+                "SecurityOptions":["name=seccomp,profile=builtin","name=userns"]}"#,
+            None,
+        );
+        assert_eq!(caps.flavor, EngineFlavor::Docker);
+        assert!(!caps.rootless);
+        assert!(caps.userns_remap);
+        assert_eq!(caps.cgroup_version, Some(1));
+        assert_eq!(
+            caps.run_args(),
+            vec!["--userns=host", "--ulimit", "nofile=65536:65536"]
+        );
+        assert!(caps.warnings().is_empty());
+        assert!(caps.summary().contains("engine_userns=remap"));
+
+        let rootless = parse_engine_info(
+            r#"{"ServerVersion":"27.3.1","CgroupVersion":"1",
+                "SecurityOptions":["name=seccomp,profile=builtin","name=rootless"]}"#,
+            None,
+        );
+        assert!(rootless.rootless);
+        assert!(!rootless.run_args().contains(&"--userns=host".to_string()));
+        assert!(rootless.warnings()[0].contains("cgroup v1"));
+
+        let unknown = parse_engine_info("not json", None);
+        assert_eq!(unknown.flavor, EngineFlavor::Unknown);
+        assert!(unknown.run_args().is_empty());
+        assert!(unknown.summary().contains("engine_ulimit_nofile=unset"));
+    }
+
+    #[test]
+    fn ownership_labels_and_container_listing_round_trip() {
+        assert_eq!(
+            ownership_labels(Path::new("/rpmbuild"), "almalinux-9.7-x86_64"),
+            vec![
+                "--label",
+                "io.bioconda2rpm.topdir=/rpmbuild",
+                "--label",
+                "io.bioconda2rpm.target-id=almalinux-9.7-x86_64",
+            ]
+        );
+        let listed = parse_container_listing(
+            "3f2a9c1b\tbioconda2rpm-samtools-1\tUp 3 hours\n\n7d1e\tbioconda2rpm-dev-bwa\tExited (137) 2 days ago\n",
+        );
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].name, "bioconda2rpm-samtools-1");
+        assert_eq!(listed[1].id, "7d1e");
+        assert_eq!(listed[1].status, "Exited (137) 2 days ago");
+    }
+
+    #[test]
+    fn qemu_binfmt_handler_requires_an_enabled_entry() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        assert_eq!(qemu_binfmt_handler_in(tmp.path(), "aarch64"), None);
+        fs::write(
+            tmp.path().join("qemu-aarch64"),
+            "disabled\ninterpreter /usr/bin/qemu-aarch64-static\n",
+        )
+        .expect("write entry");
+        assert_eq!(qemu_binfmt_handler_in(tmp.path(), "aarch64"), None);
+        fs::write(
+            tmp.path().join("qemu-aarch64"),
+            "enabled\ninterpreter /usr/bin/qemu-aarch64-static\nflags: F\n",
+        )
+        .expect("write entry");
+        assert_eq!(
+            qemu_binfmt_handler_in(tmp.path(), "aarch64").as_deref(),
+            Some("qemu-aarch64")
+        );
+        assert_eq!(qemu_binfmt_handler_in(tmp.path(), "x86_64"), None);
+    }
+}
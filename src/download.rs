@@ -0,0 +1,266 @@
+use crate::audit::AuditedCommand;
+use crate::config::DownloadsConfig;
+use crate::priority_specs::log_external_progress;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+static DOWNLOAD_MANAGER: OnceLock<DownloadManager> = OnceLock::new();
+
+/// Install the `[downloads]` settings of the project config. The first call
+/// wins; commands call it right after loading their config, before any
+/// download starts.
+pub fn configure(config: &DownloadsConfig) {
+    let _ = DOWNLOAD_MANAGER.set(DownloadManager::new(config.clone()));
+}
+
+/// The process-wide download manager; defaults apply until [`configure`].
+pub fn manager() -> &'static DownloadManager {
+    DOWNLOAD_MANAGER.get_or_init(|| DownloadManager::new(DownloadsConfig::default()))
+}
+
+/// Runs every host-side download through `curl` with a shared parallelism
+/// limit, a per-download rate cap, jittered retry rounds and per-host mirror
+/// failover, logging `phase=download` progress events.
+pub struct DownloadManager {
+    config: DownloadsConfig,
+    active: Mutex<usize>,
+    slot_freed: Condvar,
+}
+
+/// Holds one of the `max-parallel` download slots until dropped.
+struct DownloadSlot<'a> {
+    manager: &'a DownloadManager,
+}
+
+impl Drop for DownloadSlot<'_> {
+    fn drop(&mut self) {
+        let mut active = self
+            .manager
+            .active
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        *active -= 1;
+        self.manager.slot_freed.notify_one();
+    }
+}
+
+impl DownloadManager {
+    pub fn new(config: DownloadsConfig) -> Self {
+        Self {
+            config,
+            active: Mutex::new(0),
+            slot_freed: Condvar::new(),
+        }
+    }
+
+    /// Download `url` to `dest`, replacing it. A failed download leaves no
+    /// file behind. Returns the URL that served the file.
+    pub fn download_file(&self, url: &str, dest: &Path) -> Result<String> {
+        let result = self.fetch(url, Some(dest));
+        match result {
+            Ok((served_by, _)) => Ok(served_by),
+            Err(err) => {
+                let _ = fs::remove_file(dest);
+                Err(err)
+            }
+        }
+    }
+
+    /// Download `url` into memory, e.g. an API response.
+    pub fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        self.fetch(url, None).map(|(_, output)| output.stdout)
+    }
+
+    /// `url` followed by its mirror variants for the URL's host.
+    pub fn candidate_urls(&self, url: &str) -> Vec<String> {
+        let mut candidates = vec![url.to_string()];
+        let Some((scheme, rest)) = url.split_once("://") else {
+            return candidates;
+        };
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let host = authority
+            .rsplit('@')
+            .next()
+            .unwrap_or(authority)
+            .split(':')
+            .next()
+            .unwrap_or_default();
+        if scheme.is_empty() || host.is_empty() {
+            return candidates;
+        }
+        for mirror in self.config.mirrors.get(host).into_iter().flatten() {
+            candidates.push(format!("{}{path}", mirror.trim_end_matches('/')));
+        }
+        candidates
+    }
+
+    fn fetch(&self, url: &str, dest: Option<&Path>) -> Result<(String, Output)> {
+        let _slot = self.acquire_slot(url);
+        let candidates = self.candidate_urls(url);
+        let started = Instant::now();
+        let mut last_error = String::new();
+        for round in 0..=self.config.retries {
+            if round > 0 {
+                let delay = retry_delay(self.config.retry_delay_secs, round, jitter_seed());
+                log_external_progress(format!(
+                    "phase=download status=retry url={url} round={round} delay_ms={} error={last_error}",
+                    delay.as_millis()
+                ));
+                std::thread::sleep(delay);
+            }
+            for (idx, candidate) in candidates.iter().enumerate() {
+                if idx > 0 {
+                    log_external_progress(format!(
+                        "phase=download status=failover url={url} mirror={candidate}"
+                    ));
+                }
+                match self.run_curl(candidate, dest) {
+                    Ok(output) => {
+                        let bytes = match dest {
+                            Some(dest) => fs::metadata(dest).map(|meta| meta.len()).unwrap_or(0),
+                            None => output.stdout.len() as u64,
+                        };
+                        log_external_progress(format!(
+                            "phase=download status=completed url={url} served_by={candidate} bytes={bytes} elapsed_ms={}",
+                            started.elapsed().as_millis()
+                        ));
+                        return Ok((candidate.clone(), output));
+                    }
+                    Err(err) => last_error = format!("{err:#}"),
+                }
+            }
+        }
+        log_external_progress(format!(
+            "phase=download status=failed url={url} attempts={} error={last_error}",
+            (self.config.retries as usize + 1) * candidates.len()
+        ));
+        bail!("downloading {url} failed: {last_error}")
+    }
+
+    fn acquire_slot(&self, url: &str) -> DownloadSlot<'_> {
+        let limit = self.config.max_parallel.max(1);
+        let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        if *active >= limit {
+            log_external_progress(format!(
+                "phase=download status=queued url={url} active={} max_parallel={limit}",
+                *active
+            ));
+        }
+        while *active >= limit {
+            active = self
+                .slot_freed
+                .wait(active)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        *active += 1;
+        DownloadSlot { manager: self }
+    }
+
+    fn run_curl(&self, url: &str, dest: Option<&Path>) -> Result<Output> {
+        let mut command = Command::new("curl");
+        command.args([
+            "-L",
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--connect-timeout",
+            "30",
+        ]);
+        if let Some(rate) = self.config.max_rate.as_deref() {
+            command.arg("--limit-rate").arg(rate);
+        }
+        if let Some(dest) = dest {
+            command.arg("-o").arg(dest);
+        }
+        let output = command.arg(url).audited_output().context("running curl")?;
+        if !output.status.success() {
+            bail!(
+                "curl failed for {url}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output)
+    }
+}
+
+/// `base_secs * 2^(round - 1)` plus up to half of that again, picked by `seed`.
+fn retry_delay(base_secs: u64, round: u32, seed: u64) -> Duration {
+    let base_ms = base_secs.saturating_mul(1000) << (round.saturating_sub(1)).min(6);
+    let jitter_ms = if base_ms == 0 {
+        0
+    } else {
+        seed % (base_ms / 2 + 1)
+    };
+    Duration::from_millis(base_ms + jitter_ms)
+}
+
+fn jitter_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos() as u64)
+        .unwrap_or_default();
+    nanos ^ (std::process::id() as u64).rotate_left(17)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn downloads_fail_over_to_host_mirrors_with_jittered_retries() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mirror_root = tmp.path().join("mirror");
+        fs::create_dir_all(mirror_root.join("pub")).expect("mirror dir");
+        fs::write(mirror_root.join("pub/tool.tar.gz"), b"payload").expect("mirror file");
+        let manager = DownloadManager::new(DownloadsConfig {
+            retries: 1,
+            retry_delay_secs: 0,
+            mirrors: BTreeMap::from([(
+                "ftp.example.invalid".to_string(),
+                vec![
+                    "https://mirror.invalid/".to_string(),
+                    format!("file://{}", mirror_root.display()),
+                ],
+            )]),
+            ..DownloadsConfig::default()
+        });
+
+        let url = "https://ftp.example.invalid/pub/tool.tar.gz";
+        assert_eq!(
+            manager.candidate_urls(url),
+            vec![
+                url.to_string(),
+                "https://mirror.invalid/pub/tool.tar.gz".to_string(),
+                format!("file://{}/pub/tool.tar.gz", mirror_root.display()),
+            ]
+        );
+        assert_eq!(
+            manager.candidate_urls("https://other.example/x"),
+            vec!["https://other.example/x".to_string()]
+        );
+
+        // The original host does not resolve; the file:// mirror serves it.
+        let dest = tmp.path().join("tool.tar.gz");
+        let served_by = manager.download_file(url, &dest).expect("download");
+        assert!(served_by.starts_with("file://"));
+        assert_eq!(fs::read(&dest).expect("read download"), b"payload");
+
+        let missing = tmp.path().join("missing.tar.gz");
+        assert!(
+            manager
+                .download_file("file:///nonexistent/tool.tar.gz", &missing)
+                .is_err()
+        );
+        assert!(!missing.exists());
+
+        assert_eq!(retry_delay(2, 1, 0), Duration::from_secs(2));
+        assert_eq!(retry_delay(2, 3, 0), Duration::from_secs(8));
+        assert!(retry_delay(2, 3, u64::MAX) <= Duration::from_secs(12));
+        assert_eq!(retry_delay(0, 2, 7), Duration::ZERO);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::priority_specs::{
+    FAILED_BUILD_MARKERS_DIR, GIT_SOURCE_CACHE_DIR, conda_adapter_scratch_dir,
+    log_external_progress,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct GcRequest {
+    pub session_topdir: PathBuf,
+    /// Work trees and adapter scratch entries untouched for longer are removed.
+    pub max_age: Duration,
+    /// Newest git source clones kept per repository URL.
+    pub keep_git_revisions: usize,
+    pub dry_run: bool,
+}
+
+/// Entries removed (or, with `dry_run`, removable) in one area.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct GcCategory {
+    pub removed: usize,
+    pub bytes: u64,
+    /// Entries that could not be removed, e.g. trees owned by a container root.
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct GcSummary {
+    /// `<topdir>/.build-work/<label>` trees and `.build-start-<label>.ts` stamps.
+    pub build_work: GcCategory,
+    /// `<topdir>/SOURCES/git-cache/<repo>-<rev>-<hash>` clones.
+    pub git_cache: GcCategory,
+    /// conda-build work dirs and temp files of the conda metadata adapter.
+    pub conda_adapter: GcCategory,
+    /// `failed_builds` markers of targets whose work tree is gone.
+    pub failed_build_markers: GcCategory,
+}
+
+impl GcSummary {
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.build_work.bytes
+            + self.git_cache.bytes
+            + self.conda_adapter.bytes
+            + self.failed_build_markers.bytes
+    }
+
+    pub fn failed(&self) -> usize {
+        self.build_work.failed
+            + self.git_cache.failed
+            + self.conda_adapter.failed
+            + self.failed_build_markers.failed
+    }
+}
+
+/// Prune intermediate artifacts of earlier runs. Callers hold the workspace
+/// lock so no live build is using what gets removed.
+pub fn run_gc(request: &GcRequest) -> Result<GcSummary> {
+    let now = SystemTime::now();
+    let topdir = &request.session_topdir;
+    let mut summary = GcSummary::default();
+
+    let build_work = topdir.join(".build-work");
+    for path in entries_older_than(&build_work, request.max_age, now)? {
+        prune(
+            &path,
+            "build-work",
+            request.dry_run,
+            &mut summary.build_work,
+        );
+    }
+    let stamps = dir_entries(topdir)?
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(".build-start-") && name.ends_with(".ts"))
+        })
+        .filter(|path| older_than(path, request.max_age, now))
+        .collect::<Vec<_>>();
+    for path in stamps {
+        prune(
+            &path,
+            "build-stamp",
+            request.dry_run,
+            &mut summary.build_work,
+        );
+    }
+
+    let git_cache = topdir.join("SOURCES").join(GIT_SOURCE_CACHE_DIR);
+    for path in stale_git_cache_entries(&git_cache, request.keep_git_revisions)? {
+        prune(&path, "git-cache", request.dry_run, &mut summary.git_cache);
+    }
+
+    let scratch = conda_adapter_scratch_dir();
+    for area in ["conda-bld", "tmp"] {
+        for path in entries_older_than(&scratch.join(area), request.max_age, now)? {
+            prune(
+                &path,
+                "conda-adapter",
+                request.dry_run,
+                &mut summary.conda_adapter,
+            );
+        }
+    }
+
+    for target_root in dir_entries(&topdir.join("targets"))? {
+        let markers_dir = target_root.join("reports").join(FAILED_BUILD_MARKERS_DIR);
+        for marker in dir_entries(&markers_dir)? {
+            let Some(label) = fs::read_to_string(&marker).ok().and_then(|raw| {
+                raw.trim_end()
+                    .split_once('\t')
+                    .map(|(_, label)| label.to_string())
+            }) else {
+                continue;
+            };
+            // In a dry run the work tree is still there when it would be pruned.
+            let tree = build_work.join(&label);
+            if !tree.exists() || (request.dry_run && older_than(&tree, request.max_age, now)) {
+                prune(
+                    &marker,
+                    "failed-build-marker",
+                    request.dry_run,
+                    &mut summary.failed_build_markers,
+                );
+            }
+        }
+    }
+    Ok(summary)
+}
+
+fn prune(path: &Path, kind: &str, dry_run: bool, category: &mut GcCategory) {
+    let bytes = disk_usage(path);
+    if dry_run {
+        log_external_progress(format!(
+            "phase=gc status=would-remove kind={kind} path={} bytes={bytes}",
+            path.display()
+        ));
+        category.removed += 1;
+        category.bytes += bytes;
+        return;
+    }
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match removed {
+        Ok(()) => {
+            log_external_progress(format!(
+                "phase=gc status=removed kind={kind} path={} bytes={bytes}",
+                path.display()
+            ));
+            category.removed += 1;
+            category.bytes += bytes;
+        }
+        Err(err) => {
+            log_external_progress(format!(
+                "phase=gc status=warning kind={kind} path={} reason={err}",
+                path.display()
+            ));
+            category.failed += 1;
+        }
+    }
+}
+
+fn dir_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        entries.push(
+            entry
+                .with_context(|| format!("reading entry in {}", dir.display()))?
+                .path(),
+        );
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+fn entries_older_than(dir: &Path, max_age: Duration, now: SystemTime) -> Result<Vec<PathBuf>> {
+    Ok(dir_entries(dir)?
+        .into_iter()
+        .filter(|path| older_than(path, max_age, now))
+        .collect())
+}
+
+fn older_than(path: &Path, max_age: Duration, now: SystemTime) -> bool {
+    fs::symlink_metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|age| age >= max_age)
+}
+
+/// Git cache clones beyond the newest `keep` per repository URL, plus
+/// `.partial` staging dirs of interrupted clones. Entries are named
+/// `<repo>-<rev>-<url-hash>`, so the hash suffix groups revisions of one URL.
+fn stale_git_cache_entries(cache_dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let mut by_url: BTreeMap<String, Vec<(SystemTime, PathBuf)>> = BTreeMap::new();
+    let mut stale = Vec::new();
+    for path in dir_entries(cache_dir)? {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.ends_with(".partial") {
+            stale.push(path);
+            continue;
+        }
+        let Some((_, hash)) = name.rsplit_once('-') else {
+            continue;
+        };
+        let hash = hash.to_string();
+        let modified = fs::symlink_metadata(&path)
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        by_url.entry(hash).or_default().push((modified, path));
+    }
+    for mut clones in by_url.into_values() {
+        clones.sort_by(|a, b| b.cmp(a));
+        stale.extend(clones.into_iter().skip(keep).map(|(_, path)| path));
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+/// Apparent size of `path` and everything below it; symlinks are not followed.
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_age(path: &Path, age: Duration) {
+        let file = fs::File::open(path).expect("open");
+        file.set_modified(SystemTime::now() - age)
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn gc_prunes_old_work_trees_and_surplus_git_clones() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let topdir = tmp.path();
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let old_tree = topdir.join(".build-work/samtools");
+        let fresh_tree = topdir.join(".build-work/bcftools");
+        for tree in [&old_tree, &fresh_tree] {
+            fs::create_dir_all(tree.join("SOURCES")).expect("mkdir");
+            fs::write(tree.join("SOURCES/src.tar.gz"), vec![0u8; 100]).expect("write");
+        }
+        set_age(&old_tree, 30 * day);
+        let markers = topdir
+            .join("targets/el9/reports")
+            .join(FAILED_BUILD_MARKERS_DIR);
+        fs::create_dir_all(&markers).expect("mkdir markers");
+        fs::write(markers.join("samtools.txt"), "1\tsamtools\n").expect("marker");
+        fs::write(markers.join("bcftools.txt"), "2\tbcftools\n").expect("marker");
+
+        let cache = topdir.join("SOURCES").join(GIT_SOURCE_CACHE_DIR);
+        for (name, age) in [
+            ("nanopolish-v0.13.0-0badc0de", 3),
+            ("nanopolish-v0.14.0-0badc0de", 1),
+            ("nanopolish-v0.14.0-12345678", 9),
+            ("nanopolish-v0.15.0-0badc0de.partial", 0),
+        ] {
+            let clone = cache.join(name);
+            fs::create_dir_all(&clone).expect("mkdir clone");
+            fs::write(clone.join("README"), "x").expect("write");
+            set_age(&clone, age * day);
+        }
+
+        let request = GcRequest {
+            session_topdir: topdir.to_path_buf(),
+            max_age: 14 * day,
+            keep_git_revisions: 1,
+            dry_run: true,
+        };
+        let planned = run_gc(&request).expect("dry run");
+        assert_eq!(planned.build_work.removed, 1);
+        assert_eq!(planned.build_work.bytes, 100);
+        assert_eq!(planned.git_cache.removed, 2);
+        assert!(old_tree.exists());
+
+        let summary = run_gc(&GcRequest {
+            dry_run: false,
+            ..request
+        })
+        .expect("gc");
+        assert_eq!(summary.build_work, planned.build_work);
+        assert_eq!(summary.build_work.bytes + summary.git_cache.bytes, 102);
+        assert!(!old_tree.exists());
+        assert!(fresh_tree.exists());
+        assert!(!cache.join("nanopolish-v0.13.0-0badc0de").exists());
+        assert!(cache.join("nanopolish-v0.14.0-0badc0de").exists());
+        assert!(cache.join("nanopolish-v0.14.0-12345678").exists());
+        assert!(!markers.join("samtools.txt").exists());
+        assert!(markers.join("bcftools.txt").exists());
+    }
+}
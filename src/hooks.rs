@@ -0,0 +1,293 @@
+use crate::audit::AuditedCommand;
+use crate::config::HooksConfig;
+use crate::priority_specs::{BuildSummary, ReportEntry, log_external_progress};
+use crate::telemetry::clone_io_error;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PrePackage,
+    PostPackage,
+    PostRun,
+}
+
+impl HookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookEvent::PrePackage => "pre-package",
+            HookEvent::PostPackage => "post-package",
+            HookEvent::PostRun => "post-run",
+        }
+    }
+}
+
+/// Package context of a `pre-package` or `post-package` hook. Before the
+/// build the status is `building`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookPackage {
+    pub name: String,
+    pub package_name: String,
+    pub version: String,
+    pub status: String,
+    pub reason: String,
+    pub payload_spec_path: String,
+    pub meta_spec_path: String,
+    pub staged_build_sh: String,
+    pub meta_nevra: String,
+}
+
+impl From<&ReportEntry> for HookPackage {
+    fn from(entry: &ReportEntry) -> Self {
+        Self {
+            name: entry.software.clone(),
+            package_name: entry.package_name.clone(),
+            version: entry.version.clone(),
+            status: entry.status.clone(),
+            reason: entry.reason.clone(),
+            payload_spec_path: entry.payload_spec_path.clone(),
+            meta_spec_path: entry.meta_spec_path.clone(),
+            staged_build_sh: entry.staged_build_sh.clone(),
+            meta_nevra: entry.meta_nevra.clone(),
+        }
+    }
+}
+
+/// Run context of a `post-run` hook.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookRun {
+    pub requested: usize,
+    pub generated: usize,
+    pub up_to_date: usize,
+    pub skipped: usize,
+    pub quarantined: usize,
+    pub kpi_success_rate: f64,
+    pub report_json: PathBuf,
+    pub report_csv: PathBuf,
+    pub report_md: PathBuf,
+}
+
+impl From<&BuildSummary> for HookRun {
+    fn from(summary: &BuildSummary) -> Self {
+        Self {
+            requested: summary.requested,
+            generated: summary.generated,
+            up_to_date: summary.up_to_date,
+            skipped: summary.skipped,
+            quarantined: summary.quarantined,
+            kpi_success_rate: summary.kpi_success_rate,
+            report_json: summary.report_json.clone(),
+            report_csv: summary.report_csv.clone(),
+            report_md: summary.report_md.clone(),
+        }
+    }
+}
+
+/// JSON document a hook reads from stdin.
+#[derive(Debug, Clone, Serialize)]
+struct HookPayload<'a> {
+    event: &'static str,
+    target_id: &'a str,
+    topdir: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<HookPackage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run: Option<HookRun>,
+}
+
+/// Runs the `[hooks]` programs of the project config for one build target.
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    config: HooksConfig,
+    topdir: PathBuf,
+    target_id: String,
+}
+
+impl HookRunner {
+    /// `None` when no hook is configured.
+    pub fn from_config(config: &HooksConfig, topdir: &Path, target_id: &str) -> Option<Self> {
+        if config.pre_package.is_empty()
+            && config.post_package.is_empty()
+            && config.post_run.is_empty()
+        {
+            return None;
+        }
+        Some(Self {
+            config: config.clone(),
+            topdir: topdir.to_path_buf(),
+            target_id: target_id.to_string(),
+        })
+    }
+
+    /// Failing package hooks quarantine the package.
+    pub fn fail_on_error(&self) -> bool {
+        self.config.fail_on_error
+    }
+
+    /// Run the `pre-package` or `post-package` hook; `Ok(false)` when it is
+    /// not configured.
+    pub fn run_package(&self, event: HookEvent, package: HookPackage) -> Result<bool> {
+        self.run(event, Some(package), None)
+    }
+
+    /// Run the `post-run` hook; `Ok(false)` when it is not configured.
+    pub fn run_post_run(&self, run: HookRun) -> Result<bool> {
+        self.run(HookEvent::PostRun, None, Some(run))
+    }
+
+    fn run(
+        &self,
+        event: HookEvent,
+        package: Option<HookPackage>,
+        run: Option<HookRun>,
+    ) -> Result<bool> {
+        let argv = match event {
+            HookEvent::PrePackage => &self.config.pre_package,
+            HookEvent::PostPackage => &self.config.post_package,
+            HookEvent::PostRun => &self.config.post_run,
+        };
+        let Some((program, args)) = argv.split_first() else {
+            return Ok(false);
+        };
+        let name = package
+            .as_ref()
+            .map(|package| package.name.clone())
+            .unwrap_or_default();
+        let payload = serde_json::to_vec(&HookPayload {
+            event: event.as_str(),
+            target_id: &self.target_id,
+            topdir: &self.topdir,
+            package,
+            run,
+        })
+        .context("serializing hook payload")?;
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .env("BIOCONDA2RPM_HOOK_EVENT", event.as_str())
+            .env("BIOCONDA2RPM_TARGET_ID", &self.target_id)
+            .env("BIOCONDA2RPM_TOPDIR", &self.topdir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if !name.is_empty() {
+            command.env("BIOCONDA2RPM_PACKAGE", &name);
+        }
+        let (mut child, pending_audit) = command
+            .audited_spawn()
+            .with_context(|| format!("starting {} hook {program}", event.as_str()))?;
+        // Written from a thread so a hook that prints before reading stdin
+        // cannot block on a full pipe; hooks that ignore stdin close it early.
+        let writer = child.stdin.take().map(|mut stdin| {
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(&payload);
+            })
+        });
+        let output = child.wait_with_output();
+        pending_audit.finish(&output.as_ref().map(|o| o.status).map_err(clone_io_error));
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        let output =
+            output.with_context(|| format!("waiting for {} hook {program}", event.as_str()))?;
+        let exit_code = output
+            .status
+            .code()
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "none".to_string());
+        log_external_progress(format!(
+            "phase=hook status={} event={} package={} exit_code={exit_code}",
+            if output.status.success() {
+                "completed"
+            } else {
+                "failed"
+            },
+            event.as_str(),
+            if name.is_empty() { "-" } else { name.as_str() }
+        ));
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let last_line = stderr.lines().rev().find(|line| !line.trim().is_empty());
+            match last_line {
+                Some(line) => bail!(
+                    "{} hook exited {exit_code}: {}",
+                    event.as_str(),
+                    line.trim()
+                ),
+                None => bail!("{} hook exited {exit_code}", event.as_str()),
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn package_hook_receives_json_payload_and_reports_failures() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let script = tmp.path().join("hook.sh");
+        fs::write(
+            &script,
+            "#!/bin/bash\ncat > \"$1/$BIOCONDA2RPM_HOOK_EVENT-$BIOCONDA2RPM_PACKAGE.json\"\n[ \"$BIOCONDA2RPM_PACKAGE\" != bad ] || { echo 'virus found' >&2; exit 3; }\n",
+        )
+        .expect("write hook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+        let argv = vec![
+            script.display().to_string(),
+            tmp.path().display().to_string(),
+        ];
+        let config = HooksConfig {
+            post_package: argv,
+            ..HooksConfig::default()
+        };
+        assert!(HookRunner::from_config(&HooksConfig::default(), tmp.path(), "el9").is_none());
+        let runner = HookRunner::from_config(&config, tmp.path(), "el9").expect("runner");
+        let package = |name: &str| HookPackage {
+            name: name.to_string(),
+            package_name: name.to_string(),
+            version: "1.21".to_string(),
+            status: "generated".to_string(),
+            reason: String::new(),
+            payload_spec_path: format!("SPECS/phoreus-{name}-1.21.spec"),
+            meta_spec_path: format!("SPECS/phoreus-{name}.spec"),
+            staged_build_sh: String::new(),
+            meta_nevra: String::new(),
+        };
+
+        assert!(
+            !runner
+                .run_package(HookEvent::PrePackage, package("samtools"))
+                .expect("unconfigured hook")
+        );
+        assert!(
+            runner
+                .run_package(HookEvent::PostPackage, package("samtools"))
+                .expect("hook")
+        );
+        let payload: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(tmp.path().join("post-package-samtools.json")).expect("payload"),
+        )
+        .expect("json payload");
+        assert_eq!(payload["event"], "post-package");
+        assert_eq!(payload["target_id"], "el9");
+        assert_eq!(payload["package"]["version"], "1.21");
+        assert_eq!(payload["package"]["status"], "generated");
+
+        let err = runner
+            .run_package(HookEvent::PostPackage, package("bad"))
+            .expect_err("failing hook");
+        assert_eq!(
+            format!("{err:#}"),
+            "post-package hook exited 3: virus found"
+        );
+    }
+}
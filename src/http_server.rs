@@ -0,0 +1,262 @@
+use crate::priority_specs::log_external_progress;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Largest request body read; bigger requests are refused.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Longest request line or header line read; longer ones are refused.
+const MAX_HEADER_LINE_BYTES: usize = 8 * 1024;
+/// Most header lines read per request.
+const MAX_HEADERS: usize = 100;
+
+/// One HTTP/1.1 request; header names are lower-cased.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    /// Percent-decoded path without the query.
+    pub path: String,
+    pub query: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+    pub peer: Option<SocketAddr>,
+}
+
+pub struct HttpResponse {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: HttpBody,
+}
+
+pub enum HttpBody {
+    Bytes(Vec<u8>),
+    /// Copied from disk while the response is written; `len` is the file
+    /// size when it was opened.
+    File {
+        file: File,
+        len: u64,
+    },
+}
+
+impl HttpBody {
+    fn len(&self) -> u64 {
+        match self {
+            Self::Bytes(bytes) => bytes.len() as u64,
+            Self::File { len, .. } => *len,
+        }
+    }
+}
+
+pub type Handler = Arc<dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static>;
+
+impl HttpRequest {
+    /// Percent-decoded value of query parameter `name`.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| percent_decode(&value.replace('+', " ")))
+        })
+    }
+
+    /// Token of an `Authorization: Bearer <token>` header.
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.headers
+            .get("authorization")?
+            .strip_prefix("Bearer ")
+            .map(str::trim)
+    }
+}
+
+impl HttpResponse {
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body: HttpBody::Bytes(body.into()),
+        }
+    }
+
+    /// `200 OK` streaming the file at `path` without reading it into memory.
+    pub fn file(content_type: &'static str, path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            status: "200 OK",
+            content_type,
+            body: HttpBody::File { file, len },
+        })
+    }
+
+    pub fn json(status: &'static str, value: &impl Serialize) -> Self {
+        match serde_json::to_vec_pretty(value) {
+            Ok(body) => Self {
+                status,
+                content_type: "application/json",
+                body: HttpBody::Bytes(body),
+            },
+            Err(err) => Self::error("500 Internal Server Error", &format!("{err:#}")),
+        }
+    }
+
+    pub fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: HttpBody::Bytes(format!("{status}: {message}\n").into_bytes()),
+        }
+    }
+}
+
+/// `--listen` value: `host:port`, or `:port` for every interface.
+pub fn parse_listen_addr(listen: &str) -> Result<SocketAddr> {
+    let addr = if listen.starts_with(':') {
+        format!("0.0.0.0{listen}")
+    } else {
+        listen.to_string()
+    };
+    addr.to_socket_addrs()
+        .with_context(|| format!("invalid listen address '{listen}'"))?
+        .next()
+        .with_context(|| format!("listen address '{listen}' did not resolve"))
+}
+
+/// Answer connections on `listener` with `handler`, one thread per
+/// connection, until the process exits. `phase` names the progress events.
+pub fn serve_connections(listener: TcpListener, phase: &'static str, handler: Handler) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log_external_progress(format!("phase={phase} status=accept-failed reason={err}"));
+                continue;
+            }
+        };
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream, handler.as_ref()) {
+                log_external_progress(format!(
+                    "phase={phase} status=connection-failed reason={err:#}"
+                ));
+            }
+        });
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    handler: &(dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync),
+) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let (response, head_only, refused) = match read_request(&stream) {
+        Ok(request) => (handler(&request), request.method == "HEAD", false),
+        Err(err) => (
+            HttpResponse::error("400 Bad Request", &format!("{err:#}")),
+            false,
+            true,
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    if !head_only {
+        match response.body {
+            HttpBody::Bytes(bytes) => stream.write_all(&bytes)?,
+            HttpBody::File { file, len } => {
+                io::copy(&mut file.take(len), &mut stream)?;
+            }
+        }
+    }
+    stream.flush()?;
+    if refused {
+        // Drain what is left of a refused request so closing the socket
+        // does not reset the connection before the client read the 400.
+        stream.shutdown(Shutdown::Write)?;
+        let _ = io::copy(&mut (&stream).take(MAX_BODY_BYTES as u64), &mut io::sink());
+    }
+    Ok(())
+}
+
+/// Next CRLF- or LF-terminated line, refused when it is longer than
+/// [`MAX_HEADER_LINE_BYTES`]. Empty at end of stream.
+fn read_header_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    reader
+        .take(MAX_HEADER_LINE_BYTES as u64 + 1)
+        .read_line(&mut line)?;
+    if line.len() > MAX_HEADER_LINE_BYTES {
+        bail!("request line or header over {MAX_HEADER_LINE_BYTES} bytes");
+    }
+    Ok(line)
+}
+
+fn read_request(stream: &TcpStream) -> Result<HttpRequest> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request_line = read_header_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line");
+    };
+    let mut headers = BTreeMap::new();
+    for count in 0.. {
+        let line = read_header_line(&mut reader)?;
+        if line.is_empty() || line.trim().is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            bail!("more than {MAX_HEADERS} request headers");
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let length = match headers.get("content-length") {
+        Some(value) => value
+            .parse::<usize>()
+            .with_context(|| format!("invalid Content-Length '{value}'"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        bail!("request body over {MAX_BODY_BYTES} bytes");
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Ok(HttpRequest {
+        method: method.to_string(),
+        path: percent_decode(path),
+        query: query.to_string(),
+        headers,
+        body,
+        peer: stream.peer_addr().ok(),
+    })
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%'
+            && let Some(hex) = value.get(idx + 1..idx + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            out.push(byte);
+            idx += 3;
+            continue;
+        }
+        out.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
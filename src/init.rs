@@ -0,0 +1,416 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
+use crate::cli::{
+    InitArgs, default_managed_recipe_root, default_topdir, infer_recipe_repo_root,
+    normalize_recipe_root_input,
+};
+use crate::config::{STARTER_CONFIG, default_config_path, load_project_config};
+use crate::priority_specs::{
+    container_image_exists, ensure_container_engine_available, ensure_container_profile_available,
+    log_external_progress,
+};
+use crate::recipe_repo;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// What `init` sets up once flags and answers are combined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitPlan {
+    pub topdir: PathBuf,
+    pub recipe_root: PathBuf,
+    pub config_path: PathBuf,
+    pub container_engine: String,
+    pub clone_recipes: bool,
+    pub prepare_image: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InitCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct InitSummary {
+    pub plan: InitPlan,
+    pub target_id: String,
+    /// The starter config was written; `false` keeps an existing file.
+    pub config_written: bool,
+    pub checks: Vec<InitCheck>,
+}
+
+impl InitSummary {
+    pub fn failed_checks(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+}
+
+/// Combine flags with answers read from `input`; unanswered (empty) prompts
+/// keep the default shown in brackets.
+pub fn prompt_init_plan(
+    args: &InitArgs,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<InitPlan> {
+    let topdir = match &args.topdir {
+        Some(topdir) => topdir.clone(),
+        None => PathBuf::from(ask(
+            input,
+            output,
+            "Workspace topdir",
+            &default_topdir().display().to_string(),
+        )?),
+    };
+    let container_engine = match &args.container_engine {
+        Some(engine) => engine.clone(),
+        None => ask(
+            input,
+            output,
+            "Container engine (docker or podman)",
+            "docker",
+        )?,
+    };
+    let clone_recipes = !args.no_recipes
+        && args.recipe_root.is_none()
+        && confirm(input, output, "Clone bioconda-recipes now (about 1 GB)")?;
+    let prepare_image = !args.no_image
+        && confirm(
+            input,
+            output,
+            &format!(
+                "Pull or build the {} build image",
+                args.effective_container_image()
+            ),
+        )?;
+    Ok(plan_from(
+        args,
+        topdir,
+        container_engine,
+        clone_recipes,
+        prepare_image,
+    ))
+}
+
+/// The plan without prompts: flags, else defaults.
+pub fn default_init_plan(args: &InitArgs) -> InitPlan {
+    plan_from(
+        args,
+        args.topdir.clone().unwrap_or_else(default_topdir),
+        args.container_engine
+            .clone()
+            .unwrap_or_else(|| "docker".to_string()),
+        !args.no_recipes && args.recipe_root.is_none(),
+        !args.no_image,
+    )
+}
+
+fn plan_from(
+    args: &InitArgs,
+    topdir: PathBuf,
+    container_engine: String,
+    clone_recipes: bool,
+    prepare_image: bool,
+) -> InitPlan {
+    let recipe_root = args
+        .recipe_root
+        .as_deref()
+        .map(normalize_recipe_root_input)
+        .unwrap_or_else(|| default_managed_recipe_root(&topdir));
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| default_config_path(&topdir));
+    InitPlan {
+        topdir,
+        recipe_root,
+        config_path,
+        container_engine,
+        clone_recipes,
+        prepare_image,
+    }
+}
+
+fn ask(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> Result<String> {
+    write!(output, "{question} [{default}]: ").context("writing prompt")?;
+    output.flush().context("writing prompt")?;
+    let mut answer = String::new();
+    input.read_line(&mut answer).context("reading answer")?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+fn confirm(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> Result<bool> {
+    loop {
+        let answer = ask(input, output, &format!("{question}? (y/n)"), "y")?;
+        match answer.to_ascii_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => writeln!(output, "please answer y or n").context("writing prompt")?,
+        }
+    }
+}
+
+/// Create the workspace layout and starter config, then clone recipes and
+/// prepare the build image as planned and run the health checks. Steps that
+/// fail become failed checks so one run reports everything left to fix.
+pub fn run_init(args: &InitArgs, plan: InitPlan) -> Result<InitSummary> {
+    let target_id = args.effective_target_id();
+    let target_root = plan.topdir.join("targets").join(&target_id);
+    for dir in [
+        plan.topdir.clone(),
+        plan.topdir.join("SPECS"),
+        plan.topdir.join("SOURCES"),
+        target_root.join("BAD_SPEC"),
+        target_root.join("reports"),
+    ] {
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    log_external_progress(format!(
+        "phase=init status=layout topdir={} target_id={target_id}",
+        plan.topdir.display()
+    ));
+    let config_written = write_starter_config(&plan.config_path)?;
+
+    let mut checks = Vec::new();
+    checks.push(
+        match load_project_config(Some(&plan.config_path), &plan.topdir) {
+            Ok(_) => check_ok("config", plan.config_path.display().to_string()),
+            Err(err) => check_fail("config", format!("{err:#}")),
+        },
+    );
+    checks.push(recipes_check(&plan));
+    let engine_ready = ensure_container_engine_available(&plan.container_engine);
+    checks.push(match &engine_ready {
+        Ok(()) => check_ok("container-engine", plan.container_engine.clone()),
+        Err(err) => check_fail(
+            "container-engine",
+            format!("{err:#}; install it or pass --container-engine"),
+        ),
+    });
+    if engine_ready.is_ok() {
+        checks.push(image_check(args, &plan));
+    }
+    checks.push(conda_adapter_check());
+    for check in &checks {
+        log_external_progress(format!(
+            "phase=init status=check name={} result={}",
+            check.name,
+            check.status.as_str()
+        ));
+    }
+    Ok(InitSummary {
+        plan,
+        target_id,
+        config_written,
+        checks,
+    })
+}
+
+/// Write [`STARTER_CONFIG`] unless a config already exists.
+fn write_starter_config(path: &Path) -> Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    write_atomic(path, STARTER_CONFIG)
+        .with_context(|| format!("writing starter config {}", path.display()))?;
+    Ok(true)
+}
+
+fn recipes_check(plan: &InitPlan) -> InitCheck {
+    if plan.clone_recipes {
+        let request = recipe_repo::RecipeRepoRequest {
+            recipe_root: plan.recipe_root.clone(),
+            recipe_repo_root: infer_recipe_repo_root(&plan.recipe_root),
+            recipe_ref: None,
+            sync: false,
+            policy: crate::cli::RecipeSyncPolicy::Require,
+            pin: None,
+        };
+        if let Err(err) = recipe_repo::ensure_recipe_repository(&request) {
+            return check_fail("recipes", format!("{err:#}"));
+        }
+    }
+    if plan.recipe_root.is_dir() {
+        check_ok("recipes", plan.recipe_root.display().to_string())
+    } else if plan.clone_recipes {
+        check_fail(
+            "recipes",
+            format!("{} missing after clone", plan.recipe_root.display()),
+        )
+    } else {
+        check_warn(
+            "recipes",
+            format!(
+                "{} not found; the first build clones it, or pass --recipe-root",
+                plan.recipe_root.display()
+            ),
+        )
+    }
+}
+
+fn image_check(args: &InitArgs, plan: &InitPlan) -> InitCheck {
+    let image = args.effective_container_image();
+    if plan.prepare_image {
+        return match ensure_container_profile_available(
+            &plan.container_engine,
+            args.container_profile,
+            &args.effective_target_arch(),
+        ) {
+            Ok(()) => check_ok("build-image", image.to_string()),
+            Err(err) => check_fail("build-image", format!("{err:#}")),
+        };
+    }
+    match container_image_exists(&plan.container_engine, image) {
+        Ok(true) => check_ok("build-image", image.to_string()),
+        Ok(false) => check_warn(
+            "build-image",
+            format!("{image} not present; the first build pulls or builds it"),
+        ),
+        Err(err) => check_fail("build-image", format!("{err:#}")),
+    }
+}
+
+/// The conda render adapter is optional: without it `--metadata-adapter
+/// auto` uses the native parser and the production profile fails.
+fn conda_adapter_check() -> InitCheck {
+    let output = Command::new("python3")
+        .arg("-c")
+        .arg("import conda_build; print(conda_build.__version__)")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .audited_output();
+    match output {
+        Ok(output) if output.status.success() => check_ok(
+            "conda-adapter",
+            format!(
+                "conda-build {}",
+                String::from_utf8_lossy(&output.stdout).trim()
+            ),
+        ),
+        _ => check_warn(
+            "conda-adapter",
+            "host python3 has no conda-build; auto uses the native parser, or set [adapter-env] managed = true"
+                .to_string(),
+        ),
+    }
+}
+
+fn check_ok(name: &'static str, detail: String) -> InitCheck {
+    InitCheck {
+        name,
+        status: CheckStatus::Ok,
+        detail,
+    }
+}
+
+fn check_warn(name: &'static str, detail: String) -> InitCheck {
+    InitCheck {
+        name,
+        status: CheckStatus::Warn,
+        detail,
+    }
+}
+
+fn check_fail(name: &'static str, detail: String) -> InitCheck {
+    InitCheck {
+        name,
+        status: CheckStatus::Fail,
+        detail,
+    }
+}
+
+/// Reject a topdir that exists as something other than a directory before
+/// anything is written.
+pub fn validate_init_plan(plan: &InitPlan) -> Result<()> {
+    if plan.topdir.exists() && !plan.topdir.is_dir() {
+        bail!(
+            "topdir {} exists and is not a directory",
+            plan.topdir.display()
+        );
+    }
+    if plan.container_engine.trim().is_empty()
+        || plan.container_engine.contains(char::is_whitespace)
+    {
+        bail!(
+            "container engine '{}' must be a single program name",
+            plan.container_engine
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct InitCli {
+        #[command(flatten)]
+        init: InitArgs,
+    }
+
+    #[test]
+    fn prompt_init_plan_keeps_flags_and_takes_answers_or_defaults() {
+        let args = InitCli::parse_from(["init", "--container-engine", "podman", "--no-image"]).init;
+        let mut input = "/srv/b2r\nmaybe\nn\n".as_bytes();
+        let mut output = Vec::new();
+        let plan = prompt_init_plan(&args, &mut input, &mut output).expect("plan");
+        assert_eq!(plan.topdir, PathBuf::from("/srv/b2r"));
+        assert_eq!(plan.container_engine, "podman");
+        assert!(!plan.clone_recipes);
+        assert!(!plan.prepare_image);
+        assert_eq!(
+            plan.config_path,
+            PathBuf::from("/srv/b2r/bioconda2rpm.toml")
+        );
+        assert_eq!(
+            plan.recipe_root,
+            PathBuf::from("/srv/b2r/bioconda-recipes/recipes")
+        );
+        let prompts = String::from_utf8(output).expect("utf8 prompts");
+        assert!(prompts.contains("Workspace topdir ["));
+        assert!(!prompts.contains("Container engine"));
+        assert!(prompts.contains("please answer y or n"));
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        assert!(write_starter_config(&tmp.path().join("bioconda2rpm.toml")).expect("write"));
+        assert!(!write_starter_config(&tmp.path().join("bioconda2rpm.toml")).expect("keep"));
+    }
+}
@@ -0,0 +1,439 @@
+use crate::atomic_file::write_atomic;
+use anyhow::{Context, Result};
+use csv::Writer;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How far a license reaches into software that bundles the package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LicenseClass {
+    Permissive,
+    /// File- or library-level copyleft (LGPL, MPL, EPL, CDDL).
+    WeakCopyleft,
+    /// Copyleft covering the combined work (GPL, AGPL).
+    StrongCopyleft,
+    Unknown,
+}
+
+impl LicenseClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LicenseClass::Permissive => "permissive",
+            LicenseClass::WeakCopyleft => "weak-copyleft",
+            LicenseClass::StrongCopyleft => "strong-copyleft",
+            LicenseClass::Unknown => "unknown",
+        }
+    }
+
+    pub fn is_copyleft(self) -> bool {
+        matches!(
+            self,
+            LicenseClass::WeakCopyleft | LicenseClass::StrongCopyleft
+        )
+    }
+}
+
+/// A recipe license as an SPDX-like expression and its class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedLicense {
+    pub expression: String,
+    pub class: LicenseClass,
+    /// Normalized terms; a package offering alternatives (`OR`) lists each.
+    terms: Vec<String>,
+}
+
+/// Normalize a recipe `about.license` such as `GPL-3`, `GPLv3`,
+/// `GPL (>= 2)`, `BSD 3-Clause` or `GPL-2 | GPL-3` to SPDX identifiers.
+/// Alternatives (`|`, `OR`) take the least restrictive class, combinations
+/// (`AND`, `&`, `,`) the most restrictive.
+pub fn normalize_license(raw: &str) -> NormalizedLicense {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return NormalizedLicense {
+            expression: "unknown".to_string(),
+            class: LicenseClass::Unknown,
+            terms: Vec::new(),
+        };
+    }
+    let mut terms = Vec::new();
+    let alternatives = split_license(raw, &["|", " OR ", " or "])
+        .into_iter()
+        .map(|alternative| {
+            let parts = split_license(&alternative, &[" AND ", " and ", "&", ","])
+                .iter()
+                .map(|part| normalize_license_term(part))
+                .collect::<Vec<_>>();
+            let class = parts
+                .iter()
+                .map(|(_, class)| *class)
+                .max()
+                .unwrap_or(LicenseClass::Unknown);
+            let expression = parts
+                .iter()
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            terms.extend(parts.into_iter().map(|(id, _)| id));
+            (expression, class)
+        })
+        .collect::<Vec<_>>();
+    let known = alternatives
+        .iter()
+        .map(|(_, class)| *class)
+        .filter(|class| *class != LicenseClass::Unknown)
+        .min();
+    NormalizedLicense {
+        expression: alternatives
+            .iter()
+            .map(|(expression, _)| expression.clone())
+            .collect::<Vec<_>>()
+            .join(" OR "),
+        class: known.unwrap_or(LicenseClass::Unknown),
+        terms,
+    }
+}
+
+fn split_license(raw: &str, separators: &[&str]) -> Vec<String> {
+    let mut parts = vec![raw.to_string()];
+    for separator in separators {
+        parts = parts
+            .iter()
+            .flat_map(|part| part.split(separator))
+            .map(|part| part.trim().to_string())
+            .filter(|part| !part.is_empty())
+            .collect();
+    }
+    parts
+}
+
+fn normalize_license_term(raw: &str) -> (String, LicenseClass) {
+    let upper = raw.to_ascii_uppercase();
+    let or_later = upper.contains(">=") || upper.contains('+') || upper.contains("OR-LATER");
+    // `GPL-3.0-or-later` -> `GPL3`, `GPLv2` -> `GPL2`, `BSD 3-Clause` -> `BSD3CLAUSE`.
+    let key = upper
+        .replace("OR-LATER", "")
+        .replace("-ONLY", "")
+        .replace("LICENSE", "")
+        .replace("VERSION", "")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '.')
+        .collect::<String>()
+        .replace(".0", "");
+    let version_of = |family: &str| -> Option<String> {
+        let rest = key.strip_prefix(family)?;
+        let rest = rest.strip_prefix('V').unwrap_or(rest);
+        Some(rest.to_string())
+    };
+    for (family, class) in [
+        ("AGPL", LicenseClass::StrongCopyleft),
+        ("LGPL", LicenseClass::WeakCopyleft),
+        ("GPL", LicenseClass::StrongCopyleft),
+    ] {
+        let Some(version) = version_of(family) else {
+            continue;
+        };
+        let id = match version.as_str() {
+            "" => family.to_string(),
+            v if v.chars().all(|c| c.is_ascii_digit() || c == '.') => {
+                let v = if v.contains('.') {
+                    v.to_string()
+                } else {
+                    format!("{v}.0")
+                };
+                let suffix = if or_later { "or-later" } else { "only" };
+                format!("{family}-{v}-{suffix}")
+            }
+            _ => continue,
+        };
+        return (id, class);
+    }
+    let id = match key.as_str() {
+        "MIT" | "EXPAT" => "MIT",
+        "BSD" => "BSD",
+        "BSD2" | "BSD2CLAUSE" => "BSD-2-Clause",
+        "BSD3" | "BSD3CLAUSE" | "NEWBSD" => "BSD-3-Clause",
+        "APACHE" | "APACHE2" | "APACHE2.0" | "ASL2" => "Apache-2.0",
+        "ISC" => "ISC",
+        "ZLIB" => "Zlib",
+        "BSL1" | "BOOST" | "BSL" => "BSL-1.0",
+        "UNLICENSE" => "Unlicense",
+        "PUBLICDOMAIN" => "Public-Domain",
+        "PSF" | "PSF2" | "PYTHON" => "PSF-2.0",
+        "ARTISTIC" | "ARTISTIC2" => "Artistic-2.0",
+        "CC0" | "CC01" => "CC0-1.0",
+        "CCBY4" | "CCBY4.0" => "CC-BY-4.0",
+        "MPL" | "MPL2" => "MPL-2.0",
+        "EPL" | "EPL1" => "EPL-1.0",
+        "EPL2" => "EPL-2.0",
+        "CDDL" | "CDDL1" => "CDDL-1.0",
+        _ => return (raw.to_string(), LicenseClass::Unknown),
+    };
+    let class = if id.starts_with("MPL") || id.starts_with("EPL") || id.starts_with("CDDL") {
+        LicenseClass::WeakCopyleft
+    } else {
+        LicenseClass::Permissive
+    };
+    (id.to_string(), class)
+}
+
+/// One package of a dependency closure.
+#[derive(Debug, Clone)]
+pub struct ClosurePackage {
+    pub name: String,
+    pub version: String,
+    /// `about.license` as written in the recipe.
+    pub license: String,
+}
+
+/// Closure of one requested root, root included.
+#[derive(Debug, Clone)]
+pub struct ClosureLicenses {
+    pub root: String,
+    pub packages: Vec<ClosurePackage>,
+    /// Dependencies satisfied by distro RPMs; their licenses are not known here.
+    pub system_provided: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LicenseReportSummary {
+    pub packages: usize,
+    pub copyleft: usize,
+    pub conflicts: Vec<String>,
+    pub csv_path: PathBuf,
+    pub md_path: PathBuf,
+}
+
+/// Copyleft components bundled into a root that is not itself strong
+/// copyleft, and GPL-2.0-only components next to GPL-3.0 or Apache-2.0 ones.
+fn license_conflicts(
+    closure: &ClosureLicenses,
+    licenses: &BTreeMap<String, NormalizedLicense>,
+) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    let root_key = closure.root.to_ascii_lowercase();
+    let root_license = licenses.get(&root_key);
+    let root_class = root_license
+        .map(|license| license.class)
+        .unwrap_or(LicenseClass::Unknown);
+    if matches!(
+        root_class,
+        LicenseClass::Permissive | LicenseClass::WeakCopyleft
+    ) {
+        for (name, license) in licenses {
+            if *name != root_key && license.class == LicenseClass::StrongCopyleft {
+                conflicts.push(format!(
+                    "{} component {name} ({}) in {} root {} ({})",
+                    license.class.as_str(),
+                    license.expression,
+                    root_class.as_str(),
+                    closure.root,
+                    root_license
+                        .map(|license| license.expression.as_str())
+                        .unwrap_or_default()
+                ));
+            }
+        }
+    }
+    let gpl2_only = licenses
+        .iter()
+        .filter(|(_, license)| license.terms.iter().all(|term| term == "GPL-2.0-only"))
+        .filter(|(_, license)| !license.terms.is_empty());
+    for (gpl2_name, _) in gpl2_only {
+        for (name, license) in licenses {
+            let incompatible = !license.terms.is_empty()
+                && license.terms.iter().all(|term| {
+                    term == "Apache-2.0"
+                        || term.starts_with("GPL-3.0")
+                        || term.starts_with("AGPL-3.0")
+                });
+            if incompatible {
+                conflicts.push(format!(
+                    "GPL-2.0-only component {gpl2_name} incompatible with {name} ({})",
+                    license.expression
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Write `<dir>/<root>_licenses.csv` and `.md` for one root's closure.
+pub fn write_closure_license_report(
+    dir: &Path,
+    closure: &ClosureLicenses,
+) -> Result<LicenseReportSummary> {
+    let licenses = closure
+        .packages
+        .iter()
+        .map(|package| {
+            (
+                package.name.to_ascii_lowercase(),
+                normalize_license(&package.license),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    let conflicts = license_conflicts(closure, &licenses);
+    let mut packages = closure.packages.clone();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let stem = closure.root.to_ascii_lowercase();
+    let csv_path = dir.join(format!("{stem}_licenses.csv"));
+    let md_path = dir.join(format!("{stem}_licenses.md"));
+
+    let mut writer = Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            "package",
+            "version",
+            "license",
+            "normalized_license",
+            "class",
+            "root",
+        ])
+        .context("writing license csv header")?;
+    let mut by_class: BTreeMap<LicenseClass, usize> = BTreeMap::new();
+    let mut rows = String::new();
+    for package in &packages {
+        let license = &licenses[&package.name.to_ascii_lowercase()];
+        let is_root = package.name.eq_ignore_ascii_case(&closure.root);
+        *by_class.entry(license.class).or_default() += 1;
+        writer
+            .write_record([
+                package.name.as_str(),
+                package.version.as_str(),
+                package.license.as_str(),
+                license.expression.as_str(),
+                license.class.as_str(),
+                if is_root { "true" } else { "false" },
+            ])
+            .context("writing license csv row")?;
+        let marker = if license.class.is_copyleft() {
+            " **copyleft**"
+        } else {
+            ""
+        };
+        rows.push_str(&format!(
+            "| {}{} | {} | {} | {}{marker} |\n",
+            package.name,
+            if is_root { " (root)" } else { "" },
+            package.version,
+            license.expression.replace('|', "\\|"),
+            license.class.as_str()
+        ));
+    }
+    let csv = writer.into_inner().context("flushing license csv writer")?;
+    write_atomic(&csv_path, csv).with_context(|| format!("writing {}", csv_path.display()))?;
+
+    let copyleft = by_class
+        .iter()
+        .filter(|(class, _)| class.is_copyleft())
+        .map(|(_, count)| count)
+        .sum();
+    let mut md = String::new();
+    md.push_str(&format!("# Closure Licenses: {}\n\n", closure.root));
+    md.push_str(&format!("- Packages: {}\n", packages.len()));
+    for (class, count) in &by_class {
+        md.push_str(&format!("- {}: {count}\n", class.as_str()));
+    }
+    if !closure.system_provided.is_empty() {
+        md.push_str(&format!(
+            "- System-provided (license not tracked): {}\n",
+            closure.system_provided.join(", ")
+        ));
+    }
+    md.push_str("\n## Conflicts\n\n");
+    if conflicts.is_empty() {
+        md.push_str("None.\n");
+    }
+    for conflict in &conflicts {
+        md.push_str(&format!("- {conflict}\n"));
+    }
+    md.push_str("\n## Packages\n\n| Package | Version | License | Class |\n|---|---|---|---|\n");
+    md.push_str(&rows);
+    write_atomic(&md_path, md).with_context(|| format!("writing {}", md_path.display()))?;
+
+    Ok(LicenseReportSummary {
+        packages: packages.len(),
+        copyleft,
+        conflicts,
+        csv_path,
+        md_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closure_license_report_flags_copyleft_and_conflicts() {
+        for (raw, expression, class) in [
+            ("GPL-3", "GPL-3.0-only", LicenseClass::StrongCopyleft),
+            ("GPLv3", "GPL-3.0-only", LicenseClass::StrongCopyleft),
+            (
+                "GPL (>= 2)",
+                "GPL-2.0-or-later",
+                LicenseClass::StrongCopyleft,
+            ),
+            ("LGPL-2.1", "LGPL-2.1-only", LicenseClass::WeakCopyleft),
+            ("BSD 3-Clause", "BSD-3-Clause", LicenseClass::Permissive),
+            ("Apache 2.0", "Apache-2.0", LicenseClass::Permissive),
+            (
+                "GPL-2 | MIT",
+                "GPL-2.0-only OR MIT",
+                LicenseClass::Permissive,
+            ),
+            (
+                "MIT AND GPL-3.0-or-later",
+                "MIT AND GPL-3.0-or-later",
+                LicenseClass::StrongCopyleft,
+            ),
+            ("Custom Academic", "Custom Academic", LicenseClass::Unknown),
+            ("", "unknown", LicenseClass::Unknown),
+        ] {
+            let license = normalize_license(raw);
+            assert_eq!(
+                (license.expression.as_str(), license.class),
+                (expression, class),
+                "{raw}"
+            );
+        }
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let package = |name: &str, license: &str| ClosurePackage {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            license: license.to_string(),
+        };
+        let summary = write_closure_license_report(
+            tmp.path(),
+            &ClosureLicenses {
+                root: "pysam".to_string(),
+                packages: vec![
+                    package("pysam", "MIT"),
+                    package("htslib", "MIT"),
+                    package("gsl", "GPL-3"),
+                    package("oldlib", "GPL-2.0-only"),
+                ],
+                system_provided: vec!["zlib".to_string()],
+            },
+        )
+        .expect("license report");
+        assert_eq!(summary.packages, 4);
+        assert_eq!(summary.copyleft, 2);
+        assert!(
+            summary.conflicts.contains(
+                &"strong-copyleft component gsl (GPL-3.0-only) in permissive root pysam (MIT)"
+                    .to_string()
+            )
+        );
+        assert!(summary.conflicts.contains(
+            &"GPL-2.0-only component oldlib incompatible with gsl (GPL-3.0-only)".to_string()
+        ));
+        let csv = std::fs::read_to_string(&summary.csv_path).expect("csv");
+        assert!(csv.contains("gsl,1.0,GPL-3,GPL-3.0-only,strong-copyleft,false\n"));
+        assert!(csv.contains("pysam,1.0,MIT,MIT,permissive,true\n"));
+        assert!(summary.md_path.ends_with("pysam_licenses.md"));
+    }
+}
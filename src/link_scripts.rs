@@ -0,0 +1,339 @@
+use std::fs;
+use std::path::Path;
+
+/// Directory below the install prefix holding deferred link scripts.
+const FIRST_RUN_HOOK_DIR: &str = "libexec/bioconda2rpm";
+
+/// Start of the payload `%files` section; scriptlets go in front of it.
+const FILES_ANCHOR: &str = "\n%files\n";
+
+/// Start of the module file install at the end of payload `%install`.
+const MODULE_INSTALL_ANCHOR: &str = "\nmkdir -p %{buildroot}%{phoreus_moddir}\n";
+
+/// Commands that manage conda environments or packages; no RPM equivalent.
+const CONDA_COMMANDS: &[&str] = &["conda", "mamba", "micromamba", "pip", "pip3"];
+
+/// Commands that reach the network; unsafe inside an RPM transaction.
+const NETWORK_COMMANDS: &[&str] = &["curl", "wget", "rsync", "scp", "ftp", "git"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkScriptKind {
+    PostLink,
+    PreUnlink,
+    PreLink,
+}
+
+impl LinkScriptKind {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            LinkScriptKind::PostLink => "post-link.sh",
+            LinkScriptKind::PreUnlink => "pre-unlink.sh",
+            LinkScriptKind::PreLink => "pre-link.sh",
+        }
+    }
+
+    fn scriptlet(self) -> &'static str {
+        match self {
+            LinkScriptKind::PostLink => "%post",
+            LinkScriptKind::PreUnlink => "%preun",
+            LinkScriptKind::PreLink => "%pre",
+        }
+    }
+}
+
+/// How a link script is carried into the payload RPM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTranslation {
+    /// Runs as the matching RPM scriptlet.
+    Scriptlet,
+    /// Installed under the prefix for the admin to run once; `%post` says so.
+    FirstRunHook { reason: &'static str },
+    /// Left out of the RPM and flagged in the report.
+    Untranslated { reason: &'static str },
+}
+
+impl LinkTranslation {
+    pub fn status(&self) -> &'static str {
+        match self {
+            LinkTranslation::Scriptlet => "scriptlet",
+            LinkTranslation::FirstRunHook { .. } => "first-run-hook",
+            LinkTranslation::Untranslated { .. } => "untranslated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkScript {
+    pub kind: LinkScriptKind,
+    pub body: String,
+    pub translation: LinkTranslation,
+}
+
+/// `PKG_*` values conda exports to link scripts.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkScriptEnv<'a> {
+    pub package_name: &'a str,
+    pub version: &'a str,
+    pub build_number: &'a str,
+}
+
+/// Link scripts of a recipe, looked up in the variant dir first and then in
+/// the recipe dir, as `build.sh` is.
+pub fn discover_link_scripts(variant_dir: &Path, recipe_dir: &Path) -> Vec<LinkScript> {
+    [
+        LinkScriptKind::PreLink,
+        LinkScriptKind::PostLink,
+        LinkScriptKind::PreUnlink,
+    ]
+    .into_iter()
+    .filter_map(|kind| {
+        let body = [variant_dir, recipe_dir]
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(kind.file_name())).ok())?;
+        let translation = classify_link_script(kind, &body);
+        Some(LinkScript {
+            kind,
+            body,
+            translation,
+        })
+    })
+    .collect()
+}
+
+/// Scriptlet unless the script manages conda packages or, for `post-link.sh`,
+/// downloads data; conda's deprecated `pre-link.sh` is never translated.
+pub fn classify_link_script(kind: LinkScriptKind, body: &str) -> LinkTranslation {
+    if kind == LinkScriptKind::PreLink {
+        return LinkTranslation::Untranslated {
+            reason: "pre-link.sh is deprecated in conda",
+        };
+    }
+    let mut network = false;
+    for line in body.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let words = line
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'))
+            .collect::<Vec<_>>();
+        if words.iter().any(|word| CONDA_COMMANDS.contains(word)) {
+            return LinkTranslation::Untranslated {
+                reason: "manages conda or pip packages",
+            };
+        }
+        network |= line.contains("://") || words.iter().any(|word| NETWORK_COMMANDS.contains(word));
+    }
+    match (network, kind) {
+        (false, _) => LinkTranslation::Scriptlet,
+        (true, LinkScriptKind::PostLink) => LinkTranslation::FirstRunHook {
+            reason: "downloads data",
+        },
+        (true, _) => LinkTranslation::Untranslated {
+            reason: "downloads data",
+        },
+    }
+}
+
+/// Payload spec with the translated link scripts added, and one report
+/// warning per script. Scriptlets go before `%files`; first-run hooks are
+/// written at the end of `%install`.
+pub fn with_link_scriptlets(
+    spec: String,
+    scripts: &[LinkScript],
+    env: LinkScriptEnv<'_>,
+) -> (String, Vec<String>) {
+    let mut spec = spec;
+    let mut notes = Vec::new();
+    for script in scripts {
+        let name = script.kind.file_name();
+        let note = match &script.translation {
+            LinkTranslation::Scriptlet => {
+                insert_before(&mut spec, FILES_ANCHOR, &render_scriptlet(script, env))
+                    .then(|| format!("{name} translated to {}", script.kind.scriptlet()))
+            }
+            LinkTranslation::FirstRunHook { reason } => {
+                let hook = format!("%{{phoreus_prefix}}/{FIRST_RUN_HOOK_DIR}/{name}");
+                let notice = format!(
+                    "%post\necho \"{}: finish setup by running {hook} ({name} {reason})\" >&2\n",
+                    env.package_name
+                );
+                (spec.contains(FILES_ANCHOR)
+                    && insert_before(
+                        &mut spec,
+                        MODULE_INSTALL_ANCHOR,
+                        &render_first_run_hook(script, env, &hook, reason),
+                    )
+                    && insert_before(&mut spec, FILES_ANCHOR, &notice))
+                .then(|| {
+                    format!(
+                        "{name} deferred to first-run hook {FIRST_RUN_HOOK_DIR}/{name} ({reason})"
+                    )
+                })
+            }
+            LinkTranslation::Untranslated { reason } => {
+                Some(format!("{name} not translated ({reason})"))
+            }
+        };
+        notes.push(note.unwrap_or_else(|| format!("{name} not translated (no %files section)")));
+    }
+    (spec, notes)
+}
+
+/// Insert `text` and a blank line at the start of the first `anchor` line.
+fn insert_before(spec: &mut String, anchor: &str, text: &str) -> bool {
+    let Some(at) = spec.find(anchor) else {
+        return false;
+    };
+    spec.insert_str(at + 1, &format!("{text}\n"));
+    true
+}
+
+fn env_exports(env: LinkScriptEnv<'_>) -> String {
+    format!(
+        "export PREFIX=\"%{{phoreus_prefix}}\" CONDA_PREFIX=\"%{{phoreus_prefix}}\"\n\
+export PKG_NAME=\"{}\" PKG_VERSION=\"{}\" PKG_BUILDNUM=\"{}\"\n",
+        env.package_name, env.version, env.build_number
+    )
+}
+
+/// RPM expands macros in scriptlet bodies and `%install`; keep `%` literal.
+fn escape_macros(body: &str) -> String {
+    body.replace('%', "%%")
+}
+
+fn render_scriptlet(script: &LinkScript, env: LinkScriptEnv<'_>) -> String {
+    let body = escape_macros(&script.body);
+    let run = format!(
+        "{}(\n\
+{}{}\
+)\n\
+status=$?\n\
+if [ -f \"$PREFIX/.messages.txt\" ]; then cat \"$PREFIX/.messages.txt\" >&2; rm -f \"$PREFIX/.messages.txt\"; fi\n\
+exit $status\n",
+        env_exports(env),
+        body,
+        if body.ends_with('\n') { "" } else { "\n" }
+    );
+    // RPM also runs the old package's %preun on upgrade ($1 >= 1); conda
+    // runs pre-unlink only when the package is removed.
+    let run = match script.kind {
+        LinkScriptKind::PreUnlink => format!("if [ \"$1\" -eq 0 ]; then\n{run}fi\n"),
+        _ => run,
+    };
+    format!(
+        "{} -p /bin/bash\n\
+# Translated from the recipe's conda {} by bioconda2rpm.\n\
+{run}",
+        script.kind.scriptlet(),
+        script.kind.file_name(),
+    )
+}
+
+fn render_first_run_hook(
+    script: &LinkScript,
+    env: LinkScriptEnv<'_>,
+    hook: &str,
+    reason: &str,
+) -> String {
+    let body = escape_macros(&script.body);
+    format!(
+        "# Conda {name} {reason}; installed as a first-run hook instead of a scriptlet.\n\
+mkdir -p \"$(dirname %{{buildroot}}{hook})\"\n\
+cat > %{{buildroot}}{hook} <<'BIOCONDA2RPM_LINK_SCRIPT'\n\
+#!/usr/bin/env bash\n\
+# Conda {name} of {package}; run once after installing.\n\
+{exports}{body}{newline}\
+BIOCONDA2RPM_LINK_SCRIPT\n\
+chmod 0755 %{{buildroot}}{hook}\n",
+        name = script.kind.file_name(),
+        package = env.package_name,
+        exports = env_exports(env),
+        newline = if body.ends_with('\n') { "" } else { "\n" },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "%install\nmake install\n\nmkdir -p %{buildroot}%{phoreus_moddir}\n\n%files\n%{phoreus_prefix}/\n";
+
+    #[test]
+    fn link_scripts_become_scriptlets_hooks_or_report_warnings() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let recipe = tmp.path().join("blast");
+        let variant = recipe.join("2.16");
+        fs::create_dir_all(&variant).expect("variant dir");
+        fs::write(
+            variant.join("post-link.sh"),
+            "# fetch taxonomy\nwget -q https://ftp.ncbi.nlm.nih.gov/taxdb.tar.gz -O \"$PREFIX/share/taxdb.tar.gz\"\n",
+        )
+        .expect("post-link");
+        fs::write(
+            recipe.join("pre-unlink.sh"),
+            "rm -f \"$PREFIX/share/blast/registry\" # 100% cleanup\n",
+        )
+        .expect("pre-unlink");
+        fs::write(recipe.join("pre-link.sh"), "echo hi\n").expect("pre-link");
+
+        let scripts = discover_link_scripts(&variant, &recipe);
+        assert_eq!(
+            scripts
+                .iter()
+                .map(|s| (s.kind, s.translation.status()))
+                .collect::<Vec<_>>(),
+            vec![
+                (LinkScriptKind::PreLink, "untranslated"),
+                (LinkScriptKind::PostLink, "first-run-hook"),
+                (LinkScriptKind::PreUnlink, "scriptlet"),
+            ]
+        );
+        assert_eq!(
+            classify_link_script(LinkScriptKind::PostLink, "conda install -y samtools\n"),
+            LinkTranslation::Untranslated {
+                reason: "manages conda or pip packages"
+            }
+        );
+
+        let env = LinkScriptEnv {
+            package_name: "blast",
+            version: "2.16.0",
+            build_number: "1",
+        };
+        let (spec, notes) = with_link_scriptlets(SPEC.to_string(), &scripts, env);
+        assert_eq!(
+            notes,
+            vec![
+                "pre-link.sh not translated (pre-link.sh is deprecated in conda)",
+                "post-link.sh deferred to first-run hook libexec/bioconda2rpm/post-link.sh (downloads data)",
+                "pre-unlink.sh translated to %preun",
+            ]
+        );
+        let hook = spec.find("cat > %{buildroot}%{phoreus_prefix}/libexec/bioconda2rpm/post-link.sh <<'BIOCONDA2RPM_LINK_SCRIPT'\n").expect("hook");
+        assert!(
+            hook < spec
+                .find("mkdir -p %{buildroot}%{phoreus_moddir}")
+                .expect("moddir")
+        );
+        assert!(spec.contains("\n%post\necho \"blast: finish setup by running %{phoreus_prefix}/libexec/bioconda2rpm/post-link.sh (post-link.sh downloads data)\" >&2\n"));
+        assert!(spec.contains(
+            "%preun -p /bin/bash\n# Translated from the recipe's conda pre-unlink.sh by bioconda2rpm.\nif [ \"$1\" -eq 0 ]; then\n"
+        ));
+        assert!(
+            spec.contains("export PKG_NAME=\"blast\" PKG_VERSION=\"2.16.0\" PKG_BUILDNUM=\"1\"\n")
+        );
+        assert!(
+            spec.contains("rm -f \"$PREFIX/share/blast/registry\" # 100%% cleanup\n)\nstatus=$?\n")
+        );
+        assert!(spec.ends_with("exit $status\nfi\n\n%files\n%{phoreus_prefix}/\n"));
+        assert!(!spec.contains("echo hi"));
+
+        let (unchanged, notes) = with_link_scriptlets("%install\n".to_string(), &scripts[2..], env);
+        assert_eq!(unchanged, "%install\n");
+        assert_eq!(
+            notes,
+            vec!["pre-unlink.sh not translated (no %files section)"]
+        );
+    }
+}
@@ -1,13 +1,42 @@
+mod adapter_env;
+mod atomic_file;
+mod attestation;
+mod audit;
+mod build_api;
 mod build_lock;
+mod campaign;
+mod catalog;
+mod channel_updates;
 mod cli;
+mod config;
+mod config_signature;
+mod container_engine;
+mod download;
+mod gc;
+mod hooks;
+mod http_server;
+mod init;
+mod license_report;
+mod link_scripts;
 mod priority_specs;
+mod promote;
+mod recipe_model;
 mod recipe_repo;
+mod report_index;
+mod report_merge;
+mod report_shape;
+mod serve;
+mod session_archive;
+mod stability_store;
+mod telemetry;
 mod ui;
+mod workflow_export;
+mod workspace;
 
 use clap::Parser;
 use std::fs;
 use std::process::ExitCode;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 
 static SIGNAL_HANDLER_INSTALLED: OnceLock<()> = OnceLock::new();
 
@@ -32,14 +61,144 @@ fn install_signal_handler() {
     });
 }
 
+/// Session topdir and command label used to name this run's audit log.
+fn audit_target(command: &cli::Command) -> (std::path::PathBuf, &'static str) {
+    match command {
+        cli::Command::Build(args) => (args.effective_session_topdir(), "build"),
+        cli::Command::GeneratePrioritySpecs(args) => {
+            (args.effective_topdir(), "generate-priority-specs")
+        }
+        cli::Command::Regression(args) => (args.effective_topdir(), "regression"),
+        cli::Command::Recipes(args) => match &args.command {
+            Some(cli::RecipesCommand::Lint(lint)) => (lint.effective_topdir(), "recipes-lint"),
+            None => (args.effective_topdir(), "recipes"),
+        },
+        cli::Command::Debug(debug) => match &debug.command {
+            cli::DebugCommand::Adapter(args) => (args.effective_topdir(), "debug-adapter"),
+            cli::DebugCommand::CondaOnly(args) => (args.effective_topdir(), "debug-conda-only"),
+        },
+        cli::Command::Lock(lock) => match &lock.command {
+            cli::LockCommand::Status(args) => (args.effective_session_topdir(), "lock-status"),
+            cli::LockCommand::Break(args) => (args.effective_session_topdir(), "lock-break"),
+        },
+        cli::Command::Containers(containers) => match &containers.command {
+            cli::ContainersCommand::Cleanup(args) => {
+                (args.effective_session_topdir(), "containers-cleanup")
+            }
+        },
+        cli::Command::Workspace(workspace) => match &workspace.command {
+            cli::WorkspaceCommand::Snapshot(args) => {
+                (args.target.effective_session_topdir(), "workspace-snapshot")
+            }
+            cli::WorkspaceCommand::Restore(args) => {
+                (args.target.effective_session_topdir(), "workspace-restore")
+            }
+        },
+        cli::Command::Bisect(args) => (args.effective_session_topdir(), "bisect"),
+        cli::Command::Dev(args) => (args.effective_session_topdir(), "dev"),
+        cli::Command::ExportBundle(args) => (args.effective_session_topdir(), "export-bundle"),
+        cli::Command::BootstrapPhoreus(args) => {
+            (args.effective_session_topdir(), "bootstrap-phoreus")
+        }
+        cli::Command::Adapter(adapter) => match &adapter.command {
+            cli::AdapterCommand::Update(args) => (args.effective_topdir(), "adapter-update"),
+        },
+        cli::Command::Init(args) => (args.effective_topdir(), "init"),
+        cli::Command::Impact(args) => (args.effective_session_topdir(), "impact"),
+        cli::Command::Lookup(args) => (args.effective_session_topdir(), "lookup"),
+        cli::Command::Campaign(args) => (args.effective_topdir(), "campaign"),
+        cli::Command::BackfillReports(args) => {
+            (args.effective_session_topdir(), "backfill-reports")
+        }
+        cli::Command::Gc(args) => (args.effective_session_topdir(), "gc"),
+        cli::Command::Report(report) => match &report.command {
+            cli::ReportCommand::Merge(args) => (args.effective_session_topdir(), "report-merge"),
+        },
+        cli::Command::Plan(plan) => match &plan.command {
+            cli::PlanCommand::Compare(args) => (args.effective_topdir(), "plan-compare"),
+            cli::PlanCommand::Export(args) => (args.effective_topdir(), "plan-export"),
+        },
+        cli::Command::Promote(args) => (args.effective_session_topdir(), "promote"),
+        cli::Command::VerifyChain(args) => (args.effective_session_topdir(), "verify-chain"),
+        cli::Command::Serve(args) => (args.effective_session_topdir(), "serve"),
+        cli::Command::Session(session) => match &session.command {
+            cli::SessionCommand::List(args) => {
+                (args.location.effective_session_topdir(), "session-list")
+            }
+            cli::SessionCommand::Show(args) => {
+                (args.location.effective_session_topdir(), "session-show")
+            }
+        },
+    }
+}
+
+/// `request` with the `[recipes]` table of the project config applied.
+fn configured_recipe_request(
+    request: recipe_repo::RecipeRepoRequest,
+    config: Option<&std::path::Path>,
+    topdir: &std::path::Path,
+) -> anyhow::Result<recipe_repo::RecipeRepoRequest> {
+    let project_config = config::load_project_config(config, topdir)?;
+    Ok(request.with_config(&project_config.recipes))
+}
+
+/// Bundle the finished build session into `<reports>/sessions`; a failure
+/// only warns, the build outcome stands.
+fn archive_build_session(
+    args: &cli::BuildArgs,
+    reports: &std::path::Path,
+    started: chrono::DateTime<chrono::Utc>,
+    completed: bool,
+) {
+    let request = session_archive::SessionArchiveRequest {
+        reports_dir: reports.to_path_buf(),
+        target_id: args.effective_target_id(),
+        command: "build".to_string(),
+        status: if completed { "completed" } else { "failed" }.to_string(),
+        started,
+        audit_log: audit::detach_log(),
+    };
+    match session_archive::archive_session(&request) {
+        Ok(archived) => priority_specs::log_external_progress(format!(
+            "phase=session-archive status=archived id={} files={} bytes={} pruned={} archive={}",
+            archived.index.id,
+            archived.index.files.len(),
+            archived.index.total_bytes(),
+            archived.pruned.len(),
+            archived.archive.display()
+        )),
+        Err(err) => priority_specs::log_external_progress(format!(
+            "phase=session-archive status=warning reason={err:#}"
+        )),
+    }
+}
+
 fn main() -> ExitCode {
     install_signal_handler();
     let cli = cli::Cli::parse();
+    let (audit_topdir, audit_command) = audit_target(&cli.command);
+    audit::init(&audit_topdir, audit_command);
+    run_command(cli.command)
+}
 
-    match cli.command {
+/// Execute one parsed command; campaign steps re-enter here for each run.
+fn run_command(command: cli::Command) -> ExitCode {
+    match command {
         cli::Command::Build(mut args) => {
+            if args.explain_profile {
+                return match priority_specs::explain_deployment_profile(&args) {
+                    Ok(explanation) => {
+                        println!("{explanation}");
+                        ExitCode::SUCCESS
+                    }
+                    Err(err) => {
+                        eprintln!("failed to explain deployment profile: {err:#}");
+                        ExitCode::FAILURE
+                    }
+                };
+            }
             priority_specs::reset_cancellation();
-            let topdir = args.effective_topdir();
+            let topdir = args.effective_session_topdir();
             let bad_spec = args.effective_bad_spec_dir();
             let reports = args.effective_reports_dir();
             if let Err(err) = ensure_workspace_paths(&topdir, &bad_spec, &reports) {
@@ -56,8 +215,35 @@ fn main() -> ExitCode {
                 None
             };
             if progress_ui.is_none() {
-                println!("{}", args.execution_summary());
+                println!(
+                    "{} {}",
+                    args.execution_summary(),
+                    container_engine::engine_capabilities(&args.container_engine).summary()
+                );
             }
+            // Verify before the build API or the workspace lock accepts any
+            // work; the build then uses this parse instead of re-reading the file.
+            let recipes_config = match config_signature::verify_project_config(
+                args.config.as_deref(),
+                &args.effective_topdir(),
+                args.config_signature,
+                args.config_public_key.as_deref(),
+                &args.config_signers,
+            ) {
+                Ok(verified) => {
+                    let recipes = verified.config.recipes.clone();
+                    args.verified_config = Some(verified);
+                    recipes
+                }
+                Err(err) => {
+                    priority_specs::clear_progress_sink();
+                    if let Some(ui) = progress_ui.take() {
+                        ui.finish(format!("build failed: config signature error: {err}"));
+                    }
+                    eprintln!("failed to verify the project config: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
             let requested_packages = match priority_specs::collect_requested_build_packages(&args) {
                 Ok(packages) => packages,
                 Err(err) => {
@@ -69,16 +255,51 @@ fn main() -> ExitCode {
                     return ExitCode::FAILURE;
                 }
             };
+            let api_settings = match args.api_listen.as_deref() {
+                Some(listen) => match http_server::parse_listen_addr(listen)
+                    .and_then(|addr| Ok((addr, build_api::api_token_from_env()?)))
+                {
+                    Ok(settings) => Some(settings),
+                    Err(err) => {
+                        priority_specs::clear_progress_sink();
+                        if let Some(ui) = progress_ui.take() {
+                            ui.finish(format!("build failed: build API error: {err}"));
+                        }
+                        eprintln!("failed to configure the build API: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                },
+                None => None,
+            };
+            let session_started = chrono::Utc::now();
             let _build_session = match build_lock::BuildSessionGuard::acquire_or_forward_build(
                 &topdir,
                 &args.effective_target_id(),
                 &requested_packages,
                 args.force,
+                &build_lock::HeartbeatPolicy {
+                    stale_after: args.effective_heartbeat_stale_after(),
+                    takeover: args.takeover_stale_owner,
+                },
             ) {
                 Ok(build_lock::BuildAcquireOutcome::Owner(guard)) => {
+                    let journal = reports.join(priority_specs::PROGRESS_EVENTS_FILE);
+                    if let Err(err) = priority_specs::install_progress_journal(&journal) {
+                        priority_specs::log_external_progress(format!(
+                            "phase=progress-journal status=warning reason={err:#}"
+                        ));
+                    }
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        priority_specs::log_external_progress(format!(
+                            "phase=workspace-lock status=recovered-stale topdir={} reason={}",
+                            topdir.display(),
+                            reason
+                        ));
+                    }
                     priority_specs::log_external_progress(format!(
-                        "phase=workspace-lock status=acquired topdir={} target_id={} packages={}",
+                        "phase=workspace-lock status=acquired topdir={} session={} target_id={} packages={}",
                         topdir.display(),
+                        args.session_name.as_deref().unwrap_or("none"),
                         args.effective_target_id(),
                         requested_packages.join(",")
                     ));
@@ -118,13 +339,73 @@ fn main() -> ExitCode {
                     return ExitCode::FAILURE;
                 }
             };
+            if !args.no_reap {
+                match container_engine::reap_stale_containers(
+                    &args.container_engine,
+                    &topdir,
+                    Some(&args.effective_target_id()),
+                    false,
+                ) {
+                    Ok(reaped) if reaped.is_empty() => {}
+                    Ok(reaped) => priority_specs::log_external_progress(format!(
+                        "phase=container-reap status=removed target_id={} count={} containers={}",
+                        args.effective_target_id(),
+                        reaped.len(),
+                        reaped
+                            .iter()
+                            .map(|c| format!("{}({})", c.name, c.status))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    )),
+                    Err(err) => priority_specs::log_external_progress(format!(
+                        "phase=container-reap status=failed target_id={} reason={err:#}",
+                        args.effective_target_id()
+                    )),
+                }
+            }
+
+            let build_api = match api_settings {
+                Some((listen, token)) => {
+                    match build_api::start(
+                        listen,
+                        topdir.clone(),
+                        args.effective_target_id(),
+                        token,
+                    ) {
+                        Ok(handle) => {
+                            priority_specs::log_external_progress(format!(
+                                "phase=build-api status=listening addr=http://{} target_id={}",
+                                handle.addr,
+                                args.effective_target_id()
+                            ));
+                            Some(handle)
+                        }
+                        Err(err) => {
+                            priority_specs::clear_progress_sink();
+                            if let Some(ui) = progress_ui.take() {
+                                ui.finish(format!("build failed: build API error: {err}"));
+                            }
+                            eprintln!("failed to start the build API: {err:#}");
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            if let Some(provenance) = args.config_provenance() {
+                priority_specs::log_external_progress(provenance.progress_line());
+            }
 
             let recipe_request = recipe_repo::RecipeRepoRequest {
                 recipe_root: args.effective_recipe_root(),
                 recipe_repo_root: args.effective_recipe_repo_root(),
                 recipe_ref: args.recipe_ref.clone(),
                 sync: args.effective_recipe_sync(),
-            };
+                policy: args.recipe_sync_policy,
+                pin: None,
+            }
+            .with_config(&recipes_config);
             let recipes = match recipe_repo::ensure_recipe_repository(&recipe_request) {
                 Ok(state) => state,
                 Err(err) => {
@@ -138,17 +419,21 @@ fn main() -> ExitCode {
             };
             args.recipe_root = Some(recipes.recipe_root.clone());
             priority_specs::log_external_progress(format!(
-                "phase=recipe-sync status=ready action=prepared recipes={} repo={} managed_git={} cloned={} fetched={} checkout={} head={}",
+                "phase=recipe-sync status=ready action=prepared recipes={} repo={} managed_git={} cloned={} fetched={} checkout={} head={} sync_status={}",
                 recipes.recipe_root.display(),
                 recipes.recipe_repo_root.display(),
                 recipes.managed_git,
                 recipes.cloned,
                 recipes.fetched,
                 recipes.checked_out.as_deref().unwrap_or("none"),
-                recipes.head.as_deref().unwrap_or("unknown")
+                recipes.head.as_deref().unwrap_or("unknown"),
+                recipes.sync_record().status
             ));
+            args.recipe_sync_state = Some(recipes.sync_record());
 
             let outcome = priority_specs::run_build(&args);
+            // The queue no longer drains requests; refuse new submissions.
+            drop(build_api);
             priority_specs::clear_progress_sink();
 
             if let Some(ui) = progress_ui.take() {
@@ -166,10 +451,13 @@ fn main() -> ExitCode {
                 };
                 ui.finish(summary);
             }
+            if !args.no_session_archive {
+                archive_build_session(&args, &reports, session_started, outcome.is_ok());
+            }
 
             match outcome {
                 Ok(summary) => {
-                    println!(
+                    let summary_line = format!(
                         "build requested={} generated={} up_to_date={} skipped={} quarantined={} kpi_scope_entries={} kpi_excluded_arch={} kpi_denominator={} kpi_successes={} kpi_success_rate={:.2}% order={} report_json={} report_csv={} report_md={}",
                         summary.requested,
                         summary.generated,
@@ -186,6 +474,8 @@ fn main() -> ExitCode {
                         summary.report_csv.display(),
                         summary.report_md.display()
                     );
+                    campaign::record_step_summary(&summary_line);
+                    println!("{summary_line}");
                     if summary.generated == 0
                         && summary.up_to_date >= 1
                         && summary.quarantined == 0
@@ -219,7 +509,12 @@ fn main() -> ExitCode {
                 build_lock::BuildSessionKind::GeneratePrioritySpecs,
                 false,
             ) {
-                Ok(guard) => guard,
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
                 Err(err) => {
                     eprintln!("failed to acquire workspace build session lock: {err:#}");
                     return ExitCode::FAILURE;
@@ -230,29 +525,36 @@ fn main() -> ExitCode {
                 recipe_repo_root: args.effective_recipe_repo_root(),
                 recipe_ref: args.recipe_ref.clone(),
                 sync: args.effective_recipe_sync(),
+                policy: args.recipe_sync_policy,
+                pin: None,
             };
-            let recipes = match recipe_repo::ensure_recipe_repository(&recipe_request) {
-                Ok(state) => state,
-                Err(err) => {
-                    eprintln!("failed to prepare bioconda recipes repository: {err:#}");
-                    return ExitCode::FAILURE;
-                }
-            };
+            let recipes =
+                match configured_recipe_request(recipe_request, None, &args.effective_topdir())
+                    .and_then(|request| recipe_repo::ensure_recipe_repository(&request))
+                {
+                    Ok(state) => state,
+                    Err(err) => {
+                        eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                };
             args.recipe_root = Some(recipes.recipe_root.clone());
             println!(
-                "recipes root={} repo={} managed_git={} cloned={} fetched={} checkout={} head={}",
+                "recipes root={} repo={} managed_git={} cloned={} fetched={} checkout={} head={} sync_status={}",
                 recipes.recipe_root.display(),
                 recipes.recipe_repo_root.display(),
                 recipes.managed_git,
                 recipes.cloned,
                 recipes.fetched,
                 recipes.checked_out.as_deref().unwrap_or("none"),
-                recipes.head.as_deref().unwrap_or("unknown")
+                recipes.head.as_deref().unwrap_or("unknown"),
+                recipes.sync_record().status
             );
+            args.recipe_sync_state = Some(recipes.sync_record());
 
             match priority_specs::run_generate_priority_specs(&args) {
                 Ok(summary) => {
-                    println!(
+                    let summary_line = format!(
                         "priority spec generation requested={} generated={} quarantined={} report_json={} report_csv={} report_md={}",
                         summary.requested,
                         summary.generated,
@@ -261,6 +563,8 @@ fn main() -> ExitCode {
                         summary.report_csv.display(),
                         summary.report_md.display(),
                     );
+                    campaign::record_step_summary(&summary_line);
+                    println!("{summary_line}");
                 }
                 Err(err) => {
                     eprintln!("priority spec generation failed: {err:#}");
@@ -283,7 +587,12 @@ fn main() -> ExitCode {
                 build_lock::BuildSessionKind::Regression,
                 false,
             ) {
-                Ok(guard) => guard,
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
                 Err(err) => {
                     eprintln!("failed to acquire workspace build session lock: {err:#}");
                     return ExitCode::FAILURE;
@@ -294,29 +603,36 @@ fn main() -> ExitCode {
                 recipe_repo_root: args.effective_recipe_repo_root(),
                 recipe_ref: args.recipe_ref.clone(),
                 sync: args.effective_recipe_sync(),
+                policy: args.recipe_sync_policy,
+                pin: None,
             };
-            let recipes = match recipe_repo::ensure_recipe_repository(&recipe_request) {
-                Ok(state) => state,
-                Err(err) => {
-                    eprintln!("failed to prepare bioconda recipes repository: {err:#}");
-                    return ExitCode::FAILURE;
-                }
-            };
+            let recipes =
+                match configured_recipe_request(recipe_request, None, &args.effective_topdir())
+                    .and_then(|request| recipe_repo::ensure_recipe_repository(&request))
+                {
+                    Ok(state) => state,
+                    Err(err) => {
+                        eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                };
             args.recipe_root = Some(recipes.recipe_root.clone());
             println!(
-                "recipes root={} repo={} managed_git={} cloned={} fetched={} checkout={} head={}",
+                "recipes root={} repo={} managed_git={} cloned={} fetched={} checkout={} head={} sync_status={}",
                 recipes.recipe_root.display(),
                 recipes.recipe_repo_root.display(),
                 recipes.managed_git,
                 recipes.cloned,
                 recipes.fetched,
                 recipes.checked_out.as_deref().unwrap_or("none"),
-                recipes.head.as_deref().unwrap_or("unknown")
+                recipes.head.as_deref().unwrap_or("unknown"),
+                recipes.sync_record().status
             );
+            args.recipe_sync_state = Some(recipes.sync_record());
 
             match priority_specs::run_regression(&args) {
                 Ok(summary) => {
-                    println!(
+                    let summary_line = format!(
                         "regression mode={:?} requested={} attempted={} succeeded={} failed={} excluded={} kpi_denominator={} kpi_successes={} kpi_success_rate={:.2}% report_json={} report_csv={} report_md={}",
                         summary.mode,
                         summary.requested,
@@ -331,6 +647,8 @@ fn main() -> ExitCode {
                         summary.report_csv.display(),
                         summary.report_md.display(),
                     );
+                    campaign::record_step_summary(&summary_line);
+                    println!("{summary_line}");
                 }
                 Err(err) => {
                     eprintln!("regression failed: {err:#}");
@@ -338,6 +656,29 @@ fn main() -> ExitCode {
                 }
             }
         }
+        cli::Command::Recipes(cli::RecipesArgs {
+            command: Some(cli::RecipesCommand::Lint(args)),
+            ..
+        }) => match priority_specs::run_recipes_lint(&args) {
+            Ok(summary) => {
+                for problem in &summary.problems {
+                    println!("{problem}");
+                }
+                println!(
+                    "recipes lint recipe={} meta={} problems={}",
+                    summary.recipe,
+                    summary.meta_path.display(),
+                    summary.problems.len()
+                );
+                if !summary.problems.is_empty() {
+                    return ExitCode::FAILURE;
+                }
+            }
+            Err(err) => {
+                eprintln!("recipes lint failed: {err:#}");
+                return ExitCode::FAILURE;
+            }
+        },
         cli::Command::Recipes(args) => {
             let topdir = args.effective_topdir();
             if let Err(err) = fs::create_dir_all(&topdir) {
@@ -352,18 +693,23 @@ fn main() -> ExitCode {
                 recipe_repo_root: args.effective_recipe_repo_root(),
                 recipe_ref: args.recipe_ref.clone(),
                 sync: args.effective_recipe_sync(),
+                policy: args.recipe_sync_policy,
+                pin: None,
             };
-            match recipe_repo::ensure_recipe_repository(&recipe_request) {
+            match configured_recipe_request(recipe_request, None, &args.effective_topdir())
+                .and_then(|request| recipe_repo::ensure_recipe_repository(&request))
+            {
                 Ok(state) => {
                     println!(
-                        "recipes root={} repo={} managed_git={} cloned={} fetched={} checkout={} head={}",
+                        "recipes root={} repo={} managed_git={} cloned={} fetched={} checkout={} head={} sync_status={}",
                         state.recipe_root.display(),
                         state.recipe_repo_root.display(),
                         state.managed_git,
                         state.cloned,
                         state.fetched,
                         state.checked_out.as_deref().unwrap_or("none"),
-                        state.head.as_deref().unwrap_or("unknown")
+                        state.head.as_deref().unwrap_or("unknown"),
+                        state.sync_record().status
                     );
                 }
                 Err(err) => {
@@ -372,8 +718,985 @@ fn main() -> ExitCode {
                 }
             }
         }
-        cli::Command::Lookup(args) => {
+        cli::Command::Debug(debug) => match debug.command {
+            cli::DebugCommand::Adapter(mut args) => {
+                // Keep stdout reserved for the JSON diff.
+                priority_specs::install_progress_sink(Arc::new(|line| eprintln!("{line}")));
+                let recipe_request = recipe_repo::RecipeRepoRequest {
+                    recipe_root: args.effective_recipe_root(),
+                    recipe_repo_root: args.effective_recipe_repo_root(),
+                    recipe_ref: None,
+                    sync: false,
+                    policy: cli::RecipeSyncPolicy::Require,
+                    pin: None,
+                };
+                let recipes = match recipe_repo::ensure_recipe_repository(&recipe_request) {
+                    Ok(state) => state,
+                    Err(err) => {
+                        eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                };
+                args.recipe_root = Some(recipes.recipe_root);
+                match priority_specs::run_debug_adapter(&args) {
+                    Ok(summary) => {
+                        let rendered = if args.compact {
+                            serde_json::to_string(&summary)
+                        } else {
+                            serde_json::to_string_pretty(&summary)
+                        };
+                        match rendered {
+                            Ok(body) => println!("{body}"),
+                            Err(err) => {
+                                eprintln!("debug adapter serialization failed: {err:#}");
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("debug adapter failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            cli::DebugCommand::CondaOnly(args) => {
+                match priority_specs::run_debug_conda_only(&args) {
+                    Ok(lint) => {
+                        let rendered = if args.compact {
+                            serde_json::to_string(&lint)
+                        } else {
+                            serde_json::to_string_pretty(&lint)
+                        };
+                        match rendered {
+                            Ok(body) => println!("{body}"),
+                            Err(err) => {
+                                eprintln!("debug conda-only serialization failed: {err:#}");
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                        if !lint.findings.is_empty() {
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("debug conda-only failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        },
+        cli::Command::Lock(lock) => match lock.command {
+            cli::LockCommand::Status(args) => {
+                let topdir = args.effective_session_topdir();
+                match build_lock::inspect_workspace_lock(
+                    &topdir,
+                    args.effective_heartbeat_stale_after(),
+                ) {
+                    Ok(status) => {
+                        let rendered = if args.compact {
+                            serde_json::to_string(&status)
+                        } else {
+                            serde_json::to_string_pretty(&status)
+                        };
+                        match rendered {
+                            Ok(body) => println!("{body}"),
+                            Err(err) => {
+                                eprintln!("lock status serialization failed: {err:#}");
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("lock status failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            cli::LockCommand::Break(args) => {
+                let topdir = args.effective_session_topdir();
+                match build_lock::break_workspace_lock(
+                    &topdir,
+                    args.effective_heartbeat_stale_after(),
+                    args.force,
+                ) {
+                    Ok(broken) => {
+                        println!(
+                            "workspace lock broken topdir={} previous_owner_pid={} stale_reason={} forced={} removed={}",
+                            broken.topdir,
+                            broken
+                                .previous_owner
+                                .as_ref()
+                                .map(|owner| owner.pid.to_string())
+                                .unwrap_or_else(|| "none".to_string()),
+                            broken.stale_reason.as_deref().unwrap_or("none"),
+                            broken.forced,
+                            broken.removed_files.join(",")
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("lock break failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        },
+        cli::Command::Containers(containers) => match containers.command {
+            cli::ContainersCommand::Cleanup(args) => {
+                let topdir = args.effective_session_topdir();
+                if !args.force
+                    && let Ok(status) = build_lock::inspect_workspace_lock(
+                        &topdir,
+                        args.effective_heartbeat_stale_after(),
+                    )
+                    && status.lock_held
+                    && !status.stale
+                {
+                    eprintln!(
+                        "containers cleanup refused: a live build holds the workspace lock of {} (owner_pid={}); rerun with --force to remove its containers",
+                        topdir.display(),
+                        status
+                            .owner
+                            .as_ref()
+                            .map(|owner| owner.pid.to_string())
+                            .unwrap_or_else(|| "unknown".to_string())
+                    );
+                    return ExitCode::FAILURE;
+                }
+                match container_engine::reap_stale_containers(
+                    &args.container_engine,
+                    &topdir,
+                    args.target_id.as_deref(),
+                    args.dry_run,
+                ) {
+                    Ok(containers) => {
+                        for container in &containers {
+                            println!(
+                                "{} id={} name={} status={}",
+                                if args.dry_run { "stale" } else { "removed" },
+                                container.id,
+                                container.name,
+                                container.status
+                            );
+                        }
+                        println!(
+                            "containers cleanup topdir={} target_id={} found={} removed={}",
+                            topdir.display(),
+                            args.target_id.as_deref().unwrap_or("all"),
+                            containers.len(),
+                            if args.dry_run { 0 } else { containers.len() }
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("containers cleanup failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        },
+        cli::Command::Workspace(workspace_args) => {
+            let (name, replace, target, restore) = match workspace_args.command {
+                cli::WorkspaceCommand::Snapshot(args) => {
+                    (args.name, args.replace, args.target, false)
+                }
+                cli::WorkspaceCommand::Restore(args) => (args.name, false, args.target, true),
+            };
+            let request = workspace::WorkspaceSnapshotRequest {
+                name,
+                session_topdir: target.effective_session_topdir(),
+                target_id: target.effective_target_id(),
+                target_root: target.effective_target_root(),
+                recipe_repo_root: target.effective_recipe_repo_root(),
+                replace,
+            };
+            let action = if restore { "restore" } else { "snapshot" };
+            let _session = match build_lock::BuildSessionGuard::acquire(
+                &request.session_topdir,
+                &request.target_id,
+                &[format!("workspace-{action}:{}", request.name)],
+                build_lock::BuildSessionKind::Workspace,
+                false,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            if restore {
+                match workspace::restore_workspace_snapshot(&request) {
+                    Ok(outcome) => {
+                        println!(
+                            "workspace restored name={} target_id={} created_at={} recipe_commit={} spec_files={} report_files={} artifacts_restored={} artifacts_removed={} artifacts_missing={}",
+                            outcome.manifest.name,
+                            outcome.manifest.target_id,
+                            outcome.manifest.created_at_utc,
+                            outcome
+                                .manifest
+                                .recipe_commit
+                                .as_deref()
+                                .unwrap_or("unknown"),
+                            outcome.manifest.spec_files,
+                            outcome.manifest.report_files,
+                            outcome.restored_artifacts,
+                            outcome.removed_artifacts,
+                            outcome.missing_artifacts.len()
+                        );
+                        for missing in &outcome.missing_artifacts {
+                            eprintln!("referenced artifact no longer present: {missing}");
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("workspace restore failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            } else {
+                match workspace::create_workspace_snapshot(&request) {
+                    Ok(manifest) => {
+                        let linked = manifest
+                            .artifacts
+                            .iter()
+                            .filter(|artifact| artifact.linked)
+                            .count();
+                        println!(
+                            "workspace snapshot name={} path={} target_id={} recipe_commit={} spec_files={} report_files={} stability_cache={} artifacts={} linked={}",
+                            manifest.name,
+                            workspace::snapshot_dir(&request.session_topdir, &manifest.name)
+                                .display(),
+                            manifest.target_id,
+                            manifest.recipe_commit.as_deref().unwrap_or("unknown"),
+                            manifest.spec_files,
+                            manifest.report_files,
+                            manifest.stability_cache,
+                            manifest.artifacts.len(),
+                            linked
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("workspace snapshot failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        }
+        cli::Command::Bisect(args) => {
+            let topdir = args.effective_session_topdir();
+            let _build_session = match build_lock::BuildSessionGuard::acquire(
+                &topdir,
+                &args.effective_target_id(),
+                &[format!("bisect:{}", args.package)],
+                build_lock::BuildSessionKind::Bisect,
+                true,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            match priority_specs::run_bisect(&args) {
+                Ok(summary) => {
+                    println!(
+                        "bisect package={} good={} bad={} candidates={} steps={} first_bad={} first_bad_summary={:?} suspects={} report_json={}",
+                        summary.package,
+                        summary.good,
+                        summary.bad,
+                        summary.candidates,
+                        summary.steps.len(),
+                        summary.first_bad.as_deref().unwrap_or("unknown"),
+                        summary.first_bad_summary.as_deref().unwrap_or(""),
+                        summary.suspects.join(","),
+                        summary.report_json.display()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("bisect failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Dev(mut args) => {
+            priority_specs::reset_cancellation();
+            let topdir = args.effective_session_topdir();
+            let _build_session = match build_lock::BuildSessionGuard::acquire(
+                &topdir,
+                &args.effective_target_id(),
+                &[format!("dev:{}", args.package)],
+                build_lock::BuildSessionKind::Dev,
+                true,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let recipe_request = recipe_repo::RecipeRepoRequest {
+                recipe_root: args.effective_recipe_root(),
+                recipe_repo_root: args.effective_recipe_repo_root(),
+                recipe_ref: None,
+                sync: false,
+                policy: cli::RecipeSyncPolicy::Require,
+                pin: None,
+            };
+            match recipe_repo::ensure_recipe_repository(&recipe_request) {
+                Ok(state) => args.recipe_root = Some(state.recipe_root),
+                Err(err) => {
+                    eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            match priority_specs::run_dev_loop(&args) {
+                Ok(summary) => {
+                    println!(
+                        "dev package={} iterations={} last_result={} container={} spec={}",
+                        summary.package,
+                        summary.iterations,
+                        if summary.last_success { "ok" } else { "failed" },
+                        summary.container,
+                        summary.payload_spec.display()
+                    );
+                    if !summary.last_success {
+                        return ExitCode::FAILURE;
+                    }
+                }
+                Err(err) => {
+                    eprintln!("dev loop failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::ExportBundle(mut args) => {
+            priority_specs::reset_cancellation();
+            let topdir = args.effective_session_topdir();
+            let _build_session = match build_lock::BuildSessionGuard::acquire(
+                &topdir,
+                &args.effective_target_id(),
+                &[format!("export-bundle:{}", args.package)],
+                build_lock::BuildSessionKind::ExportBundle,
+                true,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let recipe_request = recipe_repo::RecipeRepoRequest {
+                recipe_root: args.effective_recipe_root(),
+                recipe_repo_root: args.effective_recipe_repo_root(),
+                recipe_ref: None,
+                sync: false,
+                policy: cli::RecipeSyncPolicy::Require,
+                pin: None,
+            };
+            match recipe_repo::ensure_recipe_repository(&recipe_request) {
+                Ok(state) => args.recipe_root = Some(state.recipe_root),
+                Err(err) => {
+                    eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            match priority_specs::run_export_bundle(&args) {
+                Ok(summary) => {
+                    println!(
+                        "export-bundle package={} version={} specs={} sources={} missing_sources={} bundle_dir={} tarball={}",
+                        summary.package,
+                        summary.version,
+                        summary.specs,
+                        summary.sources,
+                        summary.missing_sources.len(),
+                        summary.bundle_dir.display(),
+                        summary.tarball.display()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("export-bundle failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::BootstrapPhoreus(args) => {
+            priority_specs::reset_cancellation();
+            let topdir = args.effective_session_topdir();
+            let _build_session = match build_lock::BuildSessionGuard::acquire(
+                &topdir,
+                &args.effective_target_id(),
+                &["bootstrap-phoreus".to_string()],
+                build_lock::BuildSessionKind::BootstrapPhoreus,
+                true,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            match priority_specs::run_bootstrap_phoreus(&args) {
+                Ok(summary) => {
+                    for package in &summary.packages {
+                        println!(
+                            "bootstrap-phoreus package={} status={} spec={}",
+                            package.name,
+                            package.status,
+                            package.spec_path.display()
+                        );
+                    }
+                    println!(
+                        "bootstrap-phoreus stage={:?} packages={} target_root={}",
+                        summary.stage,
+                        summary.packages.len(),
+                        summary.target_root.display()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("bootstrap-phoreus failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Init(args) => {
+            let plan = if args.interactive() {
+                init::prompt_init_plan(&args, &mut std::io::stdin().lock(), &mut std::io::stdout())
+            } else {
+                Ok(init::default_init_plan(&args))
+            };
+            let summary = plan
+                .and_then(|plan| init::validate_init_plan(&plan).map(|()| plan))
+                .and_then(|plan| init::run_init(&args, plan));
+            match summary {
+                Ok(summary) => {
+                    for check in &summary.checks {
+                        println!(
+                            "init check={} status={} detail={}",
+                            check.name,
+                            check.status.as_str(),
+                            check.detail
+                        );
+                    }
+                    println!(
+                        "init topdir={} config={} config_written={} recipe_root={} target_id={} checks_failed={}",
+                        summary.plan.topdir.display(),
+                        summary.plan.config_path.display(),
+                        summary.config_written,
+                        summary.plan.recipe_root.display(),
+                        summary.target_id,
+                        summary.failed_checks()
+                    );
+                    if summary.failed_checks() > 0 {
+                        return ExitCode::FAILURE;
+                    }
+                }
+                Err(err) => {
+                    eprintln!("init failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Adapter(adapter) => match adapter.command {
+            cli::AdapterCommand::Update(args) => {
+                let topdir = args.effective_topdir();
+                let updated = config::load_project_config(args.config.as_deref(), &topdir)
+                    .and_then(|config| {
+                        adapter_env::update_adapter_env(&topdir, &config.adapter_env)
+                    });
+                match updated {
+                    Ok(env) => {
+                        println!(
+                            "adapter update python={} conda_build={} conda={} frozen={}",
+                            env.python.display(),
+                            env.state.conda_build_version,
+                            env.state.conda_version,
+                            env.state.frozen.len()
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("adapter update failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        },
+        cli::Command::Plan(plan) => match plan.command {
+            cli::PlanCommand::Compare(mut args) => {
+                // Keep stdout reserved for the comparison.
+                priority_specs::install_progress_sink(Arc::new(|line| eprintln!("{line}")));
+                let recipe_request = recipe_repo::RecipeRepoRequest {
+                    recipe_root: args.effective_recipe_root(),
+                    recipe_repo_root: args.effective_recipe_repo_root(),
+                    recipe_ref: None,
+                    sync: false,
+                    policy: cli::RecipeSyncPolicy::Require,
+                    pin: None,
+                };
+                match recipe_repo::ensure_recipe_repository(&recipe_request) {
+                    Ok(state) => args.recipe_root = Some(state.recipe_root),
+                    Err(err) => {
+                        eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+                match priority_specs::run_plan_compare(&args) {
+                    Ok(comparison) if args.json => {
+                        match serde_json::to_string_pretty(&comparison) {
+                            Ok(body) => println!("{body}"),
+                            Err(err) => {
+                                eprintln!("plan compare serialization failed: {err:#}");
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    }
+                    Ok(comparison) => {
+                        print!("{}", priority_specs::render_plan_comparison(&comparison))
+                    }
+                    Err(err) => {
+                        eprintln!("plan compare failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            cli::PlanCommand::Export(mut args) => {
+                // Keep stdout reserved for the workflow.
+                priority_specs::install_progress_sink(Arc::new(|line| eprintln!("{line}")));
+                let recipe_request = recipe_repo::RecipeRepoRequest {
+                    recipe_root: args.effective_recipe_root(),
+                    recipe_repo_root: args.effective_recipe_repo_root(),
+                    recipe_ref: None,
+                    sync: false,
+                    policy: cli::RecipeSyncPolicy::Require,
+                    pin: None,
+                };
+                // Steps get the recipe root as given here, not the resolved clone.
+                let step_build_args = args.step_build_args();
+                match recipe_repo::ensure_recipe_repository(&recipe_request) {
+                    Ok(state) => args.recipe_root = Some(state.recipe_root),
+                    Err(err) => {
+                        eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+                let written =
+                    priority_specs::run_plan_export(&args, step_build_args).and_then(|plan| {
+                        let workflow = workflow_export::render_workflow(&plan, &args.format);
+                        match &args.output {
+                            Some(path) => {
+                                atomic_file::write_atomic(path, &workflow).map_err(|err| {
+                                    anyhow::anyhow!("writing {}: {err}", path.display())
+                                })?;
+                                println!(
+                                    "plan-export tasks={} output={}",
+                                    plan.tasks.len(),
+                                    path.display()
+                                );
+                            }
+                            None => print!("{workflow}"),
+                        }
+                        Ok(())
+                    });
+                if let Err(err) = written {
+                    eprintln!("plan export failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        },
+        cli::Command::Impact(mut args) => {
+            let recipe_request = recipe_repo::RecipeRepoRequest {
+                recipe_root: args.effective_recipe_root(),
+                recipe_repo_root: args.effective_recipe_repo_root(),
+                recipe_ref: None,
+                sync: false,
+                policy: cli::RecipeSyncPolicy::Require,
+                pin: None,
+            };
+            match recipe_repo::ensure_recipe_repository(&recipe_request) {
+                Ok(state) => args.recipe_root = Some(state.recipe_root),
+                Err(err) => {
+                    eprintln!("failed to prepare bioconda recipes repository: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            match priority_specs::run_impact(&args) {
+                Ok(summary) => {
+                    println!(
+                        "impact roots={} nodes={} cached={} to_build={} estimated_build_secs={:.0} estimated_disk_bytes={} critical_path_nodes={} critical_path_secs={:.0} report_json={} report_md={}",
+                        summary.roots,
+                        summary.nodes,
+                        summary.cached,
+                        summary.to_build,
+                        summary.estimated_build_secs,
+                        summary.estimated_disk_bytes,
+                        summary.critical_path_nodes,
+                        summary.critical_path_secs,
+                        summary.report_json.display(),
+                        summary.report_md.display()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("impact failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Campaign(args) => match campaign::run_campaign(&args, run_command) {
+            Ok(summary) => {
+                println!(
+                    "campaign name={} status={} steps={} succeeded={} failed={} skipped={} report_json={} report_md={}",
+                    summary.name,
+                    summary.status,
+                    summary.steps,
+                    summary.succeeded,
+                    summary.failed,
+                    summary.skipped,
+                    summary.report_json.display(),
+                    summary.report_md.display()
+                );
+                if summary.failed > 0 {
+                    return ExitCode::FAILURE;
+                }
+            }
+            Err(err) => {
+                eprintln!("campaign failed: {err:#}");
+                return ExitCode::FAILURE;
+            }
+        },
+        cli::Command::BackfillReports(args) => {
+            let _session = match build_lock::BuildSessionGuard::acquire(
+                &args.effective_session_topdir(),
+                &args.effective_target_id(),
+                &["backfill-reports".to_string()],
+                build_lock::BuildSessionKind::BackfillReports,
+                false,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            match priority_specs::run_backfill_reports(&args) {
+                Ok(summary) => {
+                    println!(
+                        "backfill-reports packages={} up_to_date={} quarantined={} spec_only={} reports_updated={} reports_skipped={} dry_run={} report_json={}",
+                        summary.packages,
+                        summary.up_to_date,
+                        summary.quarantined,
+                        summary.spec_only,
+                        summary.reports_updated,
+                        summary.reports_skipped,
+                        args.dry_run,
+                        summary.report_json.display()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("backfill-reports failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Gc(args) => {
+            let topdir = args.effective_session_topdir();
+            let _session = match build_lock::BuildSessionGuard::acquire(
+                &topdir,
+                "all",
+                &["gc".to_string()],
+                build_lock::BuildSessionKind::Gc,
+                false,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let request = gc::GcRequest {
+                session_topdir: topdir.clone(),
+                max_age: args.effective_max_age(),
+                keep_git_revisions: args.keep_git_revisions,
+                dry_run: args.dry_run,
+            };
+            match gc::run_gc(&request) {
+                Ok(summary) => {
+                    println!(
+                        "gc topdir={} dry_run={} build_work_removed={} git_cache_removed={} conda_adapter_removed={} markers_removed={} failed={} reclaimed_bytes={}",
+                        topdir.display(),
+                        args.dry_run,
+                        summary.build_work.removed,
+                        summary.git_cache.removed,
+                        summary.conda_adapter.removed,
+                        summary.failed_build_markers.removed,
+                        summary.failed(),
+                        summary.reclaimed_bytes()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("gc failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Report(report) => match report.command {
+            cli::ReportCommand::Merge(args) => {
+                let request = report_merge::ReportMergeRequest {
+                    session_topdir: args.effective_session_topdir(),
+                    reports: args.reports.clone(),
+                    target_ids: args.target_ids.clone(),
+                    output_dir: args.effective_output_dir(),
+                };
+                match report_merge::merge_reports_by_package(&request) {
+                    Ok(summary) => {
+                        let matrix = &summary.matrix;
+                        println!(
+                            "report-merge targets={} packages={} divergent={} kpi_denominator={} kpi_successes={} kpi_success_rate={:.2} csv={} md={} html={} json={}",
+                            matrix.targets.len(),
+                            matrix.packages.len(),
+                            matrix.packages.iter().filter(|row| row.divergent).count(),
+                            matrix.combined.denominator,
+                            matrix.combined.successes,
+                            matrix.combined.success_rate,
+                            summary.csv_path.display(),
+                            summary.md_path.display(),
+                            summary.html_path.display(),
+                            summary.json_path.display()
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("report merge failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+        },
+        cli::Command::Serve(args) => {
+            let listen = match http_server::parse_listen_addr(&args.listen) {
+                Ok(addr) => addr,
+                Err(err) => {
+                    eprintln!("serve failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let request = serve::ServeRequest {
+                listen,
+                session_topdir: args.effective_session_topdir(),
+                reports_dir: args.effective_reports_dir(),
+                target_root: args.effective_target_root(),
+            };
+            if let Err(err) = serve::serve(request) {
+                eprintln!("serve failed: {err:#}");
+                return ExitCode::FAILURE;
+            }
+        }
+        cli::Command::Session(session) => match session.command {
+            cli::SessionCommand::List(args) => {
+                let reports = args.location.effective_reports_dir();
+                match session_archive::list_sessions(&reports) {
+                    Ok(sessions) if sessions.is_empty() => {
+                        println!("no archived sessions under {}", reports.display());
+                    }
+                    Ok(sessions) => {
+                        for index in sessions {
+                            println!(
+                                "{} command={} target_id={} status={} files={} bytes={}",
+                                index.id,
+                                index.command,
+                                index.target_id,
+                                index.status,
+                                index.files.len(),
+                                index.total_bytes()
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("session list failed: {err:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            cli::SessionCommand::Show(args) => {
+                let reports = args.location.effective_reports_dir();
+                let shown = session_archive::load_session(&reports, &args.id).and_then(|index| {
+                    match args.member.as_deref() {
+                        Some(member) => {
+                            let contents =
+                                session_archive::read_session_member(&reports, &index, member)?;
+                            std::io::Write::write_all(&mut std::io::stdout(), &contents)?;
+                        }
+                        None => print!("{}", session_archive::render_session(&index)),
+                    }
+                    Ok(())
+                });
+                if let Err(err) = shown {
+                    eprintln!("session show failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        },
+        cli::Command::VerifyChain(args) => {
+            let public_key = match args.public_key.clone() {
+                Some(path) => Ok(path),
+                None => config::load_project_config(
+                    args.config.as_deref(),
+                    &args.effective_topdir(),
+                )
+                .and_then(|project_config| {
+                    project_config.attestation.public_key.ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "pass --public-key or set [attestation] public-key in the project config"
+                        )
+                    })
+                }),
+            };
+            let public_key = match public_key {
+                Ok(path) => path,
+                Err(err) => {
+                    eprintln!("verify-chain failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let attestations_dir = args
+                .effective_target_root()
+                .join(attestation::ATTESTATIONS_DIR);
+            match attestation::verify_chain(&args.rpm, &attestations_dir, &public_key) {
+                Ok(chain) => {
+                    for link in &chain.links {
+                        println!(
+                            "verify-chain stage={} statement={} signature=ok",
+                            link.stage.as_str(),
+                            link.statement.display()
+                        );
+                    }
+                    let package = chain.links.first();
+                    println!(
+                        "verify-chain rpm={} sha256={} package={} version={} stages={} status=verified",
+                        args.rpm.display(),
+                        chain.rpm_sha256,
+                        package.map(|link| link.package.as_str()).unwrap_or("-"),
+                        package.map(|link| link.version.as_str()).unwrap_or("-"),
+                        chain.links.len()
+                    );
+                }
+                Err(err) => {
+                    eprintln!("verify-chain failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Promote(args) => {
             let topdir = args.effective_topdir();
+            let settings = config::load_project_config(args.config.as_deref(), &topdir).and_then(
+                |project_config| {
+                    let naming = priority_specs::resolve_naming_profile(
+                        &args.naming_profile,
+                        &project_config,
+                    )?;
+                    let attestor = attestation::Attestor::from_config(
+                        &project_config.attestation,
+                        &args.effective_target_root(),
+                    )?;
+                    Ok((naming.package_prefix, attestor))
+                },
+            );
+            let (package_prefix, attestor) = match settings {
+                Ok(settings) => settings,
+                Err(err) => {
+                    eprintln!("promote failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let _session = match build_lock::BuildSessionGuard::acquire(
+                &args.effective_session_topdir(),
+                &args.effective_target_id(),
+                &["promote".to_string()],
+                build_lock::BuildSessionKind::Promote,
+                false,
+            ) {
+                Ok(guard) => {
+                    if let Some(reason) = guard.recovered_stale_owner() {
+                        eprintln!("recovered stale workspace lock: {reason}");
+                    }
+                    guard
+                }
+                Err(err) => {
+                    eprintln!("failed to acquire workspace build session lock: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            let request = promote::PromoteRequest {
+                packages: args.packages.clone(),
+                all_passing: args.all_passing,
+                from: args.effective_from(),
+                to: args.to.clone(),
+                move_artifacts: args.move_artifacts,
+                require_green: args.require_green,
+                update_repodata: !args.no_repodata,
+                reports_dir: args.effective_reports_dir(),
+                package_prefix,
+                attestor,
+                dry_run: args.dry_run,
+            };
+            match promote::run_promote(&request) {
+                Ok(summary) => {
+                    println!(
+                        "promote from={} to={} mode={} packages={} promoted={} unchanged={} missing={} repodata_updated={} attested={} dry_run={} record={}",
+                        request.from.display(),
+                        request.to.display(),
+                        if args.move_artifacts { "move" } else { "copy" },
+                        summary.packages,
+                        summary.promoted,
+                        summary.unchanged,
+                        summary.missing,
+                        summary.repodata_updated,
+                        summary.attested,
+                        args.dry_run,
+                        summary
+                            .record
+                            .as_ref()
+                            .map(|path| path.display().to_string())
+                            .unwrap_or_else(|| "-".to_string())
+                    );
+                }
+                Err(err) => {
+                    eprintln!("promote failed: {err:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        cli::Command::Lookup(args) => {
+            let topdir = args.effective_session_topdir();
             match build_lock::lookup_build_runtime(&topdir) {
                 Ok(snapshot) => {
                     let rendered = if args.compact {
@@ -0,0 +1,663 @@
+use crate::attestation::{Attestor, ResourceDescriptor, Stage, StageRecord};
+use crate::audit::AuditedCommand;
+use crate::priority_specs::{
+    PackageArtifact, ReportEntry, log_external_progress, normalize_name, package_artifact_kind,
+    rpm_version_label,
+};
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Promotion log kept in the destination repository, one JSON record per line.
+pub const PROMOTIONS_FILE: &str = "promotions.jsonl";
+
+#[derive(Debug, Clone)]
+pub struct PromoteRequest {
+    /// Packages to promote; ignored with `all_passing`.
+    pub packages: Vec<String>,
+    /// Promote every package whose latest report entry is green.
+    pub all_passing: bool,
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Remove artifacts from `from` instead of copying them.
+    pub move_artifacts: bool,
+    /// Refuse packages whose latest report entry is not green.
+    pub require_green: bool,
+    /// Run `createrepo_c --update` on the repositories that changed.
+    pub update_repodata: bool,
+    /// `build_*.json` reports giving each package's validation status.
+    pub reports_dir: PathBuf,
+    pub package_prefix: String,
+    /// Signs a publish statement per package, chained to its validate
+    /// statement.
+    pub attestor: Option<Attestor>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct PromotedPackage {
+    pub software: String,
+    /// Version and status of the latest report entry; empty when no report
+    /// lists the package.
+    pub version: String,
+    pub status: String,
+    pub module_test: String,
+    /// Artifact paths relative to the repository roots.
+    pub files: Vec<String>,
+}
+
+/// One line of [`PROMOTIONS_FILE`].
+#[derive(Debug, Clone, Serialize)]
+pub struct PromotionRecord {
+    pub promoted_at_utc: String,
+    /// `copy` or `move`.
+    pub mode: String,
+    pub from: String,
+    pub to: String,
+    pub require_green: bool,
+    pub packages: Vec<PromotedPackage>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PromoteSummary {
+    pub packages: usize,
+    /// Artifacts copied or moved.
+    pub promoted: usize,
+    /// Artifacts already present in the destination with the same content.
+    pub unchanged: usize,
+    /// `--all-passing` packages with no artifacts in the source repository.
+    pub missing: usize,
+    /// Repositories whose repodata was refreshed.
+    pub repodata_updated: usize,
+    /// Publish statements signed.
+    pub attested: usize,
+    pub record: Option<PathBuf>,
+}
+
+/// Green means built (`generated`, `up-to-date` or `reused-noarch`) with a
+/// module test that passed or did not run.
+fn is_green(entry: &ReportEntry) -> bool {
+    matches!(
+        entry.status.as_str(),
+        "generated" | "up-to-date" | "reused-noarch"
+    ) && !entry.module_test.starts_with("failed")
+}
+
+/// The build a report entry vouches for. Older payload versions and meta
+/// builds lying in the source repository are left behind.
+struct ReportedBuild {
+    /// RPM versions the payload may carry; `None` without a report entry.
+    payload_versions: Option<Vec<String>>,
+    meta_version: Option<u64>,
+    /// `<version>-<release>` of the entry's `meta_nevra`.
+    meta_version_release: Option<String>,
+}
+
+impl ReportedBuild {
+    /// Entries without a recorded meta build take the newest meta version
+    /// among `artifacts`.
+    fn new(entry: Option<&ReportEntry>, artifacts: &[(&PathBuf, &str, PackageArtifact)]) -> Self {
+        let newest_meta = artifacts
+            .iter()
+            .filter_map(|(_, _, kind)| match kind {
+                PackageArtifact::Meta(version) => Some(*version),
+                PackageArtifact::Payload(_) => None,
+            })
+            .max();
+        let Some(entry) = entry else {
+            return Self {
+                payload_versions: None,
+                meta_version: None,
+                meta_version_release: None,
+            };
+        };
+        let (meta_version, meta_version_release) = if entry.meta_version > 0 {
+            let slug = normalize_name(&entry.software);
+            let version_release = entry.meta_nevra.rsplit_once('.').and_then(|(nevr, _arch)| {
+                nevr.rsplit_once(&format!("-{slug}-"))
+                    .map(|(_, version_release)| version_release.to_string())
+            });
+            (Some(entry.meta_version), version_release)
+        } else {
+            (newest_meta, None)
+        };
+        Self {
+            payload_versions: Some(vec![
+                entry.version.clone(),
+                rpm_version_label(&entry.version),
+            ]),
+            meta_version,
+            meta_version_release,
+        }
+    }
+
+    fn includes(
+        &self,
+        name: &str,
+        kind: &PackageArtifact,
+        package_prefix: &str,
+        slug: &str,
+    ) -> bool {
+        match kind {
+            PackageArtifact::Payload(version) => self
+                .payload_versions
+                .as_ref()
+                .is_none_or(|versions| versions.contains(version)),
+            PackageArtifact::Meta(version) => {
+                if self.payload_versions.is_none() {
+                    return true;
+                }
+                if self.meta_version != Some(*version) {
+                    return false;
+                }
+                let Some(expected) = self.meta_version_release.as_deref() else {
+                    return true;
+                };
+                let stem = name.trim_end_matches(".rpm");
+                let version_release = stem.rsplit_once('.').map_or(stem, |(nevr, _arch)| nevr);
+                [
+                    format!("{package_prefix}-{slug}-"),
+                    format!("{package_prefix}-{slug}-shims-"),
+                ]
+                .iter()
+                .any(|prefix| version_release.strip_prefix(prefix.as_str()) == Some(expected))
+            }
+        }
+    }
+}
+
+/// Latest report entry per normalized package name over the `build_*.json`
+/// reports of `reports_dir`, oldest report first. Sidecar reports do not
+/// parse as entry lists and are passed over.
+fn latest_report_entries(reports_dir: &Path) -> Result<BTreeMap<String, ReportEntry>> {
+    let mut reports = Vec::new();
+    if reports_dir.is_dir() {
+        for dir_entry in fs::read_dir(reports_dir)
+            .with_context(|| format!("reading reports dir {}", reports_dir.display()))?
+        {
+            let path = dir_entry?.path();
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if !(name.starts_with("build_") && name.ends_with(".json")) {
+                continue;
+            }
+            let modified = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            reports.push((modified, name, path));
+        }
+    }
+    reports.sort();
+    let mut latest = BTreeMap::new();
+    for (_, _, path) in reports {
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(entries) = serde_json::from_str::<Vec<ReportEntry>>(&raw) else {
+            continue;
+        };
+        for entry in entries {
+            latest.insert(normalize_name(&entry.software), entry);
+        }
+    }
+    Ok(latest)
+}
+
+/// RPM paths below `root` relative to it, leaving out `repodata`.
+pub(crate) fn repository_rpms(root: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+            let path = entry
+                .with_context(|| format!("reading entry in {}", dir.display()))?
+                .path();
+            if path.is_dir() {
+                if path.file_name().is_some_and(|name| name != "repodata") {
+                    walk(root, &path, out)?;
+                }
+            } else if path.extension().is_some_and(|ext| ext == "rpm")
+                && let Ok(relative) = path.strip_prefix(root)
+            {
+                out.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+    let mut rpms = Vec::new();
+    walk(root, root, &mut rpms)?;
+    rpms.sort();
+    Ok(rpms)
+}
+
+/// Copy or move the artifacts of the requested packages from `from` to `to`,
+/// refresh repodata of the repositories that changed and append a record to
+/// `<to>/promotions.jsonl`. Nothing is touched when a requested package has no
+/// artifacts, clashes with a different RPM in `to` or, with `require_green`,
+/// is not green.
+pub fn run_promote(request: &PromoteRequest) -> Result<PromoteSummary> {
+    if !request.from.is_dir() {
+        bail!(
+            "source repository {} does not exist",
+            request.from.display()
+        );
+    }
+    if fs::canonicalize(&request.from).ok() == fs::canonicalize(&request.to).ok() {
+        bail!(
+            "source and destination repository are both {}",
+            request.from.display()
+        );
+    }
+    let reports = latest_report_entries(&request.reports_dir)?;
+    let slugs: Vec<String> = if request.all_passing {
+        reports
+            .iter()
+            .filter(|(_, entry)| is_green(entry))
+            .map(|(slug, _)| slug.clone())
+            .collect()
+    } else {
+        request
+            .packages
+            .iter()
+            .map(|package| normalize_name(package))
+            .collect()
+    };
+    if request.require_green {
+        let not_green: Vec<String> = slugs
+            .iter()
+            .filter(|slug| !reports.get(*slug).is_some_and(is_green))
+            .map(|slug| match reports.get(slug) {
+                Some(entry) if entry.module_test.starts_with("failed") => {
+                    format!("{slug} (module test {})", entry.module_test)
+                }
+                Some(entry) => format!("{slug} ({})", entry.status),
+                None => format!("{slug} (not in any report)"),
+            })
+            .collect();
+        if !not_green.is_empty() {
+            bail!(
+                "not green in {}: {}",
+                request.reports_dir.display(),
+                not_green.join(", ")
+            );
+        }
+    }
+
+    let rpms = repository_rpms(&request.from)?;
+    let mut summary = PromoteSummary::default();
+    let mut packages = Vec::new();
+    for slug in &slugs {
+        let artifacts: Vec<(&PathBuf, &str, PackageArtifact)> = rpms
+            .iter()
+            .filter_map(|path| {
+                let name = path.file_name()?.to_str()?;
+                let kind = package_artifact_kind(name, &request.package_prefix, slug)?;
+                Some((path, name, kind))
+            })
+            .collect();
+        let selection = ReportedBuild::new(reports.get(slug), &artifacts);
+        let files: Vec<String> = artifacts
+            .iter()
+            .filter(|(_, name, kind)| selection.includes(name, kind, &request.package_prefix, slug))
+            .map(|(path, _, _)| path.display().to_string())
+            .collect();
+        if files.is_empty() {
+            if request.all_passing {
+                summary.missing += 1;
+                continue;
+            }
+            bail!(
+                "no {}-{slug} artifacts in {}",
+                request.package_prefix,
+                request.from.display()
+            );
+        }
+        let entry = reports.get(slug);
+        packages.push(PromotedPackage {
+            software: slug.clone(),
+            version: entry.map(|entry| entry.version.clone()).unwrap_or_default(),
+            status: entry.map(|entry| entry.status.clone()).unwrap_or_default(),
+            module_test: entry
+                .map(|entry| entry.module_test.clone())
+                .unwrap_or_default(),
+            files,
+        });
+    }
+    summary.packages = packages.len();
+
+    // An RPM already in the destination must be the same build; a different
+    // one under the same NEVRA stops the promotion before anything moves.
+    let mut unchanged = BTreeSet::new();
+    for file in packages.iter().flat_map(|package| &package.files) {
+        let (source, dest) = (request.from.join(file), request.to.join(file));
+        if !dest.exists() {
+            continue;
+        }
+        if !same_content(&source, &dest)? {
+            bail!(
+                "{} already exists with different content than {}",
+                dest.display(),
+                source.display()
+            );
+        }
+        unchanged.insert(file.clone());
+    }
+
+    let mode = if request.move_artifacts {
+        "move"
+    } else {
+        "copy"
+    };
+    for package in &packages {
+        for file in &package.files {
+            let source = request.from.join(file);
+            let dest = request.to.join(file);
+            let outcome = if unchanged.contains(file) {
+                if request.move_artifacts && !request.dry_run {
+                    fs::remove_file(&source)
+                        .with_context(|| format!("removing {}", source.display()))?;
+                }
+                summary.unchanged += 1;
+                "unchanged"
+            } else {
+                if !request.dry_run {
+                    transfer(&source, &dest, request.move_artifacts)?;
+                }
+                summary.promoted += 1;
+                mode
+            };
+            log_external_progress(format!(
+                "phase=promote status={outcome} package={} file={file} dry_run={}",
+                package.software, request.dry_run
+            ));
+        }
+    }
+    if request.dry_run || packages.is_empty() {
+        return Ok(summary);
+    }
+
+    if request.update_repodata {
+        let mut repos = vec![request.to.as_path()];
+        if request.move_artifacts {
+            repos.push(request.from.as_path());
+        }
+        for repo in repos {
+            update_repodata(repo)?;
+            summary.repodata_updated += 1;
+        }
+    }
+
+    if let Some(attestor) = request.attestor.as_ref() {
+        for package in &packages {
+            match attest_publish(attestor, request, package) {
+                Ok(statement) => {
+                    summary.attested += 1;
+                    log_external_progress(format!(
+                        "phase=attestation status=signed package={} stage=publish statement={}",
+                        package.software,
+                        statement.display()
+                    ));
+                }
+                Err(err) => log_external_progress(format!(
+                    "phase=attestation status=warning package={} stage=publish reason={err:#}",
+                    package.software
+                )),
+            }
+        }
+    }
+
+    let record = PromotionRecord {
+        promoted_at_utc: Utc::now().to_rfc3339(),
+        mode: mode.to_string(),
+        from: request.from.display().to_string(),
+        to: request.to.display().to_string(),
+        require_green: request.require_green,
+        packages,
+    };
+    let path = request.to.join(PROMOTIONS_FILE);
+    let mut line = serde_json::to_string(&record).context("serializing promotion record")?;
+    line.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("appending promotion record to {}", path.display()))?;
+    summary.record = Some(path);
+    Ok(summary)
+}
+
+/// Publish statement of `package`: the promoted files in `to`, consuming the
+/// binary RPMs of the newest validate statement that lists all of them.
+fn attest_publish(
+    attestor: &Attestor,
+    request: &PromoteRequest,
+    package: &PromotedPackage,
+) -> Result<PathBuf> {
+    let subjects = package
+        .files
+        .iter()
+        .map(|file| ResourceDescriptor::of_file(&request.to.join(file), &request.to))
+        .collect::<Result<Vec<_>>>()?;
+    let digests = subjects
+        .iter()
+        .filter(|subject| !subject.name.ends_with(".src.rpm"))
+        .filter_map(|subject| subject.sha256().map(str::to_string))
+        .collect();
+    let Some((validate, statement)) =
+        attestor.find_covering_statement(Stage::Validate, &package.software, &digests)?
+    else {
+        bail!("no validate statement lists the promoted RPMs");
+    };
+    let materials = subjects
+        .iter()
+        .filter(|subject| subject.sha256().is_some_and(|sha| digests.contains(sha)))
+        .cloned()
+        .collect();
+    let mut record = StageRecord {
+        stage: Stage::Publish,
+        package: &statement.predicate.package,
+        version: &statement.predicate.version,
+        target_id: &statement.predicate.target_id,
+        subjects,
+        materials,
+        details: BTreeMap::new(),
+    };
+    record
+        .details
+        .insert("repository".to_string(), request.to.display().to_string());
+    attestor.attest(record, Some(&validate))
+}
+
+fn same_content(a: &Path, b: &Path) -> Result<bool> {
+    let len = |path: &Path| {
+        fs::metadata(path)
+            .map(|meta| meta.len())
+            .with_context(|| format!("reading metadata of {}", path.display()))
+    };
+    if len(a)? != len(b)? {
+        return Ok(false);
+    }
+    let read = |path: &Path| fs::read(path).with_context(|| format!("reading {}", path.display()));
+    Ok(read(a)? == read(b)?)
+}
+
+/// Place `source` at `dest` through a temporary file next to it, so the
+/// destination repository never serves a partial RPM. Moves rename when both
+/// repositories share a filesystem.
+fn transfer(source: &Path, dest: &Path, move_artifacts: bool) -> Result<()> {
+    let parent = dest.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    if move_artifacts {
+        match fs::rename(source, dest) {
+            Ok(()) => return Ok(()),
+            Err(err) if err.kind() == ErrorKind::CrossesDevices => {}
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("moving {} to {}", source.display(), dest.display()));
+            }
+        }
+    }
+    let staging = dest.with_extension(format!("rpm.tmp-{}", std::process::id()));
+    fs::copy(source, &staging)
+        .with_context(|| format!("copying {} to {}", source.display(), staging.display()))?;
+    fs::rename(&staging, dest).with_context(|| format!("activating {}", dest.display()))?;
+    if move_artifacts {
+        fs::remove_file(source).with_context(|| format!("removing {}", source.display()))?;
+    }
+    Ok(())
+}
+
+fn update_repodata(repo: &Path) -> Result<()> {
+    let output = Command::new("createrepo_c")
+        .arg("--update")
+        .arg("--quiet")
+        .arg(repo)
+        .audited_output()
+        .context("running createrepo_c; install it or pass --no-repodata")?;
+    if !output.status.success() {
+        bail!(
+            "createrepo_c failed for {}: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    log_external_progress(format!(
+        "phase=promote status=repodata-updated repo={}",
+        repo.display()
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_report(reports: &Path, name: &str, rows: &[(&str, &str, &str)]) {
+        fs::create_dir_all(reports).expect("mkdir reports");
+        let entries = rows
+            .iter()
+            .map(|(software, status, module_test)| {
+                serde_json::json!({
+                    "software": software, "priority": 0, "status": status, "reason": "",
+                    "overlap_recipe": software, "overlap_reason": "", "variant_dir": "",
+                    "package_name": software, "version": "1.21",
+                    "payload_spec_path": "", "meta_spec_path": "", "staged_build_sh": "",
+                    "module_test": module_test, "meta_version": 3,
+                    "meta_nevra": format!("phoreus-{software}-3-1.el9.x86_64")
+                })
+            })
+            .collect::<Vec<_>>();
+        fs::write(
+            reports.join(name),
+            serde_json::to_string(&entries).expect("json"),
+        )
+        .expect("write report");
+    }
+
+    #[test]
+    fn promote_moves_green_packages_and_records_them() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let reports = tmp.path().join("reports");
+        write_report(
+            &reports,
+            "build_20260101000000.json",
+            &[("samtools", "generated", ""), ("bcftools", "generated", "")],
+        );
+        write_report(
+            &reports,
+            "build_20260102000000.json",
+            &[("bcftools", "generated", "failed:import missing libhts")],
+        );
+        let from = tmp.path().join("testing");
+        let to = tmp.path().join("stable");
+        fs::create_dir_all(from.join("x86_64")).expect("mkdir testing");
+        fs::create_dir_all(from.join("repodata")).expect("mkdir repodata");
+        for name in [
+            "x86_64/phoreus-samtools-1.21-1.21-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-3-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-shims-3-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-extra-2.0-2.0-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-1.20-1.20-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-2-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-3-2.el9.x86_64.rpm",
+            "x86_64/phoreus-bcftools-1.21-1.21-1.el9.x86_64.rpm",
+            "repodata/phoreus-samtools-1.21-1.21-1.el9.x86_64.rpm",
+        ] {
+            fs::write(from.join(name), name).expect("write rpm");
+        }
+        let request = PromoteRequest {
+            packages: Vec::new(),
+            all_passing: true,
+            from: from.clone(),
+            to: to.clone(),
+            move_artifacts: true,
+            require_green: false,
+            update_repodata: false,
+            reports_dir: reports.clone(),
+            package_prefix: "phoreus".to_string(),
+            attestor: None,
+            dry_run: false,
+        };
+
+        let summary = run_promote(&request).expect("promote");
+        assert_eq!((summary.packages, summary.promoted), (1, 3));
+        assert!(
+            to.join("x86_64/phoreus-samtools-shims-3-1.el9.x86_64.rpm")
+                .exists()
+        );
+        assert!(
+            !from
+                .join("x86_64/phoreus-samtools-3-1.el9.x86_64.rpm")
+                .exists()
+        );
+        for stale in [
+            "x86_64/phoreus-samtools-extra-2.0-2.0-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-1.20-1.20-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-2-1.el9.x86_64.rpm",
+            "x86_64/phoreus-samtools-3-2.el9.x86_64.rpm",
+        ] {
+            assert!(from.join(stale).exists(), "{stale} must stay in testing");
+            assert!(!to.join(stale).exists(), "{stale} must not be promoted");
+        }
+        assert!(
+            from.join("x86_64/phoreus-bcftools-1.21-1.21-1.el9.x86_64.rpm")
+                .exists()
+        );
+        let record = fs::read_to_string(to.join(PROMOTIONS_FILE)).expect("read record");
+        let record: serde_json::Value = serde_json::from_str(record.trim()).expect("parse record");
+        assert_eq!(record["mode"], "move");
+        assert_eq!(record["packages"][0]["software"], "samtools");
+        assert_eq!(
+            record["packages"][0]["files"].as_array().map(Vec::len),
+            Some(3)
+        );
+
+        let refused = run_promote(&PromoteRequest {
+            packages: vec!["bcftools".to_string()],
+            all_passing: false,
+            require_green: true,
+            ..request.clone()
+        })
+        .expect_err("failed module test is not green");
+        assert!(format!("{refused:#}").contains("bcftools (module test failed:import"));
+        assert!(
+            from.join("x86_64/phoreus-bcftools-1.21-1.21-1.el9.x86_64.rpm")
+                .exists()
+        );
+
+        let missing = run_promote(&PromoteRequest {
+            packages: vec!["bwa".to_string()],
+            all_passing: false,
+            ..request
+        })
+        .expect_err("bwa has no artifacts");
+        assert!(format!("{missing:#}").contains("no phoreus-bwa artifacts"));
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde_yaml::Value;
+use std::fmt;
+
+/// Typed view of a rendered `meta.yaml`. Deserializing from the rendered text
+/// keeps serde_yaml's location tracking, so schema problems point at the line
+/// and column they occur at. `source` and `extra`, whose shape varies between
+/// recipes, stay untyped; `outputs` are not modelled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecipeDocument {
+    pub package: PackageSection,
+    #[serde(default)]
+    pub source: Option<Value>,
+    #[serde(default)]
+    pub build: Option<BuildSection>,
+    #[serde(default)]
+    pub requirements: Option<RequirementsSection>,
+    #[serde(default)]
+    pub test: Option<TestSection>,
+    #[serde(default)]
+    pub about: Option<AboutSection>,
+    #[serde(default)]
+    pub extra: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageSection {
+    pub name: Scalar,
+    pub version: Scalar,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BuildSection {
+    #[serde(default)]
+    pub number: Option<Scalar>,
+    #[serde(default)]
+    pub noarch: Option<Scalar>,
+    /// A string or a list of command lines.
+    #[serde(default)]
+    pub script: Option<Value>,
+    #[serde(default)]
+    pub script_env: StringList,
+    #[serde(default)]
+    pub entry_points: StringList,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RequirementsSection {
+    #[serde(default)]
+    pub build: StringList,
+    #[serde(default)]
+    pub host: StringList,
+    #[serde(default)]
+    pub run: StringList,
+    #[serde(default)]
+    pub run_constrained: StringList,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TestSection {
+    #[serde(default)]
+    pub commands: StringList,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AboutSection {
+    #[serde(default)]
+    pub home: Option<Scalar>,
+    #[serde(default)]
+    pub dev_url: Option<Scalar>,
+    #[serde(default)]
+    pub doc_url: Option<Scalar>,
+    #[serde(default)]
+    pub license: Option<Scalar>,
+    #[serde(default)]
+    pub summary: Option<Scalar>,
+}
+
+/// A string, number or boolean, kept as its string form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scalar(pub String);
+
+/// A list of scalars; a single scalar is a one-item list and empty (`~`)
+/// items are dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringList(pub Vec<String>);
+
+/// Where and why a rendered `meta.yaml` does not match [`RecipeDocument`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeSchemaError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for RecipeSchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {line} column {column}: {}", self.message)
+            }
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for RecipeSchemaError {}
+
+impl From<serde_yaml::Error> for RecipeSchemaError {
+    fn from(err: serde_yaml::Error) -> Self {
+        let location = err.location();
+        let mut message = err.to_string();
+        // The message ends with the location, which is carried separately.
+        if let Some(location) = location.as_ref() {
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+        }
+        Self {
+            line: location.as_ref().map(|location| location.line()),
+            column: location.as_ref().map(|location| location.column()),
+            message,
+        }
+    }
+}
+
+impl RecipeDocument {
+    pub fn from_rendered(rendered: &str) -> Result<Self, RecipeSchemaError> {
+        Ok(serde_yaml::from_str(rendered)?)
+    }
+}
+
+impl Scalar {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+struct ScalarVisitor;
+
+impl<'de> Visitor<'de> for ScalarVisitor {
+    type Value = Scalar;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string, number or boolean")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Scalar, E> {
+        Ok(Scalar(value.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Scalar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ScalarVisitor)
+    }
+}
+
+struct StringListVisitor;
+
+impl<'de> Visitor<'de> for StringListVisitor {
+    type Value = StringList;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<StringList, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element::<Option<Scalar>>()? {
+            items.extend(item.map(|scalar| scalar.0));
+        }
+        Ok(StringList(items))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<StringList, E> {
+        Ok(StringList::default())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<StringList, E> {
+        Ok(StringList::default())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<StringList, E> {
+        Ok(StringList(vec![value.to_string()]))
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<StringList, E> {
+        Ok(StringList(vec![value.to_string()]))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<StringList, E> {
+        Ok(StringList(vec![value.to_string()]))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<StringList, E> {
+        Ok(StringList(vec![value.to_string()]))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<StringList, E> {
+        Ok(StringList(vec![value.to_string()]))
+    }
+}
+
+impl<'de> Deserialize<'de> for StringList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(StringListVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_errors_carry_yaml_locations() {
+        let recipe = RecipeDocument::from_rendered(
+            "package:\n  name: samtools\n  version: 1.21\nrequirements:\n  run:\n    - htslib\n    -\n    - 3\n",
+        )
+        .expect("valid recipe");
+        assert_eq!(recipe.package.version.as_str(), "1.21");
+        assert_eq!(
+            recipe.requirements.expect("requirements").run.0,
+            vec!["htslib".to_string(), "3".to_string()]
+        );
+
+        let missing = RecipeDocument::from_rendered("package:\n  name: samtools\nabout: {}\n")
+            .expect_err("version is required");
+        assert_eq!((missing.line, missing.column), (Some(2), Some(3)));
+        assert!(missing.message.contains("missing field `version`"));
+
+        let nested = RecipeDocument::from_rendered(
+            "package:\n  name: samtools\n  version: '1.21'\nrequirements:\n  host:\n    - zlib\n    - {name: htslib}\n",
+        )
+        .expect_err("mapping is not a dependency");
+        assert_eq!(nested.line, Some(7));
+        assert!(
+            nested
+                .message
+                .starts_with("requirements.host[1]: invalid type: map")
+        );
+        assert!(
+            nested
+                .to_string()
+                .starts_with("line 7 column 7: requirements.host[1]")
+        );
+    }
+}
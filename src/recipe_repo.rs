@@ -1,13 +1,20 @@
+use crate::cli::RecipeSyncPolicy;
+use crate::config::{RecipePinMismatch, RecipesConfig};
 use crate::priority_specs;
+use crate::report_index::RecipeSyncRecord;
 use anyhow::{Context, Result};
 use fs2::FileExt;
 use git2::build::{CheckoutBuilder, RepoBuilder};
-use git2::{AutotagOption, FetchOptions, ObjectType, Oid, RemoteCallbacks, Repository};
+use git2::{
+    AutotagOption, FetchOptions, ObjectType, Oid, RemoteCallbacks, Repository, Sort, Status,
+    StatusOptions,
+};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 const BIOCONDA_RECIPES_REMOTE: &str = "https://github.com/bioconda/bioconda-recipes.git";
 
@@ -17,6 +24,30 @@ pub struct RecipeRepoRequest {
     pub recipe_repo_root: PathBuf,
     pub recipe_ref: Option<String>,
     pub sync: bool,
+    pub policy: RecipeSyncPolicy,
+    pub pin: Option<RecipePin>,
+}
+
+/// Commit the prepared checkout must be at, from `[recipes] expected-sha`.
+#[derive(Debug, Clone)]
+pub struct RecipePin {
+    pub expected_sha: String,
+    pub on_mismatch: RecipePinMismatch,
+}
+
+impl RecipeRepoRequest {
+    /// Applies the `[recipes]` config table: its `recipe-ref` when the command
+    /// line names none, and its `expected-sha` pin.
+    pub fn with_config(mut self, config: &RecipesConfig) -> Self {
+        if self.recipe_ref.is_none() {
+            self.recipe_ref = config.recipe_ref.clone();
+        }
+        self.pin = config.expected_sha.as_ref().map(|sha| RecipePin {
+            expected_sha: sha.to_ascii_lowercase(),
+            on_mismatch: config.on_mismatch,
+        });
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +59,151 @@ pub struct RecipeRepoOutcome {
     pub checked_out: Option<String>,
     pub head: Option<String>,
     pub managed_git: bool,
+    pub policy: RecipeSyncPolicy,
+    /// Why the sync failed when `prefer` continued on the existing checkout.
+    pub sync_error: Option<String>,
+    /// `verified` or `mismatch` when the request carried a pin.
+    pub pin_status: Option<&'static str>,
+    pub expected_sha: Option<String>,
+}
+
+impl RecipeRepoOutcome {
+    /// Entry for the report index of the run using this checkout.
+    pub fn sync_record(&self) -> RecipeSyncRecord {
+        let status = if !self.managed_git {
+            "unmanaged"
+        } else if self.sync_error.is_some() {
+            "stale"
+        } else if self.fetched {
+            "synced"
+        } else if self.policy == RecipeSyncPolicy::Offline {
+            "offline"
+        } else {
+            "local"
+        };
+        RecipeSyncRecord {
+            policy: self.policy.as_str().to_string(),
+            status: status.to_string(),
+            head: self.head.clone(),
+            error: self.sync_error.clone(),
+            expected_sha: self.expected_sha.clone(),
+            pin_status: self.pin_status.map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeCommit {
+    pub id: String,
+    pub summary: String,
+}
+
+/// Checked-out position of a recipe repository, restorable after bisecting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeHead {
+    pub branch: Option<String>,
+    pub commit: String,
+}
+
+/// Worktree changes of a recipe checkout when a run starts, so changes made
+/// during the run can be told apart from local edits that were already there.
+#[derive(Debug, Clone)]
+pub struct RecipeTreeSnapshot {
+    repo_root: PathBuf,
+    changes: BTreeMap<String, WorktreeChange>,
+}
+
+/// Status of one changed path plus its size and mtime, which catch a second
+/// edit of an already modified file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WorktreeChange {
+    status: &'static str,
+    len: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+impl RecipeTreeSnapshot {
+    /// `None` when the recipe root is not a git checkout.
+    pub fn capture(repo_root: &Path) -> Option<Self> {
+        let repo = Repository::open(repo_root).ok()?;
+        let changes = worktree_changes(&repo).ok()?;
+        Some(Self {
+            repo_root: repo_root.to_path_buf(),
+            changes,
+        })
+    }
+
+    pub fn repo_root(&self) -> &Path {
+        &self.repo_root
+    }
+
+    /// Paths with local changes at capture time.
+    pub fn local_changes(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// `<status> <path>` for every path whose worktree state changed since
+    /// the snapshot, sorted by path.
+    pub fn changes_since(&self) -> Result<Vec<String>> {
+        let repo = open_recipe_repository(&self.repo_root)?;
+        let current = worktree_changes(&repo)?;
+        let mut changed = BTreeMap::new();
+        for (path, change) in &current {
+            if self.changes.get(path) != Some(change) {
+                changed.insert(path, change.status);
+            }
+        }
+        for path in self.changes.keys() {
+            if !current.contains_key(path) {
+                changed.insert(path, "reverted");
+            }
+        }
+        Ok(changed
+            .into_iter()
+            .map(|(path, status)| format!("{status} {path}"))
+            .collect())
+    }
+}
+
+fn worktree_changes(repo: &Repository) -> Result<BTreeMap<String, WorktreeChange>> {
+    let workdir = repo
+        .workdir()
+        .context("recipe repository has no worktree")?
+        .to_path_buf();
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .context("reading recipe worktree status")?;
+    let mut changes = BTreeMap::new();
+    for entry in statuses.iter() {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let status = entry.status();
+        let label = if status.contains(Status::WT_NEW) {
+            "untracked"
+        } else if status.intersects(Status::WT_DELETED | Status::INDEX_DELETED) {
+            "deleted"
+        } else if status.intersects(Status::WT_TYPECHANGE | Status::INDEX_TYPECHANGE) {
+            "typechange"
+        } else {
+            "modified"
+        };
+        let metadata = fs::symlink_metadata(workdir.join(path)).ok();
+        changes.insert(
+            path.to_string(),
+            WorktreeChange {
+                status: label,
+                len: metadata.as_ref().map(|m| m.len()),
+                modified: metadata.and_then(|m| m.modified().ok()),
+            },
+        );
+    }
+    Ok(changes)
 }
 
 pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRepoOutcome> {
@@ -39,7 +215,14 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
     let _repo_lock = acquire_recipe_repo_lock(&request.recipe_repo_root)?;
 
     let mut cloned = false;
+    let offline = request.policy == RecipeSyncPolicy::Offline;
     if !request.recipe_repo_root.exists() {
+        if offline {
+            anyhow::bail!(
+                "recipes repository {} does not exist and --recipe-sync-policy offline does not clone",
+                request.recipe_repo_root.to_string_lossy()
+            );
+        }
         if let Some(parent) = request.recipe_repo_root.parent() {
             fs::create_dir_all(parent).with_context(|| {
                 format!(
@@ -73,11 +256,18 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
     let repo = match Repository::open(&request.recipe_repo_root) {
         Ok(repo) => repo,
         Err(err) => {
-            if fallback_recipe_root.exists() && !request.sync && request.recipe_ref.is_none() {
+            if fallback_recipe_root.exists()
+                && (!request.sync || offline)
+                && request.recipe_ref.is_none()
+            {
                 priority_specs::log_external_progress(format!(
                     "phase=recipe-sync status=completed action=prepare managed_git=false recipes={}",
                     fallback_recipe_root.to_string_lossy()
                 ));
+                let pin_status = match &request.pin {
+                    Some(pin) => Some(check_recipe_pin(pin, None)?),
+                    None => None,
+                };
                 return Ok(RecipeRepoOutcome {
                     recipe_root: fallback_recipe_root,
                     recipe_repo_root: request.recipe_repo_root.clone(),
@@ -86,6 +276,10 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
                     checked_out: None,
                     head: None,
                     managed_git: false,
+                    policy: request.policy,
+                    sync_error: None,
+                    pin_status,
+                    expected_sha: request.pin.as_ref().map(|pin| pin.expected_sha.clone()),
                 });
             }
             priority_specs::log_external_progress(format!(
@@ -107,9 +301,20 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
     ));
 
     let mut fetched = false;
-    if request.sync || request.recipe_ref.is_some() {
-        fetch_origin(&repo)?;
-        fetched = true;
+    let mut sync_error = None;
+    if offline {
+        priority_specs::log_external_progress(
+            "phase=recipe-sync status=skipped action=fetch reason=offline",
+        );
+    } else if request.sync || request.recipe_ref.is_some() {
+        match fetch_origin(&repo) {
+            Ok(()) => fetched = true,
+            Err(err) if request.policy == RecipeSyncPolicy::Prefer => {
+                sync_error = Some(format!("{err:#}"));
+                log_stale_checkout("fetch", &err, &repo);
+            }
+            Err(err) => return Err(err),
+        }
     } else {
         priority_specs::log_external_progress(
             "phase=recipe-sync status=skipped action=fetch reason=not_requested".to_string(),
@@ -127,16 +332,32 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
             "phase=recipe-sync status=completed action=checkout result={}",
             sanitize_progress_value(checked_out.as_deref().unwrap_or("unknown"))
         ));
+    } else if request.sync && fetched {
+        let checkout = default_origin_branch_name(&repo).and_then(|default_branch| {
+            priority_specs::log_external_progress(format!(
+                "phase=recipe-sync status=started action=checkout target={}",
+                sanitize_progress_value(&default_branch)
+            ));
+            checkout_named_ref(&repo, &default_branch)
+        });
+        match checkout {
+            Ok(result) => {
+                priority_specs::log_external_progress(format!(
+                    "phase=recipe-sync status=completed action=checkout result={}",
+                    sanitize_progress_value(&result)
+                ));
+                checked_out = Some(result);
+            }
+            Err(err) if request.policy == RecipeSyncPolicy::Prefer => {
+                sync_error = Some(format!("{err:#}"));
+                log_stale_checkout("checkout", &err, &repo);
+            }
+            Err(err) => return Err(err),
+        }
     } else if request.sync {
-        let default_branch = default_origin_branch_name(&repo)?;
         priority_specs::log_external_progress(format!(
-            "phase=recipe-sync status=started action=checkout target={}",
-            sanitize_progress_value(&default_branch)
-        ));
-        checked_out = Some(checkout_named_ref(&repo, &default_branch)?);
-        priority_specs::log_external_progress(format!(
-            "phase=recipe-sync status=completed action=checkout result={}",
-            sanitize_progress_value(checked_out.as_deref().unwrap_or("unknown"))
+            "phase=recipe-sync status=skipped action=checkout reason={}",
+            if offline { "offline" } else { "fetch_failed" }
         ));
     } else {
         priority_specs::log_external_progress(
@@ -154,6 +375,17 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
     }
 
     let head = head_summary(&repo).ok();
+    let pin_status = match &request.pin {
+        Some(pin) => {
+            let head_id = repo
+                .head()
+                .ok()
+                .and_then(|head| head.peel_to_commit().ok())
+                .map(|commit| commit.id().to_string());
+            Some(check_recipe_pin(pin, head_id.as_deref())?)
+        }
+        None => None,
+    };
     priority_specs::log_external_progress(format!(
         "phase=recipe-sync status=completed action=prepare managed_git=true cloned={} fetched={} checkout={} head={}",
         cloned,
@@ -170,9 +402,48 @@ pub fn ensure_recipe_repository(request: &RecipeRepoRequest) -> Result<RecipeRep
         checked_out,
         head,
         managed_git: true,
+        policy: request.policy,
+        sync_error,
+        pin_status,
+        expected_sha: request.pin.as_ref().map(|pin| pin.expected_sha.clone()),
     })
 }
 
+/// Compares the checkout's HEAD commit with the pinned SHA. A checkout that
+/// is not a git repository cannot match. Mismatches fail the run unless the
+/// pin only warns.
+fn check_recipe_pin(pin: &RecipePin, head_id: Option<&str>) -> Result<&'static str> {
+    if head_id.is_some_and(|id| id.starts_with(&pin.expected_sha)) {
+        priority_specs::log_external_progress(format!(
+            "phase=recipe-sync status=pin-verified expected_sha={}",
+            pin.expected_sha
+        ));
+        return Ok("verified");
+    }
+    let head = head_id.unwrap_or("none");
+    if pin.on_mismatch == RecipePinMismatch::Fail {
+        anyhow::bail!(
+            "recipes checkout HEAD {head} does not match [recipes] expected-sha {}",
+            pin.expected_sha
+        );
+    }
+    priority_specs::log_external_progress(format!(
+        "phase=recipe-sync status=pin-mismatch expected_sha={} head={} on_mismatch=warn",
+        pin.expected_sha, head
+    ));
+    Ok("mismatch")
+}
+
+/// `prefer` policy: report a failed sync step and keep the current checkout.
+fn log_stale_checkout(action: &str, err: &anyhow::Error, repo: &Repository) {
+    priority_specs::log_external_progress(format!(
+        "phase=recipe-sync status=degraded action={} policy=prefer head={} reason={}",
+        action,
+        sanitize_progress_value(head_summary(repo).as_deref().unwrap_or("unknown")),
+        sanitize_progress_value(format!("{err:#}"))
+    ));
+}
+
 fn resolve_recipe_root_after_prepare(requested_root: &Path, repo_root: &Path) -> PathBuf {
     if requested_root
         .file_name()
@@ -318,6 +589,122 @@ fn checkout_detached(repo: &Repository, obj: &git2::Object<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Full commit id checked out in a recipe repository, when it is a git checkout.
+pub fn recipe_repository_head(repo_root: &Path) -> Option<String> {
+    let repo = Repository::open(repo_root).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// First-parent commits after `good` up to and including `bad`, oldest first.
+pub fn recipe_commits_between(
+    repo_root: &Path,
+    good: &str,
+    bad: &str,
+) -> Result<Vec<RecipeCommit>> {
+    let repo = open_recipe_repository(repo_root)?;
+    let good_id = resolve_commit_id(&repo, good)?;
+    let bad_id = resolve_commit_id(&repo, bad)?;
+    let descends = repo
+        .graph_descendant_of(bad_id, good_id)
+        .with_context(|| format!("comparing history of '{good}' and '{bad}'"))?;
+    if !descends {
+        anyhow::bail!("bad ref '{bad}' is not a descendant of good ref '{good}'");
+    }
+
+    let mut walk = repo.revwalk().context("creating revision walk")?;
+    walk.push(bad_id)
+        .with_context(|| format!("walking history from '{bad}'"))?;
+    walk.hide(good_id)
+        .with_context(|| format!("excluding history of '{good}'"))?;
+    walk.simplify_first_parent()
+        .context("restricting walk to first parents")?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)
+        .context("sorting revision walk")?;
+    let mut commits = Vec::new();
+    for oid in walk {
+        let oid = oid.context("reading revision walk entry")?;
+        let commit = repo
+            .find_commit(oid)
+            .with_context(|| format!("reading commit {oid}"))?;
+        commits.push(RecipeCommit {
+            id: oid.to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(commits)
+}
+
+pub fn current_recipe_head(repo_root: &Path) -> Result<RecipeHead> {
+    let repo = open_recipe_repository(repo_root)?;
+    let head = repo.head().context("reading repository HEAD")?;
+    let commit = head
+        .peel_to_commit()
+        .context("resolving repository HEAD commit")?;
+    let branch = if head.is_branch() {
+        head.shorthand().map(ToString::to_string)
+    } else {
+        None
+    };
+    Ok(RecipeHead {
+        branch,
+        commit: commit.id().to_string(),
+    })
+}
+
+/// Detach the recipe checkout at `rev` without fetching.
+pub fn checkout_recipe_commit(repo_root: &Path, rev: &str) -> Result<()> {
+    let _repo_lock = acquire_recipe_repo_lock(repo_root)?;
+    let repo = open_recipe_repository(repo_root)?;
+    let commit_obj = repo
+        .revparse_single(rev)
+        .with_context(|| format!("resolving ref '{rev}'"))?
+        .peel(ObjectType::Commit)
+        .with_context(|| format!("peeling '{rev}' to commit"))?;
+    checkout_detached(&repo, &commit_obj)
+}
+
+pub fn restore_recipe_head(repo_root: &Path, head: &RecipeHead) -> Result<()> {
+    let Some(branch) = head.branch.as_deref() else {
+        return checkout_recipe_commit(repo_root, &head.commit);
+    };
+    let _repo_lock = acquire_recipe_repo_lock(repo_root)?;
+    let repo = open_recipe_repository(repo_root)?;
+    let branch_ref = format!("refs/heads/{branch}");
+    let commit_obj = repo
+        .revparse_single(&branch_ref)
+        .with_context(|| format!("resolving local branch {branch}"))?
+        .peel(ObjectType::Commit)
+        .with_context(|| format!("peeling local branch {branch} to commit"))?;
+    // Update the tree before moving HEAD; checking out HEAD after the move
+    // would see no difference and leave the detached files in place.
+    let mut checkout = CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(&commit_obj, Some(&mut checkout))
+        .with_context(|| format!("checking out local branch {branch}"))?;
+    repo.set_head(&branch_ref)
+        .with_context(|| format!("setting HEAD to local branch {branch}"))?;
+    Ok(())
+}
+
+fn open_recipe_repository(repo_root: &Path) -> Result<Repository> {
+    Repository::open(repo_root).with_context(|| {
+        format!(
+            "opening recipes git repository at {}",
+            repo_root.to_string_lossy()
+        )
+    })
+}
+
+fn resolve_commit_id(repo: &Repository, rev: &str) -> Result<Oid> {
+    let commit = repo
+        .revparse_single(rev)
+        .with_context(|| format!("resolving ref '{rev}'"))?
+        .peel_to_commit()
+        .with_context(|| format!("peeling '{rev}' to commit"))?;
+    Ok(commit.id())
+}
+
 fn head_summary(repo: &Repository) -> Result<String> {
     let head = repo.head().context("reading repository HEAD")?;
     let commit = head
@@ -498,3 +885,173 @@ fn acquire_recipe_repo_lock(repo_root: &Path) -> Result<RepoSyncLock> {
 
     Ok(RepoSyncLock { lock_file })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit_file(repo: &Repository, name: &str, body: &str, message: &str) -> Oid {
+        let workdir = repo.workdir().expect("workdir").to_path_buf();
+        fs::write(workdir.join(name), body).expect("write file");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new(name)).expect("stage file");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("bioconda2rpm", "test@example.com").expect("signature");
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .expect("commit")
+    }
+
+    #[test]
+    fn recipe_commits_between_walks_range_and_checkout_restores_head() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let repo = Repository::init(tmp.path()).expect("init repo");
+        let first = commit_file(&repo, "meta.yaml", "version: 1\n", "add samtools");
+        commit_file(&repo, "meta.yaml", "version: 2\n", "bump samtools");
+        let third = commit_file(&repo, "meta.yaml", "version: 3\n", "break samtools");
+
+        let commits =
+            recipe_commits_between(tmp.path(), &first.to_string(), "HEAD").expect("walk range");
+        let summaries = commits
+            .iter()
+            .map(|c| c.summary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(summaries, vec!["bump samtools", "break samtools"]);
+        assert_eq!(commits[1].id, third.to_string());
+        assert!(recipe_commits_between(tmp.path(), "HEAD", &first.to_string()).is_err());
+
+        let head = current_recipe_head(tmp.path()).expect("read head");
+        assert!(head.branch.is_some());
+        checkout_recipe_commit(tmp.path(), &first.to_string()).expect("checkout first");
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("meta.yaml")).expect("read meta"),
+            "version: 1\n"
+        );
+        assert_eq!(
+            current_recipe_head(tmp.path())
+                .expect("detached head")
+                .branch,
+            None
+        );
+        restore_recipe_head(tmp.path(), &head).expect("restore head");
+        assert_eq!(current_recipe_head(tmp.path()).expect("head"), head);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("meta.yaml")).expect("read meta"),
+            "version: 3\n"
+        );
+    }
+
+    #[test]
+    fn recipe_tree_snapshot_reports_changes_made_after_capture() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let repo = Repository::init(tmp.path()).expect("init repo");
+        commit_file(&repo, "meta.yaml", "version: 1\n", "add samtools");
+        commit_file(&repo, "build.sh", "make\n", "add build script");
+        fs::write(tmp.path().join("notes.txt"), "local edit\n").expect("local edit");
+
+        let snapshot = RecipeTreeSnapshot::capture(tmp.path()).expect("git checkout");
+        assert_eq!(snapshot.local_changes(), 1);
+        assert!(snapshot.changes_since().expect("status").is_empty());
+
+        fs::write(tmp.path().join("meta.yaml"), "version: 2\n").expect("mutate recipe");
+        fs::remove_file(tmp.path().join("build.sh")).expect("delete script");
+        fs::write(tmp.path().join("patch.diff"), "+x\n").expect("new file");
+        assert_eq!(
+            snapshot.changes_since().expect("status"),
+            vec![
+                "deleted build.sh",
+                "modified meta.yaml",
+                "untracked patch.diff"
+            ]
+        );
+        assert!(RecipeTreeSnapshot::capture(&tmp.path().join("missing")).is_none());
+    }
+
+    #[test]
+    fn prefer_policy_keeps_stale_checkout_when_fetch_fails() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let repo_root = tmp.path().join("bioconda-recipes");
+        let repo = Repository::init(&repo_root).expect("init repo");
+        fs::create_dir_all(repo_root.join("recipes")).expect("create recipes dir");
+        let head = commit_file(&repo, "README.md", "recipes\n", "initial");
+        repo.remote("origin", tmp.path().join("unreachable").to_str().unwrap())
+            .expect("add origin");
+        let request = |policy| RecipeRepoRequest {
+            recipe_root: repo_root.join("recipes"),
+            recipe_repo_root: repo_root.clone(),
+            recipe_ref: None,
+            sync: true,
+            policy,
+            pin: None,
+        };
+
+        assert!(ensure_recipe_repository(&request(RecipeSyncPolicy::Require)).is_err());
+
+        let outcome =
+            ensure_recipe_repository(&request(RecipeSyncPolicy::Prefer)).expect("prefer continues");
+        assert!(!outcome.fetched);
+        assert!(outcome.sync_error.is_some());
+        let record = outcome.sync_record();
+        assert_eq!(record.policy, "prefer");
+        assert_eq!(record.status, "stale");
+        assert!(record.head.unwrap().ends_with(&short_oid(head)));
+
+        let offline = ensure_recipe_repository(&request(RecipeSyncPolicy::Offline))
+            .expect("offline uses the checkout");
+        assert_eq!(offline.sync_record().status, "offline");
+        assert!(offline.sync_error.is_none());
+        let missing = RecipeRepoRequest {
+            recipe_repo_root: tmp.path().join("missing"),
+            ..request(RecipeSyncPolicy::Offline)
+        };
+        assert!(ensure_recipe_repository(&missing).is_err());
+    }
+
+    #[test]
+    fn pinned_checkout_must_match_expected_sha() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let repo_root = tmp.path().join("bioconda-recipes");
+        let repo = Repository::init(&repo_root).expect("init repo");
+        fs::create_dir_all(repo_root.join("recipes")).expect("create recipes dir");
+        let first = commit_file(&repo, "README.md", "v1\n", "first");
+        let second = commit_file(&repo, "README.md", "v2\n", "second");
+        let request = |expected_sha: &str, on_mismatch| {
+            RecipeRepoRequest {
+                recipe_root: repo_root.join("recipes"),
+                recipe_repo_root: repo_root.clone(),
+                recipe_ref: None,
+                sync: false,
+                policy: RecipeSyncPolicy::Offline,
+                pin: None,
+            }
+            .with_config(&RecipesConfig {
+                recipe_ref: None,
+                expected_sha: Some(expected_sha.to_ascii_uppercase()),
+                on_mismatch,
+            })
+        };
+
+        let verified =
+            ensure_recipe_repository(&request(&second.to_string()[..10], RecipePinMismatch::Fail))
+                .expect("pinned head matches");
+        assert_eq!(
+            verified.sync_record().pin_status.as_deref(),
+            Some("verified")
+        );
+
+        let err = ensure_recipe_repository(&request(&first.to_string(), RecipePinMismatch::Fail))
+            .expect_err("mismatch fails");
+        assert!(format!("{err:#}").contains("does not match [recipes] expected-sha"));
+
+        let warned =
+            ensure_recipe_repository(&request(&first.to_string(), RecipePinMismatch::Warn))
+                .expect("mismatch only warns");
+        let record = warned.sync_record();
+        assert_eq!(record.pin_status.as_deref(), Some("mismatch"));
+        assert_eq!(record.expected_sha, Some(first.to_string()));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::atomic_file::write_atomic;
+use crate::config_signature::ConfigProvenance;
+use crate::priority_specs::{ReportEntry, compute_arch_adjusted_kpi};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the run index in a reports dir.
+pub const REPORT_INDEX_FILE: &str = "index.json";
+
+/// Bumped when a field changes meaning; new optional fields keep the version.
+pub const REPORT_INDEX_SCHEMA_VERSION: u32 = 1;
+
+/// `<reports-dir>/index.json`: one record per run that wrote reports to the
+/// dir, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReportIndex {
+    pub schema_version: u32,
+    pub updated_at_utc: String,
+    pub runs: Vec<RunRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunRecord {
+    /// RFC 3339 time the run finished.
+    pub finished_at_utc: String,
+    /// `build`, `regression` or `generate-priority-specs`.
+    pub command: String,
+    pub target_id: String,
+    /// `completed`, `failed`, or `backfilled` for reports that predate the index.
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_secs: Option<f64>,
+    /// Command-specific counters (`requested`, `generated`, `failed`, ...).
+    #[serde(default)]
+    pub counts: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kpi: Option<RunKpi>,
+    /// Report paths by format (`json`, `csv`, `md`).
+    #[serde(default)]
+    pub reports: BTreeMap<String, String>,
+    /// State of the recipes checkout the run used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipes: Option<RecipeSyncRecord>,
+    /// Digest and signer of the project config the run used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ConfigProvenance>,
+}
+
+/// How the recipes repository was prepared for a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecipeSyncRecord {
+    /// `--recipe-sync-policy` of the run.
+    pub policy: String,
+    /// `synced`, `local` (no sync requested), `offline`, `stale` (sync failed
+    /// under `prefer`) or `unmanaged` (not a git checkout).
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// `[recipes] expected-sha` the HEAD was checked against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_sha: Option<String>,
+    /// `verified` or `mismatch` (only with `on-mismatch = "warn"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunKpi {
+    pub denominator: usize,
+    pub successes: usize,
+    pub success_rate: f64,
+}
+
+impl RunRecord {
+    pub fn new(command: &str, target_id: &str) -> Self {
+        Self {
+            finished_at_utc: Utc::now().to_rfc3339(),
+            command: command.to_string(),
+            target_id: target_id.to_string(),
+            status: "completed".to_string(),
+            error: None,
+            elapsed_secs: None,
+            counts: BTreeMap::new(),
+            kpi: None,
+            reports: BTreeMap::new(),
+            recipes: None,
+            config: None,
+        }
+    }
+
+    pub fn failed(command: &str, target_id: &str, error: &anyhow::Error) -> Self {
+        Self {
+            status: "failed".to_string(),
+            error: Some(format!("{error:#}")),
+            ..Self::new(command, target_id)
+        }
+    }
+
+    pub fn count(mut self, name: &str, value: usize) -> Self {
+        self.counts.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn kpi(mut self, denominator: usize, successes: usize, success_rate: f64) -> Self {
+        self.kpi = Some(RunKpi {
+            denominator,
+            successes,
+            success_rate,
+        });
+        self
+    }
+
+    pub fn recipes(mut self, recipes: Option<RecipeSyncRecord>) -> Self {
+        self.recipes = recipes;
+        self
+    }
+
+    pub fn config(mut self, config: Option<ConfigProvenance>) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn report_paths(mut self, json: &Path, csv: &Path, md: &Path) -> Self {
+        for (format, path) in [("json", json), ("csv", csv), ("md", md)] {
+            self.reports
+                .insert(format.to_string(), path.display().to_string());
+        }
+        self
+    }
+}
+
+/// Append `record` to the index of `reports_dir` and rewrite it atomically.
+/// A missing index is first seeded with the `build_*.json` reports already in
+/// the dir. Callers hold the workspace lock, so there is one writer per dir.
+pub fn record_run(reports_dir: &Path, record: RunRecord) -> Result<PathBuf> {
+    let path = reports_dir.join(REPORT_INDEX_FILE);
+    let mut index = if path.exists() {
+        let raw = fs::read_to_string(&path)
+            .with_context(|| format!("reading report index {}", path.display()))?;
+        serde_json::from_str::<ReportIndex>(&raw)
+            .with_context(|| format!("parsing report index {}", path.display()))?
+    } else {
+        ReportIndex {
+            schema_version: REPORT_INDEX_SCHEMA_VERSION,
+            updated_at_utc: String::new(),
+            runs: backfilled_build_runs(reports_dir, &record)?,
+        }
+    };
+    index.schema_version = REPORT_INDEX_SCHEMA_VERSION;
+    index.updated_at_utc = Utc::now().to_rfc3339();
+    index.runs.push(record);
+    let payload = serde_json::to_string_pretty(&index).context("serializing report index")?;
+    write_atomic(&path, payload)
+        .with_context(|| format!("writing report index {}", path.display()))?;
+    Ok(path)
+}
+
+/// Records of the build reports written before the index existed, ordered by
+/// modification time, leaving out the report of `current`. Sidecar reports
+/// (`_cycles.json`, ...) do not parse as entry lists and are passed over.
+fn backfilled_build_runs(reports_dir: &Path, current: &RunRecord) -> Result<Vec<RunRecord>> {
+    if !reports_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut runs = Vec::new();
+    for dir_entry in fs::read_dir(reports_dir)
+        .with_context(|| format!("reading reports dir {}", reports_dir.display()))?
+    {
+        let path = dir_entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !(name.starts_with("build_") && name.ends_with(".json"))
+            || current.reports.get("json") == Some(&path.display().to_string())
+        {
+            continue;
+        }
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(entries) = serde_json::from_str::<Vec<ReportEntry>>(&raw) else {
+            continue;
+        };
+        let modified: DateTime<Utc> = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .map(DateTime::from)
+            .unwrap_or_else(|_| Utc::now());
+        let mut record = RunRecord {
+            finished_at_utc: modified.to_rfc3339(),
+            status: "backfilled".to_string(),
+            ..build_run_record(&current.target_id, &entries)
+        };
+        record = record.report_paths(
+            &path,
+            &path.with_extension("csv"),
+            &path.with_extension("md"),
+        );
+        runs.push(record);
+    }
+    runs.sort_by(|a, b| a.finished_at_utc.cmp(&b.finished_at_utc));
+    Ok(runs)
+}
+
+/// Counts and KPI of a `build` run from its report entries.
+fn build_run_record(target_id: &str, entries: &[ReportEntry]) -> RunRecord {
+    let mut record = RunRecord::new("build", target_id).count("requested", entries.len());
+    for entry in entries {
+        let status = match entry.status.as_str() {
+            "srpm-generated" => "generated",
+            other => other,
+        };
+        *record.counts.entry(status.replace('-', "_")).or_insert(0) += 1;
+    }
+    let kpi = compute_arch_adjusted_kpi(entries);
+    record.kpi(kpi.denominator, kpi.successes, kpi.success_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_run_seeds_existing_build_reports_and_appends() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let reports = tmp.path();
+        fs::write(reports.join("build_old.json"), "[]").expect("write old report");
+        fs::write(reports.join("build_new.json"), "[]").expect("write new report");
+        fs::write(reports.join("build_old_cycles.json"), "{\"cycles\":[]}").expect("write sidecar");
+
+        let record = RunRecord::new("build", "rockylinux9-x86_64")
+            .count("requested", 2)
+            .count("generated", 1)
+            .report_paths(
+                &reports.join("build_new.json"),
+                &reports.join("build_new.csv"),
+                &reports.join("build_new.md"),
+            );
+        let path = record_run(reports, record).expect("record run");
+        let failed = RunRecord::failed(
+            "regression",
+            "rockylinux9-x86_64",
+            &anyhow::anyhow!("regression KPI gate failed"),
+        );
+        record_run(reports, failed).expect("record failure");
+
+        let index: ReportIndex =
+            serde_json::from_str(&fs::read_to_string(path).expect("read index"))
+                .expect("parse index");
+        assert_eq!(index.schema_version, REPORT_INDEX_SCHEMA_VERSION);
+        assert_eq!(index.runs.len(), 3);
+        assert_eq!(index.runs[0].status, "backfilled");
+        assert!(index.runs[0].reports["json"].ends_with("build_old.json"));
+        assert_eq!(
+            index.runs[0].kpi.as_ref().map(|kpi| kpi.denominator),
+            Some(0)
+        );
+        assert_eq!(index.runs[1].counts["generated"], 1);
+        assert!(index.runs[1].reports["md"].ends_with("build_new.md"));
+        assert_eq!(index.runs[2].status, "failed");
+        assert_eq!(
+            index.runs[2].error.as_deref(),
+            Some("regression KPI gate failed")
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use crate::atomic_file::write_atomic;
+use crate::priority_specs::{KpiSummary, ReportEntry, compute_arch_adjusted_kpi};
+use anyhow::{Context, Result, bail};
+use csv::Writer;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Cell of a package that a target's report does not list.
+const ABSENT_STATUS: &str = "-";
+
+#[derive(Debug, Clone)]
+pub struct ReportMergeRequest {
+    pub session_topdir: PathBuf,
+    /// Build report JSON files to merge. Empty means the newest `build_*.json`
+    /// of every `<topdir>/targets/<target-id>/reports` directory.
+    pub reports: Vec<PathBuf>,
+    /// Only merge these targets; empty keeps all.
+    pub target_ids: Vec<String>,
+    pub output_dir: PathBuf,
+}
+
+/// Arch-adjusted KPI of one target report or of all merged reports.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct MatrixKpi {
+    pub scope_entries: usize,
+    pub excluded_arch: usize,
+    pub denominator: usize,
+    pub successes: usize,
+    pub success_rate: f64,
+}
+
+impl From<KpiSummary> for MatrixKpi {
+    fn from(kpi: KpiSummary) -> Self {
+        Self {
+            scope_entries: kpi.scope_entries,
+            excluded_arch: kpi.excluded_arch,
+            denominator: kpi.denominator,
+            successes: kpi.successes,
+            success_rate: kpi.success_rate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixTarget {
+    pub target_id: String,
+    pub report: PathBuf,
+    pub kpi: MatrixKpi,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixRow {
+    pub package: String,
+    /// Report status per target id, `-` where the target did not list the package.
+    pub statuses: BTreeMap<String, String>,
+    /// Statuses differ between targets.
+    pub divergent: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportMatrix {
+    pub targets: Vec<MatrixTarget>,
+    pub combined: MatrixKpi,
+    pub packages: Vec<MatrixRow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportMergeSummary {
+    pub matrix: ReportMatrix,
+    pub json_path: PathBuf,
+    pub csv_path: PathBuf,
+    pub md_path: PathBuf,
+    pub html_path: PathBuf,
+}
+
+/// Join build reports of several target ids into a package × target status
+/// matrix with per-target and combined KPI, written as JSON, CSV, Markdown
+/// and HTML to `output_dir/matrix_by_package.*`.
+pub fn merge_reports_by_package(request: &ReportMergeRequest) -> Result<ReportMergeSummary> {
+    let mut inputs = if request.reports.is_empty() {
+        newest_target_reports(&request.session_topdir)?
+    } else {
+        request
+            .reports
+            .iter()
+            .map(|path| (report_target_id(path), path.clone()))
+            .collect()
+    };
+    if !request.target_ids.is_empty() {
+        inputs.retain(|(target_id, _)| request.target_ids.contains(target_id));
+    }
+    if inputs.is_empty() {
+        bail!(
+            "no build reports to merge under {}",
+            request.session_topdir.join("targets").display()
+        );
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut targets = Vec::new();
+    let mut all_entries = Vec::new();
+    let mut statuses: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for (target_id, path) in inputs {
+        if !seen.insert(target_id.clone()) {
+            bail!("more than one report for target {target_id}");
+        }
+        let entries = read_report(&path)?;
+        for entry in &entries {
+            statuses
+                .entry(entry.software.to_ascii_lowercase())
+                .or_default()
+                .insert(target_id.clone(), entry.status.clone());
+        }
+        targets.push(MatrixTarget {
+            target_id,
+            report: path,
+            kpi: compute_arch_adjusted_kpi(&entries).into(),
+        });
+        all_entries.extend(entries);
+    }
+
+    let packages = statuses
+        .into_iter()
+        .map(|(package, mut by_target)| {
+            for target in &targets {
+                by_target
+                    .entry(target.target_id.clone())
+                    .or_insert_with(|| ABSENT_STATUS.to_string());
+            }
+            let divergent = by_target.values().collect::<BTreeSet<_>>().len() > 1;
+            MatrixRow {
+                package,
+                statuses: by_target,
+                divergent,
+            }
+        })
+        .collect();
+    let matrix = ReportMatrix {
+        combined: compute_arch_adjusted_kpi(&all_entries).into(),
+        targets,
+        packages,
+    };
+
+    fs::create_dir_all(&request.output_dir)
+        .with_context(|| format!("creating {}", request.output_dir.display()))?;
+    let json_path = request.output_dir.join("matrix_by_package.json");
+    let csv_path = request.output_dir.join("matrix_by_package.csv");
+    let md_path = request.output_dir.join("matrix_by_package.md");
+    let html_path = request.output_dir.join("matrix_by_package.html");
+    let json = serde_json::to_string_pretty(&matrix).context("serializing report matrix")?;
+    write_atomic(&json_path, json).with_context(|| format!("writing {}", json_path.display()))?;
+    write_atomic(&csv_path, render_csv(&matrix)?)
+        .with_context(|| format!("writing {}", csv_path.display()))?;
+    write_atomic(&md_path, render_markdown(&matrix))
+        .with_context(|| format!("writing {}", md_path.display()))?;
+    write_atomic(&html_path, render_html(&matrix))
+        .with_context(|| format!("writing {}", html_path.display()))?;
+    Ok(ReportMergeSummary {
+        matrix,
+        json_path,
+        csv_path,
+        md_path,
+        html_path,
+    })
+}
+
+/// `<target-id>` of a `targets/<target-id>/reports/<report>.json` path, else
+/// the report file stem.
+fn report_target_id(path: &Path) -> String {
+    let parent = path.parent();
+    if parent.and_then(|dir| dir.file_name()) == Some("reports".as_ref())
+        && let Some(target) = parent
+            .and_then(|dir| dir.parent())
+            .and_then(|dir| dir.file_name())
+    {
+        return target.to_string_lossy().to_string();
+    }
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn read_report(path: &Path) -> Result<Vec<ReportEntry>> {
+    let raw = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("parsing build report {}", path.display()))
+}
+
+/// Newest `build_*.json` entry report of each target, by modification time.
+/// Sidecar reports (`_cycles.json`, ...) do not parse as entry lists and are
+/// passed over.
+fn newest_target_reports(topdir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let targets_dir = topdir.join("targets");
+    if !targets_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut target_dirs = fs::read_dir(&targets_dir)
+        .with_context(|| format!("reading {}", targets_dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    target_dirs.sort();
+
+    let mut reports = Vec::new();
+    for target_dir in target_dirs {
+        let Ok(entries) = fs::read_dir(target_dir.join("reports")) else {
+            continue;
+        };
+        let mut candidates = entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.starts_with("build_") && name.ends_with(".json")
+            })
+            .map(|entry| {
+                let modified = entry
+                    .metadata()
+                    .and_then(|meta| meta.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (modified, entry.path())
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.cmp(a));
+        if let Some((_, path)) = candidates
+            .into_iter()
+            .find(|(_, path)| read_report(path).is_ok())
+        {
+            reports.push((report_target_id(&path), path));
+        }
+    }
+    Ok(reports)
+}
+
+fn render_csv(matrix: &ReportMatrix) -> Result<Vec<u8>> {
+    let mut writer = Writer::from_writer(Vec::new());
+    let mut header = vec!["package".to_string()];
+    header.extend(matrix.targets.iter().map(|t| t.target_id.clone()));
+    header.push("divergent".to_string());
+    writer.write_record(&header).context("writing csv header")?;
+    for row in &matrix.packages {
+        let mut record = vec![row.package.clone()];
+        record.extend(row.statuses.values().cloned());
+        record.push(row.divergent.to_string());
+        writer.write_record(&record).context("writing csv row")?;
+    }
+    writer.into_inner().context("flushing csv writer")
+}
+
+fn render_markdown(matrix: &ReportMatrix) -> String {
+    let divergent = matrix.packages.iter().filter(|row| row.divergent).count();
+    let mut md = String::new();
+    md.push_str("# Build Report Matrix by Package\n\n");
+    md.push_str(&format!("- Targets: {}\n", matrix.targets.len()));
+    md.push_str(&format!("- Packages: {}\n", matrix.packages.len()));
+    md.push_str(&format!("- Divergent packages: {divergent}\n\n"));
+    md.push_str("## Reliability KPI (Arch-Adjusted)\n\n");
+    md.push_str(
+        "| Target | Report | Scope | Excluded (arch) | Denominator | Successes | Success rate |\n",
+    );
+    md.push_str("|---|---|---:|---:|---:|---:|---:|\n");
+    for target in &matrix.targets {
+        md.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {:.2}% |\n",
+            target.target_id,
+            target.report.display(),
+            target.kpi.scope_entries,
+            target.kpi.excluded_arch,
+            target.kpi.denominator,
+            target.kpi.successes,
+            target.kpi.success_rate
+        ));
+    }
+    let kpi = &matrix.combined;
+    md.push_str(&format!(
+        "| combined | | {} | {} | {} | {} | {:.2}% |\n\n",
+        kpi.scope_entries, kpi.excluded_arch, kpi.denominator, kpi.successes, kpi.success_rate
+    ));
+    md.push_str("## Status Matrix\n\n| Package |");
+    for target in &matrix.targets {
+        md.push_str(&format!(" {} |", target.target_id));
+    }
+    md.push_str("\n|---|");
+    md.push_str(&"---|".repeat(matrix.targets.len()));
+    md.push('\n');
+    for row in &matrix.packages {
+        let marker = if row.divergent { " (divergent)" } else { "" };
+        md.push_str(&format!("| {}{marker} |", row.package));
+        for status in row.statuses.values() {
+            md.push_str(&format!(" {status} |"));
+        }
+        md.push('\n');
+    }
+    md
+}
+
+fn render_html(matrix: &ReportMatrix) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Build Report Matrix by Package</title>\n<style>\n");
+    html.push_str("table { border-collapse: collapse; }\n");
+    html.push_str("th, td { border: 1px solid #ccc; padding: 2px 8px; }\n");
+    html.push_str("tr.divergent { background: #fff3cd; }\n");
+    html.push_str("td.generated, td.srpm-generated, td.up-to-date { color: #1a7f37; }\n");
+    html.push_str("td.quarantined { color: #cf222e; }\n</style>\n</head>\n<body>\n");
+    html.push_str("<h1>Build Report Matrix by Package</h1>\n");
+    html.push_str("<h2>Reliability KPI (Arch-Adjusted)</h2>\n<table>\n");
+    html.push_str("<tr><th>Target</th><th>Scope</th><th>Excluded (arch)</th><th>Denominator</th><th>Successes</th><th>Success rate</th></tr>\n");
+    let kpi_rows = matrix
+        .targets
+        .iter()
+        .map(|target| (target.target_id.as_str(), &target.kpi))
+        .chain(std::iter::once(("combined", &matrix.combined)));
+    for (label, kpi) in kpi_rows {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}%</td></tr>\n",
+            html_escape(label),
+            kpi.scope_entries,
+            kpi.excluded_arch,
+            kpi.denominator,
+            kpi.successes,
+            kpi.success_rate
+        ));
+    }
+    html.push_str("</table>\n<h2>Status Matrix</h2>\n<table>\n<tr><th>Package</th>");
+    for target in &matrix.targets {
+        html.push_str(&format!("<th>{}</th>", html_escape(&target.target_id)));
+    }
+    html.push_str("</tr>\n");
+    for row in &matrix.packages {
+        let class = if row.divergent {
+            " class=\"divergent\""
+        } else {
+            ""
+        };
+        html.push_str(&format!(
+            "<tr{class}><td>{}</td>",
+            html_escape(&row.package)
+        ));
+        for status in row.statuses.values() {
+            let status = html_escape(status);
+            html.push_str(&format!("<td class=\"{status}\">{status}</td>"));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Text safe in HTML element content and in `"`- or `'`-quoted attributes.
+pub fn html_escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_report(topdir: &Path, target_id: &str, name: &str, rows: &[(&str, &str)]) {
+        let reports = topdir.join("targets").join(target_id).join("reports");
+        fs::create_dir_all(&reports).expect("mkdir reports");
+        let entries = rows
+            .iter()
+            .map(|(software, status)| {
+                serde_json::json!({
+                    "software": software, "priority": 0, "status": status, "reason": "",
+                    "overlap_recipe": software, "overlap_reason": "", "variant_dir": "",
+                    "package_name": format!("phoreus-{software}"), "version": "1.0",
+                    "payload_spec_path": "", "meta_spec_path": "", "staged_build_sh": ""
+                })
+            })
+            .collect::<Vec<_>>();
+        fs::write(
+            reports.join(name),
+            serde_json::to_string(&entries).expect("json"),
+        )
+        .expect("write report");
+    }
+
+    #[test]
+    fn merge_by_package_joins_target_reports_into_matrix() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let topdir = tmp.path();
+        write_report(
+            topdir,
+            "rockylinux9-x86_64",
+            "build_batch_2_20260101000000.json",
+            &[("samtools", "generated"), ("bwa", "generated")],
+        );
+        write_report(
+            topdir,
+            "rockylinux9-aarch64",
+            "build_batch_2_20260101000100.json",
+            &[("samtools", "generated"), ("bwa", "quarantined")],
+        );
+        fs::write(
+            topdir.join(
+                "targets/rockylinux9-aarch64/reports/build_batch_2_20260101000100_cycles.json",
+            ),
+            "{\"cycles\": []}",
+        )
+        .expect("sidecar");
+        write_report(
+            topdir,
+            "almalinux9-x86_64",
+            "build_bwa.json",
+            &[("bwa", "up-to-date")],
+        );
+
+        let summary = merge_reports_by_package(&ReportMergeRequest {
+            session_topdir: topdir.to_path_buf(),
+            reports: Vec::new(),
+            target_ids: vec![
+                "rockylinux9-x86_64".to_string(),
+                "rockylinux9-aarch64".to_string(),
+            ],
+            output_dir: topdir.join("reports"),
+        })
+        .expect("merge");
+        let matrix = &summary.matrix;
+        assert_eq!(
+            matrix
+                .targets
+                .iter()
+                .map(|t| t.target_id.as_str())
+                .collect::<Vec<_>>(),
+            ["rockylinux9-aarch64", "rockylinux9-x86_64"]
+        );
+        assert_eq!(matrix.combined.denominator, 4);
+        assert_eq!(matrix.combined.successes, 3);
+        assert_eq!(matrix.targets[0].kpi.successes, 1);
+        let bwa = &matrix.packages[0];
+        assert_eq!(bwa.package, "bwa");
+        assert!(bwa.divergent);
+        assert_eq!(bwa.statuses["rockylinux9-aarch64"], "quarantined");
+        assert!(!matrix.packages[1].divergent);
+
+        let csv = fs::read_to_string(&summary.csv_path).expect("csv");
+        assert!(csv.starts_with("package,rockylinux9-aarch64,rockylinux9-x86_64,divergent\n"));
+        assert!(csv.contains("bwa,quarantined,generated,true\n"));
+        assert!(
+            fs::read_to_string(&summary.html_path)
+                .expect("html")
+                .contains("<td class=\"quarantined\">quarantined</td>")
+        );
+        assert!(summary.md_path.exists() && summary.json_path.exists());
+        assert_eq!(
+            html_escape("<a href='x'>\"&\"</a>"),
+            "&lt;a href=&#39;x&#39;&gt;&quot;&amp;&quot;&lt;/a&gt;"
+        );
+    }
+}
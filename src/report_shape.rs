@@ -0,0 +1,277 @@
+use crate::priority_specs::ReportEntry;
+use std::fmt;
+
+/// Derived column: container BuildRequires install plus compile seconds.
+const ELAPSED_COLUMN: &str = "elapsed";
+
+/// Report columns `--report-filter` and `--report-columns` may name: the CSV
+/// header fields plus [`ELAPSED_COLUMN`].
+const REPORT_COLUMNS: &[&str] = &[
+    "software",
+    "priority",
+    "status",
+    "reason",
+    "overlap_recipe",
+    "overlap_reason",
+    "variant_dir",
+    "package_name",
+    "version",
+    "payload_spec_path",
+    "meta_spec_path",
+    "staged_build_sh",
+    "maintainers",
+    "identifiers",
+    "dev_url",
+    "doc_url",
+    "deprecated",
+    "heuristics",
+    "warnings",
+    "module_test",
+    "dependency_install_secs",
+    "compile_secs",
+    "compiler_cache_hits",
+    "compiler_cache_misses",
+    "bioconductor_release",
+    "meta_nevra",
+    "meta_version",
+    "meta_build_secs",
+    "container_security",
+    ELAPSED_COLUMN,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FilterClause {
+    column: String,
+    op: FilterOp,
+    /// `|`-separated alternatives for `=` and `!=`; a single number otherwise.
+    values: Vec<String>,
+}
+
+/// One `--report-filter` value: comma-separated clauses that must all match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportFilter {
+    raw: String,
+    clauses: Vec<FilterClause>,
+}
+
+impl fmt::Display for ReportFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// `--report-filter` value such as `status=quarantined,priority>500`.
+pub fn parse_report_filter(raw: &str) -> Result<ReportFilter, String> {
+    let mut clauses = Vec::new();
+    for clause in raw.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let idx = clause
+            .find(['=', '!', '<', '>'])
+            .ok_or_else(|| format!("expected <column><op><value> in '{clause}'"))?;
+        let (column, rest) = clause.split_at(idx);
+        let (op, value) = [
+            ("!=", FilterOp::Ne),
+            (">=", FilterOp::Ge),
+            ("<=", FilterOp::Le),
+            ("=", FilterOp::Eq),
+            (">", FilterOp::Gt),
+            ("<", FilterOp::Lt),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| rest.strip_prefix(token).map(|value| (op, value)))
+        .ok_or_else(|| format!("unknown operator in '{clause}'; use =, !=, >, >=, < or <="))?;
+        let column = parse_report_column(column)?;
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(format!("'{clause}' needs a value"));
+        }
+        let values = match op {
+            FilterOp::Eq | FilterOp::Ne => value.split('|').map(|v| v.trim().to_string()).collect(),
+            _ => {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("'{clause}' compares against a non-numeric value"))?;
+                vec![value.to_string()]
+            }
+        };
+        clauses.push(FilterClause { column, op, values });
+    }
+    if clauses.is_empty() {
+        return Err("report filter needs at least one clause".to_string());
+    }
+    Ok(ReportFilter {
+        raw: raw.trim().to_string(),
+        clauses,
+    })
+}
+
+/// `--report-columns` entry; must name a report column.
+pub fn parse_report_column(raw: &str) -> Result<String, String> {
+    let column = raw.trim();
+    if REPORT_COLUMNS.contains(&column) {
+        Ok(column.to_string())
+    } else {
+        Err(format!(
+            "unknown report column '{column}'; expected one of {}",
+            REPORT_COLUMNS.join(", ")
+        ))
+    }
+}
+
+/// Row selection and column list applied to the CSV and Markdown reports.
+/// The JSON report and the summary counts always cover every entry.
+#[derive(Debug, Clone, Default)]
+pub struct ReportShape {
+    /// A row is kept when any filter matches; none keeps every row.
+    pub filters: Vec<ReportFilter>,
+    /// Output columns in order; empty keeps the default layout.
+    pub columns: Vec<String>,
+}
+
+impl ReportShape {
+    pub fn new(filters: &[ReportFilter], columns: &[String]) -> Self {
+        Self {
+            filters: filters.to_vec(),
+            columns: columns.to_vec(),
+        }
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        !self.filters.is_empty()
+    }
+
+    pub fn describe_filters(&self) -> String {
+        self.filters
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    }
+
+    pub fn rows<'a>(&self, entries: &'a [ReportEntry]) -> Vec<&'a ReportEntry> {
+        entries.iter().filter(|entry| self.matches(entry)).collect()
+    }
+
+    fn matches(&self, entry: &ReportEntry) -> bool {
+        self.filters.is_empty()
+            || self.filters.iter().any(|filter| {
+                filter
+                    .clauses
+                    .iter()
+                    .all(|clause| clause_matches(clause, &column_value(entry, &clause.column)))
+            })
+    }
+}
+
+fn clause_matches(clause: &FilterClause, actual: &str) -> bool {
+    let equals = |expected: &String| match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => actual == expected,
+    };
+    match clause.op {
+        FilterOp::Eq => clause.values.iter().any(equals),
+        FilterOp::Ne => !clause.values.iter().any(equals),
+        op => {
+            let (Ok(actual), Some(Ok(threshold))) = (
+                actual.parse::<f64>(),
+                clause.values.first().map(|v| v.parse::<f64>()),
+            ) else {
+                return false;
+            };
+            match op {
+                FilterOp::Gt => actual > threshold,
+                FilterOp::Ge => actual >= threshold,
+                FilterOp::Lt => actual < threshold,
+                _ => actual <= threshold,
+            }
+        }
+    }
+}
+
+/// Cell text of `column` for `entry`, as the CSV report writes it.
+pub fn column_value(entry: &ReportEntry, column: &str) -> String {
+    if column == ELAPSED_COLUMN {
+        return format!("{:.1}", entry.dependency_install_secs + entry.compile_secs);
+    }
+    match serde_json::to_value(entry)
+        .ok()
+        .as_ref()
+        .and_then(|value| value.get(column))
+    {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(software: &str, priority: i64, status: &str) -> ReportEntry {
+        serde_json::from_value(serde_json::json!({
+            "software": software, "priority": priority, "status": status, "reason": "",
+            "overlap_recipe": software, "overlap_reason": "", "variant_dir": "",
+            "package_name": format!("phoreus-{software}"), "version": "1.0",
+            "payload_spec_path": "", "meta_spec_path": "", "staged_build_sh": "",
+            "dependency_install_secs": 2.0, "compile_secs": 3.5
+        }))
+        .expect("report entry")
+    }
+
+    #[test]
+    fn report_filters_select_rows_and_columns_validate() {
+        let entries = vec![
+            entry("samtools", 900, "quarantined"),
+            entry("bwa", 200, "quarantined"),
+            entry("fastp", 800, "generated"),
+            entry("blast", 700, "skipped"),
+        ];
+        let names = |shape: &ReportShape| {
+            shape
+                .rows(&entries)
+                .iter()
+                .map(|e| e.software.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        let failures = parse_report_filter("status=quarantined,priority>500").expect("filter");
+        let shape = ReportShape::new(std::slice::from_ref(&failures), &[]);
+        assert_eq!(names(&shape), vec!["samtools"]);
+
+        let skipped =
+            parse_report_filter("status=skipped|generated, priority<=700").expect("filter");
+        let shape = ReportShape::new(&[failures, skipped], &[]);
+        assert_eq!(names(&shape), vec!["samtools", "blast"]);
+        assert_eq!(
+            shape.describe_filters(),
+            "status=quarantined,priority>500 OR status=skipped|generated, priority<=700"
+        );
+
+        let not_generated = parse_report_filter("status!=generated").expect("filter");
+        assert_eq!(
+            names(&ReportShape::new(&[not_generated], &[])),
+            vec!["samtools", "bwa", "blast"]
+        );
+        assert_eq!(names(&ReportShape::default()).len(), 4);
+
+        assert_eq!(column_value(&entries[0], "elapsed"), "5.5");
+        assert_eq!(column_value(&entries[0], "priority"), "900");
+        assert_eq!(column_value(&entries[0], "version"), "1.0");
+
+        assert!(parse_report_filter("state=quarantined").is_err());
+        assert!(parse_report_filter("priority>high").is_err());
+        assert!(parse_report_filter("status~quarantined").is_err());
+        assert!(parse_report_filter(" , ").is_err());
+        assert!(parse_report_column("elapsed_secs").is_err());
+    }
+}
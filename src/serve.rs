@@ -0,0 +1,545 @@
+use crate::build_lock::{self, BuildLookupSnapshot};
+use crate::http_server::{Handler, HttpRequest, HttpResponse, serve_connections};
+use crate::priority_specs::{PROGRESS_EVENTS_FILE, log_external_progress};
+use crate::promote::repository_rpms;
+use crate::report_index::{REPORT_INDEX_FILE, ReportIndex, RunRecord};
+use crate::report_merge::html_escape;
+use crate::ui::{self, QueueSnapshot};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Results returned by one artifact search.
+const SEARCH_LIMIT: usize = 500;
+
+/// What `serve` publishes: the reports dir, the live state of the session
+/// topdir and the artifacts of the target tree.
+#[derive(Debug, Clone)]
+pub struct ServeRequest {
+    pub listen: SocketAddr,
+    pub session_topdir: PathBuf,
+    pub reports_dir: PathBuf,
+    pub target_root: PathBuf,
+}
+
+/// A file under the reports dir or the target tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServedFile {
+    /// Path relative to its root, `/`-separated.
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_utc: String,
+}
+
+/// `GET /api/index`.
+#[derive(Debug, Serialize)]
+struct ReportsIndexResponse {
+    reports_dir: String,
+    runs: Vec<RunRecord>,
+    files: Vec<ServedFile>,
+}
+
+/// `GET /api/queue`.
+#[derive(Debug, Serialize)]
+struct QueueResponse {
+    session: BuildLookupSnapshot,
+    progress: QueueSnapshot,
+}
+
+/// Serve until the process is stopped, one thread per connection.
+pub fn serve(request: ServeRequest) -> Result<()> {
+    let listener =
+        TcpListener::bind(request.listen).with_context(|| format!("binding {}", request.listen))?;
+    log_external_progress(format!(
+        "phase=serve status=listening addr=http://{} reports_dir={} topdir={}",
+        listener.local_addr().unwrap_or(request.listen),
+        request.reports_dir.display(),
+        request.session_topdir.display()
+    ));
+    serve_connections(listener, "serve", handler(request));
+    Ok(())
+}
+
+fn handler(request: ServeRequest) -> Handler {
+    Arc::new(move |http: &HttpRequest| match http.method.as_str() {
+        "GET" | "HEAD" => route(&request, http),
+        _ => HttpResponse::error("405 Method Not Allowed", "only GET and HEAD are served"),
+    })
+}
+
+fn route(request: &ServeRequest, http: &HttpRequest) -> HttpResponse {
+    let path = http.path.as_str();
+    let search = http.query_param("q").unwrap_or_default();
+    let result = match path {
+        "/" => {
+            dashboard_html(request).map(|html| HttpResponse::ok("text/html; charset=utf-8", html))
+        }
+        "/search" => search_html(request, &search)
+            .map(|html| HttpResponse::ok("text/html; charset=utf-8", html)),
+        "/api/index" => reports_index(request).map(|index| HttpResponse::json("200 OK", &index)),
+        "/api/queue" => queue_state(request).map(|queue| HttpResponse::json("200 OK", &queue)),
+        "/api/search" => search_artifacts(&request.target_root, &search)
+            .map(|artifacts| HttpResponse::json("200 OK", &artifacts)),
+        _ => {
+            if let Some(relative) = path.strip_prefix("/reports/") {
+                return serve_file(&request.reports_dir, relative);
+            }
+            if let Some(relative) = path.strip_prefix("/artifacts/")
+                && (relative.starts_with("RPMS/") || relative.starts_with("SRPMS/"))
+            {
+                return serve_file(&request.target_root, relative);
+            }
+            return HttpResponse::error("404 Not Found", path);
+        }
+    };
+    result
+        .unwrap_or_else(|err| HttpResponse::error("500 Internal Server Error", &format!("{err:#}")))
+}
+
+/// The file at `relative` under `root`. Paths leaving `root`, through `..` or
+/// a symlink, are not found.
+fn serve_file(root: &Path, relative: &str) -> HttpResponse {
+    let relative = Path::new(relative);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return HttpResponse::error("404 Not Found", "path outside the served tree");
+    }
+    let path = root.join(relative);
+    let inside = match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path.starts_with(root) && path.is_file(),
+        _ => false,
+    };
+    if !inside {
+        return HttpResponse::error("404 Not Found", &relative.display().to_string());
+    }
+    HttpResponse::file(content_type(&path), &path)
+        .unwrap_or_else(|err| HttpResponse::error("500 Internal Server Error", &err.to_string()))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()).unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "csv" => "text/csv; charset=utf-8",
+        "rpm" => "application/x-rpm",
+        "svg" => "image/svg+xml",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+fn reports_index(request: &ServeRequest) -> Result<ReportsIndexResponse> {
+    let index_path = request.reports_dir.join(REPORT_INDEX_FILE);
+    let runs = if index_path.is_file() {
+        let raw = fs::read_to_string(&index_path)
+            .with_context(|| format!("reading {}", index_path.display()))?;
+        serde_json::from_str::<ReportIndex>(&raw)
+            .with_context(|| format!("parsing {}", index_path.display()))?
+            .runs
+    } else {
+        Vec::new()
+    };
+    Ok(ReportsIndexResponse {
+        reports_dir: request.reports_dir.display().to_string(),
+        runs,
+        files: report_files(&request.reports_dir)?,
+    })
+}
+
+/// Files under the reports dir, newest first.
+fn report_files(reports_dir: &Path) -> Result<Vec<ServedFile>> {
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<(SystemTime, ServedFile)>) -> Result<()> {
+        for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+            let path = entry
+                .with_context(|| format!("reading entry in {}", dir.display()))?
+                .path();
+            if path.is_dir() {
+                walk(root, &path, out)?;
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                let meta = fs::metadata(&path)?;
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                out.push((modified, served_file(relative, meta.len(), modified)));
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    if reports_dir.is_dir() {
+        walk(reports_dir, reports_dir, &mut files)?;
+    }
+    files.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+    Ok(files.into_iter().map(|(_, file)| file).collect())
+}
+
+fn served_file(relative: &Path, size_bytes: u64, modified: SystemTime) -> ServedFile {
+    ServedFile {
+        path: relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        size_bytes,
+        modified_utc: DateTime::<Utc>::from(modified).to_rfc3339(),
+    }
+}
+
+/// Lock owners and queued requests of the session topdir, plus package
+/// states from the progress journal of the latest build.
+fn queue_state(request: &ServeRequest) -> Result<QueueResponse> {
+    let session = build_lock::lookup_build_runtime(&request.session_topdir)?;
+    let journal = request.reports_dir.join(PROGRESS_EVENTS_FILE);
+    let lines = match fs::read_to_string(&journal) {
+        Ok(raw) => raw.lines().map(str::to_string).collect(),
+        Err(_) => Vec::new(),
+    };
+    Ok(QueueResponse {
+        session,
+        progress: ui::queue_snapshot(lines),
+    })
+}
+
+/// RPMs and SRPMs of the target tree whose file name contains `query`,
+/// case-insensitively; an empty query lists them all. At most
+/// [`SEARCH_LIMIT`] results, relative to the target root.
+pub fn search_artifacts(target_root: &Path, query: &str) -> Result<Vec<ServedFile>> {
+    let needle = query.trim().to_ascii_lowercase();
+    let mut found = Vec::new();
+    for repo in ["RPMS", "SRPMS"] {
+        let root = target_root.join(repo);
+        if !root.is_dir() {
+            continue;
+        }
+        for relative in repository_rpms(&root)? {
+            let name = relative
+                .file_name()
+                .map(|name| name.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            if !name.contains(&needle) {
+                continue;
+            }
+            let meta = fs::metadata(root.join(&relative))?;
+            found.push(served_file(
+                &Path::new(repo).join(&relative),
+                meta.len(),
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            ));
+            if found.len() == SEARCH_LIMIT {
+                return Ok(found);
+            }
+        }
+    }
+    Ok(found)
+}
+
+const PAGE_STYLE: &str = "body{font-family:sans-serif;margin:2em;max-width:70em}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:.2em .5em;text-align:left}code{background:#f4f4f4}";
+
+fn page(title: &str, refresh: bool, body: &str) -> String {
+    let refresh = if refresh {
+        "<meta http-equiv=\"refresh\" content=\"10\">"
+    } else {
+        ""
+    };
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">{refresh}<title>{}</title><style>{PAGE_STYLE}</style></head><body>\n{body}</body></html>\n",
+        html_escape(title)
+    )
+}
+
+fn search_form(query: &str) -> String {
+    format!(
+        "<form action=\"/search\"><input name=\"q\" value=\"{}\" placeholder=\"package\"> <button>Search artifacts</button></form>\n",
+        html_escape(query)
+    )
+}
+
+fn dashboard_html(request: &ServeRequest) -> Result<String> {
+    let queue = queue_state(request)?;
+    let index = reports_index(request)?;
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "<h1>bioconda2rpm</h1>\n<p><code>{}</code> &middot; <a href=\"/api/index\">index.json</a> &middot; <a href=\"/api/queue\">queue.json</a></p>",
+        html_escape(&request.target_root.display().to_string())
+    );
+    body.push_str(&search_form(""));
+
+    body.push_str("<h2>Session</h2>\n");
+    if queue.session.active_entries.is_empty() {
+        body.push_str("<p>No active session.</p>\n");
+    } else {
+        body.push_str("<table><tr><th>PID</th><th>Kind</th><th>Target</th><th>Host</th><th>Started</th><th>Heartbeat</th><th>Packages</th></tr>\n");
+        for entry in &queue.session.active_entries {
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                entry.pid,
+                html_escape(&entry.session_kind),
+                html_escape(&entry.target_id),
+                html_escape(&entry.host),
+                html_escape(&entry.started_at_utc),
+                html_escape(entry.heartbeat_at_utc.as_deref().unwrap_or("-")),
+                html_escape(&entry.packages.join(", "))
+            );
+        }
+        body.push_str("</table>\n");
+    }
+    if !queue.session.queued_requests.is_empty() {
+        let _ = writeln!(
+            body,
+            "<p>Forwarded requests waiting: {}</p>",
+            queue.session.queued_requests.len()
+        );
+    }
+
+    let progress = &queue.progress;
+    let _ = writeln!(
+        body,
+        "<h2>Queue</h2>\n<p>ready={} running={} completed={} blocked={} &middot; last phase <code>{}</code></p>",
+        progress.ready,
+        progress.running,
+        progress.completed,
+        progress.blocked,
+        html_escape(&progress.last_phase)
+    );
+    if !progress.queue_line.is_empty() {
+        let _ = writeln!(
+            body,
+            "<p><code>{}</code></p>",
+            html_escape(&progress.queue_line)
+        );
+    }
+    if !progress.packages.is_empty() {
+        body.push_str("<table><tr><th>Package</th><th>Status</th><th>Detail</th></tr>\n");
+        for package in progress.packages.iter().take(200) {
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                html_escape(&package.package),
+                html_escape(&package.status),
+                html_escape(&package.detail)
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    body.push_str("<h2>Runs</h2>\n");
+    if index.runs.is_empty() {
+        body.push_str("<p>No runs recorded.</p>\n");
+    } else {
+        body.push_str("<table><tr><th>Finished</th><th>Command</th><th>Status</th><th>Counts</th><th>Reports</th></tr>\n");
+        for run in index.runs.iter().rev().take(50) {
+            let counts = run
+                .counts
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let links = run
+                .reports
+                .iter()
+                .map(|(format, path)| report_link(&request.reports_dir, format, path))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{links}</td></tr>",
+                html_escape(&run.finished_at_utc),
+                html_escape(&run.command),
+                html_escape(&run.status),
+                html_escape(&counts)
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    body.push_str("<h2>Files</h2>\n<ul>\n");
+    for file in index.files.iter().take(200) {
+        let _ = writeln!(
+            body,
+            "<li><a href=\"/reports/{}\">{}</a> ({} bytes, {})</li>",
+            html_escape(&file.path),
+            html_escape(&file.path),
+            file.size_bytes,
+            html_escape(&file.modified_utc)
+        );
+    }
+    body.push_str("</ul>\n");
+    Ok(page("bioconda2rpm reports", true, &body))
+}
+
+/// Link to a report named in the run index; paths outside the reports dir
+/// are shown unlinked.
+fn report_link(reports_dir: &Path, format: &str, path: &str) -> String {
+    match Path::new(path).strip_prefix(reports_dir) {
+        Ok(relative) => format!(
+            "<a href=\"/reports/{}\">{}</a>",
+            html_escape(&relative.to_string_lossy()),
+            html_escape(format)
+        ),
+        Err(_) => html_escape(format),
+    }
+}
+
+fn search_html(request: &ServeRequest, query: &str) -> Result<String> {
+    let artifacts = search_artifacts(&request.target_root, query)?;
+    let mut body = String::new();
+    body.push_str("<h1>Artifact search</h1>\n<p><a href=\"/\">Dashboard</a></p>\n");
+    body.push_str(&search_form(query));
+    let _ = writeln!(
+        body,
+        "<p>{} artifact(s){}</p>",
+        artifacts.len(),
+        if artifacts.len() == SEARCH_LIMIT {
+            " (limit reached)"
+        } else {
+            ""
+        }
+    );
+    body.push_str("<table><tr><th>Artifact</th><th>Size</th><th>Modified</th></tr>\n");
+    for artifact in &artifacts {
+        let _ = writeln!(
+            body,
+            "<tr><td><a href=\"/artifacts/{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+            html_escape(&artifact.path),
+            html_escape(&artifact.path),
+            artifact.size_bytes,
+            html_escape(&artifact.modified_utc)
+        );
+    }
+    body.push_str("</table>\n");
+    Ok(page("bioconda2rpm artifact search", false, &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn get(addr: SocketAddr, target: &str) -> (String, String) {
+        let (head, body) = send(
+            addr,
+            &format!("GET {target} HTTP/1.1\r\nHost: test\r\n\r\n"),
+        );
+        (head.lines().next().unwrap_or_default().to_string(), body)
+    }
+
+    /// Response head and body of the raw `request`.
+    fn send(addr: SocketAddr, request: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).expect("connect");
+        stream.write_all(request.as_bytes()).expect("send request");
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).expect("read response");
+        let (head, body) = raw.split_once("\r\n\r\n").expect("response head");
+        (head.to_string(), body.to_string())
+    }
+
+    #[test]
+    fn serves_reports_queue_state_and_artifact_search() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let target_root = tmp.path().join("targets/almalinux-9.7-x86_64");
+        let reports_dir = target_root.join("reports");
+        fs::create_dir_all(reports_dir.join("build_logs")).expect("reports dir");
+        fs::create_dir_all(target_root.join("RPMS/x86_64")).expect("rpms dir");
+        fs::write(reports_dir.join("build_20260101.md"), "# Build <report>\n").expect("md");
+        fs::write(reports_dir.join("build_logs/samtools.log"), "log").expect("log");
+        fs::write(
+            reports_dir.join(PROGRESS_EVENTS_FILE),
+            "progress phase=batch-queue status=dispatch package=samtools running=1 queued=0\nprogress phase=batch-queue status=completed package=bwa result=generated elapsed=12s\n",
+        )
+        .expect("journal");
+        fs::write(
+            target_root.join("RPMS/x86_64/phoreus-samtools-1.21-1.21-1.el9.x86_64.rpm"),
+            "rpm",
+        )
+        .expect("rpm");
+        fs::write(tmp.path().join("secret.txt"), "secret").expect("secret");
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let request = ServeRequest {
+            listen: addr,
+            session_topdir: tmp.path().to_path_buf(),
+            reports_dir: reports_dir.clone(),
+            target_root: target_root.clone(),
+        };
+        thread::spawn(move || serve_connections(listener, "serve", handler(request)));
+
+        let (status, body) = get(addr, "/reports/build_20260101.md");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, "# Build <report>\n");
+        let (status, _) = get(addr, "/reports/../../../secret.txt");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        let (status, _) = get(addr, "/reports/%2e%2e/%2e%2e/%2e%2e/secret.txt");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+
+        let (_, body) = get(addr, "/api/index");
+        let index: serde_json::Value = serde_json::from_str(&body).expect("index json");
+        let files = index["files"].as_array().expect("files");
+        assert!(
+            files
+                .iter()
+                .any(|file| file["path"] == "build_logs/samtools.log")
+        );
+
+        let (_, body) = get(addr, "/api/queue");
+        let queue: serde_json::Value = serde_json::from_str(&body).expect("queue json");
+        assert_eq!(queue["progress"]["running"], 1);
+        assert_eq!(queue["progress"]["completed"], 1);
+        assert_eq!(queue["session"]["lock_held"], false);
+
+        let (_, body) = get(addr, "/api/search?q=SAMTOOLS");
+        let found: serde_json::Value = serde_json::from_str(&body).expect("search json");
+        assert_eq!(
+            found[0]["path"],
+            "RPMS/x86_64/phoreus-samtools-1.21-1.21-1.el9.x86_64.rpm"
+        );
+        let (_, body) = get(addr, "/search?q=bwa+mem");
+        assert!(body.contains("0 artifact(s)"));
+        assert!(body.contains("value=\"bwa mem\""));
+
+        let (status, body) = get(addr, "/");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.contains("<td>samtools</td><td>running</td>"));
+        assert!(body.contains("href=\"/reports/build_20260101.md\""));
+
+        // Artifacts are streamed with their size from metadata; HEAD sends
+        // only the head.
+        let rpm = "phoreus-bwa-0.7.18-0.7.18-1.el9.x86_64.rpm";
+        let payload = "0123456789abcdef".repeat(256 * 1024);
+        fs::write(target_root.join("RPMS/x86_64").join(rpm), &payload).expect("large rpm");
+        let (status, body) = get(addr, &format!("/artifacts/RPMS/x86_64/{rpm}"));
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body == payload);
+        let (head, body) = send(
+            addr,
+            &format!("HEAD /artifacts/RPMS/x86_64/{rpm} HTTP/1.1\r\nHost: test\r\n\r\n"),
+        );
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains(&format!("Content-Length: {}", payload.len())));
+        assert!(body.is_empty());
+
+        // Oversized header lines and header floods are refused.
+        let (head, _) = send(
+            addr,
+            &format!(
+                "GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+                "a".repeat(16 * 1024)
+            ),
+        );
+        assert!(head.starts_with("HTTP/1.1 400 Bad Request"));
+        let (head, _) = send(
+            addr,
+            &format!("GET / HTTP/1.1\r\n{}\r\n", "X-Flood: 1\r\n".repeat(200)),
+        );
+        assert!(head.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+}
@@ -0,0 +1,597 @@
+use crate::atomic_file::write_atomic;
+use crate::audit::AuditedCommand;
+use crate::priority_specs::PROGRESS_EVENTS_FILE;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Directory below the reports dir holding session archives and their indexes.
+pub const SESSIONS_DIR: &str = "sessions";
+
+const SESSION_INDEX_SCHEMA_VERSION: u32 = 1;
+
+/// One finished build session to bundle.
+#[derive(Debug, Clone)]
+pub struct SessionArchiveRequest {
+    pub reports_dir: PathBuf,
+    pub target_id: String,
+    pub command: String,
+    /// `completed` or `failed`.
+    pub status: String,
+    pub started: DateTime<Utc>,
+    /// This run's audit log, already detached with [`crate::audit::detach_log`];
+    /// moved into the archive.
+    pub audit_log: Option<PathBuf>,
+}
+
+/// `<reports>/sessions/<id>.json`, also stored in the archive.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionIndex {
+    pub schema_version: u32,
+    pub id: String,
+    pub command: String,
+    pub target_id: String,
+    pub status: String,
+    pub started_at_utc: String,
+    pub finished_at_utc: String,
+    /// Archive file name next to the index.
+    pub archive: String,
+    pub files: Vec<SessionFile>,
+    /// Members whose loose copy is gone: the audit log once the archive is
+    /// written, package logs and reports once a newer session supersedes them.
+    pub moved: Vec<String>,
+}
+
+/// A written session archive.
+#[derive(Debug, Clone)]
+pub struct ArchivedSession {
+    pub index: SessionIndex,
+    pub archive: PathBuf,
+    /// Loose package logs and reports of earlier sessions removed because
+    /// this session superseded them, as `<id>:<path>`.
+    pub pruned: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionFile {
+    /// Path inside the archive.
+    pub path: String,
+    /// `progress-events`, `audit`, `package-log` or `report`.
+    pub kind: String,
+    pub bytes: u64,
+}
+
+impl SessionIndex {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.bytes).sum()
+    }
+}
+
+/// Bundle the progress events, audit log, package logs and reports written
+/// since `request.started` into `<reports>/sessions/<id>.tar.zst` with an
+/// index, moving the audit log into it. This session's package logs and
+/// reports stay in place because reports link to the logs; the loose copies
+/// earlier sessions archived are removed, see [`prune_superseded`].
+pub fn archive_session(request: &SessionArchiveRequest) -> Result<ArchivedSession> {
+    let sessions_dir = request.reports_dir.join(SESSIONS_DIR);
+    fs::create_dir_all(&sessions_dir)
+        .with_context(|| format!("creating sessions dir {}", sessions_dir.display()))?;
+    let mut files = Vec::new();
+    collect_session_files(
+        &request.reports_dir,
+        &request.reports_dir,
+        request.started.into(),
+        &mut files,
+    )?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let audit = request
+        .audit_log
+        .as_ref()
+        .filter(|path| path.is_file())
+        .and_then(|path| Some((path, path.file_name()?.to_string_lossy().into_owned())));
+    let mut moved = Vec::new();
+    if let Some((path, name)) = &audit {
+        let member = format!("audit/{name}");
+        files.push(SessionFile {
+            path: member.clone(),
+            kind: "audit".to_string(),
+            bytes: fs::metadata(path).map(|meta| meta.len()).unwrap_or(0),
+        });
+        moved.push(member);
+    }
+    if files.is_empty() {
+        bail!(
+            "no session files under {} since {}",
+            request.reports_dir.display(),
+            request.started.to_rfc3339()
+        );
+    }
+    let (id, staging) = reserve_session_id(&sessions_dir, request.started)?;
+    let archive_name = format!("{id}.tar.zst");
+    let index = SessionIndex {
+        schema_version: SESSION_INDEX_SCHEMA_VERSION,
+        id: id.clone(),
+        command: request.command.clone(),
+        target_id: request.target_id.clone(),
+        status: request.status.clone(),
+        started_at_utc: request.started.to_rfc3339(),
+        finished_at_utc: Utc::now().to_rfc3339(),
+        archive: archive_name.clone(),
+        files,
+        moved,
+    };
+    let index_path = sessions_dir.join(format!("{id}.json"));
+    if let Err(err) = write_atomic(&index_path, serde_json::to_string_pretty(&index)?) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(err).with_context(|| format!("writing session index {}", index_path.display()));
+    }
+
+    // Stage the audit log before running tar: tar's own audit record starts a
+    // fresh log at the original path instead of landing in the archived one.
+    let staged_audit = match &audit {
+        Some((path, name)) => {
+            let staged = staging.join("audit").join(name);
+            fs::create_dir_all(staging.join("audit"))
+                .with_context(|| format!("creating {}", staging.display()))?;
+            fs::rename(path, &staged)
+                .with_context(|| format!("staging audit log {}", path.display()))?;
+            Some((path.to_path_buf(), staged, name.clone()))
+        }
+        None => None,
+    };
+    let result = write_archive(
+        &sessions_dir,
+        &index,
+        &request.reports_dir,
+        &staging,
+        &staged_audit,
+    );
+    match &staged_audit {
+        Some((original, staged, _)) if result.is_err() => {
+            restore_audit_log(staged, original)
+                .with_context(|| format!("restoring audit log {}", original.display()))?;
+        }
+        _ => {}
+    }
+    let _ = fs::remove_dir_all(&staging);
+    let archive = result?;
+    let pruned = prune_superseded(&request.reports_dir, &index)
+        .context("pruning superseded session files")?;
+    Ok(ArchivedSession {
+        index,
+        archive,
+        pruned,
+    })
+}
+
+/// Session id for a run started at `started`, with its staging dir created.
+/// The id is the UTC start time to the millisecond; a `-<n>` suffix keeps two
+/// sessions started in the same millisecond apart.
+fn reserve_session_id(sessions_dir: &Path, started: DateTime<Utc>) -> Result<(String, PathBuf)> {
+    let base = started.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+    for attempt in 1..1000 {
+        let id = if attempt == 1 {
+            base.clone()
+        } else {
+            format!("{base}-{attempt}")
+        };
+        if sessions_dir.join(format!("{id}.json")).exists()
+            || sessions_dir.join(format!("{id}.tar.zst")).exists()
+        {
+            continue;
+        }
+        let staging = sessions_dir.join(format!("{id}.staging"));
+        match fs::create_dir(&staging) {
+            Ok(()) => return Ok((id, staging)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("creating {}", staging.display()));
+            }
+        }
+    }
+    bail!(
+        "no free session id for {base} under {}",
+        sessions_dir.display()
+    )
+}
+
+/// Remove the loose package logs and reports that earlier sessions archived
+/// and `current` did not rewrite, so only the newest session keeps loose
+/// copies. A file changed since its session finished, or whose archive is
+/// missing, is kept. Pruned members are added to the earlier index's `moved`.
+fn prune_superseded(reports_dir: &Path, current: &SessionIndex) -> Result<Vec<String>> {
+    let sessions_dir = reports_dir.join(SESSIONS_DIR);
+    let mut pruned = Vec::new();
+    for mut earlier in list_sessions(reports_dir)? {
+        if earlier.id == current.id || !sessions_dir.join(&earlier.archive).is_file() {
+            continue;
+        }
+        let Ok(finished) = DateTime::parse_from_rfc3339(&earlier.finished_at_utc) else {
+            continue;
+        };
+        let finished = SystemTime::from(finished);
+        let mut removed = Vec::new();
+        for file in &earlier.files {
+            if !matches!(file.kind.as_str(), "package-log" | "report")
+                || earlier.moved.contains(&file.path)
+                || current.files.iter().any(|newer| newer.path == file.path)
+            {
+                continue;
+            }
+            let path = reports_dir.join(&file.path);
+            let unchanged = fs::metadata(&path).is_ok_and(|meta| {
+                meta.is_file()
+                    && meta.len() == file.bytes
+                    && meta.modified().is_ok_and(|modified| modified <= finished)
+            });
+            if unchanged && fs::remove_file(&path).is_ok() {
+                removed.push(file.path.clone());
+            }
+        }
+        if removed.is_empty() {
+            continue;
+        }
+        pruned.extend(removed.iter().map(|path| format!("{}:{path}", earlier.id)));
+        earlier.moved.extend(removed);
+        let index_path = sessions_dir.join(format!("{}.json", earlier.id));
+        write_atomic(&index_path, serde_json::to_string_pretty(&earlier)?)
+            .with_context(|| format!("writing session index {}", index_path.display()))?;
+    }
+    Ok(pruned)
+}
+
+fn write_archive(
+    sessions_dir: &Path,
+    index: &SessionIndex,
+    reports_dir: &Path,
+    staging: &Path,
+    staged_audit: &Option<(PathBuf, PathBuf, String)>,
+) -> Result<PathBuf> {
+    let index_name = format!("{}.json", index.id);
+    let archive = sessions_dir.join(&index.archive);
+    let partial = sessions_dir.join(format!("{}.partial", index.archive));
+    let mut tar = Command::new("tar");
+    tar.arg("--zstd")
+        .arg("-cf")
+        .arg(&partial)
+        .arg("-C")
+        .arg(sessions_dir)
+        .arg(&index_name)
+        .arg("-C")
+        .arg(reports_dir);
+    for file in index.files.iter().filter(|file| file.kind != "audit") {
+        tar.arg(&file.path);
+    }
+    if let Some((_, _, name)) = staged_audit {
+        tar.arg("-C").arg(staging).arg(format!("audit/{name}"));
+    }
+    let output = tar.audited_output().context("running tar")?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        bail!(
+            "tar failed for {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    fs::rename(&partial, &archive)
+        .with_context(|| format!("renaming {} to {}", partial.display(), archive.display()))?;
+    Ok(archive)
+}
+
+/// Put a staged audit log back, keeping records written since it was staged.
+fn restore_audit_log(staged: &Path, original: &Path) -> std::io::Result<()> {
+    if original.is_file() {
+        let newer = fs::read(original)?;
+        OpenOptions::new()
+            .append(true)
+            .open(staged)?
+            .write_all(&newer)?;
+    }
+    fs::rename(staged, original)
+}
+
+fn collect_session_files(
+    root: &Path,
+    dir: &Path,
+    since: SystemTime,
+    files: &mut Vec<SessionFile>,
+) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let rel = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        if file_type.is_dir() {
+            if rel != Path::new(SESSIONS_DIR) {
+                collect_session_files(root, &path, since, files)?;
+            }
+            continue;
+        }
+        let meta = entry.metadata()?;
+        if !file_type.is_file() || meta.modified().is_ok_and(|modified| modified < since) {
+            continue;
+        }
+        let rel = rel.to_string_lossy().into_owned();
+        let kind = if rel == PROGRESS_EVENTS_FILE {
+            "progress-events"
+        } else if rel.starts_with("build_logs/") {
+            "package-log"
+        } else {
+            "report"
+        };
+        files.push(SessionFile {
+            path: rel,
+            kind: kind.to_string(),
+            bytes: meta.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Indexes of the archived sessions under `reports_dir`, oldest first.
+pub fn list_sessions(reports_dir: &Path) -> Result<Vec<SessionIndex>> {
+    let sessions_dir = reports_dir.join(SESSIONS_DIR);
+    if !sessions_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(&sessions_dir)
+        .with_context(|| format!("reading {}", sessions_dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            sessions.push(read_index(&path)?);
+        }
+    }
+    sessions.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(sessions)
+}
+
+/// Index of session `id`; `latest` names the newest session.
+pub fn load_session(reports_dir: &Path, id: &str) -> Result<SessionIndex> {
+    if id == "latest" {
+        return list_sessions(reports_dir)?
+            .pop()
+            .with_context(|| format!("no archived sessions under {}", reports_dir.display()));
+    }
+    let path = reports_dir.join(SESSIONS_DIR).join(format!("{id}.json"));
+    if !path.is_file() {
+        bail!("no archived session '{id}' under {}", reports_dir.display());
+    }
+    read_index(&path)
+}
+
+fn read_index(path: &Path) -> Result<SessionIndex> {
+    let raw = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("parsing session index {}", path.display()))
+}
+
+/// Contents of `member` from the archive of `index`.
+pub fn read_session_member(
+    reports_dir: &Path,
+    index: &SessionIndex,
+    member: &str,
+) -> Result<Vec<u8>> {
+    if !index.files.iter().any(|file| file.path == member) {
+        bail!("session {} has no file '{member}'", index.id);
+    }
+    let archive = reports_dir.join(SESSIONS_DIR).join(&index.archive);
+    let output = Command::new("tar")
+        .arg("--zstd")
+        .arg("-xOf")
+        .arg(&archive)
+        .arg(member)
+        .audited_output()
+        .context("running tar")?;
+    if !output.status.success() {
+        bail!(
+            "tar failed for {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// `session show` listing: the session line, then one line per file.
+pub fn render_session(index: &SessionIndex) -> String {
+    let mut out = format!(
+        "session id={} command={} target_id={} status={} started={} finished={} files={} bytes={} archive={}\n",
+        index.id,
+        index.command,
+        index.target_id,
+        index.status,
+        index.started_at_utc,
+        index.finished_at_utc,
+        index.files.len(),
+        index.total_bytes(),
+        index.archive
+    );
+    for file in &index.files {
+        out.push_str(&format!(
+            "{:<16} {:>12} {}\n",
+            file.kind, file.bytes, file.path
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_available(program: &str) -> bool {
+        Command::new(program)
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    #[test]
+    fn session_archive_bundles_new_files_and_moves_the_audit_log() {
+        if !tool_available("zstd") {
+            return;
+        }
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let reports = tmp.path().join("reports");
+        fs::create_dir_all(reports.join("build_logs")).expect("mkdir logs");
+        fs::create_dir_all(reports.join(SESSIONS_DIR)).expect("mkdir sessions");
+        fs::write(reports.join("old_report.json"), "[]").expect("old report");
+        let old = SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(reports.join("old_report.json"))
+            .and_then(|file| file.set_modified(old))
+            .expect("age old report");
+        let started = Utc::now() - chrono::Duration::seconds(5);
+        fs::write(
+            reports.join(PROGRESS_EVENTS_FILE),
+            "phase=build status=started\n",
+        )
+        .expect("journal");
+        fs::write(reports.join("build_logs/samtools.log"), "make ok\n").expect("log");
+        fs::write(reports.join("build_samtools.json"), "[]").expect("report");
+        let audit = tmp.path().join("audit/20260101T000000Z-build-1.jsonl");
+        fs::create_dir_all(audit.parent().expect("audit dir")).expect("mkdir audit");
+        fs::write(&audit, "{\"program\":\"podman\"}\n").expect("audit");
+
+        let request = SessionArchiveRequest {
+            reports_dir: reports.clone(),
+            target_id: "almalinux-9.7-x86_64".to_string(),
+            command: "build".to_string(),
+            status: "completed".to_string(),
+            started,
+            audit_log: Some(audit.clone()),
+        };
+        let ArchivedSession {
+            index,
+            archive,
+            pruned,
+        } = archive_session(&request).expect("archive session");
+
+        assert!(archive.is_file() && !audit.exists() && pruned.is_empty());
+        assert_eq!(index.id, started.format("%Y%m%dT%H%M%S%.3fZ").to_string());
+        assert_eq!(
+            index
+                .files
+                .iter()
+                .map(|file| (file.kind.as_str(), file.path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("package-log", "build_logs/samtools.log"),
+                ("report", "build_samtools.json"),
+                ("progress-events", PROGRESS_EVENTS_FILE),
+                ("audit", "audit/20260101T000000Z-build-1.jsonl"),
+            ]
+        );
+        assert!(reports.join("build_logs/samtools.log").is_file());
+
+        let listed = list_sessions(&reports).expect("list sessions");
+        assert_eq!(listed, vec![index.clone()]);
+        assert_eq!(load_session(&reports, "latest").expect("latest"), index);
+        assert_eq!(
+            read_session_member(&reports, &index, "audit/20260101T000000Z-build-1.jsonl")
+                .expect("audit member"),
+            b"{\"program\":\"podman\"}\n"
+        );
+        assert_eq!(
+            read_session_member(&reports, &index, "build_logs/samtools.log").expect("log member"),
+            b"make ok\n"
+        );
+        assert!(read_session_member(&reports, &index, "old_report.json").is_err());
+        assert!(load_session(&reports, "19700101T000000Z").is_err());
+
+        // A second session started in the same millisecond gets its own id.
+        fs::write(
+            reports.join(PROGRESS_EVENTS_FILE),
+            "phase=build status=done\n",
+        )
+        .expect("journal");
+        let again = archive_session(&SessionArchiveRequest {
+            audit_log: None,
+            ..request.clone()
+        })
+        .expect("archive second session");
+        assert_eq!(again.index.id, format!("{}-2", index.id));
+        assert!(index.archive != again.index.archive && again.archive.is_file());
+        assert_eq!(list_sessions(&reports).expect("list sessions").len(), 2);
+    }
+
+    #[test]
+    fn session_archive_prunes_loose_files_a_newer_session_superseded() {
+        if !tool_available("zstd") {
+            return;
+        }
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let reports = tmp.path().join("reports");
+        fs::create_dir_all(reports.join("build_logs")).expect("mkdir logs");
+        let request = |started| SessionArchiveRequest {
+            reports_dir: reports.clone(),
+            target_id: "almalinux-9.7-x86_64".to_string(),
+            command: "build".to_string(),
+            status: "completed".to_string(),
+            started,
+            audit_log: None,
+        };
+        let age = |path: &Path| {
+            fs::File::options()
+                .write(true)
+                .open(path)
+                .and_then(|file| {
+                    file.set_modified(SystemTime::now() - std::time::Duration::from_secs(600))
+                })
+                .expect("age file");
+        };
+
+        let first_started = Utc::now() - chrono::Duration::seconds(3600);
+        for (path, body) in [
+            ("build_logs/samtools.log", "make 1\n"),
+            ("build_logs/bcftools.log", "make 1\n"),
+            ("build_samtools.json", "[1]"),
+            ("build_bcftools.json", "[1]"),
+        ] {
+            fs::write(reports.join(path), body).expect("write");
+            age(&reports.join(path));
+        }
+        let first = archive_session(&request(first_started)).expect("first session");
+        assert_eq!(first.index.files.len(), 4);
+        // Changed since it was archived, so the loose copy is kept.
+        fs::write(reports.join("build_bcftools.json"), "[1, 2]").expect("edit");
+        age(&reports.join("build_bcftools.json"));
+
+        let second_started = Utc::now() - chrono::Duration::seconds(5);
+        fs::write(reports.join("build_logs/samtools.log"), "make 2\n").expect("log");
+        fs::write(reports.join("build_samtools.json"), "[2]").expect("report");
+        let second = archive_session(&request(second_started)).expect("second session");
+
+        assert_eq!(
+            second.pruned,
+            vec![format!("{}:build_logs/bcftools.log", first.index.id)]
+        );
+        assert!(!reports.join("build_logs/bcftools.log").exists());
+        assert!(reports.join("build_bcftools.json").is_file());
+        assert!(reports.join("build_logs/samtools.log").is_file());
+        let first_index = load_session(&reports, &first.index.id).expect("first index");
+        assert_eq!(
+            first_index.moved,
+            vec!["build_logs/bcftools.log".to_string()]
+        );
+        assert_eq!(
+            read_session_member(&reports, &first_index, "build_logs/bcftools.log")
+                .expect("pruned member"),
+            b"make 1\n"
+        );
+        assert_eq!(
+            read_session_member(&reports, &first_index, "build_logs/samtools.log")
+                .expect("superseded member"),
+            b"make 1\n"
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use crate::audit::AuditedCommand;
+use crate::build_lock::current_host_name;
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Standard OpenTelemetry variables honoured when `--otlp-endpoint` is unset.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const OTLP_TRACES_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+pub const OTLP_HEADERS_ENV: &str = "OTEL_EXPORTER_OTLP_HEADERS";
+const SERVICE_NAME: &str = "bioconda2rpm";
+const EXPORT_TIMEOUT_SECS: &str = "5";
+/// Span kind INTERNAL and status codes from the OTLP trace proto.
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+/// Report statuses that end a package trace without an error.
+const OK_STATUSES: [&str; 4] = ["generated", "up-to-date", "reused-noarch", "skipped"];
+
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Posts one OTLP/HTTP JSON trace per package build to a collector through the
+/// host `curl`, so no gRPC/protobuf stack is linked in.
+#[derive(Debug, Clone)]
+pub struct TraceExporter {
+    traces_url: String,
+    headers: Vec<(String, String)>,
+    session_id: String,
+    target_id: String,
+    host: String,
+}
+
+impl TraceExporter {
+    /// `endpoint` is the collector base URL (`/v1/traces` is appended) unless
+    /// it already names the traces path.
+    pub fn new(endpoint: &str, session_id: String, target_id: &str) -> Result<Self> {
+        Ok(Self {
+            traces_url: otlp_traces_url(endpoint)?,
+            headers: parse_otlp_headers(&std::env::var(OTLP_HEADERS_ENV).unwrap_or_default())?,
+            session_id,
+            target_id: target_id.to_string(),
+            host: current_host_name(),
+        })
+    }
+
+    pub fn traces_url(&self) -> &str {
+        &self.traces_url
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn export(&self, trace: &PackageTrace) -> Result<()> {
+        let payload =
+            serde_json::to_string(&self.otlp_payload(trace)).context("serializing OTLP trace")?;
+        post_json(&self.traces_url, &self.headers, &payload, "OTLP export")
+    }
+
+    fn otlp_payload(&self, trace: &PackageTrace) -> Value {
+        let failed = !OK_STATUSES.contains(&trace.status.as_str());
+        let session = attribute("bioconda2rpm.session.id", &self.session_id);
+        let package = attribute("bioconda2rpm.package", &trace.package);
+        let end_nanos = trace
+            .spans
+            .last()
+            .map(|span| span.end_nanos)
+            .unwrap_or(trace.start_nanos)
+            .max(trace.start_nanos);
+        let mut spans = vec![json!({
+            "traceId": trace.trace_id,
+            "spanId": trace.root_span_id,
+            "name": format!("build {}", trace.package),
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": trace.start_nanos.to_string(),
+            "endTimeUnixNano": end_nanos.to_string(),
+            "attributes": [
+                session.clone(),
+                package.clone(),
+                attribute("bioconda2rpm.status", &trace.status),
+                attribute("bioconda2rpm.reason", &trace.reason),
+            ],
+            "status": span_status(failed, &trace.reason),
+        })];
+        let last = trace.spans.len().saturating_sub(1);
+        for (idx, span) in trace.spans.iter().enumerate() {
+            // Phases run in sequence, so a failed package failed in its last phase.
+            let span_failed = failed && idx == last;
+            spans.push(json!({
+                "traceId": trace.trace_id,
+                "spanId": span.span_id,
+                "parentSpanId": trace.root_span_id,
+                "name": span.name,
+                "kind": SPAN_KIND_INTERNAL,
+                "startTimeUnixNano": span.start_nanos.to_string(),
+                "endTimeUnixNano": span.end_nanos.to_string(),
+                "attributes": [session.clone(), package.clone()],
+                "status": span_status(span_failed, &trace.reason),
+            }));
+        }
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        attribute("service.name", SERVICE_NAME),
+                        attribute("service.version", env!("CARGO_PKG_VERSION")),
+                        attribute("host.name", &self.host),
+                        attribute("bioconda2rpm.target_id", &self.target_id),
+                        attribute("bioconda2rpm.session.id", &self.session_id),
+                    ]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": SERVICE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }]
+            }]
+        })
+    }
+}
+
+/// Phase timeline of one package build; each [`PackageTrace::enter`] closes
+/// the running phase span and opens the next.
+#[derive(Debug)]
+pub struct PackageTrace {
+    package: String,
+    trace_id: String,
+    root_span_id: String,
+    start_nanos: u64,
+    spans: Vec<PhaseSpan>,
+    status: String,
+    reason: String,
+}
+
+#[derive(Debug)]
+struct PhaseSpan {
+    span_id: String,
+    name: String,
+    start_nanos: u64,
+    end_nanos: u64,
+}
+
+impl PackageTrace {
+    pub fn start(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            trace_id: format!("{:016x}{:016x}", next_id(), next_id()),
+            root_span_id: format!("{:016x}", next_id()),
+            start_nanos: unix_nanos(),
+            spans: Vec::new(),
+            status: String::new(),
+            reason: String::new(),
+        }
+    }
+
+    pub fn enter(&mut self, phase: &str) {
+        let now = unix_nanos();
+        self.close_phase(now);
+        self.spans.push(PhaseSpan {
+            span_id: format!("{:016x}", next_id()),
+            name: phase.to_string(),
+            start_nanos: now,
+            end_nanos: 0,
+        });
+    }
+
+    /// End the running phase without starting another, e.g. when a
+    /// pre-rendered package waits for dispatch.
+    pub fn suspend(&mut self) {
+        self.close_phase(unix_nanos());
+    }
+
+    pub fn finish(&mut self, status: &str, reason: &str) {
+        self.close_phase(unix_nanos());
+        self.status = status.to_string();
+        self.reason = reason.to_string();
+    }
+
+    fn close_phase(&mut self, now: u64) {
+        if let Some(span) = self.spans.last_mut()
+            && span.end_nanos == 0
+        {
+            span.end_nanos = now;
+        }
+    }
+}
+
+/// POSTs a JSON body through the host `curl`; any non-2xx answer is an error.
+/// Headers and body go through a curl config on stdin so endpoint credentials
+/// never reach argv or the audit log.
+pub fn post_json(url: &str, headers: &[(String, String)], payload: &str, what: &str) -> Result<()> {
+    let mut config = String::from("header = \"Content-Type: application/json\"\n");
+    for (name, value) in headers {
+        config.push_str(&format!(
+            "header = \"{}\"\n",
+            curl_config_quote(&format!("{name}: {value}"))
+        ));
+    }
+    config.push_str(&format!(
+        "data-binary = \"{}\"\n",
+        curl_config_quote(payload)
+    ));
+
+    let (mut child, pending_audit) = Command::new("curl")
+        .arg("-sS")
+        .arg("--max-time")
+        .arg(EXPORT_TIMEOUT_SECS)
+        .arg("-o")
+        .arg("/dev/null")
+        .arg("-w")
+        .arg("%{http_code}")
+        .arg("-K")
+        .arg("-")
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .audited_spawn()
+        .with_context(|| format!("running curl for {what}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .with_context(|| format!("writing {what} request to curl"))?;
+    }
+    let output = child.wait_with_output();
+    pending_audit.finish(&output.as_ref().map(|o| o.status).map_err(clone_io_error));
+    let output = output.with_context(|| format!("waiting for curl {what}"))?;
+    let code = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || !code.starts_with('2') {
+        bail!(
+            "{what} to {url} returned HTTP {}: {}",
+            if code.is_empty() { "none" } else { &code },
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Collector traces URL for an OTLP base endpoint.
+pub fn otlp_traces_url(endpoint: &str) -> Result<String> {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if !(endpoint.starts_with("http://") || endpoint.starts_with("https://")) {
+        bail!("OTLP endpoint must be an http:// or https:// URL: {endpoint}");
+    }
+    if endpoint.ends_with("/v1/traces") {
+        Ok(endpoint.to_string())
+    } else {
+        Ok(format!("{endpoint}/v1/traces"))
+    }
+}
+
+/// `OTEL_EXPORTER_OTLP_HEADERS` format: comma-separated `name=value` pairs
+/// with percent-encoded values.
+pub fn parse_otlp_headers(raw: &str) -> Result<Vec<(String, String)>> {
+    parse_header_list(raw, OTLP_HEADERS_ENV)
+}
+
+/// Comma-separated `name=value` header pairs with percent-encoded values, as
+/// read from the environment variable `source`.
+pub fn parse_header_list(raw: &str, source: &str) -> Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    for pair in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let Some((name, value)) = pair.split_once('=') else {
+            bail!("invalid {source} entry (expected name=value): {pair}");
+        };
+        let name = name.trim();
+        if name.is_empty() || name.contains([':', ' ', '\n', '\r']) {
+            bail!("invalid {source} header name: {name:?}");
+        }
+        headers.push((name.to_string(), percent_decode(value.trim())));
+    }
+    Ok(headers)
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%'
+            && let Some(hex) = value.get(idx + 1..idx + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            decoded.push(byte);
+            idx += 3;
+            continue;
+        }
+        decoded.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn curl_config_quote(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn span_status(failed: bool, reason: &str) -> Value {
+    if failed {
+        json!({ "code": STATUS_ERROR, "message": reason })
+    } else {
+        json!({ "code": STATUS_OK })
+    }
+}
+
+pub fn clone_io_error(err: &std::io::Error) -> std::io::Error {
+    std::io::Error::new(err.kind(), err.to_string())
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Non-zero pseudo-random id (splitmix64 over time, pid and a counter); trace
+/// ids only need to be unique, not unpredictable.
+fn next_id() -> u64 {
+    let counter = ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut z = unix_nanos()
+        ^ (u64::from(std::process::id()) << 32)
+        ^ counter.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otlp_endpoint_and_headers_follow_the_exporter_conventions() {
+        assert_eq!(
+            otlp_traces_url("http://collector:4318/").expect("base endpoint"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            otlp_traces_url("https://otel.example.org/v1/traces").expect("traces endpoint"),
+            "https://otel.example.org/v1/traces"
+        );
+        assert!(otlp_traces_url("collector:4317").is_err());
+        assert_eq!(
+            parse_otlp_headers("Authorization=Bearer%20abc, x-team = bio").expect("headers"),
+            vec![
+                ("Authorization".to_string(), "Bearer abc".to_string()),
+                ("x-team".to_string(), "bio".to_string()),
+            ]
+        );
+        assert!(parse_otlp_headers("broken").is_err());
+        assert_eq!(
+            curl_config_quote(r#"{"a":"b\"c"}"#),
+            r#"{\"a\":\"b\\\"c\"}"#
+        );
+    }
+
+    #[test]
+    fn package_trace_becomes_root_span_with_sequential_phase_children() {
+        let exporter = TraceExporter {
+            traces_url: "http://collector:4318/v1/traces".to_string(),
+            headers: Vec::new(),
+            session_id: "nightly-42".to_string(),
+            target_id: "almalinux-9.7-x86_64".to_string(),
+            host: "builder-1".to_string(),
+        };
+        let mut trace = PackageTrace::start("samtools");
+        for phase in ["resolution", "source-staging", "rendering"] {
+            trace.enter(phase);
+        }
+        trace.suspend();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        trace.enter("payload-build");
+        trace.finish("quarantined", "payload spec build failed in container");
+
+        let payload = exporter.otlp_payload(&trace);
+        let resource = &payload["resourceSpans"][0];
+        assert!(
+            resource["resource"]["attributes"]
+                .as_array()
+                .expect("resource attributes")
+                .contains(&attribute("bioconda2rpm.session.id", "nightly-42"))
+        );
+        let spans = resource["scopeSpans"][0]["spans"]
+            .as_array()
+            .expect("spans");
+        assert_eq!(spans.len(), 5);
+        let trace_id = spans[0]["traceId"].as_str().expect("trace id");
+        assert_eq!(trace_id.len(), 32);
+        assert_eq!(spans[0]["name"], "build samtools");
+        assert_eq!(spans[0]["status"]["code"], STATUS_ERROR);
+        let names: Vec<&str> = spans[1..]
+            .iter()
+            .map(|span| {
+                assert_eq!(span["traceId"], trace_id);
+                assert_eq!(span["parentSpanId"], spans[0]["spanId"]);
+                span["name"].as_str().expect("span name")
+            })
+            .collect();
+        assert_eq!(
+            names,
+            ["resolution", "source-staging", "rendering", "payload-build"]
+        );
+        assert_eq!(spans[3]["status"]["code"], STATUS_OK);
+        assert_eq!(spans[4]["status"]["code"], STATUS_ERROR);
+        assert_eq!(spans[1]["endTimeUnixNano"], spans[2]["startTimeUnixNano"]);
+        let nanos = |span: &Value, field: &str| {
+            span[field]
+                .as_str()
+                .and_then(|v| v.parse::<u64>().ok())
+                .expect("unix nanos")
+        };
+        // The suspended rendering span ends before the payload build starts.
+        assert!(nanos(&spans[3], "endTimeUnixNano") < nanos(&spans[4], "startTimeUnixNano"));
+        assert!(nanos(&spans[4], "endTimeUnixNano") >= nanos(&spans[4], "startTimeUnixNano"));
+    }
+}
@@ -9,6 +9,7 @@ use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::{Color, Style};
 use ratatui::widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap};
+use serde::Serialize;
 use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use std::sync::mpsc::{self, Receiver, Sender};
@@ -91,15 +92,20 @@ impl UiState {
                 .cloned()
                 .unwrap_or_else(|| "running".to_string());
             let mapped_status = match status.as_str() {
-                "running" | "started" => "running",
+                "running" | "started" | "stage" => "running",
                 "completed" => "generated",
                 "failed" => "quarantined",
                 other => other,
             };
-            let detail = kv
+            let elapsed = kv
                 .get("elapsed")
                 .cloned()
                 .unwrap_or_else(|| "container-build".to_string());
+            // Show which part of the build (sources, deps, %build, ...) runs.
+            let detail = match kv.get("stage") {
+                Some(stage) => format!("{stage} {elapsed}"),
+                None => elapsed,
+            };
             self.seq = self.seq.saturating_add(1);
             self.packages.insert(
                 label.clone(),
@@ -207,8 +213,15 @@ impl UiState {
             match ps.status.as_str() {
                 "running" | "started" => running += 1,
                 "queued" | "waiting" | "pending" | "planned" => ready += 1,
-                "generated" | "up-to-date" | "skipped" => completed += 1,
-                "blocked" | "quarantined" => blocked += 1,
+                "generated" | "srpm-generated" | "up-to-date" | "reused-noarch" | "skipped" => {
+                    completed += 1
+                }
+                "blocked"
+                | "quarantined"
+                | "spec-invalid"
+                | "license-gate"
+                | "runtime-bootstrap-broken"
+                | "oversize" => blocked += 1,
                 _ => {}
             }
         }
@@ -236,6 +249,55 @@ impl UiState {
     }
 }
 
+/// Queue and package states of a run, folded from its progress lines the
+/// same way the TUI folds them.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueSnapshot {
+    pub last_phase: String,
+    pub last_status_line: String,
+    pub queue_line: String,
+    pub ready: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub blocked: usize,
+    /// Most recently updated first.
+    pub packages: Vec<QueuePackage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuePackage {
+    pub package: String,
+    pub status: String,
+    pub detail: String,
+}
+
+pub fn queue_snapshot(lines: impl IntoIterator<Item = String>) -> QueueSnapshot {
+    let mut state = UiState::new(String::new());
+    for line in lines {
+        state.ingest_log(line);
+    }
+    let (ready, running, completed, blocked) = state.scheduler_counters();
+    let mut packages = state.packages.into_iter().collect::<Vec<_>>();
+    packages.sort_by(|a, b| b.1.seq.cmp(&a.1.seq).then_with(|| a.0.cmp(&b.0)));
+    QueueSnapshot {
+        last_phase: state.last_phase,
+        last_status_line: state.last_status_line,
+        queue_line: state.queue_line,
+        ready,
+        running,
+        completed,
+        blocked,
+        packages: packages
+            .into_iter()
+            .map(|(package, ps)| QueuePackage {
+                package,
+                status: ps.status,
+                detail: ps.detail,
+            })
+            .collect(),
+    }
+}
+
 pub struct ProgressUi {
     tx: Sender<UiEvent>,
     join: Option<JoinHandle<()>>,
@@ -428,8 +490,13 @@ fn draw_ui(frame: &mut ratatui::Frame<'_>, state: &UiState) {
     let rank = |status: &str| -> usize {
         match status {
             "running" | "started" => 0,
-            "quarantined" | "blocked" => 1,
-            "generated" | "up-to-date" => 2,
+            "quarantined"
+            | "spec-invalid"
+            | "license-gate"
+            | "runtime-bootstrap-broken"
+            | "oversize"
+            | "blocked" => 1,
+            "generated" | "srpm-generated" | "up-to-date" | "reused-noarch" => 2,
             "queued" | "waiting" => 3,
             "pending" | "planned" => 4,
             "skipped" => 5,
@@ -458,9 +525,13 @@ fn draw_ui(frame: &mut ratatui::Frame<'_>, state: &UiState) {
     rows.truncate(visible_capacity);
     let table_rows = rows.into_iter().map(|(pkg, ps)| {
         let style = match ps.status.as_str() {
-            "generated" => Style::default().fg(Color::Green),
-            "up-to-date" => Style::default().fg(Color::LightGreen),
-            "quarantined" => Style::default().fg(Color::Red),
+            "generated" | "srpm-generated" => Style::default().fg(Color::Green),
+            "up-to-date" | "reused-noarch" => Style::default().fg(Color::LightGreen),
+            "quarantined"
+            | "spec-invalid"
+            | "license-gate"
+            | "runtime-bootstrap-broken"
+            | "oversize" => Style::default().fg(Color::Red),
             "skipped" => Style::default().fg(Color::Yellow),
             "queued" | "waiting" | "pending" | "planned" => Style::default().fg(Color::Blue),
             "blocked" => Style::default().fg(Color::LightRed),
@@ -543,11 +614,23 @@ fn parse_progress_kv(line: &str) -> BTreeMap<String, String> {
 }
 
 fn is_passing_status(status: &str) -> bool {
-    matches!(status, "generated" | "up-to-date")
+    matches!(
+        status,
+        "generated" | "srpm-generated" | "up-to-date" | "reused-noarch"
+    )
 }
 
 fn is_failing_status(status: &str) -> bool {
-    matches!(status, "quarantined" | "blocked" | "failed")
+    matches!(
+        status,
+        "quarantined"
+            | "spec-invalid"
+            | "license-gate"
+            | "runtime-bootstrap-broken"
+            | "oversize"
+            | "blocked"
+            | "failed"
+    )
 }
 
 fn render_recent_outcome_items(
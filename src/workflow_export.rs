@@ -0,0 +1,281 @@
+use crate::cli::WorkflowFormat;
+
+/// A planned closure as workflow tasks, dependencies first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkflowPlan {
+    /// Workflow / DAG name derived from the requested packages.
+    pub name: String,
+    pub tasks: Vec<WorkflowTask>,
+    /// Command each task runs, followed by its package name.
+    pub build_command: Vec<String>,
+    /// Flags appended after the package name.
+    pub build_args: Vec<String>,
+    /// Retries of a failed task (Argo and Airflow).
+    pub retries: u32,
+    /// Tasks running at once. A `build` that finds the session lock held
+    /// forwards its package and exits, so steps sharing a topdir need 1.
+    pub parallelism: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkflowTask {
+    /// Task name: the package key lowercased, other characters as `-`.
+    pub name: String,
+    pub package: String,
+    /// Task names this one waits for; all listed earlier in the plan.
+    pub depends_on: Vec<String>,
+}
+
+/// Task name valid in Argo DAGs, Airflow task ids and make targets.
+pub fn workflow_task_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    name.trim_matches('-').to_string()
+}
+
+impl WorkflowPlan {
+    fn command_for(&self, package: &str) -> Vec<String> {
+        let mut argv = self.build_command.clone();
+        argv.push(package.to_string());
+        argv.extend(self.build_args.iter().cloned());
+        argv
+    }
+}
+
+pub fn render_workflow(plan: &WorkflowPlan, format: &WorkflowFormat) -> String {
+    match format {
+        WorkflowFormat::Argo => render_argo(plan),
+        WorkflowFormat::Airflow => render_airflow(plan),
+        WorkflowFormat::Makefile => render_makefile(plan),
+    }
+}
+
+/// Argo `Workflow` with one DAG task per package, all running the
+/// `build-package` container template.
+fn render_argo(plan: &WorkflowPlan) -> String {
+    let mut out = format!(
+        r#"# Generated by bioconda2rpm plan export; one task per package, dependencies first.
+apiVersion: argoproj.io/v1alpha1
+kind: Workflow
+metadata:
+  generateName: {}-
+spec:
+  entrypoint: build-closure
+  parallelism: {}
+  arguments:
+    parameters:
+      - name: image
+        value: bioconda2rpm:latest
+  templates:
+    - name: build-closure
+      dag:
+        tasks:
+"#,
+        workflow_task_name(&plan.name),
+        plan.parallelism
+    );
+    for task in &plan.tasks {
+        out.push_str(&format!(
+            "          - name: {}\n            template: build-package\n            arguments:\n              parameters:\n                - name: package\n                  value: {}\n",
+            task.name,
+            quote_string(&task.package)
+        ));
+        if !task.depends_on.is_empty() {
+            out.push_str(&format!(
+                "            dependencies: [{}]\n",
+                task.depends_on.join(", ")
+            ));
+        }
+    }
+    out.push_str(&format!(
+        "    - name: build-package\n      inputs:\n        parameters:\n          - name: package\n      retryStrategy:\n        limit: {}\n      container:\n        image: \"{{{{workflow.parameters.image}}}}\"\n        command:\n",
+        plan.retries
+    ));
+    for arg in plan.command_for("{{inputs.parameters.package}}") {
+        out.push_str(&format!("          - {}\n", quote_string(&arg)));
+    }
+    out
+}
+
+/// Airflow DAG module with one `BashOperator` per package.
+fn render_airflow(plan: &WorkflowPlan) -> String {
+    let dag_id = workflow_task_name(&plan.name).replace('-', "_");
+    let mut out = format!(
+        r#"# Generated by bioconda2rpm plan export; one task per package, dependencies first.
+from datetime import datetime
+
+from airflow import DAG
+from airflow.operators.bash import BashOperator
+
+with DAG(
+    dag_id="{dag_id}",
+    start_date=datetime(2024, 1, 1),
+    schedule=None,
+    catchup=False,
+    max_active_tasks={},
+    default_args={{"retries": {}}},
+) as dag:
+    tasks = {{}}
+"#,
+        plan.parallelism, plan.retries
+    );
+    for task in &plan.tasks {
+        out.push_str(&format!(
+            "    tasks[\"{name}\"] = BashOperator(task_id=\"{name}\", bash_command={command})\n",
+            name = task.name,
+            command = quote_string(&shell_join(&plan.command_for(&task.package)))
+        ));
+    }
+    for task in &plan.tasks {
+        for dep in &task.depends_on {
+            out.push_str(&format!(
+                "    tasks[\"{dep}\"] >> tasks[\"{}\"]\n",
+                task.name
+            ));
+        }
+    }
+    out
+}
+
+/// Makefile with one stamp-file target per package, so a rerun skips
+/// finished packages; `.NOTPARALLEL` keeps a parallelism of 1 under `make -j`.
+fn render_makefile(plan: &WorkflowPlan) -> String {
+    let stamp = |name: &str| format!("$(STAMPS)/{name}");
+    let (program, subcommand) = plan
+        .build_command
+        .split_first()
+        .map(|(program, rest)| (program.as_str(), rest))
+        .unwrap_or(("bioconda2rpm", &[]));
+    let mut out = format!(
+        "# Generated by bioconda2rpm plan export; one target per package, dependencies first.\n\
+BIOCONDA2RPM ?= {}\n\
+STAMPS ?= .bioconda2rpm-stamps\n\
+\n\
+.PHONY: all\n\
+{}all: {}\n",
+        make_quote(program),
+        if plan.parallelism <= 1 {
+            ".NOTPARALLEL:\n"
+        } else {
+            ""
+        },
+        plan.tasks
+            .iter()
+            .map(|task| stamp(&task.name))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    for task in &plan.tasks {
+        let deps = task
+            .depends_on
+            .iter()
+            .map(|dep| format!(" {}", stamp(dep)))
+            .collect::<String>();
+        let recipe = subcommand
+            .iter()
+            .chain([&task.package])
+            .chain(&plan.build_args)
+            .map(|arg| make_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        out.push_str(&format!(
+            "\n{}:{deps}\n\t$(BIOCONDA2RPM) {recipe}\n\t@mkdir -p $(STAMPS) && touch $@\n",
+            stamp(&task.name)
+        ));
+    }
+    out
+}
+
+/// Shell-quoted argument with `$` escaped from make.
+fn make_quote(arg: &str) -> String {
+    shell_quote(arg).replace('$', "$$")
+}
+
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn shell_join(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Double-quoted string literal, valid both in YAML and in Python.
+fn quote_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workflows_run_one_no_deps_build_per_package_after_its_dependencies() {
+        let plan = WorkflowPlan {
+            name: "samtools".to_string(),
+            tasks: vec![
+                WorkflowTask {
+                    name: "htslib".to_string(),
+                    package: "htslib".to_string(),
+                    depends_on: Vec::new(),
+                },
+                WorkflowTask {
+                    name: "samtools".to_string(),
+                    package: "samtools".to_string(),
+                    depends_on: vec!["htslib".to_string()],
+                },
+            ],
+            build_command: vec![
+                "bioconda2rpm".to_string(),
+                "build".to_string(),
+                "--no-deps".to_string(),
+            ],
+            build_args: vec!["--topdir".to_string(), "/srv/rpm build".to_string()],
+            retries: 2,
+            parallelism: 1,
+        };
+
+        let argo = render_workflow(&plan, &WorkflowFormat::Argo);
+        assert!(argo.contains("kind: Workflow\n"));
+        assert!(argo.contains("  parallelism: 1\n"));
+        assert!(argo.contains("          - name: samtools\n"));
+        assert!(argo.contains("            dependencies: [htslib]\n"));
+        assert!(argo.contains("      retryStrategy:\n        limit: 2\n"));
+        assert!(argo.contains("          - \"{{inputs.parameters.package}}\"\n"));
+        assert!(argo.contains("          - \"/srv/rpm build\"\n"));
+
+        let airflow = render_workflow(&plan, &WorkflowFormat::Airflow);
+        assert!(airflow.contains("dag_id=\"samtools\""));
+        assert!(airflow.contains("    max_active_tasks=1,\n"));
+        assert!(airflow.contains(
+            "tasks[\"samtools\"] = BashOperator(task_id=\"samtools\", bash_command=\"bioconda2rpm build --no-deps samtools --topdir '/srv/rpm build'\")"
+        ));
+        assert!(airflow.contains("    tasks[\"htslib\"] >> tasks[\"samtools\"]\n"));
+
+        let makefile = render_workflow(&plan, &WorkflowFormat::Makefile);
+        assert!(makefile.contains(".NOTPARALLEL:\nall: $(STAMPS)/htslib $(STAMPS)/samtools\n"));
+        assert!(makefile.contains(
+            "$(STAMPS)/samtools: $(STAMPS)/htslib\n\t$(BIOCONDA2RPM) build --no-deps samtools --topdir '/srv/rpm build'\n"
+        ));
+
+        assert_eq!(workflow_task_name("R-Base_4.5"), "r-base-4-5");
+    }
+}
@@ -0,0 +1,382 @@
+use crate::atomic_file::write_atomic;
+use crate::recipe_repo;
+use crate::stability_store::STABILITY_CACHE_FILE_NAME;
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOTS_DIR_NAME: &str = "snapshots";
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const ARTIFACT_DIRS: [&str; 2] = ["RPMS", "SRPMS"];
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceSnapshotRequest {
+    pub name: String,
+    pub session_topdir: PathBuf,
+    pub target_id: String,
+    pub target_root: PathBuf,
+    pub recipe_repo_root: PathBuf,
+    pub replace: bool,
+}
+
+/// An RPM or SRPM present in the target tree when the snapshot was taken.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SnapshotArtifact {
+    /// Path relative to the target root, e.g. `RPMS/x86_64/foo.rpm`.
+    pub path: String,
+    pub size: u64,
+    /// True when the snapshot holds a hard link; otherwise the file is only referenced.
+    pub linked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSnapshotManifest {
+    pub name: String,
+    pub created_at_utc: String,
+    pub target_id: String,
+    pub recipe_repo_root: String,
+    pub recipe_commit: Option<String>,
+    pub spec_files: usize,
+    pub report_files: usize,
+    pub stability_cache: bool,
+    pub artifacts: Vec<SnapshotArtifact>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceRestoreOutcome {
+    pub manifest: WorkspaceSnapshotManifest,
+    pub restored_artifacts: usize,
+    pub removed_artifacts: usize,
+    pub missing_artifacts: Vec<String>,
+}
+
+pub fn snapshot_dir(session_topdir: &Path, name: &str) -> PathBuf {
+    session_topdir.join(SNAPSHOTS_DIR_NAME).join(name)
+}
+
+/// Capture SPECS, target reports (including the stability cache) and a
+/// manifest of RPM/SRPM artifacts under `<session_topdir>/snapshots/<name>`.
+pub fn create_workspace_snapshot(
+    request: &WorkspaceSnapshotRequest,
+) -> Result<WorkspaceSnapshotManifest> {
+    let dir = snapshot_dir(&request.session_topdir, &request.name);
+    if dir.exists() && !request.replace {
+        bail!(
+            "workspace snapshot '{}' already exists at {} (use --replace to overwrite it)",
+            request.name,
+            dir.display()
+        );
+    }
+    // Stage next to the final location so an interrupted snapshot never
+    // leaves a half-written directory under the requested name.
+    let staging = dir.with_extension("partial");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("removing stale snapshot staging {}", staging.display()))?;
+    }
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("creating snapshot staging {}", staging.display()))?;
+
+    let spec_files = copy_tree(
+        &request.session_topdir.join("SPECS"),
+        &staging.join("SPECS"),
+    )?;
+    let reports_dir = request.target_root.join("reports");
+    let report_files = copy_tree(&reports_dir, &staging.join("reports"))?;
+    let stability_cache = reports_dir.join(STABILITY_CACHE_FILE_NAME).is_file();
+
+    let mut artifacts = Vec::new();
+    for artifact_dir in ARTIFACT_DIRS {
+        for rel in collect_files(&request.target_root.join(artifact_dir))? {
+            let rel = Path::new(artifact_dir).join(rel);
+            let source = request.target_root.join(&rel);
+            let size = fs::metadata(&source)
+                .with_context(|| format!("reading artifact metadata {}", source.display()))?
+                .len();
+            let dest = staging.join(&rel);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("creating snapshot dir {}", parent.display()))?;
+            }
+            // Hard links keep snapshots cheap; across filesystems the
+            // artifact is only referenced by path and size.
+            let linked = fs::hard_link(&source, &dest).is_ok();
+            artifacts.push(SnapshotArtifact {
+                path: rel.to_string_lossy().to_string(),
+                size,
+                linked,
+            });
+        }
+    }
+
+    let manifest = WorkspaceSnapshotManifest {
+        name: request.name.clone(),
+        created_at_utc: Utc::now().to_rfc3339(),
+        target_id: request.target_id.clone(),
+        recipe_repo_root: request.recipe_repo_root.to_string_lossy().to_string(),
+        recipe_commit: recipe_repo::recipe_repository_head(&request.recipe_repo_root),
+        spec_files,
+        report_files,
+        stability_cache,
+        artifacts,
+    };
+    let body = serde_json::to_string_pretty(&manifest)
+        .context("serializing workspace snapshot manifest")?;
+    write_atomic(staging.join(MANIFEST_FILE_NAME), body)
+        .with_context(|| format!("writing snapshot manifest in {}", staging.display()))?;
+
+    if dir.exists() {
+        fs::remove_dir_all(&dir)
+            .with_context(|| format!("removing previous snapshot {}", dir.display()))?;
+    }
+    fs::rename(&staging, &dir).with_context(|| format!("finalizing snapshot {}", dir.display()))?;
+    Ok(manifest)
+}
+
+pub fn load_snapshot_manifest(dir: &Path) -> Result<WorkspaceSnapshotManifest> {
+    let path = dir.join(MANIFEST_FILE_NAME);
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("reading snapshot manifest {}", path.display()))?;
+    serde_json::from_str(&raw)
+        .with_context(|| format!("parsing snapshot manifest {}", path.display()))
+}
+
+/// Replace SPECS and target reports with the snapshot copies and bring the
+/// RPM/SRPM trees back to the artifact set recorded in the manifest.
+pub fn restore_workspace_snapshot(
+    request: &WorkspaceSnapshotRequest,
+) -> Result<WorkspaceRestoreOutcome> {
+    let dir = snapshot_dir(&request.session_topdir, &request.name);
+    if !dir.is_dir() {
+        bail!(
+            "workspace snapshot '{}' not found at {}",
+            request.name,
+            dir.display()
+        );
+    }
+    let manifest = load_snapshot_manifest(&dir)?;
+    if manifest.target_id != request.target_id {
+        bail!(
+            "workspace snapshot '{}' was taken for target {} but restore targets {}",
+            request.name,
+            manifest.target_id,
+            request.target_id
+        );
+    }
+
+    replace_tree(&dir.join("SPECS"), &request.session_topdir.join("SPECS"))?;
+    replace_tree(&dir.join("reports"), &request.target_root.join("reports"))?;
+
+    let recorded = manifest
+        .artifacts
+        .iter()
+        .map(|artifact| PathBuf::from(&artifact.path))
+        .collect::<BTreeSet<_>>();
+    let mut removed_artifacts = 0usize;
+    for artifact_dir in ARTIFACT_DIRS {
+        for rel in collect_files(&request.target_root.join(artifact_dir))? {
+            let rel = Path::new(artifact_dir).join(rel);
+            if recorded.contains(&rel) {
+                continue;
+            }
+            let path = request.target_root.join(&rel);
+            fs::remove_file(&path)
+                .with_context(|| format!("removing artifact {}", path.display()))?;
+            removed_artifacts += 1;
+        }
+    }
+
+    let mut restored_artifacts = 0usize;
+    let mut missing_artifacts = Vec::new();
+    for artifact in &manifest.artifacts {
+        let dest = request.target_root.join(&artifact.path);
+        if !artifact.linked {
+            if dest.is_file() {
+                restored_artifacts += 1;
+            } else {
+                missing_artifacts.push(artifact.path.clone());
+            }
+            continue;
+        }
+        let source = dir.join(&artifact.path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating artifact dir {}", parent.display()))?;
+        }
+        if dest.exists() {
+            fs::remove_file(&dest)
+                .with_context(|| format!("replacing artifact {}", dest.display()))?;
+        }
+        if fs::hard_link(&source, &dest).is_err() {
+            fs::copy(&source, &dest).with_context(|| {
+                format!(
+                    "restoring artifact {} -> {}",
+                    source.display(),
+                    dest.display()
+                )
+            })?;
+        }
+        restored_artifacts += 1;
+    }
+
+    Ok(WorkspaceRestoreOutcome {
+        manifest,
+        restored_artifacts,
+        removed_artifacts,
+        missing_artifacts,
+    })
+}
+
+fn replace_tree(source: &Path, dest: &Path) -> Result<usize> {
+    if dest.exists() {
+        fs::remove_dir_all(dest).with_context(|| format!("clearing {}", dest.display()))?;
+    }
+    fs::create_dir_all(dest).with_context(|| format!("creating {}", dest.display()))?;
+    copy_tree(source, dest)
+}
+
+fn copy_tree(source: &Path, dest: &Path) -> Result<usize> {
+    let files = collect_files(source)?;
+    for rel in &files {
+        let to = dest.join(rel);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        let from = source.join(rel);
+        fs::copy(&from, &to)
+            .with_context(|| format!("copying {} -> {}", from.display(), to.display()))?;
+    }
+    Ok(files.len())
+}
+
+/// Regular files below `root` as sorted relative paths; a missing root is empty.
+fn collect_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if root.is_dir() {
+        collect_files_into(root, Path::new(""), &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files_into(root: &Path, rel: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = root.join(rel);
+    for entry in fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))? {
+        let entry = entry.with_context(|| format!("reading entry in {}", dir.display()))?;
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("reading file type in {}", dir.display()))?;
+        let child = rel.join(entry.file_name());
+        if file_type.is_dir() {
+            collect_files_into(root, &child, files)?;
+        } else if file_type.is_file() {
+            files.push(child);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, body: &str) {
+        fs::create_dir_all(path.parent().expect("parent")).expect("create parent");
+        fs::write(path, body).expect("write file");
+    }
+
+    fn request(root: &Path, name: &str) -> WorkspaceSnapshotRequest {
+        WorkspaceSnapshotRequest {
+            name: name.to_string(),
+            session_topdir: root.to_path_buf(),
+            target_id: "almalinux-9.7-x86_64".to_string(),
+            target_root: root.join("targets/almalinux-9.7-x86_64"),
+            recipe_repo_root: root.join("bioconda-recipes"),
+            replace: false,
+        }
+    }
+
+    #[test]
+    fn snapshot_and_restore_round_trip_workspace_state() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let root = tmp.path();
+        let req = request(root, "before-sync");
+        let rpm = req.target_root.join("RPMS/x86_64/phoreus-samtools-1.0.rpm");
+        write(&root.join("SPECS/phoreus-samtools.spec"), "Version: 1.0\n");
+        write(
+            &req.target_root.join("reports/build_stability.json"),
+            "{\"samtools\":\"stable\"}",
+        );
+        write(&rpm, "rpm-v1");
+
+        let manifest = create_workspace_snapshot(&req).expect("create snapshot");
+        assert_eq!(manifest.spec_files, 1);
+        assert!(manifest.stability_cache);
+        assert_eq!(manifest.recipe_commit, None);
+        assert_eq!(
+            manifest.artifacts,
+            vec![SnapshotArtifact {
+                path: "RPMS/x86_64/phoreus-samtools-1.0.rpm".to_string(),
+                size: 6,
+                linked: true,
+            }]
+        );
+        assert!(create_workspace_snapshot(&req).is_err());
+
+        write(&root.join("SPECS/phoreus-samtools.spec"), "Version: 2.0\n");
+        write(
+            &req.target_root.join("reports/build_stability.json"),
+            "{\"samtools\":\"failed\"}",
+        );
+        fs::remove_file(&rpm).expect("remove rpm");
+        write(
+            &req.target_root.join("RPMS/x86_64/phoreus-samtools-2.0.rpm"),
+            "rpm-v2",
+        );
+
+        let outcome = restore_workspace_snapshot(&req).expect("restore snapshot");
+        assert_eq!(outcome.restored_artifacts, 1);
+        assert_eq!(outcome.removed_artifacts, 1);
+        assert!(outcome.missing_artifacts.is_empty());
+        assert_eq!(
+            fs::read_to_string(root.join("SPECS/phoreus-samtools.spec")).expect("spec"),
+            "Version: 1.0\n"
+        );
+        assert_eq!(
+            fs::read_to_string(req.target_root.join("reports/build_stability.json"))
+                .expect("stability"),
+            "{\"samtools\":\"stable\"}"
+        );
+        assert_eq!(fs::read_to_string(&rpm).expect("rpm"), "rpm-v1");
+        assert!(
+            !req.target_root
+                .join("RPMS/x86_64/phoreus-samtools-2.0.rpm")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn restore_rejects_missing_snapshot_and_other_target() {
+        let tmp = tempfile::TempDir::new().expect("create temp dir");
+        let req = request(tmp.path(), "base");
+        assert!(restore_workspace_snapshot(&req).is_err());
+
+        create_workspace_snapshot(&req).expect("create empty snapshot");
+        let other = WorkspaceSnapshotRequest {
+            target_id: "fedora-43-x86_64".to_string(),
+            ..req.clone()
+        };
+        let err = restore_workspace_snapshot(&other).expect_err("target mismatch");
+        assert!(err.to_string().contains("fedora-43-x86_64"));
+
+        let replaced = create_workspace_snapshot(&WorkspaceSnapshotRequest {
+            replace: true,
+            ..req
+        });
+        assert!(replaced.is_ok());
+    }
+}